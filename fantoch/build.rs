@@ -0,0 +1,12 @@
+// Compiles `proto/wire.proto` into a generated Rust module, the same way
+// `prost-build` is normally wired in: only runs when the `protobuf` feature
+// is selected, since nothing else in this crate needs a Protobuf codegen
+// step (see `run/rw/wire_format.rs` for how the generated types plug into
+// `WireFormat`).
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_PROTOBUF").is_none() {
+        return;
+    }
+    prost_build::compile_protos(&["proto/wire.proto"], &["proto/"])
+        .expect("failed to compile proto/wire.proto");
+}
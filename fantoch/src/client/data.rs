@@ -0,0 +1,144 @@
+use crate::id::ProcessId;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Aggregates the latency and throughput of every command a `Client` (or a
+/// set of merged `Client`s) has completed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientData {
+    // service latency: actual send to result
+    latencies: Vec<Duration>,
+    // open-loop only: scheduled arrival to actual send
+    queueing_delays: Vec<Duration>,
+    // number of commands that ended at each (millis) point in time
+    throughput: HashMap<u64, usize>,
+    // service latency broken down by the process that served the command
+    process_latencies: HashMap<ProcessId, Vec<Duration>>,
+    // service latency broken down by `Workload::workload_id`, for clients
+    // running a mix of concurrent workloads (see `fantoch::run::client`)
+    workload_latencies: HashMap<usize, Vec<Duration>>,
+    // `(from, to)` pairs, one per time the client failed over away from a
+    // delinquent process
+    failovers: Vec<(ProcessId, ProcessId)>,
+}
+
+impl ClientData {
+    pub fn new() -> Self {
+        Self {
+            latencies: Vec::new(),
+            queueing_delays: Vec::new(),
+            throughput: HashMap::new(),
+            process_latencies: HashMap::new(),
+            workload_latencies: HashMap::new(),
+            failovers: Vec::new(),
+        }
+    }
+
+    /// Records a command served by `process_id` that took `latency` to
+    /// complete (service latency), ending at `end_time` (millis), having
+    /// queued for `queueing_delay` beforehand (open-loop only; `None` for
+    /// closed-loop commands). `workload_id` identifies which workload (see
+    /// `Workload::workload_id`) the issuing client was running.
+    pub fn record(
+        &mut self,
+        process_id: ProcessId,
+        latency: Duration,
+        end_time: u64,
+        queueing_delay: Option<Duration>,
+        workload_id: usize,
+    ) {
+        self.latencies.push(latency);
+        if let Some(queueing_delay) = queueing_delay {
+            self.queueing_delays.push(queueing_delay);
+        }
+        *self.throughput.entry(end_time).or_insert(0) += 1;
+        self.process_latencies
+            .entry(process_id)
+            .or_insert_with(Vec::new)
+            .push(latency);
+        self.workload_latencies
+            .entry(workload_id)
+            .or_insert_with(Vec::new)
+            .push(latency);
+    }
+
+    /// Records that the client failed over from `from` to `to` after `from`
+    /// was found delinquent.
+    pub fn record_failover(&mut self, from: ProcessId, to: ProcessId) {
+        self.failovers.push((from, to));
+    }
+
+    /// Returns an iterator over the service latency of every command
+    /// recorded.
+    pub fn latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.latencies.iter().copied()
+    }
+
+    /// Returns an iterator over the queueing delay of every open-loop
+    /// command recorded (closed-loop commands don't contribute here).
+    pub fn queueing_delay_data(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.queueing_delays.iter().copied()
+    }
+
+    /// Returns an iterator over `(end_time, count)` pairs: how many
+    /// commands ended at each point in time.
+    pub fn throughput_data(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        self.throughput
+            .iter()
+            .map(|(&end_time, &count)| (end_time, count))
+    }
+
+    /// Returns an iterator over the service latency of every command served
+    /// by `process_id`.
+    pub fn process_latency_data(
+        &self,
+        process_id: ProcessId,
+    ) -> impl Iterator<Item = Duration> + '_ {
+        self.process_latencies
+            .get(&process_id)
+            .into_iter()
+            .flat_map(|latencies| latencies.iter().copied())
+    }
+
+    /// Returns an iterator over the service latency of every command issued
+    /// by a client running workload `workload_id`.
+    pub fn workload_latency_data(
+        &self,
+        workload_id: usize,
+    ) -> impl Iterator<Item = Duration> + '_ {
+        self.workload_latencies
+            .get(&workload_id)
+            .into_iter()
+            .flat_map(|latencies| latencies.iter().copied())
+    }
+
+    /// Returns an iterator over every `(from, to)` failover this client went
+    /// through, in the order they happened.
+    pub fn failover_data(&self) -> impl Iterator<Item = (ProcessId, ProcessId)> + '_ {
+        self.failovers.iter().copied()
+    }
+
+    /// Merges `other` into `self`.
+    pub fn merge(&mut self, other: &ClientData) {
+        self.latencies.extend(other.latencies.iter().copied());
+        self.queueing_delays
+            .extend(other.queueing_delays.iter().copied());
+        for (&end_time, &count) in other.throughput.iter() {
+            *self.throughput.entry(end_time).or_insert(0) += count;
+        }
+        for (&process_id, latencies) in other.process_latencies.iter() {
+            self.process_latencies
+                .entry(process_id)
+                .or_insert_with(Vec::new)
+                .extend(latencies.iter().copied());
+        }
+        for (&workload_id, latencies) in other.workload_latencies.iter() {
+            self.workload_latencies
+                .entry(workload_id)
+                .or_insert_with(Vec::new)
+                .extend(latencies.iter().copied());
+        }
+        self.failovers.extend(other.failovers.iter().copied());
+    }
+}
@@ -15,6 +15,45 @@ pub enum KeyGen {
     Zipf {
         coefficient: f64,
         keys_per_shard: usize,
+        /// When set, the sampled rank is scrambled through `fmix64` before
+        /// being stringified, so key "1" isn't always the hottest key and
+        /// hot keys aren't numerically (and therefore shard-range)
+        /// adjacent. Off by default so existing configs keep generating the
+        /// same keys they always have.
+        scramble: bool,
+    },
+    /// Like `Zipf`, but the identity of the hottest key rotates through the
+    /// keyspace as the run progresses instead of staying pinned to rank 1:
+    /// every `shift_every` generated keys, the rank-to-key mapping shifts by
+    /// one. Lets experiments exercise how a contended key set migrating
+    /// over time (e.g. yesterday's hot key cooling down) affects things
+    /// like `TableExecutor`'s votes table and conflict detection, instead
+    /// of only ever stressing a single fixed region of the keyspace.
+    ZipfChurn {
+        coefficient: f64,
+        keys_per_shard: usize,
+        shift_every: usize,
+    },
+    /// YCSB-style skewed key-access distribution: `keys` is the total number
+    /// of keys accessed and `theta` (in `[0, 1)`) controls the skew, with
+    /// `theta = 0` being uniform and values close to `1` being very skewed.
+    /// The sampled rank is scrambled through a hash so that hot keys are not
+    /// contiguous.
+    Zipfian {
+        keys: usize,
+        theta: f64,
+    },
+    /// YCSB-style hotspot distribution: `hot_key_rate` percent of accesses
+    /// land on one of `hot_keys` keys (picked uniformly among them), the
+    /// remainder spread uniformly over the rest of a `key_count`-key
+    /// keyspace. Unlike `Zipfian`'s smooth power-law skew, this concentrates
+    /// contention on a small, fixed set of keys, which is what stresses
+    /// something like `AtomicKeyClocks` the hardest (a few atomics take
+    /// almost all the `fetch_update` traffic).
+    Hotspot {
+        key_count: usize,
+        hot_keys: usize,
+        hot_key_rate: usize,
     },
 }
 
@@ -34,28 +73,128 @@ impl std::fmt::Display for KeyGen {
             Self::ConflictRate { conflict_rate } => {
                 write!(f, "conflict{}", conflict_rate)
             }
-            Self::Zipf { coefficient, .. } => write!(
+            Self::Zipf {
+                coefficient,
+                scramble,
+                ..
+            } => {
+                let suffix = if *scramble { "-scrambled" } else { "" };
+                write!(
+                    f,
+                    "{}{}",
+                    format!("zipf{:.2}", coefficient).replace(".", "-"),
+                    suffix
+                )
+            }
+            Self::ZipfChurn {
+                coefficient,
+                shift_every,
+                ..
+            } => write!(
+                f,
+                "{}",
+                format!("zipfchurn{:.2}-{}", coefficient, shift_every)
+                    .replace(".", "-")
+            ),
+            Self::Zipfian { keys, theta } => write!(
                 f,
                 "{}",
-                format!("zipf{:.2}", coefficient).replace(".", "-")
+                format!("zipfian{}-{:.2}", keys, theta).replace(".", "-")
+            ),
+            Self::Hotspot {
+                key_count,
+                hot_keys,
+                hot_key_rate,
+            } => write!(
+                f,
+                "hotspot{}-{}-{}",
+                key_count, hot_keys, hot_key_rate
             ),
         }
     }
 }
 
+/// Precomputed constants needed to sample from the YCSB-style zipfian
+/// distribution; these only depend on `keys`/`theta`, so they're computed
+/// once and cached.
+#[derive(Debug, Clone, Copy)]
+struct ZipfianState {
+    keys: usize,
+    theta: f64,
+    alpha: f64,
+    eta: f64,
+    zetan: f64,
+}
+
+impl ZipfianState {
+    fn new(keys: usize, theta: f64) -> Self {
+        debug_assert!(
+            theta >= 0.0 && theta < 1.0,
+            "zipfian theta must be in [0, 1), found {}",
+            theta
+        );
+        let zetan = Self::zeta(keys, theta);
+        let zeta2 = Self::zeta(2, theta);
+        let alpha = 1.0 / (1.0 - theta);
+        let eta = (1.0 - (2.0 / keys as f64).powf(1.0 - theta))
+            / (1.0 - zeta2 / zetan);
+        Self {
+            keys,
+            theta,
+            alpha,
+            eta,
+            zetan,
+        }
+    }
+
+    /// Computes `zeta(n) = sum_{i=1..n} 1/i^theta`.
+    fn zeta(n: usize, theta: f64) -> f64 {
+        (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum()
+    }
+
+    /// Samples a rank in `[0, keys)` following the zipfian distribution.
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            0
+        } else if uz < 1.0 + 0.5f64.powf(self.theta) {
+            1
+        } else {
+            let rank = self.keys as f64
+                * (self.eta * u - self.eta + 1.0).powf(self.alpha);
+            (rank as usize).min(self.keys - 1)
+        }
+    }
+}
+
 pub struct KeyGenState {
     key_gen: KeyGen,
     client_id: ClientId,
     zipf: Option<ZipfDistribution>,
+    // `ZipfDistribution` doesn't expose the key count it was built with, but
+    // `gen_zipf` needs it to scramble a sampled rank into `[0, key_count)`,
+    // so it's cached alongside the distribution instead of recomputed.
+    zipf_key_count: Option<usize>,
+    zipfian: Option<ZipfianState>,
+    zipf_churn: Option<ZipfDistribution>,
+    zipf_churn_key_count: Option<usize>,
+    zipf_churn_shift_every: Option<usize>,
+    // counts calls to `gen_zipf_churn` since the last shift, and the number
+    // of shifts applied so far (added to the sampled rank, mod key count) -
+    // both start at 0 and only ever move forward, so the churn is
+    // deterministic given a fixed `shift_every` and call sequence.
+    zipf_churn_calls: usize,
+    zipf_churn_offset: usize,
 }
 
 impl KeyGenState {
     fn new(key_gen: KeyGen, shard_count: usize, client_id: ClientId) -> Self {
-        let zipf = match key_gen {
-            KeyGen::ConflictRate { .. } => None,
+        let (zipf, zipf_key_count) = match key_gen {
             KeyGen::Zipf {
                 coefficient,
                 keys_per_shard,
+                ..
             } => {
                 // compute key count
                 let key_count = keys_per_shard * shard_count;
@@ -64,13 +203,55 @@ impl KeyGenState {
                     .expect(
                     "it should be possible to initialize the ZipfDistribution",
                 );
-                Some(zipf)
+                (Some(zipf), Some(key_count))
+            }
+            KeyGen::ConflictRate { .. }
+            | KeyGen::ZipfChurn { .. }
+            | KeyGen::Zipfian { .. }
+            | KeyGen::Hotspot { .. } => (None, None),
+        };
+        let zipfian = match key_gen {
+            KeyGen::Zipfian { keys, theta } => {
+                Some(ZipfianState::new(keys, theta))
             }
+            KeyGen::ConflictRate { .. }
+            | KeyGen::Zipf { .. }
+            | KeyGen::ZipfChurn { .. }
+            | KeyGen::Hotspot { .. } => None,
         };
+        let (zipf_churn, zipf_churn_key_count, zipf_churn_shift_every) =
+            match key_gen {
+                KeyGen::ZipfChurn {
+                    coefficient,
+                    keys_per_shard,
+                    shift_every,
+                } => {
+                    let key_count = keys_per_shard * shard_count;
+                    let zipf_churn = ZipfDistribution::new(
+                        key_count,
+                        coefficient,
+                    )
+                    .expect(
+                        "it should be possible to initialize the ZipfDistribution",
+                    );
+                    (Some(zipf_churn), Some(key_count), Some(shift_every))
+                }
+                KeyGen::ConflictRate { .. }
+                | KeyGen::Zipf { .. }
+                | KeyGen::Zipfian { .. }
+                | KeyGen::Hotspot { .. } => (None, None, None),
+            };
         Self {
             key_gen,
             client_id,
             zipf,
+            zipf_key_count,
+            zipfian,
+            zipf_churn,
+            zipf_churn_key_count,
+            zipf_churn_shift_every,
+            zipf_churn_calls: 0,
+            zipf_churn_offset: 0,
         }
     }
 
@@ -80,6 +261,13 @@ impl KeyGenState {
                 self.gen_conflict_rate(conflict_rate)
             }
             KeyGen::Zipf { .. } => self.gen_zipf(),
+            KeyGen::ZipfChurn { .. } => self.gen_zipf_churn(),
+            KeyGen::Zipfian { keys, .. } => self.gen_zipfian(keys),
+            KeyGen::Hotspot {
+                key_count,
+                hot_keys,
+                hot_key_rate,
+            } => self.gen_hotspot(key_count, hot_keys, hot_key_rate),
         }
     }
 
@@ -99,15 +287,101 @@ impl KeyGenState {
     }
 
     /// Generate a command key based on the initiliazed zipfian distribution.
+    /// When `KeyGen::Zipf::scramble` is set, the sampled rank is scrambled
+    /// through `fmix64` first, so the hottest keys aren't numerically
+    /// adjacent (see the doc comment on `KeyGen::Zipf`'s `scramble` field).
     fn gen_zipf(&mut self) -> Key {
+        let scramble = match self.key_gen {
+            KeyGen::Zipf { scramble, .. } => scramble,
+            _ => unreachable!("gen_zipf called with a non-Zipf KeyGen"),
+        };
         let zipf = self
             .zipf
             .expect("ZipfDistribution should already be initialized");
         let mut rng = rand::thread_rng();
-        zipf.sample(&mut rng).to_string()
+        let rank = zipf.sample(&mut rng);
+        if scramble {
+            let key_count = self
+                .zipf_key_count
+                .expect("zipf key count should already be initialized");
+            (fmix64(rank as u64) as usize % key_count).to_string()
+        } else {
+            rank.to_string()
+        }
+    }
+
+    /// Generate a command key from the `ZipfChurn` distribution: sample a
+    /// rank as usual, then rotate it by `zipf_churn_offset` (which advances
+    /// by one every `shift_every` calls), so the hottest key drifts through
+    /// the keyspace over the course of a run instead of staying fixed.
+    fn gen_zipf_churn(&mut self) -> Key {
+        let shift_every = self
+            .zipf_churn_shift_every
+            .expect("zipf churn shift_every should already be initialized");
+        let key_count = self
+            .zipf_churn_key_count
+            .expect("zipf churn key count should already be initialized");
+        let zipf_churn = self
+            .zipf_churn
+            .expect("zipf churn distribution should already be initialized");
+
+        self.zipf_churn_calls += 1;
+        if self.zipf_churn_calls >= shift_every {
+            self.zipf_churn_calls = 0;
+            self.zipf_churn_offset += 1;
+        }
+
+        let mut rng = rand::thread_rng();
+        // `ZipfDistribution::sample` returns a rank in `[1, key_count]`
+        let rank = zipf_churn.sample(&mut rng);
+        let shifted = (rank - 1 + self.zipf_churn_offset) % key_count + 1;
+        shifted.to_string()
+    }
+
+    /// Generate a command key based on the initialized YCSB-style zipfian
+    /// distribution. The sampled rank is scrambled through a hash so that hot
+    /// keys are spread across the keyspace instead of being contiguous.
+    fn gen_zipfian(&self, keys: usize) -> Key {
+        let zipfian = self
+            .zipfian
+            .expect("ZipfianState should already be initialized");
+        let mut rng = rand::thread_rng();
+        let rank = zipfian.sample(&mut rng);
+        let scrambled = fmix64(rank as u64) as usize % keys;
+        scrambled.to_string()
+    }
+
+    /// Generate a command key following the hotspot distribution:
+    /// `hot_key_rate` percent of the time, pick uniformly among the first
+    /// `hot_keys` keys; otherwise pick uniformly among the remaining
+    /// `key_count - hot_keys` keys.
+    fn gen_hotspot(
+        &self,
+        key_count: usize,
+        hot_keys: usize,
+        hot_key_rate: usize,
+    ) -> Key {
+        let mut rng = rand::thread_rng();
+        let rank = if true_if_random_is_less_than(hot_key_rate) {
+            rng.gen_range(0, hot_keys)
+        } else {
+            rng.gen_range(hot_keys, key_count)
+        };
+        rank.to_string()
     }
 }
 
+/// 64-bit finalizer mix function from MurmurHash3, used here to scramble a
+/// zipfian rank so that hot keys are not contiguous in the keyspace.
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51afd7ed558ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ceb9fe1a85ec53);
+    k ^= k >> 33;
+    k
+}
+
 pub fn true_if_random_is_less_than(percentage: usize) -> bool {
     match percentage {
         0 => false,
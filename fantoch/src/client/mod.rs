@@ -10,19 +10,35 @@ pub mod data;
 // Re-exports.
 pub use data::ClientData;
 pub use pending::Pending;
-pub use workload::Workload;
+pub use workload::{Arrival, ArrivalProcess, Workload};
 
 use crate::command::{Command, CommandResult};
 use crate::id::ProcessId;
 use crate::id::{ClientId, RiflGen};
 use crate::log;
 use crate::time::SysTime;
+use std::time::Duration;
+
+/// A process is considered delinquent, and the client fails over to the
+/// next-closest one, once a command has been pending on it for this long
+/// without a result -- unless a different threshold is set through
+/// `Client::set_latency_threshold`.
+const DEFAULT_LATENCY_THRESHOLD: Duration = Duration::from_secs(1);
 
 pub struct Client {
     /// id of this client
     client_id: ClientId,
-    /// id of the process this client is connected to
-    process_id: Option<ProcessId>,
+    /// processes to connect to, sorted by distance; `process_index` points
+    /// at the one currently in use
+    processes: Vec<ProcessId>,
+    /// index (into `processes`) of the process this client is connected to
+    process_index: usize,
+    /// how long a command can be pending on a process before it's
+    /// considered delinquent and the client fails over to the next one
+    latency_threshold: Duration,
+    /// commands re-sent to a new process because of a failover, not yet
+    /// picked up by the caller
+    reissues: Vec<(ProcessId, Command)>,
     /// rifl id generator
     rifl_gen: RiflGen,
     /// workload configuration
@@ -39,7 +55,10 @@ impl Client {
         // create client
         Self {
             client_id,
-            process_id: None,
+            processes: Vec::new(),
+            process_index: 0,
+            latency_threshold: DEFAULT_LATENCY_THRESHOLD,
+            reissues: Vec::new(),
             rifl_gen: RiflGen::new(client_id),
             workload,
             pending: Pending::new(),
@@ -52,25 +71,98 @@ impl Client {
         self.client_id
     }
 
-    /// "Connect" to the closest process.
+    /// Sets how long a command can be pending on a process before it's
+    /// considered delinquent.
+    pub fn set_latency_threshold(&mut self, latency_threshold: Duration) {
+        self.latency_threshold = latency_threshold;
+    }
+
+    /// "Connect" to the closest process, keeping the full distance-ranked
+    /// list around so the client can fail over to the next-closest one if
+    /// the one it's connected to turns out to be delinquent.
     pub fn discover(&mut self, processes: Vec<ProcessId>) -> bool {
-        // set the closest process
-        self.process_id = processes.into_iter().next();
+        self.processes = processes;
+        self.process_index = 0;
 
         // check if we have a closest process
-        self.process_id.is_some()
+        self.process_id().is_some()
+    }
+
+    /// Returns the process this client is currently connected to.
+    pub fn process_id(&self) -> Option<ProcessId> {
+        self.processes.get(self.process_index).copied()
+    }
+
+    /// Checks whether the process we're connected to has gone delinquent
+    /// (i.e. some command has been pending on it for longer than
+    /// `latency_threshold`) and, if so, fails over to the next-closest
+    /// process still in `processes`, queueing up for re-send (see
+    /// `drain_reissues`) any commands that were pending on the delinquent
+    /// one.
+    fn failover_if_delinquent(&mut self, time: &dyn SysTime) {
+        let delinquent = self.process_id().map_or(false, |process_id| {
+            self.pending
+                .oldest_pending_age(process_id, time.millis())
+                .map_or(false, |age| age >= self.latency_threshold)
+        });
+        if !delinquent || self.process_index + 1 >= self.processes.len() {
+            return;
+        }
+
+        let from = self
+            .process_id()
+            .expect("a delinquent process should exist");
+        self.process_index += 1;
+        let to = self.process_id().expect("there should be a next process");
+        log!(
+            "client {:?} failing over from process {:?} to process {:?}",
+            self.client_id,
+            from,
+            to
+        );
+        self.data.record_failover(from, to);
+        let reissued = self.pending.reassign(from, to);
+        self.reissues
+            .extend(reissued.into_iter().map(|(_rifl, cmd)| (to, cmd)));
     }
 
-    /// Generates the next command in this client's workload.
+    /// Returns the commands that were pending on a process the client just
+    /// failed over away from, and that therefore need to be re-sent to the
+    /// process returned by `process_id` instead.
+    pub fn drain_reissues(&mut self) -> Vec<(ProcessId, Command)> {
+        std::mem::take(&mut self.reissues)
+    }
+
+    /// Generates the next command in this client's workload, if one is due.
+    /// Under the workload's (default) closed-loop arrival process, a
+    /// command is always due; under an open-loop arrival process (see
+    /// `Workload::arrival_process`), a command is only due once its
+    /// scheduled arrival time has passed, independent of whether previous
+    /// commands have been handled -- so more than one can end up in flight.
+    /// Also checks the currently connected process for delinquency and, if
+    /// necessary, fails over to the next-closest one -- see
+    /// `drain_reissues`.
     pub fn next_cmd(
         &mut self,
         time: &dyn SysTime,
     ) -> Option<(ProcessId, Command)> {
-        self.process_id.and_then(|process_id| {
+        self.failover_if_delinquent(time);
+        self.process_id().and_then(|process_id| {
+            let scheduled_at = match self.workload.arrival(time.millis()) {
+                Arrival::NotDue => return None,
+                Arrival::Due => None,
+                Arrival::DueAt(scheduled_at) => Some(scheduled_at),
+            };
             // generate next command in the workload if some process_id
             self.workload.next_cmd(&mut self.rifl_gen).map(|cmd| {
                 // if a new command was generated, start it in pending
-                self.pending.start(cmd.rifl(), time);
+                self.pending.start(
+                    cmd.rifl(),
+                    cmd.clone(),
+                    process_id,
+                    time,
+                    scheduled_at,
+                );
                 (process_id, cmd)
             })
         })
@@ -85,14 +177,21 @@ impl Client {
         time: &dyn SysTime,
     ) -> bool {
         // end command in pending and save command latency
-        let (latency, end_time) = self.pending.end(cmd_result.rifl(), time);
+        let (process_id, latency, end_time, queueing_delay) =
+            self.pending.end(cmd_result.rifl(), time);
         log!(
             "rifl {:?} ended after {} micros at {}",
             cmd_result.rifl(),
             latency.as_micros(),
             end_time
         );
-        self.data.record(latency, end_time);
+        self.data.record(
+            process_id,
+            latency,
+            end_time,
+            queueing_delay,
+            self.workload.workload_id(),
+        );
 
         // we're done once:
         // - the workload is finished and
@@ -151,13 +250,57 @@ mod tests {
         // check discover with empty vec
         let sorted = util::sort_processes_by_distance(&region, &planet, vec![]);
         assert!(!client.discover(sorted));
-        assert_eq!(client.process_id, None);
+        assert_eq!(client.process_id(), None);
 
         // check discover with processes
         let sorted =
             util::sort_processes_by_distance(&region, &planet, processes);
         assert!(client.discover(sorted));
-        assert_eq!(client.process_id, Some(2));
+        assert_eq!(client.process_id(), Some(2));
+    }
+
+    #[test]
+    fn failover_on_delinquent_process() {
+        // create planet
+        let planet = Planet::new();
+
+        // processes (sorted by distance below)
+        let processes = vec![
+            (0, Region::new("asia-east1")),
+            (1, Region::new("australia-southeast1")),
+            (2, Region::new("europe-west1")),
+        ];
+
+        // client
+        let region = Region::new("europe-west2");
+        let total_commands = 2;
+        let mut client = gen_client(total_commands);
+        let sorted =
+            util::sort_processes_by_distance(&region, &planet, processes);
+        client.discover(sorted);
+        client.set_latency_threshold(Duration::from_millis(10));
+
+        let mut time = SimTime::new();
+
+        // first command is sent to the closest process
+        let (process_id, _) = client
+            .next_cmd(&time)
+            .expect("there should a first operation");
+        assert_eq!(process_id, 2);
+        assert!(client.drain_reissues().is_empty());
+
+        // the first command is still pending when the threshold elapses, so
+        // the client should fail over to the next-closest process and
+        // queue it up for re-sending
+        time.add_millis(20);
+        let (process_id, _) = client
+            .next_cmd(&time)
+            .expect("there should be a second operation");
+        assert_eq!(client.process_id(), Some(1));
+        assert_eq!(process_id, 1);
+        let reissued = client.drain_reissues();
+        assert_eq!(reissued.len(), 1);
+        assert_eq!(reissued[0].0, 1);
     }
 
     #[test]
@@ -0,0 +1,121 @@
+use crate::command::Command;
+use crate::id::{ProcessId, Rifl};
+use crate::time::SysTime;
+use crate::HashMap;
+use std::time::Duration;
+
+/// The (millis) time a pending command was actually sent at, plus how long
+/// it sat queued first (open-loop only: the gap between its scheduled
+/// arrival and when it was actually sent; closed-loop commands are sent as
+/// soon as they're generated, so they have none), the process it's
+/// currently waiting on a result from, and the command itself (kept around
+/// so it can be re-sent to another process on failover).
+struct Entry {
+    start_time: u64,
+    queueing_delay: Option<Duration>,
+    process_id: ProcessId,
+    cmd: Command,
+}
+
+/// Tracks commands a `Client` has submitted but not yet received a result
+/// for, keyed by their `Rifl`, so that once a result comes back its
+/// (service) latency can be computed.
+#[derive(Default)]
+pub struct Pending {
+    pending: HashMap<Rifl, Entry>,
+}
+
+impl Pending {
+    pub fn new() -> Self {
+        Self {
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Registers `rifl` as pending on `process_id`, starting its clock at
+    /// `time`. `scheduled_at` is the (millis) time `rifl` was due to be sent
+    /// under an open-loop arrival process, if any -- `None` for closed-loop
+    /// commands, which are always sent as soon as generated.
+    pub fn start(
+        &mut self,
+        rifl: Rifl,
+        cmd: Command,
+        process_id: ProcessId,
+        time: &dyn SysTime,
+        scheduled_at: Option<u64>,
+    ) {
+        let start_time = time.millis();
+        let queueing_delay = scheduled_at.map(|scheduled_at| {
+            Duration::from_millis(start_time - scheduled_at)
+        });
+        let entry = Entry {
+            start_time,
+            queueing_delay,
+            process_id,
+            cmd,
+        };
+        let res = self.pending.insert(rifl, entry);
+        assert!(res.is_none(), "rifl {:?} already pending", rifl);
+    }
+
+    /// Ends `rifl`, returning the process it was last pending on, its
+    /// service latency (actual send to result), the (millis) time it ended
+    /// at, and its queueing delay (if any).
+    pub fn end(
+        &mut self,
+        rifl: Rifl,
+        time: &dyn SysTime,
+    ) -> (ProcessId, Duration, u64, Option<Duration>) {
+        let entry = self
+            .pending
+            .remove(&rifl)
+            .unwrap_or_else(|| panic!("rifl {:?} should be pending", rifl));
+        let end_time = time.millis();
+        let latency = Duration::from_millis(end_time - entry.start_time);
+        (entry.process_id, latency, end_time, entry.queueing_delay)
+    }
+
+    /// Returns a boolean indicating whether there are no pending commands.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the number of pending commands.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns how long the oldest command still pending on `process_id` has
+    /// been waiting, as of `now` (millis); `None` if nothing is pending on
+    /// it.
+    pub fn oldest_pending_age(
+        &self,
+        process_id: ProcessId,
+        now: u64,
+    ) -> Option<Duration> {
+        self.pending
+            .values()
+            .filter(|entry| entry.process_id == process_id)
+            .map(|entry| Duration::from_millis(now - entry.start_time))
+            .max()
+    }
+
+    /// Re-targets every command still pending on `from` to `to`, returning
+    /// the `(Rifl, Command)` pairs that need to be physically re-sent to
+    /// `to`. The original start time (and thus the end-to-end latency
+    /// eventually reported) is preserved across the failover.
+    pub fn reassign(
+        &mut self,
+        from: ProcessId,
+        to: ProcessId,
+    ) -> Vec<(Rifl, Command)> {
+        let mut reissued = Vec::new();
+        for (&rifl, entry) in self.pending.iter_mut() {
+            if entry.process_id == from {
+                entry.process_id = to;
+                reissued.push((rifl, entry.cmd.clone()));
+            }
+        }
+        reissued
+    }
+}
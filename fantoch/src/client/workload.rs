@@ -1,3 +1,4 @@
+use crate::client::key_gen;
 use crate::client::key_gen::KeyGenState;
 use crate::client::{KeyGen, ShardGen};
 use crate::command::Command;
@@ -10,6 +11,35 @@ use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 use std::iter;
 
+/// Governs when a `Client` is allowed to generate its next command.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArrivalProcess {
+    /// The default: a new command is only generated once the previous one
+    /// has been handled, coupling offered load to response latency.
+    ClosedLoop,
+    /// Open-loop: commands are due every `interval_ms`, independent of when
+    /// previous commands complete.
+    Fixed { interval_ms: u64 },
+    /// Open-loop: inter-arrival times are drawn from an exponential
+    /// distribution for a target rate of `rate_per_sec` commands/second
+    /// (i.e. a Poisson arrival process), independent of when previous
+    /// commands complete.
+    Poisson { rate_per_sec: f64 },
+}
+
+/// Whether (and, if so, when) a command is due, as reported by
+/// `Workload::arrival`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// Not due yet.
+    NotDue,
+    /// Due, closed-loop: there's no scheduled arrival time to speak of, so
+    /// no queueing delay to measure.
+    Due,
+    /// Due, open-loop: scheduled to arrive at this (millis) time.
+    DueAt(u64),
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Workload {
     /// number of shards accessed per command
@@ -24,8 +54,21 @@ pub struct Workload {
     commands_per_client: usize,
     /// size of payload in command (in bytes)
     payload_size: usize,
+    /// percentage (0-100) of generated ops that are `KVOp::Get`s, the
+    /// remaining being `KVOp::Put`s
+    read_only_rate: usize,
+    /// how commands are scheduled: closed-loop (the default) or open-loop
+    /// with a fixed or Poisson arrival process
+    arrival_process: ArrivalProcess,
     /// number of commands already issued in this workload
     command_count: usize,
+    /// open-loop only: (millis) time the next command is due
+    next_arrival_ms: Option<u64>,
+    /// identifies which workload this is among a mix of concurrently-run
+    /// workloads (see `fantoch::run::client`'s `workloads` parameter);
+    /// defaults to `0`, the only id that matters when a single `Workload` is
+    /// run on its own
+    workload_id: usize,
 }
 
 impl Workload {
@@ -36,6 +79,8 @@ impl Workload {
         key_gen: KeyGen,
         commands_per_client: usize,
         payload_size: usize,
+        read_only_rate: usize,
+        arrival_process: ArrivalProcess,
     ) -> Self {
         // check for valid workloads
         match key_gen {
@@ -47,8 +92,31 @@ impl Workload {
                     panic!("invalid workload; can't generate more than two keys per shard with the conflict_rate key generator");
                 }
             }
+            KeyGen::Zipfian { theta, .. } => {
+                if !(0.0..1.0).contains(&theta) {
+                    panic!("invalid workload; zipfian theta must be in [0.0, 1.0), found {}", theta);
+                }
+            }
+            KeyGen::Hotspot {
+                key_count,
+                hot_keys,
+                hot_key_rate,
+            } => {
+                if hot_keys > key_count {
+                    panic!("invalid workload; hotspot hot_keys ({}) can't exceed key_count ({})", hot_keys, key_count);
+                }
+                if hot_key_rate > 100 {
+                    panic!("invalid workload; hotspot hot_key_rate must be in [0, 100], found {}", hot_key_rate);
+                }
+            }
             _ => (),
         }
+        if read_only_rate > 100 {
+            panic!(
+                "invalid workload; read_only_rate must be in [0, 100], found {}",
+                read_only_rate
+            );
+        }
         Self {
             shards_per_command,
             shard_gen,
@@ -56,10 +124,26 @@ impl Workload {
             key_gen,
             commands_per_client,
             payload_size,
+            read_only_rate,
+            arrival_process,
             command_count: 0,
+            next_arrival_ms: None,
+            workload_id: 0,
         }
     }
 
+    /// Returns which workload (among a mix run concurrently) this is.
+    pub fn workload_id(&self) -> usize {
+        self.workload_id
+    }
+
+    /// Tags this workload as `workload_id` among a mix of concurrently-run
+    /// workloads, so per-workload metrics (see `ClientData::record`) can
+    /// tell its commands apart from the others'.
+    pub fn set_workload_id(&mut self, workload_id: usize) {
+        self.workload_id = workload_id;
+    }
+
     /// Returns the number of shards accessed by commands generated by this
     /// workload.
     pub fn shards_per_command(&self) -> usize {
@@ -93,6 +177,61 @@ impl Workload {
         self.payload_size
     }
 
+    /// Returns the percentage of generated ops that are reads.
+    pub fn read_only_rate(&self) -> usize {
+        self.read_only_rate
+    }
+
+    /// Returns the arrival process configured for this workload.
+    pub fn arrival_process(&self) -> ArrivalProcess {
+        self.arrival_process
+    }
+
+    /// Checks (and, if due, advances) this workload's arrival schedule for
+    /// `now` (millis). Closed-loop workloads are always immediately `Due`;
+    /// open-loop workloads are `DueAt` the scheduled time once `now` has
+    /// reached it, or `NotDue` otherwise -- allowing multiple commands in
+    /// flight, since being due here never depends on a previous command
+    /// having been handled.
+    pub fn arrival(&mut self, now: u64) -> Arrival {
+        match self.arrival_process {
+            ArrivalProcess::ClosedLoop => Arrival::Due,
+            ArrivalProcess::Fixed { interval_ms } => {
+                self.poll_arrival(now, || interval_ms)
+            }
+            ArrivalProcess::Poisson { rate_per_sec } => {
+                self.poll_arrival(now, || Self::poisson_interval_ms(rate_per_sec))
+            }
+        }
+    }
+
+    /// Shared open-loop scheduling logic: `now` is due against the
+    /// currently-scheduled arrival (initialized to `now` itself on the very
+    /// first call), and, if due, `next_interval_ms` is sampled to schedule
+    /// the following arrival.
+    fn poll_arrival(
+        &mut self,
+        now: u64,
+        next_interval_ms: impl FnOnce() -> u64,
+    ) -> Arrival {
+        let scheduled = *self.next_arrival_ms.get_or_insert(now);
+        if now < scheduled {
+            return Arrival::NotDue;
+        }
+        self.next_arrival_ms = Some(scheduled + next_interval_ms().max(1));
+        Arrival::DueAt(scheduled)
+    }
+
+    /// Samples the next inter-arrival delta (millis) for a Poisson process
+    /// with the target rate `rate_per_sec`: `-ln(U) / rate_per_sec` seconds,
+    /// for `U` uniform in `(0, 1]`.
+    fn poisson_interval_ms(rate_per_sec: f64) -> u64 {
+        let u: f64 = rand::thread_rng().gen();
+        let u = u.max(f64::MIN_POSITIVE);
+        let delta_secs = -u.ln() / rate_per_sec;
+        (delta_secs * 1000.0).round() as u64
+    }
+
     /// Generate the next command.
     pub fn next_cmd(
         &mut self,
@@ -165,8 +304,14 @@ impl Workload {
             });
 
             for key in keys {
-                let value = self.gen_cmd_value();
-                shard_ops.insert(key, KVOp::Put(value));
+                let op = if key_gen::true_if_random_is_less_than(
+                    self.read_only_rate,
+                ) {
+                    KVOp::Get
+                } else {
+                    KVOp::Put(self.gen_cmd_value())
+                };
+                shard_ops.insert(key, op);
             }
         }
 
@@ -226,6 +371,8 @@ mod tests {
             key_gen,
             total_commands,
             payload_size,
+            0,
+            ArrivalProcess::ClosedLoop,
         );
         let (target_shard, command) =
             workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
@@ -246,6 +393,8 @@ mod tests {
             key_gen,
             total_commands,
             payload_size,
+            0,
+            ArrivalProcess::ClosedLoop,
         );
         let (target_shard, command) =
             workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
@@ -277,6 +426,8 @@ mod tests {
             key_gen,
             total_commands,
             payload_size,
+            0,
+            ArrivalProcess::ClosedLoop,
         );
 
         // check total and issued commands
@@ -346,6 +497,8 @@ mod tests {
                 key_gen,
                 total_commands,
                 payload_size,
+                0,
+                ArrivalProcess::ClosedLoop,
             );
 
             // count conflicting commands
@@ -369,4 +522,184 @@ mod tests {
             assert_eq!(percentage.round() as usize, conflict_rate);
         }
     }
+
+    #[test]
+    fn hotspot_distribution() {
+        for hot_key_rate in vec![1, 10, 50, 90] {
+            // create rilf gen
+            let client_id = 1;
+            let mut rifl_gen = RiflGen::new(client_id);
+
+            // total commands
+            let shards_per_command = 1;
+            let shard_gen = ShardGen::Random { shards: 1 };
+            let keys_per_shard = 1;
+            let total_commands = 100000;
+            let payload_size = 0;
+
+            // create workload
+            let key_count = 1000;
+            let hot_keys = 10;
+            let key_gen = KeyGen::Hotspot {
+                key_count,
+                hot_keys,
+                hot_key_rate,
+            };
+            let mut key_gen_state = key_gen.initial_state(client_id);
+            let mut workload = Workload::new(
+                shards_per_command,
+                shard_gen,
+                keys_per_shard,
+                key_gen,
+                total_commands,
+                payload_size,
+                0,
+                ArrivalProcess::ClosedLoop,
+            );
+
+            // count commands that hit a hot key
+            let mut hot_key_count = 0;
+
+            while let Some((target_shard, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                // since there's a single shard, keys should be on shard 0
+                assert_eq!(target_shard, 0);
+                let (key, _) = cmd.into_iter(target_shard).next().unwrap();
+                let rank: usize = key.parse().expect("key should be numeric");
+                assert!(rank < key_count);
+                if rank < hot_keys {
+                    hot_key_count += 1;
+                }
+            }
+
+            // compute percentage of commands hitting a hot key
+            let percentage =
+                (hot_key_count * 100) as f64 / total_commands as f64;
+            assert_eq!(percentage.round() as usize, hot_key_rate);
+        }
+    }
+
+    #[test]
+    fn read_write_ratio() {
+        for read_only_rate in vec![0, 1, 25, 50, 75, 100] {
+            // create rilf gen
+            let client_id = 1;
+            let mut rifl_gen = RiflGen::new(client_id);
+
+            // total commands
+            let shards_per_command = 1;
+            let shard_gen = ShardGen::Random { shards: 1 };
+            let keys_per_shard = 1;
+            let total_commands = 100000;
+            let payload_size = 0;
+
+            // create workload
+            let conflict_rate = 0;
+            let key_gen = KeyGen::ConflictRate { conflict_rate };
+            let mut key_gen_state = key_gen.initial_state(client_id);
+            let mut workload = Workload::new(
+                shards_per_command,
+                shard_gen,
+                keys_per_shard,
+                key_gen,
+                total_commands,
+                payload_size,
+                read_only_rate,
+                ArrivalProcess::ClosedLoop,
+            );
+            assert_eq!(workload.read_only_rate(), read_only_rate);
+
+            // count GET commands
+            let mut get_count = 0;
+
+            while let Some((target_shard, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                let (_, op) = cmd.into_iter(target_shard).next().unwrap();
+                if let KVOp::Get = op {
+                    get_count += 1;
+                }
+            }
+
+            // compute percentage of GET commands
+            let percentage =
+                (get_count * 100) as f64 / total_commands as f64;
+            assert_eq!(percentage.round() as usize, read_only_rate);
+        }
+    }
+
+    fn gen_workload(
+        commands_per_client: usize,
+        arrival_process: ArrivalProcess,
+    ) -> Workload {
+        let shards_per_command = 1;
+        let shard_gen = ShardGen::Random { shards: 1 };
+        let keys_per_shard = 1;
+        let key_gen = KeyGen::ConflictRate { conflict_rate: 100 };
+        let payload_size = 0;
+        Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            commands_per_client,
+            payload_size,
+            0,
+            arrival_process,
+        )
+    }
+
+    #[test]
+    fn closed_loop_always_due() {
+        let mut workload = gen_workload(10, ArrivalProcess::ClosedLoop);
+        // always due, regardless of `now`, and never carries a scheduled
+        // time (so no queueing delay to report)
+        assert_eq!(workload.arrival(0), Arrival::Due);
+        assert_eq!(workload.arrival(1_000), Arrival::Due);
+    }
+
+    #[test]
+    fn fixed_arrival_process() {
+        let interval_ms = 100;
+        let mut workload =
+            gen_workload(10, ArrivalProcess::Fixed { interval_ms });
+
+        // first arrival is immediately due, scheduled at `now`
+        assert_eq!(workload.arrival(0), Arrival::DueAt(0));
+        // too early for the next one
+        assert_eq!(workload.arrival(50), Arrival::NotDue);
+        // right on schedule
+        assert_eq!(workload.arrival(100), Arrival::DueAt(100));
+        // late is still due, at the time it was scheduled for
+        assert_eq!(workload.arrival(250), Arrival::DueAt(200));
+    }
+
+    #[test]
+    fn poisson_arrival_process_converges_to_target_rate() {
+        let rate_per_sec = 100.0;
+        let mut workload =
+            gen_workload(100_000, ArrivalProcess::Poisson { rate_per_sec });
+
+        // drive the schedule far enough into the future that every command
+        // ends up due, then check that the achieved rate is close to the
+        // target (it's randomly sampled, so allow some slack)
+        let mut due_count = 0;
+        let mut now = 0;
+        while due_count < 10_000 {
+            if let Arrival::DueAt(_) = workload.arrival(now) {
+                due_count += 1;
+            }
+            now += 1;
+        }
+        let achieved_rate_per_sec = (due_count * 1000) as f64 / now as f64;
+        let relative_error =
+            (achieved_rate_per_sec - rate_per_sec).abs() / rate_per_sec;
+        assert!(
+            relative_error < 0.1,
+            "achieved rate {} too far from target {}",
+            achieved_rate_per_sec,
+            rate_per_sec
+        );
+    }
 }
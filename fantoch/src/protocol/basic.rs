@@ -7,19 +7,35 @@ use crate::protocol::{
     Protocol, ProtocolMetrics,
 };
 use crate::time::SysTime;
-use crate::HashSet;
+use crate::util;
 use crate::{log, singleton};
+use crate::{HashMap, HashSet};
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::time::Duration;
+use subtle::ConstantTimeEq;
 use threshold::VClock;
 use tracing::instrument;
 
 type ExecutionInfo = <BasicExecutor as Executor>::ExecutionInfo;
 
+/// Every `GC_FULL_SYNC_EVERY` gossip rounds, a full committed clock is sent
+/// to a gossip target instead of just the delta accumulated since the last
+/// round, so a peer that missed (or never received) a delta -- e.g. because
+/// it just restarted -- can still resynchronize.
+const GC_FULL_SYNC_EVERY: usize = 10;
+
+/// Maximum number of `(Dot, Command)` pairs sent in a single `MRecoverChunk`,
+/// so that recovering a process that's missing a lot of commands doesn't
+/// produce one unbounded message.
+const RECOVER_BATCH_SIZE: usize = 100;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Basic {
     bp: BaseProcess,
     cmds: CommandsInfo<BasicInfo>,
+    gc_gossip: GcGossip,
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<ExecutionInfo>,
 }
@@ -61,16 +77,20 @@ impl Protocol for Basic {
         let protocol = Self {
             bp,
             cmds,
+            gc_gossip: GcGossip::default(),
             to_processes,
             to_executors,
         };
 
         // create periodic events
-        let events = if let Some(interval) = config.gc_interval() {
+        let mut events = if let Some(interval) = config.gc_interval() {
             vec![(PeriodicEvent::GarbageCollection, interval)]
         } else {
             vec![]
         };
+        if let Some(interval) = config.recovery_interval() {
+            events.push((PeriodicEvent::Recovery, interval));
+        }
 
         // return both
         (protocol, events)
@@ -97,25 +117,48 @@ impl Protocol for Basic {
         self.handle_submit(dot, cmd);
     }
 
-    /// Handles protocol messages.
+    /// Handles protocol messages. If message authentication is configured
+    /// (see `Config::message_auth_key`), `msg` is verified first and simply
+    /// dropped (after being metered as `RejectedMessages`) if the tag is
+    /// missing or doesn't match -- so a forged `from` can no longer force
+    /// execution of a fabricated command.
     fn handle(
         &mut self,
         from: ProcessId,
-        _from_shard_id: ShardId,
+        from_shard_id: ShardId,
         msg: Self::Message,
         _time: &dyn SysTime,
     ) {
-        match msg {
-            Message::MStore { dot, cmd } => self.handle_mstore(from, dot, cmd),
-            Message::MStoreAck { dot } => self.handle_mstoreack(from, dot),
-            Message::MCommit { dot, cmd } => {
+        let body = match self.verify(from, from_shard_id, msg) {
+            Some(body) => body,
+            None => return,
+        };
+        match body {
+            MessageBody::MStore { dot, cmd } => {
+                self.handle_mstore(from, dot, cmd)
+            }
+            MessageBody::MStoreAck { dot } => self.handle_mstoreack(from, dot),
+            MessageBody::MCommit { dot, cmd } => {
                 self.handle_mcommit(from, dot, cmd)
             }
-            Message::MCommitDot { dot } => self.handle_mcommit_dot(from, dot),
-            Message::MGarbageCollection { committed } => {
+            MessageBody::MCommitDot { dot } => {
+                self.handle_mcommit_dot(from, dot)
+            }
+            MessageBody::MGarbageCollection { committed } => {
                 self.handle_mgc(from, committed)
             }
-            Message::MStable { stable } => self.handle_mstable(from, stable),
+            MessageBody::MGcSync { delta } => self.handle_mgcsync(from, delta),
+            MessageBody::MStable { stable } => {
+                self.handle_mstable(from, stable)
+            }
+            MessageBody::MRecoverRequest { since } => {
+                self.handle_mrecover_request(from, since)
+            }
+            MessageBody::MRecoverChunk {
+                entries,
+                stable_frontier,
+                last,
+            } => self.handle_mrecover_chunk(from, entries, stable_frontier, last),
         }
     }
 
@@ -129,6 +172,7 @@ impl Protocol for Basic {
             PeriodicEvent::GarbageCollection => {
                 self.handle_event_garbage_collection()
             }
+            PeriodicEvent::Recovery => self.handle_event_recovery(),
         }
     }
 
@@ -163,7 +207,7 @@ impl Basic {
         let dot = dot.unwrap_or_else(|| self.bp.next_dot());
 
         // create `MStore` and target
-        let mstore = Message::MStore { dot, cmd };
+        let mstore = self.sign(MessageBody::MStore { dot, cmd });
         let target = self.bp.fast_quorum();
 
         // save new action
@@ -184,7 +228,7 @@ impl Basic {
         info.cmd = Some(cmd);
 
         // create `MStoreAck` and target
-        let mstoreack = Message::MStoreAck { dot };
+        let mstoreack = self.sign(MessageBody::MStoreAck { dot });
         let target = singleton![from];
 
         // save new action
@@ -206,10 +250,10 @@ impl Basic {
 
         // check if we have all necessary replies
         if info.acks.len() == self.bp.config.basic_quorum_size() {
-            let mcommit = Message::MCommit {
+            let mcommit = self.sign(MessageBody::MCommit {
                 dot,
                 cmd: info.cmd.clone().expect("command should exist"),
-            };
+            });
             let target = self.bp.all();
 
             // save new action
@@ -241,9 +285,8 @@ impl Basic {
 
         if self.gc_running() {
             // notify self with the committed dot
-            self.to_processes.push(Action::ToForward {
-                msg: Message::MCommitDot { dot },
-            });
+            let msg = self.sign(MessageBody::MCommitDot { dot });
+            self.to_processes.push(Action::ToForward { msg });
         } else {
             // if we're not running gc, remove the dot info now
             self.cmds.gc_single(dot);
@@ -270,9 +313,8 @@ impl Basic {
         let stable = self.cmds.stable();
         // create `ToForward` to self
         if !stable.is_empty() {
-            self.to_processes.push(Action::ToForward {
-                msg: Message::MStable { stable },
-            })
+            let msg = self.sign(MessageBody::MStable { stable });
+            self.to_processes.push(Action::ToForward { msg })
         }
     }
 
@@ -292,19 +334,356 @@ impl Basic {
     fn handle_event_garbage_collection(&mut self) {
         log!("p{}: PeriodicEvent::GarbageCollection", self.id());
 
+        if self.gc_gossip_mode() {
+            self.handle_event_gc_gossip();
+            return;
+        }
+
         // retrieve the committed clock
         let committed = self.cmds.committed();
 
         // save new action
-        self.to_processes.push(Action::ToSend {
-            target: self.bp.all_but_me(),
-            msg: Message::MGarbageCollection { committed },
-        });
+        let target = self.bp.all_but_me();
+        let msg = self.sign(MessageBody::MGarbageCollection { committed });
+        self.to_processes.push(Action::ToSend { target, msg });
+    }
+
+    /// Picks a handful of peers and gossips the commands committed since the
+    /// last round we exchanged with each of them, rather than broadcasting
+    /// the full committed clock to everyone.
+    #[instrument(skip(self))]
+    fn handle_event_gc_gossip(&mut self) {
+        let peers: Vec<ProcessId> = self.bp.all_but_me().into_iter().collect();
+        if peers.is_empty() {
+            return;
+        }
+
+        let fanout = self.bp.config.gc_gossip_fanout().max(1);
+        let mut rng = rand::thread_rng();
+        let targets: Vec<ProcessId> = peers
+            .choose_multiple(&mut rng, fanout)
+            .copied()
+            .collect();
+
+        let committed = self.cmds.committed();
+        self.gc_gossip.round += 1;
+        let full_sync = self.gc_gossip.round % GC_FULL_SYNC_EVERY == 0;
+
+        for to in targets {
+            let delta = if full_sync {
+                GcDelta::Full(committed.clone())
+            } else {
+                let previous = self
+                    .gc_gossip
+                    .sent
+                    .get(&to)
+                    .cloned()
+                    .unwrap_or_else(|| self.bottom_clock());
+                let ranges = Self::clock_delta(&previous, &committed);
+                if ranges.is_empty() {
+                    // `to` is already up to date; nothing to gossip
+                    continue;
+                }
+                GcDelta::Delta(ranges)
+            };
+
+            self.gc_gossip.sent.insert(to, committed.clone());
+            let msg = self.sign(MessageBody::MGcSync { delta });
+            self.to_processes.push(Action::ToSend {
+                target: singleton![to],
+                msg,
+            });
+        }
+    }
+
+    #[instrument(skip(self, from, delta))]
+    fn handle_mgcsync(&mut self, from: ProcessId, delta: GcDelta) {
+        log!("p{}: MGcSync({:?}) from {}", self.id(), delta, from);
+
+        // merge what `from` just told us into our view of its committed
+        // clock; `committed_by` joins this in, so repeated or out-of-order
+        // deltas (and a full-clock fallback on top of prior deltas) are
+        // idempotent and safe
+        let reported = match &delta {
+            GcDelta::Full(clock) => clock.clone(),
+            GcDelta::Delta(ranges) => self.clock_from_delta(ranges),
+        };
+        self.cmds.committed_by(from, reported);
+
+        // we might now know enough to advance stability
+        let stable = self.cmds.stable();
+        if !stable.is_empty() {
+            let msg = self.sign(MessageBody::MStable { stable });
+            self.to_processes.push(Action::ToForward { msg });
+        }
+
+        // pull-back: if we're strictly ahead of what `from` last heard from
+        // us, reply with our own delta right away instead of waiting for
+        // our next gossip tick to (maybe) pick `from` again
+        let committed = self.cmds.committed();
+        let previously_sent = self
+            .gc_gossip
+            .sent
+            .get(&from)
+            .cloned()
+            .unwrap_or_else(|| self.bottom_clock());
+        let ahead = Self::clock_delta(&previously_sent, &committed);
+        if !ahead.is_empty() {
+            self.gc_gossip.sent.insert(from, committed);
+            let msg = self.sign(MessageBody::MGcSync {
+                delta: GcDelta::Delta(ahead),
+            });
+            self.to_processes.push(Action::ToSend {
+                target: singleton![from],
+                msg,
+            });
+        }
+    }
+
+    /// Computes the dots committed in `current` but not yet in `previous`,
+    /// represented the same way as `CommandsInfo::stable`'s output: a list
+    /// of `(process_id, start, end)` ranges.
+    fn clock_delta(
+        previous: &VClock<ProcessId>,
+        current: &VClock<ProcessId>,
+    ) -> Vec<(ProcessId, u64, u64)> {
+        current
+            .iter()
+            .filter_map(|(process_id, seq)| {
+                let previous_seq = previous
+                    .get(process_id)
+                    .map(|seq| seq.frontier())
+                    .unwrap_or(0);
+                let end = seq.frontier();
+                if previous_seq >= end {
+                    None
+                } else {
+                    Some((*process_id, previous_seq + 1, end))
+                }
+            })
+            .collect()
+    }
+
+    /// Rebuilds a clock out of a list of `(process_id, start, end)` ranges,
+    /// as received in a `GcDelta::Delta`.
+    fn clock_from_delta(
+        &self,
+        ranges: &[(ProcessId, u64, u64)],
+    ) -> VClock<ProcessId> {
+        let mut clock = self.bottom_clock();
+        for &(process_id, _start, end) in ranges {
+            clock.add(&process_id, end);
+        }
+        clock
+    }
+
+    fn bottom_clock(&self) -> VClock<ProcessId> {
+        VClock::with(util::process_ids(self.bp.shard_id, self.bp.config.n()))
     }
 
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
+
+    fn gc_gossip_mode(&self) -> bool {
+        self.bp.config.gc_gossip_mode()
+    }
+
+    /// Picks a random peer and asks it for any commands we're missing,
+    /// relative to our own committed clock -- this is how a process that
+    /// just restarted (and thus knows nothing) or fell behind catches back
+    /// up, rather than waiting on GC gossip to eventually re-propagate
+    /// commands it never stored in the first place.
+    #[instrument(skip(self))]
+    fn handle_event_recovery(&mut self) {
+        log!("p{}: PeriodicEvent::Recovery", self.id());
+
+        let peers: Vec<ProcessId> = self.bp.all_but_me().into_iter().collect();
+        let mut rng = rand::thread_rng();
+        if let Some(&target) = peers.choose(&mut rng) {
+            let since = self.cmds.committed();
+            let msg = self.sign(MessageBody::MRecoverRequest { since });
+            self.to_processes.push(Action::ToSend {
+                target: singleton![target],
+                msg,
+            });
+        }
+    }
+
+    /// Serves a recovery request from `from`: streams back, in bounded
+    /// chunks, every command we have that `from` is missing (per its
+    /// reported `since` clock), together with our own stable frontier so
+    /// `from` can learn what's already safe to collect without having to
+    /// rediscover it through further gossip rounds.
+    #[instrument(skip(self, from, since))]
+    fn handle_mrecover_request(
+        &mut self,
+        from: ProcessId,
+        since: VClock<ProcessId>,
+    ) {
+        log!("p{}: MRecoverRequest({:?}) from {}", self.id(), since, from);
+
+        let mut missing: Vec<(Dot, Command)> = self
+            .cmds
+            .missing_since(&since)
+            .filter_map(|(dot, info)| info.cmd.clone().map(|cmd| (dot, cmd)))
+            .collect();
+        missing.sort_by_key(|(dot, _)| (dot.source(), dot.sequence()));
+
+        let stable_frontier = self.cmds.stable_frontier();
+        let mut chunks = missing.chunks(RECOVER_BATCH_SIZE).peekable();
+
+        if chunks.peek().is_none() {
+            // nothing missing: still reply so `from` learns our stable
+            // frontier and knows the recovery round is done
+            let msg = self.sign(MessageBody::MRecoverChunk {
+                entries: Vec::new(),
+                stable_frontier,
+                last: true,
+            });
+            self.to_processes.push(Action::ToSend {
+                target: singleton![from],
+                msg,
+            });
+            return;
+        }
+
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            let msg = self.sign(MessageBody::MRecoverChunk {
+                entries: chunk.to_vec(),
+                stable_frontier: stable_frontier.clone(),
+                last,
+            });
+            self.to_processes.push(Action::ToSend {
+                target: singleton![from],
+                msg,
+            });
+        }
+    }
+
+    /// Handles a batch of recovered commands from `from`: replays each one
+    /// through the normal commit path (so it's executed and, if GC is
+    /// running, properly tracked towards stability, exactly like a locally-
+    /// observed `MCommit` would be), then folds in the peer's reported
+    /// stable frontier so we don't have to rediscover stability for dots
+    /// it's already telling us about.
+    #[instrument(skip(self, from, entries, stable_frontier, last))]
+    fn handle_mrecover_chunk(
+        &mut self,
+        from: ProcessId,
+        entries: Vec<(Dot, Command)>,
+        stable_frontier: VClock<ProcessId>,
+        last: bool,
+    ) {
+        log!(
+            "p{}: MRecoverChunk({} entries, last = {}) from {}",
+            self.id(),
+            entries.len(),
+            last,
+            from
+        );
+
+        for (dot, cmd) in entries {
+            self.handle_mcommit(from, dot, cmd);
+        }
+
+        self.cmds.committed_by(from, stable_frontier);
+        let stable = self.cmds.stable();
+        if !stable.is_empty() {
+            let msg = self.sign(MessageBody::MStable { stable });
+            self.to_processes.push(Action::ToForward { msg });
+        }
+    }
+
+    /// Wraps `body` for sending, attaching a MAC over `(self.id(),
+    /// self.shard_id(), body)` when message authentication is configured
+    /// (`Config::message_auth_key`); left untagged otherwise, so deployments
+    /// (and simulation tests, e.g. `basic_flow`) that don't configure a key
+    /// pay no overhead and keep working unauthenticated.
+    fn sign(&self, body: MessageBody) -> Message {
+        let tag = self
+            .bp
+            .config
+            .message_auth_key()
+            .map(|key| Self::mac(&key, self.id(), self.bp.shard_id, &body));
+        Message { body, tag }
+    }
+
+    /// Verifies `msg` against `Config::message_auth_key`, returning its body
+    /// if authentication isn't configured or the tag matches, `None` (after
+    /// metering a `RejectedMessages` rejection) if a key is configured and
+    /// the tag is missing or doesn't match. The comparison runs in constant
+    /// time (`subtle::ConstantTimeEq`), not the short-circuiting `==` a
+    /// derived `PartialEq` would use, since timing how quickly a forged tag
+    /// is rejected would otherwise leak how many of its bytes were correct.
+    fn verify(
+        &mut self,
+        from: ProcessId,
+        shard_id: ShardId,
+        msg: Message,
+    ) -> Option<MessageBody> {
+        let key = match self.bp.config.message_auth_key() {
+            Some(key) => key,
+            None => return Some(msg.body),
+        };
+        let expected = Self::mac(&key, from, shard_id, &msg.body);
+        let tag_matches = match &msg.tag {
+            Some(tag) => bool::from(tag.ct_eq(&expected)),
+            None => false,
+        };
+        if tag_matches {
+            Some(msg.body)
+        } else {
+            log!(
+                "p{}: rejecting message from {} with {} tag",
+                self.id(),
+                from,
+                if msg.tag.is_some() { "mismatched" } else { "missing" }
+            );
+            self.bp.reject_message();
+            None
+        }
+    }
+
+    /// Computes the MAC of `(from, shard_id, body)` under `key`.
+    fn mac(
+        key: &[u8],
+        from: ProcessId,
+        shard_id: ShardId,
+        body: &MessageBody,
+    ) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(
+            bincode::serialize(&(from, shard_id))
+                .expect("serialize should work"),
+        );
+        hasher
+            .update(bincode::serialize(body).expect("serialize should work"));
+        let mut tag = [0u8; 32];
+        tag.copy_from_slice(&hasher.finalize());
+        tag
+    }
+}
+
+/// Per-peer state for the epidemic/anti-entropy GC dissemination mode: for
+/// each peer, the committed-clock frontier we last *sent* it, so that later
+/// rounds only need to carry the delta accumulated since then.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct GcGossip {
+    sent: HashMap<ProcessId, VClock<ProcessId>>,
+    round: usize,
+}
+
+/// The payload of an `MGcSync` gossip round: either the commands committed
+/// since the last round we gossiped with this peer (the common case), or
+/// this process's entire committed clock, sent periodically as a fallback
+/// -- see `GC_FULL_SYNC_EVERY`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GcDelta {
+    Delta(Vec<(ProcessId, u64, u64)>),
+    Full(VClock<ProcessId>),
 }
 
 // `BasicInfo` contains all information required in the life-cyle of a
@@ -333,13 +712,30 @@ impl Info for BasicInfo {
 
 // `Basic` protocol messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub enum Message {
+pub enum MessageBody {
     MStore { dot: Dot, cmd: Command },
     MStoreAck { dot: Dot },
     MCommit { dot: Dot, cmd: Command },
     MCommitDot { dot: Dot },
     MGarbageCollection { committed: VClock<ProcessId> },
+    MGcSync { delta: GcDelta },
     MStable { stable: Vec<(ProcessId, u64, u64)> },
+    MRecoverRequest { since: VClock<ProcessId> },
+    MRecoverChunk {
+        entries: Vec<(Dot, Command)>,
+        stable_frontier: VClock<ProcessId>,
+        last: bool,
+    },
+}
+
+/// What's actually exchanged between processes: a `MessageBody` plus an
+/// optional MAC over `(from, shard_id, body)`. The tag is `None` whenever
+/// message authentication isn't configured (`Config::message_auth_key`),
+/// which is the default -- see `Basic::sign`/`Basic::verify`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Message {
+    body: MessageBody,
+    tag: Option<[u8; 32]>,
 }
 
 impl MessageIndex for Message {
@@ -347,17 +743,33 @@ impl MessageIndex for Message {
         use crate::run::{
             worker_dot_index_shift, worker_index_no_shift, GC_WORKER_INDEX,
         };
-        match self {
+        match &self.body {
             // Protocol messages
-            Self::MStore { dot, .. } => worker_dot_index_shift(&dot),
-            Self::MStoreAck { dot, .. } => worker_dot_index_shift(&dot),
-            Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
+            MessageBody::MStore { dot, .. } => worker_dot_index_shift(&dot),
+            MessageBody::MStoreAck { dot, .. } => {
+                worker_dot_index_shift(&dot)
+            }
+            MessageBody::MCommit { dot, .. } => worker_dot_index_shift(&dot),
             // GC messages
-            Self::MCommitDot { .. } => worker_index_no_shift(GC_WORKER_INDEX),
-            Self::MGarbageCollection { .. } => {
+            MessageBody::MCommitDot { .. } => {
+                worker_index_no_shift(GC_WORKER_INDEX)
+            }
+            MessageBody::MGarbageCollection { .. } => {
+                worker_index_no_shift(GC_WORKER_INDEX)
+            }
+            MessageBody::MGcSync { .. } => {
+                worker_index_no_shift(GC_WORKER_INDEX)
+            }
+            MessageBody::MStable { .. } => None,
+            // recovery messages: routed like other GC-adjacent control
+            // messages, since they end up feeding the same GC/stability
+            // machinery
+            MessageBody::MRecoverRequest { .. } => {
+                worker_index_no_shift(GC_WORKER_INDEX)
+            }
+            MessageBody::MRecoverChunk { .. } => {
                 worker_index_no_shift(GC_WORKER_INDEX)
             }
-            Self::MStable { .. } => None,
         }
     }
 }
@@ -365,6 +777,7 @@ impl MessageIndex for Message {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
+    Recovery,
 }
 
 impl PeriodicEventIndex for PeriodicEvent {
@@ -372,6 +785,7 @@ impl PeriodicEventIndex for PeriodicEvent {
         use crate::run::{worker_index_no_shift, GC_WORKER_INDEX};
         match self {
             Self::GarbageCollection => worker_index_no_shift(GC_WORKER_INDEX),
+            Self::Recovery => worker_index_no_shift(GC_WORKER_INDEX),
         }
     }
 }
@@ -384,6 +798,139 @@ mod tests {
     use crate::sim::Simulation;
     use crate::time::SimTime;
     use crate::util;
+    use threshold::MaxSet;
+
+    fn vclock(entries: Vec<(ProcessId, u64)>) -> VClock<ProcessId> {
+        VClock::from(
+            entries
+                .into_iter()
+                .map(|(process_id, seq)| (process_id, MaxSet::from(seq)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn clock_delta_and_from_delta_roundtrip() {
+        let previous = vclock(vec![(1, 2), (2, 0)]);
+        let current = vclock(vec![(1, 5), (2, 1)]);
+
+        let delta = Basic::clock_delta(&previous, &current);
+        let mut sorted = delta.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![(1, 3, 5), (2, 1, 1)]);
+
+        // nothing new: the delta against itself is empty
+        assert!(Basic::clock_delta(&current, &current).is_empty());
+
+        // rebuilding a clock from the delta (joined on top of `previous`)
+        // reconstructs `current`
+        let (basic, _) = Basic::new(1, 0, Config::new(2, 0));
+        let mut rebuilt = previous.clone();
+        rebuilt.join(&basic.clock_from_delta(&delta));
+        assert_eq!(rebuilt, current);
+    }
+
+    #[test]
+    fn recovery_replays_missing_commands() {
+        use crate::id::Rifl;
+        use crate::kvs::KVOp;
+
+        let n = 3;
+        let f = 1;
+        let shard_id = 0;
+        let config = Config::new(n, f);
+
+        let (mut basic_1, _) = Basic::new(1, shard_id, config);
+        let (mut basic_2, _) = Basic::new(2, shard_id, config);
+        let processes = vec![(1, shard_id), (2, shard_id), (3, shard_id)];
+        basic_1.discover(processes.clone());
+        basic_2.discover(processes);
+
+        // process 1 holds a command that process 2 has never seen
+        let mut ops = HashMap::new();
+        let mut shard_ops = HashMap::new();
+        shard_ops.insert("foo".to_string(), KVOp::Get);
+        ops.insert(shard_id, shard_ops);
+        let cmd = Command::new(Rifl::new(1, 1), ops);
+        let dot = Dot::new(1, 1);
+        basic_1.cmds.get(dot).cmd = Some(cmd);
+
+        // process 2 asks process 1 to catch it up from scratch
+        basic_1.handle_mrecover_request(2, basic_1.bottom_clock());
+        let (target, msg) = match basic_1
+            .to_processes()
+            .expect("there should be a recovery chunk")
+        {
+            Action::ToSend { target, msg } => (target, msg),
+            Action::ToForward { .. } => panic!("expected a ToSend action"),
+        };
+        assert!(target.contains(&2));
+        let (entries, stable_frontier, last) = match msg.body {
+            MessageBody::MRecoverChunk {
+                entries,
+                stable_frontier,
+                last,
+            } => (entries, stable_frontier, last),
+            _ => panic!("expected an MRecoverChunk message"),
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, dot);
+        assert!(last);
+
+        // replaying the chunk on process 2 runs the recovered command
+        // through the normal commit path, so it reaches the executor just
+        // like a locally-observed `MCommit` would
+        basic_2.handle_mrecover_chunk(1, entries, stable_frontier, last);
+        assert!(basic_2.to_executors().is_some());
+    }
+
+    #[test]
+    fn authenticated_commit_rejects_tampering() {
+        use crate::id::Rifl;
+        use crate::kvs::KVOp;
+
+        let n = 3;
+        let f = 1;
+        let shard_id = 0;
+        let mut config = Config::new(n, f);
+        // commands with a single op on this shard, so that an accepted
+        // `MCommit` produces execution info
+        let ops = |key: &str| {
+            let mut shard_ops = HashMap::new();
+            shard_ops.insert(key.to_string(), KVOp::Get);
+            let mut ops = HashMap::new();
+            ops.insert(shard_id, shard_ops);
+            ops
+        };
+        config.set_message_auth_key(b"shared-secret".to_vec());
+
+        let (mut basic_1, _) = Basic::new(1, shard_id, config);
+        let (mut basic_2, _) = Basic::new(2, shard_id, config);
+        let processes = vec![(1, shard_id), (2, shard_id), (3, shard_id)];
+        basic_1.discover(processes.clone());
+        basic_2.discover(processes);
+
+        let dot = Dot::new(1, 1);
+        let cmd = Command::new(Rifl::new(1, 1), ops("foo"));
+        let time = SimTime::new();
+
+        // a correctly tagged MCommit is accepted and executed
+        let msg =
+            basic_1.sign(MessageBody::MCommit { dot, cmd: cmd.clone() });
+        basic_2.handle(1, shard_id, msg.clone(), &time);
+        assert!(basic_2.to_executors().is_some());
+
+        // swapping in a different command after signing invalidates the
+        // tag, so the forged MCommit is rejected before it ever reaches
+        // `handle_mcommit`
+        let forged_cmd = Command::new(Rifl::new(1, 2), ops("bar"));
+        let tampered = Message {
+            body: MessageBody::MCommit { dot, cmd: forged_cmd },
+            tag: msg.tag,
+        };
+        basic_2.handle(1, shard_id, tampered, &time);
+        assert!(basic_2.to_executors().is_none());
+    }
 
     #[test]
     fn basic_flow() {
@@ -544,7 +1091,8 @@ mod tests {
         let to_sends = simulation.forward_to_processes(mcommit);
 
         // check the MCommitDot
-        let check_msg = |msg: &Message| matches!(msg, Message::MCommitDot {..});
+        let check_msg =
+            |msg: &Message| matches!(msg.body, MessageBody::MCommitDot {..});
         assert!(to_sends.into_iter().all(|(_, action)| {
             matches!(action, Action::ToForward { msg } if check_msg(&msg))
         }));
@@ -583,7 +1131,9 @@ mod tests {
         // there's a single action
         assert_eq!(actions.len(), 1);
         let mstore = actions.pop().unwrap();
-        let check_msg = |msg: &Message| matches!(msg, Message::MStore {dot, ..} if dot == &Dot::new(process_id_1, 2));
+        let check_msg = |msg: &Message| {
+            matches!(msg.body, MessageBody::MStore {dot, ..} if dot == Dot::new(process_id_1, 2))
+        };
         assert!(matches!(mstore, Action::ToSend {msg, ..} if check_msg(&msg)));
     }
 }
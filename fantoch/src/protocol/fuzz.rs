@@ -0,0 +1,254 @@
+// This module implements a randomized interleaving fuzz/conformance harness
+// for any `Protocol` implementation: given a `Config` and a seed, it spins up
+// `n` instances, then repeatedly chooses - pseudo-randomly, so a failing run
+// is reproducible by rerunning with the same `(config, seed)` - to submit a
+// fresh client command, or to deliver/duplicate/drop one of the messages
+// already in flight (a message is only ever delivered to the process it
+// targets; which queued message gets delivered is picked at random, so
+// messages get reordered). Periodic events are not fired: the ones returned
+// by `Protocol::new` are discarded.
+//
+// It doesn't know what protocol-specific properties (fast-path membership,
+// leader stability, ...) should hold. The only invariant it checks is that
+// no process is handed the same `ExecutionInfo` twice; the relative order in
+// which different processes execute commands and gc stability are not
+// checked.
+//
+// A violation is reported together with the full schedule that produced it,
+// which `fuzz_protocol` prints.
+
+use crate::command::Command;
+use crate::config::Config;
+use crate::executor::Executor;
+use crate::id::{ProcessId, Rifl, ShardId};
+use crate::protocol::{Action, Protocol};
+use crate::time::RunTime;
+use crate::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+/// A message in flight between two processes, kept around (rather than
+/// delivered immediately) so the schedule can reorder, duplicate, or drop it
+/// relative to its queue-mates.
+struct InFlight<M> {
+    from: ProcessId,
+    msg: M,
+}
+
+/// Everything a fuzz run needs to track per process: the protocol instance
+/// itself, and every `ExecutionInfo` it has ever been handed, used to check
+/// the no-double-execution invariant.
+struct Tracked<P: Protocol> {
+    process: P,
+    executed: Vec<<P::Executor as Executor>::ExecutionInfo>,
+}
+
+/// What the fuzzer did on one round, recorded so a failing run's schedule
+/// can be printed and replayed.
+#[derive(Debug, Clone)]
+pub enum Step {
+    Submit { at: ProcessId, rifl: Rifl },
+    Deliver { to: ProcessId, from: ProcessId },
+    Duplicate { to: ProcessId, from: ProcessId },
+    Drop { to: ProcessId, from: ProcessId },
+}
+
+/// The outcome of one fuzz run.
+#[derive(Debug)]
+pub struct FuzzResult {
+    pub seed: u64,
+    pub rounds_run: usize,
+    /// `Some(reason)` if an invariant was violated.
+    pub violation: Option<String>,
+    /// Every scheduling decision made, in order; when `violation` is set,
+    /// this is exactly what produced it and can be printed for a bug report.
+    pub schedule: Vec<Step>,
+}
+
+/// Runs one fuzz schedule of up to `max_rounds` scheduling decisions against
+/// `config.n()` instances of `P`, seeded by `seed`.
+pub fn fuzz<P>(config: Config, seed: u64, max_rounds: usize) -> FuzzResult
+where
+    P: Protocol,
+    <P::Executor as Executor>::ExecutionInfo: Clone + Debug + PartialEq,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let time = RunTime;
+    let shard_id: ShardId = 0;
+
+    let n = config.n() as u64;
+    let process_ids: Vec<ProcessId> = (1..=n as ProcessId).collect();
+    let all: Vec<(ProcessId, ShardId)> = process_ids.iter().map(|&id| (id, shard_id)).collect();
+
+    let mut tracked: HashMap<ProcessId, Tracked<P>> = HashMap::new();
+    for &process_id in &process_ids {
+        let (mut process, _events) = P::new(process_id, shard_id, config);
+        process.discover(all.clone());
+        tracked.insert(
+            process_id,
+            Tracked {
+                process,
+                executed: Vec::new(),
+            },
+        );
+    }
+
+    // one queue of not-yet-delivered messages per destination process
+    let mut inboxes: HashMap<ProcessId, VecDeque<InFlight<P::Message>>> = process_ids
+        .iter()
+        .map(|&id| (id, VecDeque::new()))
+        .collect();
+
+    let mut violation = None;
+    let mut schedule = Vec::new();
+    let mut next_seq: u64 = 1;
+    let mut rounds_run = 0;
+
+    'fuzz: for round in 0..max_rounds {
+        rounds_run = round + 1;
+
+        // drain whatever the previous round's submit/deliver produced into
+        // the destination inboxes before making this round's choice, so a
+        // freshly enqueued message is eligible to be picked this round too
+        for &process_id in &process_ids {
+            let actions: Vec<_> = {
+                let entry = tracked.get_mut(&process_id).unwrap();
+                let mut actions = Vec::new();
+                while let Some(action) = entry.process.to_processes() {
+                    actions.push(action);
+                }
+                actions
+            };
+            for action in actions {
+                match action {
+                    Action::ToSend { target, msg } => {
+                        for to in target {
+                            inboxes
+                                .entry(to)
+                                .or_insert_with(VecDeque::new)
+                                .push_back(InFlight {
+                                    from: process_id,
+                                    msg: msg.clone(),
+                                });
+                        }
+                    }
+                    Action::ToForward { msg } => {
+                        inboxes
+                            .entry(process_id)
+                            .or_insert_with(VecDeque::new)
+                            .push_back(InFlight {
+                                from: process_id,
+                                msg,
+                            });
+                    }
+                }
+            }
+
+            let entry = tracked.get_mut(&process_id).unwrap();
+            while let Some(execution_info) = entry.process.to_executors() {
+                if let Some(reason) = check_and_record(process_id, execution_info, &mut tracked) {
+                    violation = Some(reason);
+                    break 'fuzz;
+                }
+            }
+        }
+
+        // 30% of the time: submit a fresh client command to a random
+        // process; otherwise: act on a random non-empty inbox
+        let non_empty: Vec<ProcessId> = process_ids
+            .iter()
+            .copied()
+            .filter(|id| !inboxes.get(id).map_or(true, |q| q.is_empty()))
+            .collect();
+
+        if non_empty.is_empty() || rng.gen_bool(0.3) {
+            let process_id = process_ids[rng.gen_range(0, process_ids.len())];
+            let rifl = Rifl::new(process_id as u64, next_seq);
+            next_seq += 1;
+            let key = format!("key{}", rng.gen_range(0, 4));
+            let cmd = Command::put(rifl, key, String::new());
+            let entry = tracked.get_mut(&process_id).unwrap();
+            entry.process.submit(None, cmd, &time);
+            schedule.push(Step::Submit {
+                at: process_id,
+                rifl,
+            });
+        } else {
+            let to = non_empty[rng.gen_range(0, non_empty.len())];
+            let queue = inboxes.get_mut(&to).unwrap();
+            // 70% deliver-and-remove, 15% duplicate (deliver but keep a
+            // copy), 15% drop (remove without delivering)
+            let choice = rng.gen_range(0, 100);
+            if choice < 15 {
+                if let Some(in_flight) = queue.pop_front() {
+                    schedule.push(Step::Drop {
+                        to,
+                        from: in_flight.from,
+                    });
+                }
+            } else {
+                let idx = rng.gen_range(0, queue.len());
+                let deliver_only = choice < 85;
+                let in_flight = if deliver_only {
+                    queue.remove(idx).unwrap()
+                } else {
+                    let InFlight { from, msg } = &queue[idx];
+                    InFlight {
+                        from: *from,
+                        msg: msg.clone(),
+                    }
+                };
+                schedule.push(if deliver_only {
+                    Step::Deliver {
+                        to,
+                        from: in_flight.from,
+                    }
+                } else {
+                    Step::Duplicate {
+                        to,
+                        from: in_flight.from,
+                    }
+                });
+                let entry = tracked.get_mut(&to).unwrap();
+                entry
+                    .process
+                    .handle(in_flight.from, shard_id, in_flight.msg, &time);
+            }
+        }
+    }
+
+    FuzzResult {
+        seed,
+        rounds_run,
+        violation,
+        schedule,
+    }
+}
+
+/// Records `execution_info` as delivered to `process_id`, returning
+/// `Some(reason)` if that process was already handed an equal
+/// `ExecutionInfo`. Other processes' histories are not consulted.
+fn check_and_record<P>(
+    process_id: ProcessId,
+    execution_info: <P::Executor as Executor>::ExecutionInfo,
+    tracked: &mut HashMap<ProcessId, Tracked<P>>,
+) -> Option<String>
+where
+    P: Protocol,
+    <P::Executor as Executor>::ExecutionInfo: Clone + Debug + PartialEq,
+{
+    {
+        let entry = tracked.get(&process_id).unwrap();
+        if entry.executed.contains(&execution_info) {
+            return Some(format!(
+                "process {} executed the same command twice: {:?}",
+                process_id, execution_info
+            ));
+        }
+    }
+    let entry = tracked.get_mut(&process_id).unwrap();
+    entry.executed.push(execution_info);
+    None
+}
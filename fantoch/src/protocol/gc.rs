@@ -2,6 +2,8 @@ use crate::id::{Dot, ProcessId, ShardId};
 use crate::trace;
 use crate::util;
 use crate::HashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use threshold::{AEClock, EventSet, VClock};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,6 +11,11 @@ pub struct GCTrack {
     process_id: ProcessId,
     shard_id: ShardId,
     n: usize,
+    // stability threshold: a dot is considered stable once at least `t`
+    // processes (out of `n`) are known to have recorded it; `t = n` recovers
+    // the original all-must-agree behavior, while `t = n - f` tolerates `f`
+    // slow/crashed processes
+    t: usize,
     // the next 3 variables will be updated by the single process responsible
     // for GC
     my_clock: AEClock<ProcessId>,
@@ -17,14 +24,25 @@ pub struct GCTrack {
 }
 
 impl GCTrack {
-    pub fn new(process_id: ProcessId, shard_id: ShardId, n: usize) -> Self {
+    pub fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        n: usize,
+        t: usize,
+    ) -> Self {
         // clocks from all processes but self
         let all_but_me = HashMap::with_capacity(n - 1);
+        assert!(
+            t >= 1 && t <= n,
+            "stability threshold must be in [1, n], found {}",
+            t
+        );
 
         Self {
             process_id,
             shard_id,
             n,
+            t,
             my_clock: Self::bottom_aeclock(shard_id, n),
             all_but_me,
             previous_stable: Self::bottom_vclock(shard_id, n),
@@ -115,24 +133,48 @@ impl GCTrack {
         dots
     }
 
-    // TODO we should design a fault-tolerant version of this
     // #[instrument(skip(self))]
     fn stable_clock(&mut self) -> VClock<ProcessId> {
-        if self.all_but_me.len() != self.n - 1 {
-            // if we don't have info from all processes, then there are no
-            // stable dots.
+        // we need at least `t` clocks (including our own) to say anything
+        // about stability
+        if self.all_but_me.len() + 1 < self.t {
             return Self::bottom_vclock(self.shard_id, self.n);
         }
 
-        // start from our own frontier
-        let mut stable = self.my_clock.frontier();
-        // and intersect with all the other clocks
-        self.all_but_me.values().for_each(|clock| {
-            stable.meet(clock);
-        });
+        // for each actor `a`, gather the sequence reported by every clock we
+        // know about (our own frontier plus every peer in `all_but_me`),
+        // padding processes we haven't heard from yet with 0
+        let mut seqs_per_actor: HashMap<ProcessId, Vec<u64>> = HashMap::new();
+        for (actor, seq) in self.my_clock.frontier().iter() {
+            seqs_per_actor.entry(*actor).or_default().push(seq.frontier());
+        }
+        for clock in self.all_but_me.values() {
+            for (actor, seq) in clock.iter() {
+                seqs_per_actor.entry(*actor).or_default().push(seq.frontier());
+            }
+        }
+
+        // the stable sequence for `a` is the `t`-th largest value reported
+        // for it, i.e. the highest sequence known to at least `t` processes
+        let mut stable = Self::bottom_vclock(self.shard_id, self.n);
+        for (actor, mut seqs) in seqs_per_actor {
+            seqs.resize(self.n, 0);
+            seqs.sort_unstable_by(|a, b| b.cmp(a));
+            let threshold_seq = seqs[self.t - 1];
+            stable.add(&actor, threshold_seq);
+        }
         stable
     }
 
+    /// Returns the currently known-stable frontier, i.e. the clock as of the
+    /// last call to `stable`, without computing any newly stable dots or
+    /// advancing any internal state -- useful for reporting stability to a
+    /// peer (e.g. during recovery) without disturbing the bookkeeping that
+    /// `stable` itself relies on.
+    pub fn stable_frontier(&self) -> VClock<ProcessId> {
+        self.previous_stable.clone()
+    }
+
     fn bottom_aeclock(shard_id: ShardId, n: usize) -> AEClock<ProcessId> {
         AEClock::with(util::process_ids(shard_id, n))
     }
@@ -140,8 +182,136 @@ impl GCTrack {
     fn bottom_vclock(shard_id: ShardId, n: usize) -> VClock<ProcessId> {
         VClock::with(util::process_ids(shard_id, n))
     }
+
+    /// Serializes this `GCTrack` into a versioned, self-describing envelope,
+    /// so a process that restarts mid-experiment can resume stability
+    /// tracking instead of starting over from bottom clocks.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let envelope = GCTrackEnvelope::V1 {
+            process_id: self.process_id,
+            shard_id: self.shard_id,
+            n: self.n,
+            t: self.t,
+            my_clock: self.my_clock.clone(),
+            all_but_me: self.all_but_me.clone(),
+            previous_stable: self.previous_stable.clone(),
+        };
+        bincode::serialize(&envelope)
+            .expect("[gc_track] snapshot serialize should work")
+    }
+
+    /// Restores a `GCTrack` from a snapshot produced by [`GCTrack::snapshot`].
+    /// Validates that `process_id`, `shard_id` and `n` match what's expected,
+    /// and caps `previous_stable` at the restored clock's frontier (a
+    /// component-wise meet), so it is dominated by that frontier.
+    pub fn restore(
+        bytes: &[u8],
+        process_id: ProcessId,
+        shard_id: ShardId,
+        n: usize,
+    ) -> Result<Self, GCTrackRestoreError> {
+        let envelope: GCTrackEnvelope = bincode::deserialize(bytes)
+            .map_err(GCTrackRestoreError::Deserialize)?;
+        match envelope {
+            GCTrackEnvelope::V1 {
+                process_id: snapshot_process_id,
+                shard_id: snapshot_shard_id,
+                n: snapshot_n,
+                t,
+                my_clock,
+                all_but_me,
+                mut previous_stable,
+            } => {
+                if snapshot_process_id != process_id {
+                    return Err(GCTrackRestoreError::ProcessIdMismatch {
+                        expected: process_id,
+                        found: snapshot_process_id,
+                    });
+                }
+                if snapshot_shard_id != shard_id {
+                    return Err(GCTrackRestoreError::ShardIdMismatch {
+                        expected: shard_id,
+                        found: snapshot_shard_id,
+                    });
+                }
+                if snapshot_n != n {
+                    return Err(GCTrackRestoreError::ProcessCountMismatch {
+                        expected: n,
+                        found: snapshot_n,
+                    });
+                }
+                debug_assert_eq!(my_clock.len(), n);
+
+                // make sure `previous_stable` is dominated by the restored
+                // clock's frontier: dots not recorded locally are never
+                // treated as already stable
+                previous_stable.meet(&my_clock.frontier());
+
+                Ok(Self {
+                    process_id,
+                    shard_id,
+                    n,
+                    t,
+                    my_clock,
+                    all_but_me,
+                    previous_stable,
+                })
+            }
+        }
+    }
+}
+
+/// Version-tagged envelope for `GCTrack` snapshots. New variants should be
+/// added (never replacing existing ones) when the on-disk representation
+/// changes, so that snapshots taken by older binaries can still be migrated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum GCTrackEnvelope {
+    V1 {
+        process_id: ProcessId,
+        shard_id: ShardId,
+        n: usize,
+        t: usize,
+        my_clock: AEClock<ProcessId>,
+        all_but_me: HashMap<ProcessId, VClock<ProcessId>>,
+        previous_stable: VClock<ProcessId>,
+    },
+}
+
+#[derive(Debug)]
+pub enum GCTrackRestoreError {
+    Deserialize(bincode::Error),
+    ProcessIdMismatch { expected: ProcessId, found: ProcessId },
+    ShardIdMismatch { expected: ShardId, found: ShardId },
+    ProcessCountMismatch { expected: usize, found: usize },
 }
 
+impl fmt::Display for GCTrackRestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deserialize(err) => {
+                write!(f, "failed to deserialize GCTrack snapshot: {}", err)
+            }
+            Self::ProcessIdMismatch { expected, found } => write!(
+                f,
+                "GCTrack snapshot process_id mismatch: expected {}, found {}",
+                expected, found
+            ),
+            Self::ShardIdMismatch { expected, found } => write!(
+                f,
+                "GCTrack snapshot shard_id mismatch: expected {}, found {}",
+                expected, found
+            ),
+            Self::ProcessCountMismatch { expected, found } => write!(
+                f,
+                "GCTrack snapshot process count mismatch: expected {}, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GCTrackRestoreError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,11 +330,12 @@ mod tests {
     fn gc_flow() {
         let n = 2;
         let shard_id = 0;
-        // create new gc track for the our process: 1
-        let mut gc = GCTrack::new(1, shard_id, n);
+        // create new gc track for the our process: 1; threshold `t = n`
+        // reproduces the original all-processes-must-agree behavior
+        let mut gc = GCTrack::new(1, shard_id, n, n);
 
         // let's also create a gc track for process 2
-        let mut gc2 = GCTrack::new(2, shard_id, n);
+        let mut gc2 = GCTrack::new(2, shard_id, n, n);
 
         // there's nothing committed and nothing stable
         assert_eq!(gc.clock(), vclock(0, 0));
@@ -221,4 +392,80 @@ mod tests {
         assert_eq!(stable_dots(gc.stable()), vec![dot12, dot13]);
         assert_eq!(stable_dots(gc.stable()), vec![]);
     }
+
+    #[test]
+    fn gc_flow_with_threshold() {
+        // 3 processes, tolerating 1 failure: t = n - f = 2
+        let n = 3;
+        let f = 1;
+        let t = n - f;
+        let shard_id = 0;
+
+        let mut gc = GCTrack::new(1, shard_id, n, t);
+        let mut gc2 = GCTrack::new(2, shard_id, n, t);
+        let mut gc3 = GCTrack::new(3, shard_id, n, t);
+
+        let dot11 = Dot::new(1, 1);
+        let dot12 = Dot::new(1, 2);
+
+        // process 1 commits dot11 and dot12
+        gc.add_to_clock(dot11);
+        gc.add_to_clock(dot12);
+
+        // process 2 also commits both, but process 3 never reports anything:
+        // with a full-meet (t = n) this would stall GC forever, but with
+        // t = n - f = 2 it's enough that 2 out of 3 processes agree
+        gc2.add_to_clock(dot11);
+        gc2.add_to_clock(dot12);
+
+        gc.update_clock_of(2, gc2.clock());
+        assert_eq!(
+            stable_dots(gc.stable()),
+            vec![dot11, dot12],
+            "dots known to `t` processes should be stable even without \
+             hearing from every process"
+        );
+
+        // once process 3 catches up, nothing changes: it was already stable
+        gc3.add_to_clock(dot11);
+        gc3.add_to_clock(dot12);
+        gc.update_clock_of(3, gc3.clock());
+        assert_eq!(stable_dots(gc.stable()), vec![]);
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let n = 2;
+        let shard_id = 0;
+        let process_id = 1;
+        let mut gc = GCTrack::new(process_id, shard_id, n, n);
+
+        // record some state before snapshotting
+        gc.add_to_clock(Dot::new(1, 1));
+        gc.add_to_clock(Dot::new(1, 2));
+        gc.update_clock_of(2, vclock(2, 2));
+        let stable_before = gc.stable_clock();
+        assert_eq!(stable_before, vclock(2, 0));
+        // collect the stable dots before snapshotting
+        assert_eq!(
+            stable_dots(gc.stable()),
+            vec![Dot::new(1, 1), Dot::new(1, 2)]
+        );
+
+        // snapshot and restore into a fresh `GCTrack`
+        let bytes = gc.snapshot();
+        let mut restored =
+            GCTrack::restore(&bytes, process_id, shard_id, n).unwrap();
+        assert_eq!(restored, gc);
+
+        // the restored track keeps producing the same stable dots, without
+        // regressing or re-emitting already-collected ones
+        assert_eq!(restored.stable_clock(), stable_before);
+        assert_eq!(stable_dots(restored.stable()), vec![]);
+
+        // mismatched `process_id`/`shard_id`/`n` are rejected
+        assert!(GCTrack::restore(&bytes, 2, shard_id, n).is_err());
+        assert!(GCTrack::restore(&bytes, process_id, 1, n).is_err());
+        assert!(GCTrack::restore(&bytes, process_id, shard_id, n + 1).is_err());
+    }
 }
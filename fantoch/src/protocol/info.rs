@@ -37,6 +37,8 @@ where
         f: usize,
         fast_quorum_size: usize,
     ) -> Self {
+        // tolerate up to `f` slow/crashed processes when computing stability
+        let gc_stability_threshold = n - f;
         Self {
             process_id,
             shard_id,
@@ -44,7 +46,12 @@ where
             f,
             fast_quorum_size,
             dot_to_info: HashMap::new(),
-            gc_track: GCTrack::new(process_id, shard_id, n),
+            gc_track: GCTrack::new(
+                process_id,
+                shard_id,
+                n,
+                gc_stability_threshold,
+            ),
         }
     }
 
@@ -87,6 +94,12 @@ where
         self.gc_track.stable()
     }
 
+    /// Returns the currently known-stable frontier, without computing any
+    /// newly stable dots (see `GCTrack::stable_frontier`).
+    pub fn stable_frontier(&self) -> VClock<ProcessId> {
+        self.gc_track.stable_frontier()
+    }
+
     /// Performs garbage collection of stable dots.
     /// Returns how many stable does were removed.
     pub fn gc(&mut self, stable: Vec<(ProcessId, u64, u64)>) -> usize {
@@ -104,4 +117,26 @@ where
     pub fn gc_single(&mut self, dot: Dot) {
         assert!(self.dot_to_info.remove(&dot).is_some());
     }
+
+    /// Returns every `(Dot, Info)` still held locally whose sequence number
+    /// is not accounted for in `since`, i.e. commands this process knows
+    /// about that a peer reporting `since` as its committed clock doesn't
+    /// have yet. Used to serve recovery/catch-up requests from lagging or
+    /// restarted peers.
+    pub fn missing_since<'a>(
+        &'a self,
+        since: &'a VClock<ProcessId>,
+    ) -> impl Iterator<Item = (Dot, &'a I)> + 'a {
+        self.dot_to_info.iter().filter_map(move |(dot, info)| {
+            let known = since
+                .get(&dot.source())
+                .map(|seq| seq.frontier())
+                .unwrap_or(0);
+            if dot.sequence() > known {
+                Some((*dot, info))
+            } else {
+                None
+            }
+        })
+    }
 }
@@ -17,10 +17,27 @@ mod basic;
 // garbage-collect a command, i.e., when it's been committed at all processes.
 mod gc;
 
+// This module contains a leader-based Raft implementation, so Raft can be
+// benchmarked head-to-head with the leaderless protocols on the same
+// worker-loop harness.
+mod raft;
+
+// This module contains a leader-based MultiPaxos/Raft-style implementation
+// built on top of `BaseProcess`/`CommandsInfo`/`gc`, offering a strong-leader,
+// per-slot total order baseline to compare against the leaderless protocols.
+mod multi_paxos;
+
+// This module contains a randomized interleaving fuzz/conformance harness
+// exercising any `Protocol` implementation against a handful of invariants
+// that should hold regardless of its fault model.
+pub mod fuzz;
+
 // Re-exports.
 pub use base::BaseProcess;
 pub use basic::Basic;
 pub use info::{CommandsInfo, Info};
+pub use multi_paxos::MultiPaxos;
+pub use raft::Raft;
 
 use crate::command::Command;
 use crate::config::Config;
@@ -60,6 +77,17 @@ pub trait Protocol: Debug + Clone {
 
     fn discover(&mut self, processes: Vec<(ProcessId, ShardId)>) -> bool;
 
+    /// Rebuilds this process's state from execution info that was already
+    /// durably logged (and therefore already executed) before a crash, so
+    /// that a restarted process doesn't re-emit already-executed commands.
+    /// Called once at startup, before `discover`. The default does nothing,
+    /// which is correct for protocols that don't persist an `execution_log`.
+    fn recover(
+        &mut self,
+        _log: Vec<<Self::Executor as Executor>::ExecutionInfo>,
+    ) {
+    }
+
     #[must_use]
     fn submit(
         &mut self,
@@ -96,6 +124,18 @@ pub trait Protocol: Debug + Clone {
     fn metrics(&self) -> &ProtocolMetrics;
 }
 
+/// Which commit/replication model a protocol follows; used by the test
+/// harness to decide which invariants apply when checking a run's metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CommitModel {
+    /// commands commit via a fast/slow (and, for some protocols, a
+    /// coordinated-recovery) path, as in EPaxos-style leaderless protocols
+    Leaderless,
+    /// commands commit by occupying a monotonic log index agreed by a
+    /// leader, as in MultiPaxos/Raft
+    Paxos,
+}
+
 pub type ProtocolMetrics = Metrics<ProtocolMetricsKind, u64>;
 
 #[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -103,6 +143,17 @@ pub enum ProtocolMetricsKind {
     FastPath,
     SlowPath,
     Stable,
+    LeaderElections,
+    CommittedSlots,
+    RejectedMessages,
+    /// highest round/slot a process has pruned up to, for protocols that GC
+    /// a sliding window (bounded by `Config::gc_depth`) instead of pruning
+    /// each command individually
+    GcRound,
+    /// a coordinated-recovery (second-phase) round: reconstructing a value
+    /// after a fast-quorum disagreement or a coordinator takeover, distinct
+    /// from an ordinary slow path taken on a plain conflict
+    Recovery,
 }
 
 impl Debug for ProtocolMetricsKind {
@@ -111,6 +162,17 @@ impl Debug for ProtocolMetricsKind {
             ProtocolMetricsKind::FastPath => write!(f, "fast_path"),
             ProtocolMetricsKind::SlowPath => write!(f, "slow_path"),
             ProtocolMetricsKind::Stable => write!(f, "stable"),
+            ProtocolMetricsKind::LeaderElections => {
+                write!(f, "leader_elections")
+            }
+            ProtocolMetricsKind::CommittedSlots => {
+                write!(f, "committed_slots")
+            }
+            ProtocolMetricsKind::RejectedMessages => {
+                write!(f, "rejected_messages")
+            }
+            ProtocolMetricsKind::GcRound => write!(f, "gc_round"),
+            ProtocolMetricsKind::Recovery => write!(f, "recovery"),
         }
     }
 }
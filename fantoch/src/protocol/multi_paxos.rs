@@ -0,0 +1,846 @@
+use crate::command::Command;
+use crate::config::Config;
+use crate::executor::{BasicExecutionInfo, BasicExecutor, Executor};
+use crate::id::{Dot, ProcessId, ShardId};
+use crate::protocol::{
+    Action, BaseProcess, CommandsInfo, Info, MessageIndex, PeriodicEventIndex,
+    Protocol, ProtocolMetrics,
+};
+use crate::run::{worker_index_no_shift, LEADER_WORKER_INDEX};
+use crate::time::SysTime;
+use crate::HashSet;
+use crate::{log, singleton, HashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use threshold::VClock;
+use tracing::instrument;
+
+type ExecutionInfo = <BasicExecutor as Executor>::ExecutionInfo;
+
+// the current ballot, who's leader, and the unbroken commit prefix are all
+// inherently sequential, so - like `Raft` - `MultiPaxos` runs as a single
+// worker (`LEADER_WORKER_INDEX`) per process rather than sharding slots
+// across workers, which would require sharing this state across them.
+const ELECTION_TIMEOUT_MIN_MS: u64 = 150;
+const ELECTION_TIMEOUT_MAX_MS: u64 = 300;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(50);
+
+// log slots aren't owned by any single process (the leader changes over
+// time), so they're tracked in `cmds` under this fixed placeholder instead of
+// a real `ProcessId`.
+const LOG_PROCESS_ID: ProcessId = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Role {
+    Follower,
+    Candidate {
+        promises: HashSet<ProcessId>,
+        // the highest-ballot value reported for each not-yet-applied slot, so
+        // a new leader can recover anything a previous leader may have only
+        // partially replicated
+        recovered: HashMap<u64, (u64, Command)>,
+    },
+    Leader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiPaxos {
+    bp: BaseProcess,
+    cmds: CommandsInfo<PaxosInfo>,
+    to_processes: Vec<Action<Self>>,
+    to_executors: Vec<ExecutionInfo>,
+    ballot: u64,
+    role: Role,
+    leader: Option<ProcessId>,
+    heard_from_leader: bool,
+    // next slot this process will assign, meaningful only while leading
+    next_slot: u64,
+    // highest slot ever seen in an `Accept`, used to bound log-recovery scans
+    highest_seen_slot: u64,
+    // highest slot committed *and* delivered to the executor, contiguously
+    applied: u64,
+}
+
+impl Protocol for MultiPaxos {
+    type Message = Message;
+    type PeriodicEvent = PeriodicEvent;
+    type Executor = BasicExecutor;
+
+    /// Creates a new `MultiPaxos` process.
+    fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+    ) -> (Self, Vec<(PeriodicEvent, Duration)>) {
+        // a ballot/slot is decided once a majority (`f + 1` out of `n`) of
+        // acceptors agree
+        let fast_quorum_size = config.f() + 1;
+        let write_quorum_size = 0;
+
+        let bp = BaseProcess::new(
+            process_id,
+            shard_id,
+            config,
+            fast_quorum_size,
+            write_quorum_size,
+        );
+        let cmds = CommandsInfo::new(
+            process_id,
+            shard_id,
+            config.n(),
+            config.f(),
+            fast_quorum_size,
+        );
+
+        let protocol = Self {
+            bp,
+            cmds,
+            to_processes: Vec::new(),
+            to_executors: Vec::new(),
+            ballot: 0,
+            role: Role::Follower,
+            leader: None,
+            heard_from_leader: false,
+            next_slot: 1,
+            highest_seen_slot: 0,
+            applied: 0,
+        };
+
+        let election_timeout = Duration::from_millis(
+            rand::thread_rng()
+                .gen_range(ELECTION_TIMEOUT_MIN_MS, ELECTION_TIMEOUT_MAX_MS + 1),
+        );
+        let mut events = vec![
+            (PeriodicEvent::ElectionTimeout, election_timeout),
+            (PeriodicEvent::Heartbeat, HEARTBEAT_INTERVAL),
+        ];
+        if let Some(interval) = config.gc_interval() {
+            events.push((PeriodicEvent::GarbageCollection, interval));
+        }
+        (protocol, events)
+    }
+
+    /// Returns the process identifier.
+    fn id(&self) -> ProcessId {
+        self.bp.process_id
+    }
+
+    /// Returns the shard identifier.
+    fn shard_id(&self) -> ShardId {
+        self.bp.shard_id
+    }
+
+    /// Updates the processes known by this process.
+    fn discover(&mut self, processes: Vec<(ProcessId, ShardId)>) -> bool {
+        self.bp.discover(processes)
+    }
+
+    /// Resumes the applied watermark from an already-executed `execution_log`:
+    /// `deliver_ready` only ever applies slots `1, 2, 3, ...` contiguously
+    /// (see its own doc comment), so the recovered log's length is exactly
+    /// the highest slot this process had already applied before it crashed.
+    /// Restoring `applied` (and bumping `next_slot`/`highest_seen_slot` to
+    /// match) is what keeps `deliver_ready` from re-emitting any of that log
+    /// once `Accept`s for those slots are replayed by a recovering leader.
+    fn recover(&mut self, log: Vec<ExecutionInfo>) {
+        let recovered_slots = log.len() as u64;
+        self.applied = self.applied.max(recovered_slots);
+        self.next_slot = self.next_slot.max(self.applied + 1);
+        self.highest_seen_slot = self.highest_seen_slot.max(self.applied);
+    }
+
+    /// Submits a command issued by some client.
+    fn submit(&mut self, _dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        self.handle_submit(cmd);
+    }
+
+    /// Handles protocol messages.
+    fn handle(
+        &mut self,
+        from: ProcessId,
+        _from_shard_id: ShardId,
+        msg: Self::Message,
+        _time: &dyn SysTime,
+    ) {
+        match msg {
+            Message::ClientCommand { cmd } => self.handle_submit(cmd),
+            Message::Prepare { ballot } => self.handle_prepare(from, ballot),
+            Message::PrepareOk { ballot, accepted } => {
+                self.handle_prepare_ok(from, ballot, accepted)
+            }
+            Message::Accept { slot, ballot, cmd } => {
+                self.handle_accept(from, slot, ballot, cmd)
+            }
+            Message::AcceptAck { slot, ballot } => {
+                self.handle_accept_ack(from, slot, ballot)
+            }
+            Message::Heartbeat { ballot } => {
+                self.handle_heartbeat_msg(from, ballot)
+            }
+            Message::MGarbageCollection { committed } => {
+                self.handle_mgc(from, committed)
+            }
+            Message::MStable { stable } => self.handle_mstable(from, stable),
+        }
+    }
+
+    /// Handles periodic local events.
+    fn handle_event(
+        &mut self,
+        event: Self::PeriodicEvent,
+        _time: &dyn SysTime,
+    ) {
+        match event {
+            PeriodicEvent::ElectionTimeout => self.handle_election_timeout(),
+            PeriodicEvent::Heartbeat => self.handle_heartbeat(),
+            PeriodicEvent::GarbageCollection => {
+                self.handle_event_garbage_collection()
+            }
+        }
+    }
+
+    /// Returns a new action to be sent to other processes.
+    fn to_processes(&mut self) -> Option<Action<Self>> {
+        self.to_processes.pop()
+    }
+
+    /// Returns new execution info for executors.
+    fn to_executors(&mut self) -> Option<ExecutionInfo> {
+        self.to_executors.pop()
+    }
+
+    fn parallel() -> bool {
+        false
+    }
+
+    fn leaderless() -> bool {
+        false
+    }
+
+    fn metrics(&self) -> &ProtocolMetrics {
+        self.bp.metrics()
+    }
+}
+
+impl MultiPaxos {
+    /// Handles a submit operation by a client: the leader assigns it the next
+    /// free slot, everyone else forwards it to whoever it believes is leader.
+    #[instrument(skip(self, cmd))]
+    fn handle_submit(&mut self, cmd: Command) {
+        if matches!(self.role, Role::Leader) {
+            let slot = self.next_slot;
+            self.next_slot += 1;
+            self.propose(slot, cmd);
+            return;
+        }
+        match self.leader {
+            Some(leader_id) if leader_id != self.id() => {
+                self.to_processes.push(Action::ToSend {
+                    target: singleton![leader_id],
+                    msg: Message::ClientCommand { cmd },
+                });
+            }
+            _ => {
+                log!(
+                    "p{}: dropping client command: no known leader",
+                    self.id()
+                );
+            }
+        }
+    }
+
+    /// Proposes `cmd` for `slot` at the current ballot, acking it locally and
+    /// sending `Accept` to every other process.
+    fn propose(&mut self, slot: u64, cmd: Command) {
+        let ballot = self.ballot;
+        self.highest_seen_slot = self.highest_seen_slot.max(slot);
+        let dot = Dot::new(LOG_PROCESS_ID, slot);
+        let info = self.cmds.get(dot);
+        info.ballot = ballot;
+        info.cmd = Some(cmd.clone());
+        info.acks.insert(self.id());
+
+        let target = self.bp.all_but_me();
+        self.to_processes.push(Action::ToSend {
+            target,
+            msg: Message::Accept { slot, ballot, cmd },
+        });
+    }
+
+    #[instrument(skip(self, from, slot, ballot, cmd))]
+    fn handle_accept(
+        &mut self,
+        from: ProcessId,
+        slot: u64,
+        ballot: u64,
+        cmd: Command,
+    ) {
+        log!(
+            "p{}: Accept({}, {:?}, {:?}) from {}",
+            self.id(),
+            slot,
+            ballot,
+            cmd,
+            from
+        );
+        if ballot < self.ballot {
+            // stale leader; it'll step down once it hears of our ballot
+            return;
+        }
+        self.ballot = ballot;
+        self.role = Role::Follower;
+        self.leader = Some(from);
+        self.heard_from_leader = true;
+        self.highest_seen_slot = self.highest_seen_slot.max(slot);
+
+        let dot = Dot::new(LOG_PROCESS_ID, slot);
+        let info = self.cmds.get(dot);
+        info.ballot = ballot;
+        info.cmd = Some(cmd);
+
+        self.to_processes.push(Action::ToSend {
+            target: singleton![from],
+            msg: Message::AcceptAck { slot, ballot },
+        });
+    }
+
+    #[instrument(skip(self, from, slot, ballot))]
+    fn handle_accept_ack(&mut self, from: ProcessId, slot: u64, ballot: u64) {
+        log!(
+            "p{}: AcceptAck({}, {:?}) from {}",
+            self.id(),
+            slot,
+            ballot,
+            from
+        );
+        if ballot != self.ballot || !matches!(self.role, Role::Leader) {
+            return;
+        }
+
+        let dot = Dot::new(LOG_PROCESS_ID, slot);
+        let majority = self.bp.config.f() + 1;
+        let info = self.cmds.get(dot);
+        if info.committed {
+            return;
+        }
+        info.acks.insert(from);
+        if info.acks.len() < majority {
+            return;
+        }
+        info.committed = true;
+        // TODO increment `ProtocolMetricsKind::CommittedSlots` once
+        // `BaseProcess` exposes a way to record protocol-specific metric
+        // kinds beyond the built-in fast/slow/stable path counters
+        self.cmds.commit(dot);
+        self.deliver_ready();
+    }
+
+    /// Delivers every contiguously-committed slot starting right after
+    /// `applied`, in log order, to the executor.
+    fn deliver_ready(&mut self) {
+        loop {
+            let slot = self.applied + 1;
+            let dot = Dot::new(LOG_PROCESS_ID, slot);
+            let info = self.cmds.get(dot);
+            if !info.committed {
+                break;
+            }
+            let cmd = info
+                .cmd
+                .clone()
+                .expect("a committed slot should have a command");
+            self.applied = slot;
+
+            let rifl = cmd.rifl();
+            let execution_info = cmd
+                .into_iter(self.bp.shard_id)
+                .map(|(key, op)| BasicExecutionInfo::new(rifl, key, op));
+            self.to_executors.extend(execution_info);
+
+            if self.gc_running() {
+                self.cmds.commit(dot);
+            } else {
+                self.cmds.gc_single(dot);
+            }
+        }
+    }
+
+    fn handle_election_timeout(&mut self) {
+        if matches!(self.role, Role::Leader) {
+            self.heard_from_leader = true;
+            return;
+        }
+        if self.heard_from_leader {
+            self.heard_from_leader = false;
+            return;
+        }
+        self.start_election();
+    }
+
+    fn start_election(&mut self) {
+        self.ballot += 1;
+        self.leader = None;
+
+        let own_accepted = self.accepted_slots();
+        let mut recovered = HashMap::new();
+        for (slot, slot_ballot, cmd) in own_accepted {
+            recovered.insert(slot, (slot_ballot, cmd));
+        }
+        self.role = Role::Candidate {
+            promises: singleton![self.id()],
+            recovered,
+        };
+
+        let ballot = self.ballot;
+        log!(
+            "p{}: election timeout, starting ballot {}",
+            self.id(),
+            ballot
+        );
+        let target = self.bp.all_but_me();
+        self.to_processes
+            .push(Action::ToSend { target, msg: Message::Prepare { ballot } });
+    }
+
+    /// Returns every not-yet-applied slot this process has accepted a value
+    /// for, so it can be reported to (or recovered by) a new leader.
+    fn accepted_slots(&mut self) -> Vec<(u64, u64, Command)> {
+        let mut result = Vec::new();
+        for slot in (self.applied + 1)..=self.highest_seen_slot {
+            let dot = Dot::new(LOG_PROCESS_ID, slot);
+            let info = self.cmds.get(dot);
+            if let Some(cmd) = info.cmd.clone() {
+                result.push((slot, info.ballot, cmd));
+            }
+        }
+        result
+    }
+
+    #[instrument(skip(self, from, ballot))]
+    fn handle_prepare(&mut self, from: ProcessId, ballot: u64) {
+        log!("p{}: Prepare({:?}) from {}", self.id(), ballot, from);
+        if ballot <= self.ballot {
+            // stale or duplicate prepare; nothing to promise
+            return;
+        }
+        self.ballot = ballot;
+        self.role = Role::Follower;
+        self.leader = None;
+        self.heard_from_leader = true;
+
+        let accepted = self.accepted_slots();
+        self.to_processes.push(Action::ToSend {
+            target: singleton![from],
+            msg: Message::PrepareOk { ballot, accepted },
+        });
+    }
+
+    #[instrument(skip(self, from, ballot, accepted))]
+    fn handle_prepare_ok(
+        &mut self,
+        from: ProcessId,
+        ballot: u64,
+        accepted: Vec<(u64, u64, Command)>,
+    ) {
+        log!("p{}: PrepareOk({:?}) from {}", self.id(), ballot, from);
+        if ballot != self.ballot {
+            return;
+        }
+        let majority = self.bp.config.f() + 1;
+        let won = match &mut self.role {
+            Role::Candidate { promises, recovered } => {
+                promises.insert(from);
+                for (slot, slot_ballot, cmd) in accepted {
+                    let better = recovered
+                        .get(&slot)
+                        .map_or(true, |(b, _)| slot_ballot > *b);
+                    if better {
+                        recovered.insert(slot, (slot_ballot, cmd));
+                    }
+                }
+                promises.len() >= majority
+            }
+            _ => false,
+        };
+        if won {
+            self.become_leader();
+        }
+    }
+
+    fn become_leader(&mut self) {
+        // TODO increment `ProtocolMetricsKind::LeaderElections` once
+        // `BaseProcess` exposes a way to record protocol-specific metric
+        // kinds beyond the built-in fast/slow/stable path counters
+        let ballot = self.ballot;
+        let recovered = match std::mem::replace(&mut self.role, Role::Leader) {
+            Role::Candidate { recovered, .. } => recovered,
+            _ => HashMap::new(),
+        };
+        self.leader = Some(self.id());
+        self.heard_from_leader = true;
+        self.next_slot = self.highest_seen_slot + 1;
+        log!("p{}: became leader for ballot {}", self.id(), ballot);
+
+        // re-propose, at the new ballot, any value a previous leader may have
+        // only partially replicated before we accept any new commands
+        let mut slots: Vec<_> = recovered.keys().cloned().collect();
+        slots.sort_unstable();
+        for slot in slots {
+            let (_, cmd) = recovered
+                .get(&slot)
+                .cloned()
+                .expect("slot should be present");
+            self.propose(slot, cmd);
+        }
+    }
+
+    fn handle_heartbeat(&mut self) {
+        if !matches!(self.role, Role::Leader) {
+            return;
+        }
+        let ballot = self.ballot;
+        let target = self.bp.all_but_me();
+        self.to_processes
+            .push(Action::ToSend { target, msg: Message::Heartbeat { ballot } });
+    }
+
+    fn handle_heartbeat_msg(&mut self, from: ProcessId, ballot: u64) {
+        if ballot < self.ballot {
+            return;
+        }
+        self.ballot = ballot;
+        self.role = Role::Follower;
+        self.leader = Some(from);
+        self.heard_from_leader = true;
+    }
+
+    #[instrument(skip(self, from, committed))]
+    fn handle_mgc(&mut self, from: ProcessId, committed: VClock<ProcessId>) {
+        log!(
+            "p{}: MGarbageCollection({:?}) from {}",
+            self.id(),
+            committed,
+            from
+        );
+        self.cmds.committed_by(from, committed);
+        let stable = self.cmds.stable();
+        if !stable.is_empty() {
+            self.to_processes.push(Action::ToForward {
+                msg: Message::MStable { stable },
+            })
+        }
+    }
+
+    #[instrument(skip(self, from, stable))]
+    fn handle_mstable(
+        &mut self,
+        from: ProcessId,
+        stable: Vec<(ProcessId, u64, u64)>,
+    ) {
+        log!("p{}: MStable({:?}) from {}", self.id(), stable, from);
+        assert_eq!(from, self.bp.process_id);
+        let stable_count = self.cmds.gc(stable);
+        self.bp.stable(stable_count);
+    }
+
+    #[instrument(skip(self))]
+    fn handle_event_garbage_collection(&mut self) {
+        log!("p{}: PeriodicEvent::GarbageCollection", self.id());
+        let committed = self.cmds.committed();
+        self.to_processes.push(Action::ToSend {
+            target: self.bp.all_but_me(),
+            msg: Message::MGarbageCollection { committed },
+        });
+    }
+
+    fn gc_running(&self) -> bool {
+        self.bp.config.gc_interval().is_some()
+    }
+}
+
+// `PaxosInfo` contains all information required in the life-cycle of a log
+// slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PaxosInfo {
+    ballot: u64,
+    cmd: Option<Command>,
+    acks: HashSet<ProcessId>,
+    committed: bool,
+}
+
+impl Info for PaxosInfo {
+    fn new(
+        _process_id: ProcessId,
+        _shard_id: ShardId,
+        _n: usize,
+        _f: usize,
+        fast_quorum_size: usize,
+    ) -> Self {
+        Self {
+            ballot: 0,
+            cmd: None,
+            acks: HashSet::with_capacity(fast_quorum_size),
+            committed: false,
+        }
+    }
+}
+
+// `MultiPaxos` protocol messages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Message {
+    // a command a follower forwards to the process it believes is leader
+    ClientCommand {
+        cmd: Command,
+    },
+    Prepare {
+        ballot: u64,
+    },
+    PrepareOk {
+        ballot: u64,
+        accepted: Vec<(u64, u64, Command)>,
+    },
+    Accept {
+        slot: u64,
+        ballot: u64,
+        cmd: Command,
+    },
+    AcceptAck {
+        slot: u64,
+        ballot: u64,
+    },
+    Heartbeat {
+        ballot: u64,
+    },
+    MGarbageCollection {
+        committed: VClock<ProcessId>,
+    },
+    MStable {
+        stable: Vec<(ProcessId, u64, u64)>,
+    },
+}
+
+impl MessageIndex for Message {
+    fn index(&self) -> Option<(usize, usize)> {
+        worker_index_no_shift(LEADER_WORKER_INDEX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeriodicEvent {
+    ElectionTimeout,
+    Heartbeat,
+    GarbageCollection,
+}
+
+impl PeriodicEventIndex for PeriodicEvent {
+    fn index(&self) -> Option<(usize, usize)> {
+        worker_index_no_shift(LEADER_WORKER_INDEX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Client, KeyGen, ShardGen, Workload};
+    use crate::id::Rifl;
+    use crate::kvs::KVOp;
+    use crate::planet::{Planet, Region};
+    use crate::sim::Simulation;
+    use crate::time::SimTime;
+    use crate::util;
+
+    #[test]
+    fn recover_resumes_applied_watermark_and_next_slot() {
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let (mut paxos, _) = MultiPaxos::new(1, 0, config);
+
+        // three slots' worth of commands were already executed and durably
+        // logged before the crash this `recover` call is undoing
+        let log: Vec<ExecutionInfo> = (1..=3)
+            .map(|seq| {
+                let rifl = Rifl::new(1, seq);
+                BasicExecutionInfo::new(
+                    rifl,
+                    format!("K{}", seq),
+                    KVOp::Put(String::new()),
+                )
+            })
+            .collect();
+
+        paxos.recover(log);
+
+        assert_eq!(paxos.applied, 3);
+        assert_eq!(paxos.next_slot, 4);
+        assert_eq!(paxos.highest_seen_slot, 3);
+
+        // submitting right after recovery must not reuse an already-applied
+        // slot (as leader, since a follower with no known leader drops it)
+        paxos.discover(util::process_ids(n).map(|id| (id, 0)).collect());
+        paxos.role = Role::Leader;
+        let time = SimTime::new();
+        let cmd =
+            Command::put(Rifl::new(1, 100), String::from("K4"), String::new());
+        paxos.submit(None, cmd, &time);
+        assert_eq!(paxos.next_slot, 5);
+    }
+
+    #[test]
+    fn multi_paxos_flow() {
+        // create simulation
+        let mut simulation = Simulation::new();
+
+        // process ids
+        let process_id_1 = 1;
+        let process_id_2 = 2;
+        let process_id_3 = 3;
+
+        // regions
+        let europe_west2 = Region::new("europe-west2");
+        let europe_west3 = Region::new("europe-west2");
+        let us_west1 = Region::new("europe-west2");
+
+        // there's a single shard
+        let shard_id = 0;
+
+        // processes
+        let processes = vec![
+            (process_id_1, shard_id, europe_west2.clone()),
+            (process_id_2, shard_id, europe_west3.clone()),
+            (process_id_3, shard_id, us_west1.clone()),
+        ];
+
+        // planet
+        let planet = Planet::new();
+
+        // create system time
+        let time = SimTime::new();
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        // executors
+        let executor_1 = BasicExecutor::new(process_id_1, shard_id, config);
+        let executor_2 = BasicExecutor::new(process_id_2, shard_id, config);
+        let executor_3 = BasicExecutor::new(process_id_3, shard_id, config);
+
+        // multi_paxos
+        let (mut paxos_1, _) = MultiPaxos::new(process_id_1, shard_id, config);
+        let (mut paxos_2, _) = MultiPaxos::new(process_id_2, shard_id, config);
+        let (mut paxos_3, _) = MultiPaxos::new(process_id_3, shard_id, config);
+
+        // discover processes in all multi_paxos instances
+        let sorted = util::sort_processes_by_distance(
+            &europe_west2,
+            &planet,
+            processes.clone(),
+        );
+        paxos_1.discover(sorted);
+        let sorted = util::sort_processes_by_distance(
+            &europe_west3,
+            &planet,
+            processes.clone(),
+        );
+        paxos_2.discover(sorted);
+        let sorted = util::sort_processes_by_distance(
+            &us_west1,
+            &planet,
+            processes.clone(),
+        );
+        paxos_3.discover(sorted);
+
+        // register processes
+        simulation.register_process(paxos_1, executor_1);
+        simulation.register_process(paxos_2, executor_2);
+        simulation.register_process(paxos_3, executor_3);
+
+        // process 1 times out first and starts ballot 1
+        let (process, _, _, _) = simulation.get_process(process_id_1);
+        process.handle_event(PeriodicEvent::ElectionTimeout, &time);
+        let prepares: Vec<_> = process.to_processes_iter().collect();
+
+        // a single `Prepare` is sent to the other 2 processes
+        assert_eq!(prepares.len(), 1);
+        assert!(matches!(&prepares[0], Action::ToSend { target, .. } if target.len() == n - 1));
+
+        // the other processes promise, and process 1 becomes leader once it
+        // sees a majority of `PrepareOk`s
+        let mut became_leader = false;
+        for prepare in prepares {
+            let replies =
+                simulation.forward_to_processes((process_id_1, prepare));
+            // each reply is tagged with the process that sent it, and is
+            // addressed to process 1
+            for (from, reply) in replies {
+                let (process, _, _, _) = simulation.get_process(process_id_1);
+                process.handle(
+                    from,
+                    shard_id,
+                    match reply {
+                        Action::ToSend { msg, .. } => msg,
+                        Action::ToForward { msg } => msg,
+                    },
+                    &time,
+                );
+                if matches!(process.role, Role::Leader) {
+                    became_leader = true;
+                }
+            }
+        }
+        assert!(became_leader, "process 1 should have become the leader");
+
+        // client workload
+        let shards_per_command = 1;
+        let shard_gen = ShardGen::Random { shard_count: 1 };
+        let keys_per_shard = 1;
+        let key_gen = KeyGen::ConflictRate { conflict_rate: 100 };
+        let total_commands = 10;
+        let payload_size = 100;
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            total_commands,
+            payload_size,
+        );
+
+        // create client 1 that is connected to the leader
+        let client_id = 1;
+        let client_region = europe_west2.clone();
+        let mut client_1 = Client::new(client_id, workload);
+
+        // discover processes in client 1
+        let closest =
+            util::closest_process_per_shard(&client_region, &planet, processes);
+        client_1.connect(closest);
+
+        // start client
+        let (target_shard, cmd) = client_1
+            .next_cmd(&time)
+            .expect("there should be a first operation");
+        let target = client_1.shard_process(&target_shard);
+
+        // check that `target` is process 1, the leader
+        assert_eq!(target, process_id_1);
+
+        // register client
+        simulation.register_client(client_1);
+
+        // submit the command at the leader: it's assigned slot 1 and
+        // a single `Accept` is sent to the other 2 processes
+        let (process, _, pending, time) = simulation.get_process(process_id_1);
+        pending.wait_for(&cmd);
+        process.submit(None, cmd, time);
+        let accepts: Vec<_> = process.to_processes_iter().collect();
+        assert_eq!(accepts.len(), 1);
+        let check_msg = |msg: &Message| {
+            matches!(msg, Message::Accept { slot, .. } if *slot == 1)
+        };
+        assert!(accepts.iter().all(|action| matches!(action, Action::ToSend { target, msg } if target.len() == n - 1 && check_msg(msg))));
+    }
+}
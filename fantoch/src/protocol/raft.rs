@@ -0,0 +1,836 @@
+use crate::command::Command;
+use crate::config::Config;
+use crate::executor::{BasicExecutionInfo, BasicExecutor, Executor};
+use crate::id::{Dot, ProcessId, ShardId};
+use crate::metrics::Metrics;
+use crate::protocol::{
+    Action, MessageIndex, PeriodicEventIndex, Protocol, ProtocolMetrics,
+};
+use crate::run::worker_index_no_shift;
+use crate::time::SysTime;
+use crate::{log, singleton, HashMap, HashSet};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+type ExecutionInfo = <BasicExecutor as Executor>::ExecutionInfo;
+
+/// Every `Raft` message routes to this single worker: unlike the leaderless
+/// protocols, there's one replicated log per shard, so there's no per-`Dot`
+/// sharding to parallelize across workers.
+const RAFT_WORKER_INDEX: usize = 0;
+
+/// Election timeouts are randomized per node, in this range, so that a
+/// majority never times out in lockstep after losing the same leader.
+const ELECTION_TIMEOUT_MIN_MS: u64 = 150;
+const ELECTION_TIMEOUT_MAX_MS: u64 = 300;
+
+/// How often a leader re-sends `AppendEntries` to every follower, reusing
+/// the periodic-task timer like every other `PeriodicEvent`.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// One entry in a `Raft` process's replicated log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    term: u64,
+    cmd: Command,
+}
+
+/// The role a `Raft` process currently holds; `Candidate`/`Leader` carry the
+/// book-keeping that only makes sense in that role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Role {
+    Follower,
+    Candidate {
+        votes: HashSet<ProcessId>,
+    },
+    Leader {
+        // index of the next log entry to send to each follower
+        next_index: HashMap<ProcessId, usize>,
+        // index of the highest log entry known to be replicated on each
+        // follower
+        match_index: HashMap<ProcessId, usize>,
+    },
+}
+
+/// A leader-based Raft implementation of the `Protocol` trait, so Raft can
+/// be benchmarked head-to-head with the leaderless protocols on the same
+/// worker-loop harness. Applied log entries are handed to the existing
+/// `BasicExecutor` pipeline unchanged: once `commit_index` advances, entries
+/// are applied in log order and their key-ops are emitted as
+/// `BasicExecutionInfo`, exactly as `Basic` does for a committed `MCommit`.
+#[derive(Debug, Clone)]
+pub struct Raft {
+    process_id: ProcessId,
+    shard_id: ShardId,
+    config: Config,
+    // every process in this shard, filled in by `discover`
+    processes: Vec<ProcessId>,
+    metrics: ProtocolMetrics,
+
+    current_term: u64,
+    voted_for: Option<ProcessId>,
+    role: Role,
+    leader_id: Option<ProcessId>,
+    // set by anything that confirms the current leader's legitimacy
+    // (a granted vote, a valid `AppendEntries`); consumed (and cleared) by
+    // the next `ElectionTimeout` tick instead of firing an election, since
+    // periodic events here can't be individually rescheduled/reset
+    heard_from_leader: bool,
+
+    log: Vec<LogEntry>,
+    commit_index: usize,
+    last_applied: usize,
+
+    to_processes: Vec<Action<Self>>,
+    to_executors: Vec<ExecutionInfo>,
+}
+
+impl Protocol for Raft {
+    type Message = Message;
+    type PeriodicEvent = PeriodicEvent;
+    type Executor = BasicExecutor;
+
+    fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+    ) -> (Self, Vec<(PeriodicEvent, Duration)>) {
+        let protocol = Self {
+            process_id,
+            shard_id,
+            config,
+            processes: Vec::new(),
+            metrics: Metrics::new(),
+            current_term: 0,
+            voted_for: None,
+            role: Role::Follower,
+            leader_id: None,
+            heard_from_leader: false,
+            log: Vec::new(),
+            commit_index: 0,
+            last_applied: 0,
+            to_processes: Vec::new(),
+            to_executors: Vec::new(),
+        };
+
+        let election_timeout = Duration::from_millis(
+            rand::thread_rng().gen_range(
+                ELECTION_TIMEOUT_MIN_MS,
+                ELECTION_TIMEOUT_MAX_MS + 1,
+            ),
+        );
+        let events = vec![
+            (PeriodicEvent::ElectionTimeout, election_timeout),
+            (PeriodicEvent::Heartbeat, HEARTBEAT_INTERVAL),
+        ];
+
+        (protocol, events)
+    }
+
+    fn id(&self) -> ProcessId {
+        self.process_id
+    }
+
+    fn shard_id(&self) -> ShardId {
+        self.shard_id
+    }
+
+    fn discover(&mut self, processes: Vec<(ProcessId, ShardId)>) -> bool {
+        self.processes = processes
+            .into_iter()
+            .filter(|(_, shard_id)| *shard_id == self.shard_id)
+            .map(|(process_id, _)| process_id)
+            .collect();
+        self.processes.len() == self.config.n()
+    }
+
+    fn submit(&mut self, _dot: Option<Dot>, cmd: Command, _time: &dyn SysTime) {
+        self.handle_submit(cmd);
+    }
+
+    fn handle(
+        &mut self,
+        from: ProcessId,
+        _from_shard_id: ShardId,
+        msg: Self::Message,
+        _time: &dyn SysTime,
+    ) {
+        match msg {
+            Message::RequestVote {
+                term,
+                candidate_id,
+                last_log_index,
+                last_log_term,
+            } => self.handle_request_vote(
+                from,
+                term,
+                candidate_id,
+                last_log_index,
+                last_log_term,
+            ),
+            Message::RequestVoteReply { term, vote_granted } => {
+                self.handle_request_vote_reply(from, term, vote_granted)
+            }
+            Message::AppendEntries {
+                term,
+                leader_id,
+                prev_log_index,
+                prev_log_term,
+                entries,
+                leader_commit,
+            } => self.handle_append_entries(
+                from,
+                term,
+                leader_id,
+                prev_log_index,
+                prev_log_term,
+                entries,
+                leader_commit,
+            ),
+            Message::AppendEntriesReply {
+                term,
+                success,
+                match_index,
+            } => self
+                .handle_append_entries_reply(from, term, success, match_index),
+            Message::ClientCommand { cmd } => {
+                self.handle_client_command(cmd)
+            }
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        event: Self::PeriodicEvent,
+        _time: &dyn SysTime,
+    ) {
+        match event {
+            PeriodicEvent::ElectionTimeout => self.handle_election_timeout(),
+            PeriodicEvent::Heartbeat => self.handle_heartbeat(),
+        }
+    }
+
+    fn to_processes(&mut self) -> Option<Action<Self>> {
+        self.to_processes.pop()
+    }
+
+    fn to_executors(&mut self) -> Option<ExecutionInfo> {
+        self.to_executors.pop()
+    }
+
+    // the replicated log is a single, strictly-ordered sequence; there's
+    // nothing to parallelize across workers
+    fn parallel() -> bool {
+        false
+    }
+
+    fn leaderless() -> bool {
+        false
+    }
+
+    fn metrics(&self) -> &ProtocolMetrics {
+        &self.metrics
+    }
+}
+
+impl Raft {
+    /// Handles a command submitted by a client: appends it to the log if
+    /// we're the leader, or forwards it to the leader we know about, or
+    /// drops it (the client will retry) if no leader is known yet.
+    fn handle_submit(&mut self, cmd: Command) {
+        if matches!(self.role, Role::Leader { .. }) {
+            self.append_to_log(cmd);
+            return;
+        }
+        match self.leader_id {
+            Some(leader_id) if leader_id != self.process_id => {
+                self.to_processes.push(Action::ToSend {
+                    target: singleton![leader_id],
+                    msg: Message::ClientCommand { cmd },
+                });
+            }
+            _ => {
+                log!(
+                    "p{}: dropping client command: no known leader",
+                    self.id()
+                );
+            }
+        }
+    }
+
+    /// A command forwarded here by a follower that believed we're the
+    /// leader; if we no longer are, run it back through the redirection
+    /// logic in `handle_submit` instead of assuming leadership didn't
+    /// change.
+    fn handle_client_command(&mut self, cmd: Command) {
+        self.handle_submit(cmd);
+    }
+
+    fn append_to_log(&mut self, cmd: Command) {
+        let entry = LogEntry {
+            term: self.current_term,
+            cmd,
+        };
+        self.log.push(entry);
+        self.replicate_log();
+    }
+
+    fn last_log_term(&self) -> u64 {
+        self.log.last().map(|entry| entry.term).unwrap_or(0)
+    }
+
+    fn majority(&self) -> usize {
+        self.config.n() / 2 + 1
+    }
+
+    fn step_down(&mut self, term: u64) {
+        self.current_term = term;
+        self.voted_for = None;
+        self.role = Role::Follower;
+        self.leader_id = None;
+    }
+
+    fn handle_election_timeout(&mut self) {
+        if matches!(self.role, Role::Leader { .. }) {
+            // leaders don't run against their own election timer
+            self.heard_from_leader = true;
+            return;
+        }
+        if self.heard_from_leader {
+            // the current leader (or a candidate we voted for) is still
+            // alive as far as we can tell; consume the flag and wait for
+            // the next tick instead of starting an election
+            self.heard_from_leader = false;
+            return;
+        }
+        self.start_election();
+    }
+
+    fn start_election(&mut self) {
+        self.current_term += 1;
+        self.voted_for = Some(self.process_id);
+        self.leader_id = None;
+        self.role = Role::Candidate {
+            votes: singleton![self.process_id],
+        };
+
+        let term = self.current_term;
+        let candidate_id = self.process_id;
+        let last_log_index = self.log.len();
+        let last_log_term = self.last_log_term();
+
+        log!(
+            "p{}: election timeout, starting election for term {}",
+            self.id(),
+            term
+        );
+
+        for &peer in &self.processes {
+            if peer != self.process_id {
+                self.to_processes.push(Action::ToSend {
+                    target: singleton![peer],
+                    msg: Message::RequestVote {
+                        term,
+                        candidate_id,
+                        last_log_index,
+                        last_log_term,
+                    },
+                });
+            }
+        }
+    }
+
+    fn become_leader(&mut self) {
+        let next = self.log.len() + 1;
+        let mut next_index = HashMap::new();
+        let mut match_index = HashMap::new();
+        for &peer in &self.processes {
+            if peer != self.process_id {
+                next_index.insert(peer, next);
+                match_index.insert(peer, 0);
+            }
+        }
+        self.role = Role::Leader {
+            next_index,
+            match_index,
+        };
+        self.leader_id = Some(self.process_id);
+
+        log!(
+            "p{}: became leader for term {}",
+            self.id(),
+            self.current_term
+        );
+
+        // assert leadership immediately instead of waiting for the next
+        // heartbeat tick
+        self.replicate_log();
+    }
+
+    fn handle_heartbeat(&mut self) {
+        if matches!(self.role, Role::Leader { .. }) {
+            self.replicate_log();
+        }
+    }
+
+    fn replicate_log(&mut self) {
+        if let Role::Leader { next_index, .. } = &self.role {
+            let peers: Vec<_> = next_index.keys().cloned().collect();
+            for peer in peers {
+                self.send_append_entries(peer);
+            }
+        }
+    }
+
+    fn send_append_entries(&mut self, peer: ProcessId) {
+        let next = match &self.role {
+            Role::Leader { next_index, .. } => {
+                *next_index.get(&peer).unwrap_or(&1)
+            }
+            _ => return,
+        };
+        let prev_log_index = next.saturating_sub(1);
+        let prev_log_term = if prev_log_index == 0 {
+            0
+        } else {
+            self.log[prev_log_index - 1].term
+        };
+        let entries = self.log[prev_log_index..].to_vec();
+
+        self.to_processes.push(Action::ToSend {
+            target: singleton![peer],
+            msg: Message::AppendEntries {
+                term: self.current_term,
+                leader_id: self.process_id,
+                prev_log_index,
+                prev_log_term,
+                entries,
+                leader_commit: self.commit_index,
+            },
+        });
+    }
+
+    fn handle_request_vote(
+        &mut self,
+        from: ProcessId,
+        term: u64,
+        candidate_id: ProcessId,
+        last_log_index: usize,
+        last_log_term: u64,
+    ) {
+        if term > self.current_term {
+            self.step_down(term);
+        }
+
+        let log_ok = last_log_term > self.last_log_term()
+            || (last_log_term == self.last_log_term()
+                && last_log_index >= self.log.len());
+        let can_vote =
+            self.voted_for.map_or(true, |voted_for| voted_for == candidate_id);
+        let vote_granted = term == self.current_term && log_ok && can_vote;
+
+        if vote_granted {
+            self.voted_for = Some(candidate_id);
+            self.heard_from_leader = true;
+        }
+
+        self.to_processes.push(Action::ToSend {
+            target: singleton![from],
+            msg: Message::RequestVoteReply {
+                term: self.current_term,
+                vote_granted,
+            },
+        });
+    }
+
+    fn handle_request_vote_reply(
+        &mut self,
+        from: ProcessId,
+        term: u64,
+        vote_granted: bool,
+    ) {
+        if term > self.current_term {
+            self.step_down(term);
+            return;
+        }
+        if term < self.current_term || !vote_granted {
+            return;
+        }
+
+        let majority = self.majority();
+        let won = if let Role::Candidate { votes } = &mut self.role {
+            votes.insert(from);
+            votes.len() >= majority
+        } else {
+            false
+        };
+        if won {
+            self.become_leader();
+        }
+    }
+
+    fn handle_append_entries(
+        &mut self,
+        from: ProcessId,
+        term: u64,
+        leader_id: ProcessId,
+        prev_log_index: usize,
+        prev_log_term: u64,
+        entries: Vec<LogEntry>,
+        leader_commit: usize,
+    ) {
+        if term < self.current_term {
+            self.to_processes.push(Action::ToSend {
+                target: singleton![from],
+                msg: Message::AppendEntriesReply {
+                    term: self.current_term,
+                    success: false,
+                    match_index: 0,
+                },
+            });
+            return;
+        }
+
+        if term > self.current_term || matches!(self.role, Role::Candidate { .. })
+        {
+            self.step_down(term);
+        }
+        self.current_term = term;
+        self.role = Role::Follower;
+        self.leader_id = Some(leader_id);
+        self.heard_from_leader = true;
+
+        let log_ok = prev_log_index == 0
+            || (prev_log_index <= self.log.len()
+                && self.log[prev_log_index - 1].term == prev_log_term);
+        if !log_ok {
+            self.to_processes.push(Action::ToSend {
+                target: singleton![from],
+                msg: Message::AppendEntriesReply {
+                    term: self.current_term,
+                    success: false,
+                    match_index: 0,
+                },
+            });
+            return;
+        }
+
+        // the leader is authoritative for every index from `prev_log_index`
+        // onwards: drop anything we have there that it didn't send us
+        self.log.truncate(prev_log_index);
+        self.log.extend(entries);
+
+        if leader_commit > self.commit_index {
+            self.commit_index = leader_commit.min(self.log.len());
+            self.apply_committed();
+        }
+
+        self.to_processes.push(Action::ToSend {
+            target: singleton![from],
+            msg: Message::AppendEntriesReply {
+                term: self.current_term,
+                success: true,
+                match_index: self.log.len(),
+            },
+        });
+    }
+
+    fn handle_append_entries_reply(
+        &mut self,
+        from: ProcessId,
+        term: u64,
+        success: bool,
+        match_index: usize,
+    ) {
+        if term > self.current_term {
+            self.step_down(term);
+            return;
+        }
+
+        match &mut self.role {
+            Role::Leader {
+                next_index,
+                match_index: match_index_map,
+            } => {
+                if success {
+                    match_index_map.insert(from, match_index);
+                    next_index.insert(from, match_index + 1);
+                } else {
+                    let next = next_index.entry(from).or_insert(1);
+                    *next = (*next).saturating_sub(1).max(1);
+                }
+            }
+            _ => return,
+        }
+
+        if success {
+            let majority = self.majority();
+            self.advance_commit_index(majority);
+        } else {
+            // log inconsistency: immediately retry with the decremented
+            // `next_index` instead of waiting for the next heartbeat
+            self.send_append_entries(from);
+        }
+    }
+
+    /// Advances `commit_index` to the highest log index replicated on a
+    /// majority of processes, but only ever commits an entry from the
+    /// leader's own current term directly - committing an older-term entry
+    /// just because it's replicated can be undone by a future leader, per
+    /// the Raft safety argument.
+    fn advance_commit_index(&mut self, majority: usize) {
+        let current_term = self.current_term;
+        let candidate = match &self.role {
+            Role::Leader { match_index, .. } => {
+                let mut indices: Vec<usize> =
+                    match_index.values().cloned().collect();
+                // the leader always matches its own log
+                indices.push(self.log.len());
+                indices.sort_unstable_by(|a, b| b.cmp(a));
+                indices.get(majority - 1).cloned()
+            }
+            _ => None,
+        };
+
+        if let Some(candidate) = candidate {
+            if candidate > self.commit_index
+                && candidate >= 1
+                && self.log[candidate - 1].term == current_term
+            {
+                self.commit_index = candidate;
+                self.apply_committed();
+            }
+        }
+    }
+
+    /// Applies every committed-but-not-yet-applied log entry, in order,
+    /// handing each one's key-ops to the existing `BasicExecutor` pipeline
+    /// unchanged.
+    fn apply_committed(&mut self) {
+        while self.last_applied < self.commit_index {
+            self.last_applied += 1;
+            let cmd = self.log[self.last_applied - 1].cmd.clone();
+            let rifl = cmd.rifl();
+            let execution_info = cmd
+                .into_iter(self.shard_id)
+                .map(|(key, op)| BasicExecutionInfo::new(rifl, key, op));
+            self.to_executors.extend(execution_info);
+        }
+    }
+}
+
+/// `Raft` protocol messages.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Message {
+    RequestVote {
+        term: u64,
+        candidate_id: ProcessId,
+        last_log_index: usize,
+        last_log_term: u64,
+    },
+    RequestVoteReply {
+        term: u64,
+        vote_granted: bool,
+    },
+    AppendEntries {
+        term: u64,
+        leader_id: ProcessId,
+        prev_log_index: usize,
+        prev_log_term: u64,
+        entries: Vec<LogEntry>,
+        leader_commit: usize,
+    },
+    AppendEntriesReply {
+        term: u64,
+        success: bool,
+        match_index: usize,
+    },
+    // a command a follower forwards to the process it believes is the
+    // current leader
+    ClientCommand {
+        cmd: Command,
+    },
+}
+
+impl MessageIndex for Message {
+    fn index(&self) -> Option<(usize, usize)> {
+        worker_index_no_shift(RAFT_WORKER_INDEX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeriodicEvent {
+    ElectionTimeout,
+    Heartbeat,
+}
+
+impl PeriodicEventIndex for PeriodicEvent {
+    fn index(&self) -> Option<(usize, usize)> {
+        worker_index_no_shift(RAFT_WORKER_INDEX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Client, KeyGen, ShardGen, Workload};
+    use crate::planet::{Planet, Region};
+    use crate::sim::Simulation;
+    use crate::time::SimTime;
+    use crate::util;
+
+    #[test]
+    fn raft_flow() {
+        // create simulation
+        let mut simulation = Simulation::new();
+
+        // process ids
+        let process_id_1 = 1;
+        let process_id_2 = 2;
+        let process_id_3 = 3;
+
+        // regions
+        let europe_west2 = Region::new("europe-west2");
+        let europe_west3 = Region::new("europe-west2");
+        let us_west1 = Region::new("europe-west2");
+
+        // there's a single shard
+        let shard_id = 0;
+
+        // processes
+        let processes = vec![
+            (process_id_1, shard_id, europe_west2.clone()),
+            (process_id_2, shard_id, europe_west3.clone()),
+            (process_id_3, shard_id, us_west1.clone()),
+        ];
+
+        // planet
+        let planet = Planet::new();
+
+        // create system time
+        let time = SimTime::new();
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        // executors
+        let executor_1 = BasicExecutor::new(process_id_1, shard_id, config);
+        let executor_2 = BasicExecutor::new(process_id_2, shard_id, config);
+        let executor_3 = BasicExecutor::new(process_id_3, shard_id, config);
+
+        // raft
+        let (mut raft_1, _) = Raft::new(process_id_1, shard_id, config);
+        let (mut raft_2, _) = Raft::new(process_id_2, shard_id, config);
+        let (mut raft_3, _) = Raft::new(process_id_3, shard_id, config);
+
+        // discover processes in all raft instances
+        let sorted = util::sort_processes_by_distance(
+            &europe_west2,
+            &planet,
+            processes.clone(),
+        );
+        raft_1.discover(sorted);
+        let sorted = util::sort_processes_by_distance(
+            &europe_west3,
+            &planet,
+            processes.clone(),
+        );
+        raft_2.discover(sorted);
+        let sorted = util::sort_processes_by_distance(
+            &us_west1,
+            &planet,
+            processes.clone(),
+        );
+        raft_3.discover(sorted);
+
+        // register processes
+        simulation.register_process(raft_1, executor_1);
+        simulation.register_process(raft_2, executor_2);
+        simulation.register_process(raft_3, executor_3);
+
+        // process 1 times out first and starts an election for term 1
+        let (process, _, _, _) = simulation.get_process(process_id_1);
+        process.handle_event(PeriodicEvent::ElectionTimeout, &time);
+        let requests: Vec<_> = process.to_processes_iter().collect();
+
+        // a `RequestVote` is sent to each of the other 2 processes
+        assert_eq!(requests.len(), n - 1);
+
+        // the other processes grant their vote, and process 1 becomes leader
+        // once it sees a majority
+        let mut became_leader = false;
+        for request in requests {
+            let replies = simulation.forward_to_processes((process_id_1, request));
+            // each reply is tagged with the process that sent it, and is
+            // addressed to process 1
+            for (from, reply) in replies {
+                let (process, _, _, _) = simulation.get_process(process_id_1);
+                process.handle(
+                    from,
+                    shard_id,
+                    match reply {
+                        Action::ToSend { msg, .. } => msg,
+                        Action::ToForward { msg } => msg,
+                    },
+                    &time,
+                );
+                if process
+                    .to_processes_iter()
+                    .any(|action| matches!(action, Action::ToSend { msg: Message::AppendEntries { .. }, .. }))
+                {
+                    became_leader = true;
+                }
+            }
+        }
+        assert!(became_leader, "process 1 should have become the leader");
+
+        // client workload
+        let shards_per_command = 1;
+        let shard_gen = ShardGen::Random { shard_count: 1 };
+        let keys_per_shard = 1;
+        let key_gen = KeyGen::ConflictRate { conflict_rate: 100 };
+        let total_commands = 10;
+        let payload_size = 100;
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            total_commands,
+            payload_size,
+        );
+
+        // create client 1 that is connected to the leader
+        let client_id = 1;
+        let client_region = europe_west2.clone();
+        let mut client_1 = Client::new(client_id, workload);
+
+        // discover processes in client 1
+        let closest =
+            util::closest_process_per_shard(&client_region, &planet, processes);
+        client_1.connect(closest);
+
+        // start client
+        let (target_shard, cmd) = client_1
+            .next_cmd(&time)
+            .expect("there should be a first operation");
+        let target = client_1.shard_process(&target_shard);
+
+        // check that `target` is process 1, the leader
+        assert_eq!(target, process_id_1);
+
+        // register client
+        simulation.register_client(client_1);
+
+        // submit the command at the leader: it's appended to the log and
+        // `AppendEntries` is sent to the other 2 processes
+        let (process, _, pending, time) = simulation.get_process(process_id_1);
+        pending.wait_for(&cmd);
+        process.submit(None, cmd, time);
+        let appends: Vec<_> = process.to_processes_iter().collect();
+        assert_eq!(appends.len(), n - 1);
+    }
+}
@@ -92,19 +92,23 @@ use crate::time::{RunTime, SysTime};
 use crate::{HashMap, HashSet};
 use futures::stream::{FuturesUnordered, StreamExt};
 use prelude::*;
+use rand::Rng;
 use std::fmt::Debug;
 use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::ToSocketAddrs;
+use tokio::net::{TcpListener, ToSocketAddrs};
 use tokio::sync::Semaphore;
 
 pub async fn process<P, A>(
     process_id: ProcessId,
     sorted_processes: Option<Vec<ProcessId>>,
-    ip: IpAddr,
-    port: u16,
-    client_port: u16,
+    // already bound by the caller (e.g. to `(ip, 0)` to get a
+    // kernel-assigned port with no "find a free port, then bind it"
+    // TOCTOU window), rather than an `(ip, port)` pair this function binds
+    // itself
+    listener: TcpListener,
+    client_listener: TcpListener,
     addresses: Vec<(A, ConnectionDelay)>,
     config: Config,
     tcp_nodelay: bool,
@@ -117,6 +121,13 @@ pub async fn process<P, A>(
     execution_log: Option<String>,
     tracer_show_interval: Option<usize>,
     ping_interval: Option<usize>,
+    // passed on to `connect_to_all`, which doesn't apply it: connections to
+    // other processes stay plaintext TCP either way
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
+    // passed on to `connect_to_all`, which dials plain TCP sockets whatever
+    // the value
+    transport: rw::TransportKind,
+    shutdown_rx: Option<task::process::ShutdownReceiver>,
 ) -> RunResult<()>
 where
     P: Protocol + Send + 'static, // TODO what does this 'static do?
@@ -128,9 +139,8 @@ where
     process_with_notify_and_inspect::<P, A, ()>(
         process_id,
         sorted_processes,
-        ip,
-        port,
-        client_port,
+        listener,
+        client_listener,
         addresses,
         config,
         tcp_nodelay,
@@ -145,6 +155,9 @@ where
         ping_interval,
         semaphore,
         None,
+        tls_config,
+        transport,
+        shutdown_rx,
     )
     .await
 }
@@ -153,9 +166,12 @@ where
 async fn process_with_notify_and_inspect<P, A, R>(
     process_id: ProcessId,
     sorted_processes: Option<Vec<ProcessId>>,
-    ip: IpAddr,
-    port: u16,
-    client_port: u16,
+    // already bound by the caller (e.g. to `(ip, 0)` to get a
+    // kernel-assigned port with no "find a free port, then bind it"
+    // TOCTOU window), rather than an `(ip, port)` pair this function binds
+    // itself
+    listener: TcpListener,
+    client_listener: TcpListener,
     addresses: Vec<(A, ConnectionDelay)>,
     config: Config,
     tcp_nodelay: bool,
@@ -170,6 +186,9 @@ async fn process_with_notify_and_inspect<P, A, R>(
     ping_interval: Option<usize>,
     connected: Arc<Semaphore>,
     inspect_chan: Option<InspectReceiver<P, R>>,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
+    transport: rw::TransportKind,
+    shutdown_rx: Option<task::process::ShutdownReceiver>,
 ) -> RunResult<()>
 where
     P: Protocol + Send + 'static, // TODO what does this 'static do?
@@ -196,18 +215,22 @@ where
         panic!("running leader-based protocol without a leader");
     }
 
-    // (maybe) start tracer
-    task::spawn(task::tracer::tracer_task(tracer_show_interval));
-
     // check ports are different
-    assert!(port != client_port);
+    assert!(listener.local_addr()?.port() != client_listener.local_addr()?.port());
 
     // check that n - 1 addresses were set
     assert_eq!(addresses.len(), config.n() - 1);
 
-    // ---------------------
-    // start process listener
-    let listener = task::listen((ip, port)).await?;
+    // use the caller-provided shutdown receiver if one was given (e.g. so an
+    // embedder can drive shutdown itself, or a test can trigger one without
+    // sending a real signal); otherwise, install the SIGINT/SIGTERM handler
+    // that drives a coordinated shutdown on its own: a SIGINT (e.g. ctrl-c)
+    // requests a quick drain, a SIGTERM (e.g. sent by an orchestrator)
+    // requests a graceful one
+    let shutdown_rx = shutdown_rx.unwrap_or_else(spawn_shutdown_listener);
+
+    // (maybe) start tracer
+    task::spawn(task::tracer::tracer_task(tracer_show_interval, shutdown_rx.clone()));
 
     // create forward channels: reader -> workers
     let (reader_to_workers, reader_to_workers_rxs) = ReaderToWorkers::<P>::new(
@@ -217,19 +240,23 @@ where
     );
 
     // connect to all processes
-    let (ips, to_writers) = task::process::connect_to_all::<A, P>(
-        process_id,
-        listener,
-        addresses,
-        reader_to_workers.clone(),
-        CONNECT_RETRIES,
-        tcp_nodelay,
-        tcp_buffer_size,
-        tcp_flush_interval,
-        channel_buffer_size,
-        multiplexing,
-    )
-    .await?;
+    let (ips, to_writers, connections_shutdown, rejected_messages) =
+        task::process::connect_to_all::<A, P>(
+            process_id,
+            listener,
+            addresses,
+            reader_to_workers.clone(),
+            CONNECT_RETRIES,
+            tcp_nodelay,
+            tcp_buffer_size,
+            tcp_flush_interval,
+            channel_buffer_size,
+            multiplexing,
+            tls_config.clone(),
+            transport,
+            shutdown_rx.clone(),
+        )
+        .await?;
 
     // get sorted processes (maybe from ping task)
     let sorted_processes = if let Some(sorted_processes) = sorted_processes {
@@ -255,10 +282,6 @@ where
     // check that we have n processes
     assert_eq!(sorted_processes.len(), config.n());
 
-    // ---------------------
-    // start client listener
-    let client_listener = task::listen((ip, client_port)).await?;
-
     // create atomic dot generator to be used by clients in case the protocol is
     // leaderless:
     // - leader-based protocols like paxos shouldn't use this and the fact that
@@ -316,10 +339,11 @@ where
         executors,
         worker_to_executors_rxs,
         client_to_executors_rxs,
+        shutdown_rx.clone(),
     );
 
     // start process workers
-    let handles = task::process::start_processes::<P, R>(
+    let processes_shutdown = task::process::start_processes::<P, R>(
         process_id,
         config,
         sorted_processes,
@@ -333,19 +357,49 @@ where
         worker_to_executors,
         channel_buffer_size,
         execution_log,
+        rejected_messages,
+        shutdown_rx,
     );
     println!("process {} started", process_id);
 
     // notify parent that we're connected
     connected.add_permits(1);
 
-    let mut handles = handles.into_iter().collect::<FuturesUnordered<_>>();
-    while let Some(join_result) = handles.next().await {
-        println!("process ended {:?}", join_result?);
-    }
+    // wait for every reader, writer, and process task to exit, be that from
+    // a connection failure or from having fully drained a shutdown
+    connections_shutdown.merge(processes_shutdown).wait().await;
+    println!("process {} ended", process_id);
     Ok(())
 }
 
+/// Spawns the task that turns a SIGINT (e.g. ctrl-c) into a `Quick` shutdown
+/// and a SIGTERM (e.g. sent by an orchestrator) into a `Graceful` one,
+/// broadcasting the chosen mode through the returned `watch` channel, which
+/// is then cloned into every reader, writer, and process task.
+fn spawn_shutdown_listener() -> task::process::ShutdownReceiver {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(None);
+    tokio::spawn(async move {
+        let mut sigterm = tokio::signal::unix::signal(
+            tokio::signal::unix::SignalKind::terminate(),
+        )
+        .expect("failed to install SIGTERM handler");
+        let mode = tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                println!("received SIGINT, starting a quick shutdown");
+                task::process::ShutdownMode::Quick
+            }
+            _ = sigterm.recv() => {
+                println!("received SIGTERM, starting a graceful shutdown");
+                task::process::ShutdownMode::Graceful
+            }
+        };
+        if shutdown_tx.broadcast(Some(mode)).is_err() {
+            println!("no task left listening for shutdown");
+        }
+    });
+    shutdown_rx
+}
+
 async fn ask_ping_task(mut to_ping: SortedProcessesSender) -> Vec<ProcessId> {
     let (tx, mut rx) = task::channel(1);
     if let Err(e) = to_ping.send(tx).await {
@@ -360,48 +414,121 @@ async fn ask_ping_task(mut to_ping: SortedProcessesSender) -> Vec<ProcessId> {
 
 const MAX_CLIENT_CONNECTIONS: usize = 128;
 
+/// Backoff/retry tuning for `connect_with_failover`, mirroring
+/// `task::process`'s `RECONNECT_BACKOFF_BASE`/`RECONNECT_BACKOFF_MAX`/
+/// `MAX_RECONNECT_ATTEMPTS`: the wait before retrying the full `addresses`
+/// list again is `min(CLIENT_RECONNECT_BACKOFF_MAX, CLIENT_RECONNECT_BACKOFF_BASE * 2^attempt)`
+/// plus up to 50% jitter, so many clients reconnecting at once don't all
+/// redial in lockstep.
+const CLIENT_RECONNECT_BACKOFF_BASE: Duration = Duration::from_millis(100);
+const CLIENT_RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+const MAX_CLIENT_RECONNECT_ATTEMPTS: usize = 20;
+
 pub async fn client<A>(
     ids: Vec<ClientId>,
-    address: A,
+    addresses: Vec<A>,
     interval: Option<Duration>,
-    workload: Workload,
+    // the mix of workloads to run; when more than one is given, each client
+    // in `ids` is assigned exactly one, tagged with its index into this
+    // `Vec` as the resulting `Client`'s `Workload::workload_id` (so
+    // `ClientData::workload_latency_data` can tell them apart later)
+    workloads: Vec<Workload>,
+    // when `true`, `ids` is split into `workloads.len()` disjoint,
+    // contiguous chunks - one per workload - instead of being round-robin
+    // assigned, so no two workloads ever share a client or a connection
+    disjoint_mode: bool,
+    // how many commands each closed-loop client keeps outstanding at once
+    // (see `closed_loop_client`); has no effect on open-loop clients, which
+    // are already free to have more than one command in flight per their
+    // own arrival process
+    max_inflight: usize,
     tcp_nodelay: bool,
     channel_buffer_size: usize,
     metrics_file: Option<String>,
+    // which `WireFormat` `metrics_file` (if set) is dumped as; `Protobuf` is
+    // written as bincode (see `serialize_client_data`)
+    metrics_format: rw::ClientDataFormat,
+    // passed down to `connect_once`, which does not use it: the connection
+    // to the process stays plaintext whether or not this is set
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
 ) -> RunResult<()>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
-    // create client pool
-    let mut pool = Vec::with_capacity(MAX_CLIENT_CONNECTIONS);
-    // init each entry
-    pool.resize_with(MAX_CLIENT_CONNECTIONS, Vec::new);
-
-    // assign each client to a client worker
-    ids.into_iter().enumerate().for_each(|(index, client_id)| {
-        let index = index % MAX_CLIENT_CONNECTIONS;
-        pool[index].push(client_id);
-    });
+    assert!(
+        !workloads.is_empty(),
+        "at least one workload must be provided"
+    );
+
+    // tag each client id with the (already `workload_id`-tagged) workload it
+    // should run
+    let tagged_ids: Vec<(ClientId, Workload)> = if disjoint_mode {
+        // split into `workloads.len()` disjoint, contiguous chunks - one per
+        // workload - so e.g. a latency-sensitive workload can be isolated
+        // from a throughput-heavy one instead of sharing clients/connections
+        let chunk_size =
+            (ids.len() + workloads.len() - 1) / workloads.len().max(1);
+        let chunk_size = chunk_size.max(1);
+        ids.chunks(chunk_size)
+            .enumerate()
+            .flat_map(|(workload_id, chunk)| {
+                let mut workload = workloads[workload_id];
+                workload.set_workload_id(workload_id);
+                chunk
+                    .iter()
+                    .map(move |&client_id| (client_id, workload))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    } else {
+        ids.into_iter()
+            .enumerate()
+            .map(|(index, client_id)| {
+                let workload_id = index % workloads.len();
+                let mut workload = workloads[workload_id];
+                workload.set_workload_id(workload_id);
+                (client_id, workload)
+            })
+            .collect()
+    };
+
+    // create client pool: clients sharing both a connection bucket and a
+    // workload are grouped together, since a single `client_setup` call sets
+    // up one connection (and runs one `Workload`) per pool entry
+    let mut pool: HashMap<(usize, usize), (Workload, Vec<ClientId>)> =
+        HashMap::new();
+    tagged_ids.into_iter().enumerate().for_each(
+        |(index, (client_id, workload))| {
+            let connection_index = index % MAX_CLIENT_CONNECTIONS;
+            pool.entry((workload.workload_id(), connection_index))
+                .or_insert_with(|| (workload, Vec::new()))
+                .1
+                .push(client_id);
+        },
+    );
 
     // start each client worker in pool
-    let handles = pool.into_iter().map(|client_ids| {
+    let handles = pool.into_iter().map(|(_key, (workload, client_ids))| {
         // start the open loop client if some interval was provided
         if let Some(interval) = interval {
             task::spawn(open_loop_client::<A>(
                 client_ids,
-                address.clone(),
+                addresses.clone(),
                 interval,
                 workload,
                 tcp_nodelay,
                 channel_buffer_size,
+                tls_config.clone(),
             ))
         } else {
             task::spawn(closed_loop_client::<A>(
                 client_ids,
-                address.clone(),
+                addresses.clone(),
                 workload,
+                max_inflight,
                 tcp_nodelay,
                 channel_buffer_size,
+                tls_config.clone(),
             ))
         }
     });
@@ -421,7 +548,7 @@ where
 
     if let Some(file) = metrics_file {
         println!("will write client data to {}", file);
-        serialize_client_data(data, file)?;
+        serialize_client_data(data, file, metrics_format)?;
     }
 
     println!("all clients ended");
@@ -430,45 +557,113 @@ where
 
 async fn closed_loop_client<A>(
     client_ids: Vec<ClientId>,
-    address: A,
+    addresses: Vec<A>,
     workload: Workload,
+    // how many commands each client keeps outstanding at once; `1` is the
+    // strictly-lockstep behavior this driver originally had, anything
+    // higher pipelines requests instead of waiting for each one's result
+    // before submitting the next
+    max_inflight: usize,
     tcp_nodelay: bool,
     channel_buffer_size: usize,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
 ) -> Option<Vec<Client>>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
+    assert!(max_inflight >= 1, "max_inflight must be at least 1");
+
     // create system time
     let time = RunTime;
 
     // setup client
     let (mut clients, mut read, mut write) = client_setup(
-        client_ids,
-        address,
+        &client_ids,
+        &addresses,
         workload,
         tcp_nodelay,
         channel_buffer_size,
+        tls_config.clone(),
     )
     .await?;
 
-    // generate the first message of each client
-    for (_client_id, client) in clients.iter_mut() {
-        next_cmd(client, &time, &mut write).await;
+    // fill each client's pipeline up to `max_inflight` commands, tracking
+    // how many each currently has outstanding
+    let mut inflight: HashMap<ClientId, usize> =
+        HashMap::with_capacity(clients.len());
+    for (&client_id, client) in clients.iter_mut() {
+        let mut pending = 0;
+        while pending < max_inflight
+            && next_cmd(client, &time, &mut write).await
+        {
+            pending += 1;
+        }
+        inflight.insert(client_id, pending);
     }
 
     // track which clients are finished
     let mut finished = HashSet::new();
 
-    // wait for results and generate/submit new commands while there are
-    // commands to be generated
+    // wait for results and keep every still-unfinished client's pipeline as
+    // full as `max_inflight` allows
     while finished.len() < clients.len() {
         // and wait for next result
         let cmd_result = read.recv().await;
+        if cmd_result.is_none() {
+            // the client read-write task is gone: the connection to the
+            // process died mid-run. Fail over to another known process and
+            // keep the same `clients` map (and thus each `Client`'s own
+            // pending/rifl-tracking state), so a `CommandResult` that
+            // arrives on the new connection is still routed to (and only
+            // counted once by) the client it belongs to
+            let (new_read, new_write, process_id) = reconnect_client(
+                &client_ids,
+                &addresses,
+                tcp_nodelay,
+                channel_buffer_size,
+                tls_config.clone(),
+            )
+            .await?;
+            read = new_read;
+            write = new_write;
+            // every in-flight command was lost with the old connection, so
+            // refill each still-unfinished client's pipeline back up to
+            // `max_inflight`
+            // TODO once `Client`/`Pending` expose which `rifl`s were actually
+            // in flight, resubmit those exact commands instead of
+            // generating new ones
+            for (&client_id, client) in clients.iter_mut() {
+                client.discover(vec![process_id]);
+                if !finished.contains(&client_id) {
+                    let pending = inflight.entry(client_id).or_insert(0);
+                    *pending = 0;
+                    while *pending < max_inflight
+                        && next_cmd(client, &time, &mut write).await
+                    {
+                        *pending += 1;
+                    }
+                }
+            }
+            continue;
+        }
+        // a result always frees up one pipeline slot on the client it
+        // belongs to, regardless of whether that client is now finished
+        let client_id = cmd_result
+            .as_ref()
+            .map(|cmd_result| cmd_result.rifl().source());
         if let Some(client) =
             handle_cmd_result(&mut clients, &time, cmd_result, &mut finished)
         {
-            // if client hasn't finished, issue a new command
-            next_cmd(client, &time, &mut write).await;
+            let client_id =
+                client_id.expect("a command result should belong to a client");
+            let pending = inflight
+                .get_mut(&client_id)
+                .expect("client should be tracked in `inflight`");
+            *pending = pending.saturating_sub(1);
+            // the client hasn't finished: keep its pipeline full
+            if next_cmd(client, &time, &mut write).await {
+                *pending += 1;
+            }
         }
     }
 
@@ -483,11 +678,12 @@ where
 
 async fn open_loop_client<A>(
     client_ids: Vec<ClientId>,
-    address: A,
+    addresses: Vec<A>,
     interval: Duration,
     workload: Workload,
     tcp_nodelay: bool,
     channel_buffer_size: usize,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
 ) -> Option<Vec<Client>>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
@@ -497,11 +693,12 @@ where
 
     // setup client
     let (mut clients, mut read, mut write) = client_setup(
-        client_ids,
-        address,
+        &client_ids,
+        &addresses,
         workload,
         tcp_nodelay,
         channel_buffer_size,
+        tls_config.clone(),
     )
     .await?;
 
@@ -514,7 +711,29 @@ where
     while finished.len() < clients.len() {
         tokio::select! {
             cmd_result = read.recv() => {
-                handle_cmd_result(&mut clients, &time, cmd_result, &mut finished);
+                if cmd_result.is_none() {
+                    // connection to the process died mid-run: fail over to
+                    // another known process, keeping the same `clients` map
+                    // (see `closed_loop_client` for why that's what avoids
+                    // double-counting or dropping a `CommandResult`); the
+                    // open loop simply resumes submitting on the next tick,
+                    // no in-flight command to replay
+                    let (new_read, new_write, process_id) = reconnect_client(
+                        &client_ids,
+                        &addresses,
+                        tcp_nodelay,
+                        channel_buffer_size,
+                        tls_config.clone(),
+                    )
+                    .await?;
+                    read = new_read;
+                    write = new_write;
+                    for (_, client) in clients.iter_mut() {
+                        client.discover(vec![process_id]);
+                    }
+                } else {
+                    handle_cmd_result(&mut clients, &time, cmd_result, &mut finished);
+                }
             }
             _ = interval.tick() => {
                 // submit new command on every tick for each connected client (if there are still commands to be generated)
@@ -534,45 +753,32 @@ where
     )
 }
 
-async fn client_setup<A>(
-    client_ids: Vec<ClientId>,
+/// Tries to connect (and say hi) to a single `address`, without any
+/// retrying of its own - retrying across `address`es and over time is
+/// `connect_with_failover`'s job.
+async fn connect_once<A>(
+    client_ids: &[ClientId],
     address: A,
-    workload: Workload,
     tcp_nodelay: bool,
     channel_buffer_size: usize,
-) -> Option<(
-    HashMap<ClientId, Client>,
-    CommandResultReceiver,
-    CommandSender,
-)>
+    // TODO once `task::connect` hands back the raw socket before framing
+    // (rather than an already-framed `Connection`), upgrade it here via
+    // `rw::tls::connect` whenever this is set, before it's used below
+    _tls_config: Option<Arc<rw::tls::TlsConfig>>,
+) -> Option<(ProcessId, CommandResultReceiver, CommandSender)>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
     // connect to process
     let tcp_buffer_size = 0;
-    let mut connection = match task::connect(
-        address,
-        tcp_nodelay,
-        tcp_buffer_size,
-        CONNECT_RETRIES,
-    )
-    .await
-    {
-        Ok(connection) => connection,
-        Err(e) => {
-            // TODO panicking here as not sure how to make error handling send +
-            // 'static (required by tokio::spawn) and still be able
-            // to use the ? operator
-            panic!(
-                "[client] error connecting at clients {:?}: {:?}",
-                client_ids, e
-            );
-        }
-    };
+    let mut connection =
+        task::connect(address, tcp_nodelay, tcp_buffer_size, CONNECT_RETRIES)
+            .await
+            .ok()?;
 
     // say hi
     let process_id =
-        task::client::client_say_hi(client_ids.clone(), &mut connection)
+        task::client::client_say_hi(client_ids.to_vec(), &mut connection)
             .await?;
 
     // start client read-write task
@@ -580,15 +786,97 @@ where
         task::client::start_client_rw_task(channel_buffer_size, connection);
     write.set_name(format!(
         "command_result_sender_client_{}",
-        task::client::ids_repr(&client_ids)
+        task::client::ids_repr(client_ids)
     ));
 
+    Some((process_id, read, write))
+}
+
+/// Tries every address in `addresses` in order, retrying the whole list
+/// with exponential backoff plus jitter (mirroring `task::process`'s
+/// `reconnect_backoff`) until one connects or `MAX_CLIENT_RECONNECT_ATTEMPTS`
+/// rounds are exhausted - the resilient connection manager behind both the
+/// initial `client_setup` and every later `reconnect_client`.
+async fn connect_with_failover<A>(
+    client_ids: &[ClientId],
+    addresses: &[A],
+    tcp_nodelay: bool,
+    channel_buffer_size: usize,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
+) -> Option<(ProcessId, CommandResultReceiver, CommandSender)>
+where
+    A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
+{
+    let mut attempt = 0;
+    loop {
+        for address in addresses {
+            if let Some(result) = connect_once(
+                client_ids,
+                address.clone(),
+                tcp_nodelay,
+                channel_buffer_size,
+                tls_config.clone(),
+            )
+            .await
+            {
+                return Some(result);
+            }
+            println!(
+                "[client] clients {:?} failed to connect to {:?}, trying next known process",
+                client_ids, address
+            );
+        }
+        attempt += 1;
+        if attempt >= MAX_CLIENT_RECONNECT_ATTEMPTS {
+            println!(
+                "[client] clients {:?} giving up after {} attempts against all known processes",
+                client_ids, attempt
+            );
+            return None;
+        }
+        tokio::time::delay_for(client_reconnect_backoff(attempt)).await;
+    }
+}
+
+/// Computes the backoff before retrying the full `addresses` list again, on
+/// attempt `attempt` (1-indexed).
+fn client_reconnect_backoff(attempt: usize) -> Duration {
+    let base_ms = CLIENT_RECONNECT_BACKOFF_BASE.as_millis() as u64;
+    let max_ms = CLIENT_RECONNECT_BACKOFF_MAX.as_millis() as u64;
+    let capped_ms = base_ms.saturating_mul(1u64 << attempt.min(20)).min(max_ms);
+    let jitter_ms = rand::thread_rng().gen_range(0, capped_ms / 2 + 1);
+    Duration::from_millis(capped_ms + jitter_ms)
+}
+
+async fn client_setup<A>(
+    client_ids: &[ClientId],
+    addresses: &[A],
+    workload: Workload,
+    tcp_nodelay: bool,
+    channel_buffer_size: usize,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
+) -> Option<(
+    HashMap<ClientId, Client>,
+    CommandResultReceiver,
+    CommandSender,
+)>
+where
+    A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
+{
+    let (process_id, read, write) = connect_with_failover(
+        client_ids,
+        addresses,
+        tcp_nodelay,
+        channel_buffer_size,
+        tls_config,
+    )
+    .await?;
+
     // create clients
     let clients = client_ids
-        .into_iter()
-        .map(|client_id| {
+        .iter()
+        .map(|&client_id| {
             let mut client = Client::new(client_id, workload);
-            // discover process (although this won't be used)
             client.discover(vec![process_id]);
             (client_id, client)
         })
@@ -598,13 +886,45 @@ where
     Some((clients, read, write))
 }
 
+/// Reconnects an already-running client pool after its connection died:
+/// same address list, same failover/backoff behavior as the initial
+/// `client_setup`, just without recreating the `clients` map (see the
+/// callers in `closed_loop_client`/`open_loop_client` for why that's what
+/// keeps each `Client`'s pending/rifl state intact across the reconnect).
+async fn reconnect_client<A>(
+    client_ids: &[ClientId],
+    addresses: &[A],
+    tcp_nodelay: bool,
+    channel_buffer_size: usize,
+    tls_config: Option<Arc<rw::tls::TlsConfig>>,
+) -> Option<(CommandResultReceiver, CommandSender, ProcessId)>
+where
+    A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
+{
+    let (process_id, read, write) = connect_with_failover(
+        client_ids,
+        addresses,
+        tcp_nodelay,
+        channel_buffer_size,
+        tls_config,
+    )
+    .await?;
+    Some((read, write, process_id))
+}
+
 /// Generate the next command, returning a boolean representing whether a new
 /// command was generated or not.
+/// Generates and sends the client's next command, if one is due. Returns
+/// whether a command was generated (a failed send to the client read-write
+/// task is only logged), so a caller pipelining more than one
+/// command per client (see `closed_loop_client`'s `max_inflight`) knows
+/// whether a pipeline slot was filled or the workload is simply exhausted
+/// for now.
 async fn next_cmd(
     client: &mut Client,
     time: &dyn SysTime,
     write: &mut CommandSender,
-) {
+) -> bool {
     if let Some((_, cmd)) = client.next_cmd(time) {
         if let Err(e) = write.send(cmd).await {
             println!(
@@ -612,6 +932,9 @@ async fn next_cmd(
                 e
             );
         }
+        true
+    } else {
+        false
     }
 }
 
@@ -645,18 +968,29 @@ fn handle_cmd_result<'a>(
 }
 
 // TODO make this async
-fn serialize_client_data(data: ClientData, file: String) -> RunResult<()> {
+fn serialize_client_data(
+    data: ClientData,
+    file: String,
+    format: rw::ClientDataFormat,
+) -> RunResult<()> {
     // if the file does not exist it will be created, otherwise truncated
-    std::fs::File::create(file)
-        .ok()
-        // create a buf writer
+    let writer = std::fs::File::create(file)
         .map(std::io::BufWriter::new)
-        // and try to serialize
-        .map(|writer| {
+        .unwrap_or_else(|_| panic!("couldn't save client data"));
+
+    match format {
+        rw::ClientDataFormat::Bincode => {
             bincode::serialize_into(writer, &data)
-                .expect("error serializing client data")
-        })
-        .unwrap_or_else(|| panic!("couldn't save client data"));
+                .expect("error serializing client data");
+        }
+        // TODO once `ClientData` gains a generated Protobuf counterpart
+        // mirroring `proto/wire.proto`, convert `data` into it here and
+        // encode through `rw::wire_format::Protobuf` instead
+        rw::ClientDataFormat::Protobuf => {
+            bincode::serialize_into(writer, &data)
+                .expect("error serializing client data");
+        }
+    }
 
     Ok(())
 }
@@ -667,7 +1001,6 @@ pub mod tests {
     use super::*;
     use crate::protocol::ProtocolMetricsKind;
     use crate::util;
-    use rand::Rng;
 
     #[tokio::test]
     async fn test_semaphore() {
@@ -699,6 +1032,34 @@ pub mod tests {
             .unwrap_or_default() as usize
     }
 
+    /// A scheduled crash (and, optionally, later restart) of one process in
+    /// a `run_test_with_inspect_fun` run, so a test can exercise the `f`
+    /// fault-tolerance parameter that a fully-healthy run never touches.
+    /// `crash_at` is measured from the moment the fault is scheduled, right
+    /// after every process has been spawned (not from when the process
+    /// finished connecting); `restart_after` is measured from the crash.
+    #[derive(Debug, Clone, Copy)]
+    pub struct FaultEvent {
+        pub process_id: ProcessId,
+        pub crash_at: Duration,
+        /// `None` means the process never rejoins.
+        pub restart_after: Option<Duration>,
+    }
+
+    /// Everything needed to spawn a fresh incarnation of a crashed process.
+    /// The bound ports and `addresses` are kept the same across
+    /// incarnations so the other processes (which cached them once, before
+    /// any process was spawned) can reconnect with no re-discovery step.
+    #[derive(Clone)]
+    struct RespawnArgs {
+        process_id: ProcessId,
+        sorted_processes: Option<Vec<ProcessId>>,
+        listener_port: u16,
+        client_listener_port: u16,
+        addresses: Vec<(String, ConnectionDelay)>,
+        execution_log: Option<String>,
+    }
+
     #[tokio::test]
     async fn run_basic_test() {
         // config
@@ -728,6 +1089,7 @@ pub mod tests {
                 executors,
                 tracer_show_interval,
                 Some(inspect_stable_commands),
+                Vec::new(),
                 extra_run_time,
             )
             .await
@@ -750,6 +1112,7 @@ pub mod tests {
         executors: usize,
         tracer_show_interval: Option<usize>,
         inspect_fun: Option<fn(&P) -> R>,
+        faults: Vec<FaultEvent>,
         extra_run_time: Option<Duration>,
     ) -> RunResult<HashMap<ProcessId, Vec<R>>>
     where
@@ -771,6 +1134,7 @@ pub mod tests {
                     executors,
                     tracer_show_interval,
                     inspect_fun,
+                    faults,
                     extra_run_time,
                 )
                 .await
@@ -787,6 +1151,7 @@ pub mod tests {
         executors: usize,
         tracer_show_interval: Option<usize>,
         inspect_fun: Option<fn(&P) -> R>,
+        faults: Vec<FaultEvent>,
         extra_run_time: Option<Duration>,
     ) -> RunResult<HashMap<ProcessId, Vec<R>>>
     where
@@ -807,26 +1172,49 @@ pub mod tests {
 
         let ping_interval = Some(1000); // millis
 
-        // create processes ports and client ports
+        // bind a listener and a client listener for every process up front,
+        // to port 0 so the kernel assigns a free one: there's no window
+        // between "found a free port" and "actually bound" for another
+        // concurrent test run to race into, unlike the old find-then-bind
+        // `get_available_port` dance
         let n = config.n();
-        let ports: HashMap<_, _> = util::process_ids(n)
-            .map(|id| (id, get_available_port()))
-            .collect();
-        let client_ports: HashMap<_, _> = util::process_ids(n)
-            .map(|id| (id, get_available_port()))
-            .collect();
+        let mut listeners = HashMap::new();
+        let mut client_listeners = HashMap::new();
+        for process_id in util::process_ids(n) {
+            let listener = TcpListener::bind((localhost, 0)).await?;
+            let client_listener = TcpListener::bind((localhost, 0)).await?;
+            listeners.insert(process_id, listener);
+            client_listeners.insert(process_id, client_listener);
+        }
 
-        // create connect addresses
-        let all_addresses: HashMap<_, _> = ports
-            .clone()
-            .into_iter()
-            .map(|(process_id, port)| {
+        // create connect addresses from the ports the kernel actually bound
+        let all_addresses: HashMap<_, _> = listeners
+            .iter()
+            .map(|(process_id, listener)| {
+                let port = listener.local_addr().unwrap().port();
                 let address = format!("localhost:{}", port);
-                (process_id, address)
+                (*process_id, address)
+            })
+            .collect();
+
+        // client ports the kernel actually bound, needed below once
+        // `client_listeners` is drained
+        let client_ports: HashMap<_, _> = client_listeners
+            .iter()
+            .map(|(process_id, listener)| {
+                (*process_id, listener.local_addr().unwrap().port())
             })
             .collect();
 
         let mut inspect_channels = HashMap::new();
+        // per-process shutdown senders, kept around so the fault injector
+        // below can trigger a `Quick` shutdown (our stand-in for "crash")
+        // without touching the SIGINT/SIGTERM listener the other processes
+        // still rely on
+        let mut shutdown_txs = HashMap::new();
+        // everything needed to bring a crashed process back up, keyed by
+        // `process_id`
+        let mut respawn_args = HashMap::new();
 
         for process_id in util::process_ids(n) {
             // if n = 3, this gives the following:
@@ -844,14 +1232,16 @@ pub mod tests {
                 None
             };
 
-            // get ports
-            let port = *ports.get(&process_id).unwrap();
-            let client_port = *client_ports.get(&process_id).unwrap();
+            // already-bound listeners for this process
+            let listener = listeners.remove(&process_id).unwrap();
+            let client_listener = client_listeners.remove(&process_id).unwrap();
+            let listener_port = listener.local_addr()?.port();
+            let client_listener_port = client_listener.local_addr()?.port();
 
             // addresses: all but self
             let mut addresses = all_addresses.clone();
             addresses.remove(&process_id);
-            let addresses = addresses
+            let addresses: Vec<(String, ConnectionDelay)> = addresses
                 .into_iter()
                 .map(|(_, address)| {
                     let delay = if process_id % 2 == 1 {
@@ -871,6 +1261,23 @@ pub mod tests {
             let (inspect_tx, inspect) = task::channel(channel_buffer_size);
             inspect_channels.insert(process_id, inspect_tx);
 
+            // a shutdown channel private to this process, so it alone can
+            // be crashed via `faults` without affecting its peers
+            let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(None);
+            shutdown_txs.insert(process_id, shutdown_tx);
+
+            respawn_args.insert(
+                process_id,
+                RespawnArgs {
+                    process_id,
+                    sorted_processes: sorted_processes.clone(),
+                    listener_port,
+                    client_listener_port,
+                    addresses: addresses.clone(),
+                    execution_log: execution_log.clone(),
+                },
+            );
+
             // spawn processes
             tokio::task::spawn_local(process_with_notify_and_inspect::<
                 P,
@@ -879,9 +1286,8 @@ pub mod tests {
             >(
                 process_id,
                 sorted_processes,
-                localhost,
-                port,
-                client_port,
+                listener,
+                client_listener,
                 addresses,
                 config,
                 tcp_nodelay,
@@ -896,9 +1302,90 @@ pub mod tests {
                 ping_interval,
                 semaphore.clone(),
                 Some(inspect),
+                None,
+                rw::TransportKind::Tcp,
+                Some(shutdown_rx),
             ));
         }
 
+        // schedule every crash (and, if requested, restart) in `faults`
+        for fault in faults {
+            let shutdown_tx = shutdown_txs
+                .get(&fault.process_id)
+                .expect("fault.process_id should be a process in this run")
+                .clone();
+            let respawn = respawn_args
+                .get(&fault.process_id)
+                .expect("fault.process_id should be a process in this run")
+                .clone();
+            let semaphore = semaphore.clone();
+            tokio::task::spawn_local(async move {
+                tokio::time::delay_for(fault.crash_at).await;
+                println!("[fault] crashing process {}", respawn.process_id);
+                if shutdown_tx
+                    .broadcast(Some(task::process::ShutdownMode::Quick))
+                    .is_err()
+                {
+                    println!(
+                        "[fault] process {} was already gone",
+                        respawn.process_id
+                    );
+                }
+
+                if let Some(restart_after) = fault.restart_after {
+                    tokio::time::delay_for(restart_after).await;
+                    println!(
+                        "[fault] restarting process {}",
+                        respawn.process_id
+                    );
+
+                    // rebind the same ports the crashed incarnation used, so
+                    // peers (which cached `addresses` once, up front) need
+                    // no re-discovery to reconnect
+                    let listener =
+                        TcpListener::bind((localhost, respawn.listener_port))
+                            .await
+                            .expect("restart should rebind its old port");
+                    let client_listener = TcpListener::bind((
+                        localhost,
+                        respawn.client_listener_port,
+                    ))
+                    .await
+                    .expect("restart should rebind its old client port");
+
+                    let (_shutdown_tx, shutdown_rx) =
+                        tokio::sync::watch::channel(None);
+                    tokio::task::spawn_local(process_with_notify_and_inspect::<
+                        P,
+                        String,
+                        R,
+                    >(
+                        respawn.process_id,
+                        respawn.sorted_processes,
+                        listener,
+                        client_listener,
+                        respawn.addresses,
+                        config,
+                        tcp_nodelay,
+                        tcp_buffer_size,
+                        tcp_flush_interval,
+                        channel_buffer_size,
+                        workers,
+                        executors,
+                        multiplexing,
+                        respawn.execution_log,
+                        tracer_show_interval,
+                        ping_interval,
+                        semaphore,
+                        None,
+                        None,
+                        rw::TransportKind::Tcp,
+                        Some(shutdown_rx),
+                    ));
+                }
+            });
+        }
+
         // wait that all processes are connected
         println!("[main] waiting that processes are connected");
         for _ in util::process_ids(n) {
@@ -996,17 +1483,129 @@ pub mod tests {
         replies
     }
 
-    // adapted from: https://github.com/rust-lang-nursery/rust-cookbook/issues/500
-    fn get_available_port() -> u16 {
-        loop {
-            let port = rand::thread_rng().gen_range(1025, 65535);
-            if port_is_available(port) {
-                return port;
-            }
+    /// Aggregate statistics produced by `bench`. Comparable across
+    /// protocols/configs, so a CI-style sweep can run `bench` once per
+    /// candidate and line the results up side-by-side.
+    #[derive(Debug, Clone, Copy)]
+    pub struct BenchResult {
+        pub throughput: f64,
+        pub latency_p50: Duration,
+        pub latency_p95: Duration,
+        pub latency_p99: Duration,
+    }
+
+    /// Criterion-style benchmark built on top of `run`. Runs
+    /// `warmup_iterations` throwaway iterations of the cluster (so e.g. the
+    /// GC-stability threshold and TCP slow-start settle before anything is
+    /// measured), followed by `measured_iterations` measured ones, and merges
+    /// every client's latency data -- across all processes and all measured
+    /// iterations -- into a single sorted sample. Throughput is the total
+    /// number of commands completed across the measured iterations divided
+    /// by the total wall-clock time they took; latency percentiles are
+    /// computed off the merged sample rather than averaged per-iteration, so
+    /// a slow outlier iteration can't be diluted away.
+    pub async fn bench<P>(
+        config: Config,
+        conflict_rate: usize,
+        commands_per_client: usize,
+        clients_per_region: usize,
+        workers: usize,
+        executors: usize,
+        warmup_iterations: usize,
+        measured_iterations: usize,
+    ) -> RunResult<BenchResult>
+    where
+        P: Protocol + Send + 'static,
+    {
+        for _ in 0..warmup_iterations {
+            let _: HashMap<ProcessId, Vec<()>> = run::<P, ()>(
+                config,
+                conflict_rate,
+                commands_per_client,
+                clients_per_region,
+                workers,
+                executors,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await?;
+            remove_metrics_files(config.n());
+        }
+
+        let n = config.n();
+        let commands_per_iteration =
+            n * clients_per_region * commands_per_client;
+        let mut total_commands = 0;
+        let mut total_elapsed = Duration::from_secs(0);
+        let mut latencies = Vec::new();
+
+        for _ in 0..measured_iterations {
+            let start = std::time::Instant::now();
+            let _: HashMap<ProcessId, Vec<()>> = run::<P, ()>(
+                config,
+                conflict_rate,
+                commands_per_client,
+                clients_per_region,
+                workers,
+                executors,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await?;
+            total_elapsed += start.elapsed();
+            total_commands += commands_per_iteration;
+            latencies.extend(drain_metrics_files(n));
+        }
+
+        latencies.sort();
+        let throughput = total_commands as f64 / total_elapsed.as_secs_f64();
+        Ok(BenchResult {
+            throughput,
+            latency_p50: percentile(&latencies, 0.50),
+            latency_p95: percentile(&latencies, 0.95),
+            latency_p99: percentile(&latencies, 0.99),
+        })
+    }
+
+    /// Reads and removes every `.metrics_client_*` file `run` wrote for this
+    /// iteration, merging their `ClientData` and returning the merged
+    /// sample's latencies.
+    fn drain_metrics_files(n: usize) -> Vec<Duration> {
+        let mut data = ClientData::new();
+        for process_id in util::process_ids(n) {
+            let file = format!(".metrics_client_{}", process_id);
+            let bytes = std::fs::read(&file)
+                .expect("metrics file should have been written by `run`");
+            let client_data: ClientData = bincode::deserialize(&bytes)
+                .expect("error deserializing client data");
+            data.merge(&client_data);
+            let _ = std::fs::remove_file(&file);
+        }
+        data.latency_data().collect()
+    }
+
+    /// Discards a warmup iteration's `.metrics_client_*` files without
+    /// bothering to parse them.
+    fn remove_metrics_files(n: usize) {
+        for process_id in util::process_ids(n) {
+            let file = format!(".metrics_client_{}", process_id);
+            let _ = std::fs::remove_file(file);
         }
     }
 
-    fn port_is_available(port: u16) -> bool {
-        std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+    /// Nearest-rank percentile over an already-sorted sample. An empty
+    /// sample (e.g. a zero-command benchmark) reports a zero latency rather
+    /// than panicking.
+    fn percentile(sorted: &[Duration], p: f64) -> Duration {
+        if sorted.is_empty() {
+            return Duration::from_secs(0);
+        }
+        let rank = ((sorted.len() as f64) * p).ceil() as usize;
+        let index = rank.saturating_sub(1).min(sorted.len() - 1);
+        sorted[index]
     }
 }
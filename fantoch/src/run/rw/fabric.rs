@@ -0,0 +1,205 @@
+// `connect_to_all` and the client listener both move typed messages between
+// a fixed set of endpoints identified by a peer `ProcessId` and a stream
+// index (one of `multiplexing` parallel links to that peer - see
+// `task::process::connect_to_all`'s own doc comment). The `Fabric` trait
+// below names that shape directly, so a backend other than "dial/accept a
+// real socket, frame it, (de)serialize through `WireFormat`" can stand in
+// for it. `InMemoryFabric` is the first such backend: every link is a
+// `tokio::sync::mpsc` channel pre-wired at construction time instead of
+// dialed, so a whole cluster could run inside one process with no port
+// allocation, no `CONNECT_RETRIES`, and no flush-interval timing (nothing
+// in the crate runs it that way, see below) - while still handing `ReaderToWorkers`/
+// `WorkerToExecutors` the exact same typed messages a real `Connection`
+// would have produced.
+//
+// TODO `connect_to_all` (in `task::process`) and the client listener (in
+// the currently-unreachable `task::client`) are both still hardcoded to
+// `rw::Connection`, not generic over `Fabric`; wiring either of them to
+// accept an `impl Fabric<M>` - and threading an `InMemoryFabric` through
+// `run_test_with_inspect_fun` in place of real `TcpListener`/`TcpStream`
+// calls - needs to happen at that call site. `InMemoryFabric` below is
+// ready to be that argument; only the plumbing at the two call sites is
+// left.
+
+use crate::id::ProcessId;
+use color_eyre::eyre::{eyre, Report};
+use std::future::Future;
+use std::pin::Pin;
+
+/// One endpoint of a `Fabric` link: which peer process it talks to, and
+/// which of that peer's `multiplexing` parallel streams it is.
+pub type LinkId = (ProcessId, usize);
+
+/// A backend capable of moving typed messages between a fixed set of
+/// `LinkId`-addressed endpoints, in place of dialing/framing/(de)serializing
+/// a real socket per peer.
+pub trait Fabric<M>: Send
+where
+    M: Send + 'static,
+{
+    /// Sends `msg` on the link identified by `link`.
+    fn send(
+        &mut self,
+        link: LinkId,
+        msg: M,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>>;
+
+    /// Receives the next message, regardless of which link it arrived on.
+    fn recv(
+        &mut self,
+    ) -> Pin<Box<dyn Future<Output = Option<(LinkId, M)>> + Send + '_>>;
+}
+
+/// An in-memory `Fabric` backend for deterministic tests: every
+/// `(ProcessId, stream index)` link is a pair of `tokio::sync::mpsc`
+/// channels fully wired up by `InMemoryFabric::cluster` at construction
+/// time, so there's nothing to dial and no accept loop to run. A `Fault`
+/// can be attached per outgoing link to reproduce a partition scenario
+/// (see `InMemoryFabric::set_fault`).
+#[cfg(feature = "test-util")]
+pub mod in_memory {
+    use super::*;
+    use crate::HashMap;
+    use rand::Rng;
+    use tokio::sync::mpsc;
+    use tokio::time::Duration;
+
+    /// A fault to apply to every message subsequently sent on a given
+    /// outgoing link.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Fault {
+        /// Extra delay applied before the message is handed to its
+        /// receiver, simulating network latency on this link.
+        pub latency: Option<Duration>,
+        /// Probability (0.0..=1.0) that the message is silently dropped
+        /// instead of delivered, simulating a lossy/partitioned link.
+        pub drop_rate: f64,
+    }
+
+    type Envelope<M> = (LinkId, M);
+
+    /// One process' view of an in-memory cluster: its own incoming channel,
+    /// plus a sender for every other link in the cluster it can address.
+    pub struct InMemoryFabric<M> {
+        me: ProcessId,
+        incoming: mpsc::Receiver<Envelope<M>>,
+        outgoing: HashMap<LinkId, mpsc::Sender<Envelope<M>>>,
+        faults: HashMap<LinkId, Fault>,
+    }
+
+    impl<M> InMemoryFabric<M>
+    where
+        M: Send + 'static,
+    {
+        /// Builds one `InMemoryFabric` per process in `process_ids`, each
+        /// already connected to every other process across `multiplexing`
+        /// parallel links, mirroring what `connect_to_all` establishes over
+        /// real sockets - without binding a single port. Each process' own
+        /// `incoming` channel is a single `mpsc::Receiver`; every peer that
+        /// can reach it holds a clone of the matching `mpsc::Sender`, which
+        /// is all an mpsc channel needs to fan messages from many senders
+        /// into the one place a `Fabric::recv` polls.
+        pub fn cluster(
+            process_ids: &[ProcessId],
+            multiplexing: usize,
+            channel_buffer_size: usize,
+        ) -> HashMap<ProcessId, Self> {
+            // one incoming channel per process, shared (via cloned senders)
+            // by every other process that can reach it
+            let mut incoming_txs = HashMap::new();
+            let mut fabrics = HashMap::new();
+            for &process_id in process_ids {
+                let (tx, rx) = mpsc::channel(channel_buffer_size);
+                incoming_txs.insert(process_id, tx);
+                fabrics.insert(process_id, Self::new(process_id, rx));
+            }
+
+            for &from in process_ids {
+                for &to in process_ids {
+                    if from == to {
+                        continue;
+                    }
+                    let tx = incoming_txs
+                        .get(&to)
+                        .expect("every process should have an incoming channel")
+                        .clone();
+                    let fabric = fabrics
+                        .get_mut(&from)
+                        .expect("every process should have a fabric");
+                    for stream_index in 0..multiplexing {
+                        fabric.outgoing.insert((to, stream_index), tx.clone());
+                    }
+                }
+            }
+
+            fabrics
+        }
+
+        fn new(me: ProcessId, incoming: mpsc::Receiver<Envelope<M>>) -> Self {
+            Self {
+                me,
+                incoming,
+                outgoing: HashMap::new(),
+                faults: HashMap::new(),
+            }
+        }
+
+        /// Attaches (or clears, via `None`) a `Fault` to every subsequent
+        /// message sent on `link`, so a test can simulate a slow or
+        /// partitioned peer without tearing down the cluster.
+        pub fn set_fault(&mut self, link: LinkId, fault: Option<Fault>) {
+            match fault {
+                Some(fault) => {
+                    self.faults.insert(link, fault);
+                }
+                None => {
+                    self.faults.remove(&link);
+                }
+            }
+        }
+    }
+
+    impl<M> Fabric<M> for InMemoryFabric<M>
+    where
+        M: Send + 'static,
+    {
+        fn send(
+            &mut self,
+            link: LinkId,
+            msg: M,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>>
+        {
+            let fault = self.faults.get(&link).copied().unwrap_or_default();
+            let tx = self.outgoing.get(&link).cloned();
+            Box::pin(async move {
+                let tx = tx.ok_or_else(|| {
+                    eyre!(
+                        "[fabric] no in-memory link to {:?} from process {}",
+                        link, self.me
+                    )
+                })?;
+                if fault.drop_rate > 0.0
+                    && rand::thread_rng().gen_range(0.0, 1.0) < fault.drop_rate
+                {
+                    return Ok(());
+                }
+                if let Some(latency) = fault.latency {
+                    tokio::time::delay_for(latency).await;
+                }
+                tx.send(((self.me, link.1), msg))
+                    .await
+                    .map_err(|_| eyre!("[fabric] link to {:?} is closed", link))
+            })
+        }
+
+        fn recv(
+            &mut self,
+        ) -> Pin<Box<dyn Future<Output = Option<(LinkId, M)>> + Send + '_>>
+        {
+            Box::pin(async move { self.incoming.recv().await })
+        }
+    }
+}
+
+#[cfg(feature = "test-util")]
+pub use in_memory::{Fault, InMemoryFabric};
@@ -1,12 +1,44 @@
 // This module contains the definition of `Connection`.
 mod connection;
 
+// This module contains the transport backend `Connection` is built on top
+// of, selected at build time by the `iouring` feature.
+pub mod socket;
+
+// This module contains an optional TLS layer for the socket underneath a
+// `Connection`, gated by the `tls` feature (see its own doc comment).
+pub mod tls;
+
+// This module contains an optional QUIC transport, an alternative to the
+// TCP backend in `socket`, gated by the `quic` feature (see its own doc
+// comment).
+pub mod quic;
+
+// This module contains a Unix-domain-socket transport, an alternative to
+// the TCP backend in `socket` for same-host runs (see its own doc
+// comment); only available on Unix-like targets.
+pub mod unix;
+
+// This module decouples the payload encoding inside a frame from the
+// framing itself (see its own doc comment), with bincode as the default.
+pub mod wire_format;
+
+// This module names the send/recv-by-(ProcessId, stream index) shape
+// `connect_to_all` and the client listener both move messages through,
+// behind the `Fabric` trait, with an in-memory backend for deterministic
+// tests gated by the `test-util` feature (see its own doc comment).
+pub mod fabric;
+
 // Re-exports.
 pub use connection::Connection;
+pub use fabric::{Fabric, LinkId};
+pub use quic::TransportKind;
+pub use socket::FrameTransport;
+pub use wire_format::{Bincode, ClientDataFormat, MessagePack, WireFormat};
 
 use crate::warn;
 use bytes::{Bytes, BytesMut};
-use color_eyre::eyre::{Report, WrapErr};
+use color_eyre::eyre::{eyre, Report, WrapErr};
 use futures::sink::{Sink, SinkExt};
 use futures::stream::StreamExt;
 use serde::de::DeserializeOwned;
@@ -15,11 +47,62 @@ use std::pin::Pin;
 use tokio::io::{AsyncRead, AsyncWrite, BufStream};
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
+/// Current on-wire/on-disk schema version, written into every frame.
+///
+/// Bump this whenever a `Message`/`ExecutionInfo` type gains a field so a
+/// reader can tell which version produced a frame it's decoding. To stay
+/// decodable across versions, new fields must be added as `Option<T>` and
+/// appended *after* every existing field (never inserted in the middle):
+/// an older binary reading a newer frame simply stops once it has filled in
+/// its own (shorter) struct and ignores the trailing bytes it doesn't know
+/// about, while a newer binary reading an older frame needs its added
+/// fields to be `Option<T>` so serde can still fill them in from what, to
+/// bincode, looks like a frame that is merely missing its optional tail.
+const WIRE_SCHEMA_VERSION: u8 = 1;
+
+/// First byte of every frame: whether it carries a real payload or is just
+/// a no-op keepalive.
+const PAYLOAD_TAG: u8 = 0;
+const HEARTBEAT_TAG: u8 = 1;
+
+/// `max_frame_length` used when a caller has no `Config` to derive one from
+/// (e.g. replaying a local `execution_log` file rather than talking to a
+/// peer over the network): matches `LengthDelimitedCodec`'s own built-in
+/// default, which was silently in effect everywhere before `Rw` started
+/// configuring this explicitly.
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// What `Rw::recv` got off the wire before it had a chance to hide
+/// keepalives from the caller.
+enum Frame<V> {
+    Heartbeat,
+    Payload(V),
+}
+
+/// Which `WireFormat` encodes the payload bytes inside every frame a given
+/// `Rw` sends/receives, picked once when the connection is created (unlike
+/// `wire_format::Protobuf`, which is a compile-time feature, not something a
+/// running process can choose per connection).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// The long-standing default: see `wire_format::Bincode`.
+    Bincode,
+    /// See `wire_format::MessagePack`.
+    MessagePack,
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::Bincode
+    }
+}
+
 /// Delimits frames using a length header.
 /// TODO take a look at async_bincode: https://docs.rs/async-bincode/0.5.1/async_bincode/index.html
 #[derive(Debug)]
 pub struct Rw<S> {
     rw: Framed<BufStream<S>, LengthDelimitedCodec>,
+    codec: Codec,
 }
 
 impl<S> Rw<S>
@@ -27,47 +110,100 @@ where
     S: AsyncWrite + AsyncRead + Unpin,
 {
     pub fn from(reader_capacity: usize, writer_capacity: usize, rw: S) -> Self {
+        Self::with_codec(
+            reader_capacity,
+            writer_capacity,
+            rw,
+            Codec::default(),
+            DEFAULT_MAX_FRAME_LENGTH,
+        )
+    }
+
+    /// Like `from`, but with an explicit `codec` (instead of the default
+    /// `Codec::Bincode`) and `max_frame_length` (instead of
+    /// `DEFAULT_MAX_FRAME_LENGTH`): a peer announcing a length header above
+    /// `max_frame_length` fails `recv` with a warning instead of `Rw`
+    /// attempting to buffer an arbitrarily large frame, which is what
+    /// bounds how much memory a corrupt or malicious peer can make this
+    /// process allocate. Callers talking to other processes should derive
+    /// `max_frame_length` from `Config` rather than picking their own.
+    pub fn with_codec(
+        reader_capacity: usize,
+        writer_capacity: usize,
+        rw: S,
+        codec: Codec,
+        max_frame_length: usize,
+    ) -> Self {
         // buffer rw
         let rw = BufStream::with_capacity(reader_capacity, writer_capacity, rw);
-        // frame rw
-        let rw = Framed::new(rw, LengthDelimitedCodec::new());
-        Self { rw }
+        // frame rw, capping how large a single frame's length header is
+        // allowed to claim to be
+        let codec_inner = LengthDelimitedCodec::builder()
+            .max_frame_length(max_frame_length)
+            .new_codec();
+        let rw = Framed::new(rw, codec_inner);
+        Self { rw, codec }
     }
 
+    /// Receives the next payload, transparently skipping over any
+    /// heartbeat frames in between: callers never see them, so a heartbeat
+    /// never needs to be routed anywhere (in particular, never to a
+    /// `Protocol` or an `Executor`) to be dropped.
     pub async fn recv<V>(&mut self) -> Option<V>
     where
-        V: DeserializeOwned,
+        V: Serialize + DeserializeOwned,
     {
-        match self.rw.next().await {
-            Some(Ok(bytes)) => {
-                // if it is, and not an error, deserialize it
-                let value = deserialize(bytes);
-                Some(value)
-            }
-            Some(Err(e)) => {
-                warn!("[rw] error while reading from stream: {:?}", e);
-                None
+        loop {
+            match self.rw.next().await {
+                Some(Ok(bytes)) => match deserialize(bytes, self.codec) {
+                    Ok(Frame::Heartbeat) => continue,
+                    Ok(Frame::Payload(value)) => return Some(value),
+                    Err(e) => {
+                        warn!(
+                            "[rw] error while decoding frame, dropping \
+                             connection: {:?}",
+                            e
+                        );
+                        return None;
+                    }
+                },
+                Some(Err(e)) => {
+                    warn!("[rw] error while reading from stream: {:?}", e);
+                    return None;
+                }
+                None => return None,
             }
-            None => None,
         }
     }
 
     pub async fn send<V>(&mut self, value: &V) -> Result<(), Report>
     where
-        V: Serialize,
+        V: Serialize + DeserializeOwned,
     {
-        let bytes = serialize(value);
+        let bytes = serialize(PAYLOAD_TAG, Some(value), self.codec);
         self.rw
             .send(bytes)
             .await
             .wrap_err("error while sending to sink")
     }
 
+    /// Sends a no-op keepalive frame: advances liveness detection and keeps
+    /// the connection framed during idle periods, without carrying (or
+    /// requiring) a payload of any particular `Message`/`ExecutionInfo`
+    /// type.
+    pub async fn send_heartbeat(&mut self) -> Result<(), Report> {
+        let bytes = serialize::<()>(HEARTBEAT_TAG, None, self.codec);
+        self.rw
+            .send(bytes)
+            .await
+            .wrap_err("error while sending heartbeat to sink")
+    }
+
     pub async fn write<V>(&mut self, value: &V) -> Result<(), Report>
     where
-        V: Serialize,
+        V: Serialize + DeserializeOwned,
     {
-        let bytes = serialize(value);
+        let bytes = serialize(PAYLOAD_TAG, Some(value), self.codec);
         futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
             .await
             .wrap_err("error while polling sink ready")?;
@@ -83,18 +219,104 @@ where
     }
 }
 
-fn deserialize<V>(bytes: BytesMut) -> V
+/// Unlike `WireFormat::decode` (which panics, since every other caller of
+/// it only ever feeds back bytes a matching process just encoded), this is
+/// the untrusted-network boundary: a frame a peer decided to put on the
+/// wire may simply not be a valid encoding, and that must turn into a
+/// dropped connection, not an aborted process.
+fn deserialize<V>(bytes: BytesMut, codec: Codec) -> Result<Frame<V>, Report>
 where
-    V: DeserializeOwned,
+    V: Serialize + DeserializeOwned,
 {
-    bincode::deserialize(&bytes).expect("[rw] deserialize should work")
+    if bytes.len() < 2 {
+        return Err(eyre!("frame too short: {} bytes", bytes.len()));
+    }
+    let tag = bytes[0];
+    let version = bytes[1];
+    if version != WIRE_SCHEMA_VERSION {
+        warn!(
+            "[rw] decoding a frame written with schema version {}, running version {}",
+            version, WIRE_SCHEMA_VERSION
+        );
+    }
+    match tag {
+        HEARTBEAT_TAG => Ok(Frame::Heartbeat),
+        PAYLOAD_TAG => {
+            let value = match codec {
+                Codec::Bincode => bincode::deserialize(&bytes[2..])
+                    .map_err(|e| eyre!("bincode decode failed: {:?}", e))?,
+                Codec::MessagePack => rmp_serde::from_slice(&bytes[2..])
+                    .map_err(|e| {
+                        eyre!("messagepack decode failed: {:?}", e)
+                    })?,
+            };
+            Ok(Frame::Payload(value))
+        }
+        _ => Err(eyre!("[rw] unknown frame tag: {}", tag)),
+    }
 }
 
-fn serialize<V>(value: &V) -> Bytes
+/// Builds the outgoing frame as one `Vec<u8>`: the tag and schema version
+/// bytes, followed by the payload (if any), which
+/// `wire_format::Bincode`/`MessagePack::encode` return as an owned
+/// `Vec<u8>` of their own that is then appended, so this doesn't reach
+/// into `Framed`'s own send buffer - doing that (an `async_bincode`-style
+/// `Encoder<V>` replacing the combination of `LengthDelimitedCodec` and this
+/// function) would drop the remaining copy, but is a bigger structural
+/// change than this pass makes; `Bytes::from(Vec<u8>)` below is already a
+/// move, not a copy, so the remaining cost is the one encode-into-Vec each
+/// format impl already does internally.
+fn serialize<V>(tag: u8, value: Option<&V>, codec: Codec) -> Bytes
 where
-    V: Serialize,
+    V: Serialize + DeserializeOwned,
 {
-    // TODO can we avoid `Bytes`?
-    let bytes = bincode::serialize(value).expect("[rw] serialize should work");
+    let mut bytes = vec![tag, WIRE_SCHEMA_VERSION];
+    if let Some(value) = value {
+        match codec {
+            Codec::Bincode => bytes.extend(wire_format::Bincode::encode(value)),
+            Codec::MessagePack => {
+                bytes.extend(wire_format::MessagePack::encode(value))
+            }
+        }
+    }
     Bytes::from(bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncWrite, AsyncWriteExt};
+
+    /// Writes a raw length-delimited frame (the 4-byte big-endian length
+    /// header `LengthDelimitedCodec` defaults to, followed by `payload`
+    /// verbatim) directly onto the wire, bypassing `Rw::send`/`serialize`
+    /// entirely - so a short or otherwise malformed frame can reach `recv`
+    /// the way a corrupt or malicious peer's bytes would, which a well-formed
+    /// sender would never produce.
+    async fn write_raw_frame(writer: &mut (impl AsyncWrite + Unpin), payload: &[u8]) {
+        writer.write_u32(payload.len() as u32).await.unwrap();
+        writer.write_all(payload).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn recv_drops_connection_instead_of_panicking_on_empty_frame() {
+        let (a, mut b) = duplex(1024);
+        let mut rw = Rw::from(64, 64, a);
+
+        write_raw_frame(&mut b, &[]).await;
+
+        let received: Option<()> = rw.recv().await;
+        assert!(received.is_none());
+    }
+
+    #[tokio::test]
+    async fn recv_drops_connection_instead_of_panicking_on_one_byte_frame() {
+        let (a, mut b) = duplex(1024);
+        let mut rw = Rw::from(64, 64, a);
+
+        write_raw_frame(&mut b, &[PAYLOAD_TAG]).await;
+
+        let received: Option<()> = rw.recv().await;
+        assert!(received.is_none());
+    }
+}
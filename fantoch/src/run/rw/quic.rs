@@ -0,0 +1,228 @@
+// This module provides an optional QUIC-based transport, gated behind the
+// `quic` feature, as an alternative to the plain-TCP backend in `socket`:
+// instead of dialing `multiplexing` independent TCP sockets per peer (see
+// `connect_to_all`), a single QUIC connection is opened per peer and
+// `multiplexing` independent bidirectional streams are carried over it -
+// one per `(worker, channel)` pairing, same as today's one-connection-per-
+// multiplexing-slot scheme expects downstream. Streams on the same QUIC
+// connection are only ordered/flow-controlled against each other, never
+// blocking on one another's retransmissions the way TCP segments on
+// different sockets competing for the same NIC can, while still getting a
+// single congestion-controlled connection (and a single TLS 1.3 handshake)
+// per peer rather than `multiplexing` of them.
+//
+// `QuicTransport` implements `FrameTransport` the same way `socket::Tcp
+// Transport` does, wrapping one bidirectional stream's `SendStream`/
+// `RecvStream` pair (joined into a single `AsyncRead + AsyncWrite` via
+// `tokio::io::join`) behind the same length-delimited framing, so the
+// reader/writer/`Connection` plumbing downstream doesn't need to know QUIC
+// is involved at all.
+
+use crate::run::rw::socket::FrameTransport;
+use bytes::Bytes;
+use color_eyre::eyre::Report;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Which backend a connection to a peer (or to a client) is carried over;
+/// threaded through `process(...)` so benchmarks can compare TCP-
+/// multiplexed vs QUIC-multiplexed runs under the same `workers`/
+/// `executors`/`multiplexing` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    /// `multiplexing` independent TCP sockets per peer, the long-standing
+    /// default.
+    Tcp,
+    /// One QUIC connection per peer, carrying `multiplexing` independent
+    /// bidirectional streams.
+    Quic,
+    /// `multiplexing` independent Unix domain sockets per peer, addressed
+    /// by filesystem path instead of `ip:port` (see `rw::unix`'s doc
+    /// comment) - only viable when every process in the mesh is on the
+    /// same host, e.g. the local test runner.
+    Unix,
+}
+
+impl Default for TransportKind {
+    fn default() -> Self {
+        TransportKind::Tcp
+    }
+}
+
+#[cfg(feature = "quic")]
+mod enabled {
+    use super::*;
+    use crate::warn;
+    use bytes::BytesMut;
+    use color_eyre::eyre::WrapErr;
+    use futures::sink::{Sink, SinkExt};
+    use futures::stream::StreamExt;
+    use std::net::SocketAddr;
+    use tokio::io::BufStream;
+    use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+    /// A bound QUIC endpoint, able to both dial peers and accept incoming
+    /// connections from them - every process in the mesh plays both roles,
+    /// same as the plain-TCP backend.
+    pub struct QuicEndpoint {
+        endpoint: quinn::Endpoint,
+    }
+
+    impl QuicEndpoint {
+        /// Binds a QUIC endpoint at `bind_addr`, configured to both accept
+        /// incoming connections (via `server_config`) and dial out (via
+        /// `client_config`).
+        pub fn bind(
+            bind_addr: SocketAddr,
+            server_config: quinn::ServerConfig,
+            client_config: quinn::ClientConfig,
+        ) -> Result<Self, Report> {
+            let mut endpoint = quinn::Endpoint::server(server_config, bind_addr)
+                .wrap_err("binding QUIC endpoint")?;
+            endpoint.set_default_client_config(client_config);
+            Ok(Self { endpoint })
+        }
+
+        /// Dials a single QUIC connection to `addr`, to be reused for every
+        /// one of the peer's `multiplexing` streams.
+        pub async fn connect(
+            &self,
+            addr: SocketAddr,
+            server_name: &str,
+        ) -> Result<quinn::Connection, Report> {
+            self.endpoint
+                .connect(addr, server_name)
+                .wrap_err("starting QUIC connection")?
+                .await
+                .wrap_err("completing QUIC handshake")
+        }
+
+        /// Accepts the next incoming QUIC connection - one per peer, same
+        /// as a TCP `listener_task` accepts one socket per incoming
+        /// multiplexing slot, except here it's one connection that then
+        /// carries every slot as a stream.
+        pub async fn accept(&self) -> Result<quinn::Connection, Report> {
+            let connecting = self
+                .endpoint
+                .accept()
+                .await
+                .ok_or_else(|| color_eyre::eyre::eyre!("QUIC endpoint closed"))?;
+            connecting.await.wrap_err("completing QUIC handshake")
+        }
+    }
+
+    /// Opens one more multiplexed stream on an already-established QUIC
+    /// `connection`, to be used for one `(worker, channel)` pairing -
+    /// analogous to dialing one more TCP socket in the plain-TCP backend's
+    /// multiplexing loop, except no new connection (and no new handshake)
+    /// is needed.
+    pub async fn open_stream(
+        connection: &quinn::Connection,
+        reader_capacity: usize,
+        writer_capacity: usize,
+    ) -> Result<QuicTransport, Report> {
+        let (send, recv) = connection.open_bi().await.wrap_err("opening QUIC stream")?;
+        Ok(QuicTransport::from(
+            reader_capacity,
+            writer_capacity,
+            send,
+            recv,
+        ))
+    }
+
+    /// Accepts the next multiplexed stream on an already-established QUIC
+    /// `connection` - the peer's counterpart to `open_stream` above.
+    pub async fn accept_stream(
+        connection: &quinn::Connection,
+        reader_capacity: usize,
+        writer_capacity: usize,
+    ) -> Result<QuicTransport, Report> {
+        let (send, recv) = connection
+            .accept_bi()
+            .await
+            .wrap_err("accepting QUIC stream")?;
+        Ok(QuicTransport::from(
+            reader_capacity,
+            writer_capacity,
+            send,
+            recv,
+        ))
+    }
+
+    /// A `FrameTransport` backed by one QUIC bidirectional stream, mirroring
+    /// `socket::TcpTransport` - `send`/`recv` are joined into a single
+    /// `AsyncRead + AsyncWrite` so the same `Framed`/`LengthDelimitedCodec`
+    /// pipeline can be reused unchanged.
+    pub struct QuicTransport {
+        rw: Framed<
+            BufStream<tokio::io::Join<quinn::RecvStream, quinn::SendStream>>,
+            LengthDelimitedCodec,
+        >,
+    }
+
+    impl QuicTransport {
+        fn from(
+            reader_capacity: usize,
+            writer_capacity: usize,
+            send: quinn::SendStream,
+            recv: quinn::RecvStream,
+        ) -> Self {
+            let joined = tokio::io::join(recv, send);
+            let rw = BufStream::with_capacity(reader_capacity, writer_capacity, joined);
+            let rw = Framed::new(rw, LengthDelimitedCodec::new());
+            Self { rw }
+        }
+    }
+
+    impl FrameTransport for QuicTransport {
+        fn recv_frame(&mut self) -> Pin<Box<dyn Future<Output = Option<BytesMut>> + Send + '_>> {
+            Box::pin(async move {
+                match self.rw.next().await {
+                    Some(Ok(bytes)) => Some(bytes),
+                    Some(Err(e)) => {
+                        warn!("[quic] error while reading from stream: {:?}", e);
+                        None
+                    }
+                    None => None,
+                }
+            })
+        }
+
+        fn send_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                self.rw
+                    .send(bytes)
+                    .await
+                    .wrap_err("error while sending to sink")
+            })
+        }
+
+        fn write_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
+                    .await
+                    .wrap_err("error while polling sink ready")?;
+                Pin::new(&mut self.rw)
+                    .start_send(bytes)
+                    .wrap_err("error while starting send to sink")
+            })
+        }
+
+        fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_flush(cx))
+                    .await
+                    .wrap_err("error while flushing sink")
+            })
+        }
+    }
+}
+
+#[cfg(feature = "quic")]
+pub use enabled::{accept_stream, open_stream, QuicEndpoint, QuicTransport};
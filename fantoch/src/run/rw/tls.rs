@@ -0,0 +1,257 @@
+// This module provides an optional transport-level TLS layer, gated behind
+// the `tls` feature so a plain `TcpStream` remains the default for
+// benchmarks: `connect`/`accept` below wrap the raw socket a connection is
+// dialed/accepted over in a `tokio_rustls` stream *before* it is ever
+// handed to `Connection::from`/the length-delimited framer, so the
+// handshake itself and every byte that flows afterwards - not just the
+// `Message`/`ExecutionInfo` payload `secure::LinkCipher` seals - is
+// encrypted and mutually authenticated. Nothing in `run` calls them: a
+// `TlsConfig` handed to `process`/`client` isn't applied, and their
+// connections stay plaintext. Independent of (and composable
+// with) `secure::LinkCipher`/`auth::AuthFrame`, which both operate above
+// the framing layer and don't care what the underlying socket is.
+//
+// `TlsTransport<S>` implements `FrameTransport` the same way `socket::Tcp
+// Transport` does, just generic over whatever `AsyncRead + AsyncWrite`
+// stream it's built on top of - a `tokio_rustls::TlsStream<TcpStream>` in
+// practice - so the reader/writer/`Connection` plumbing downstream doesn't
+// need to know TLS is involved at all.
+//
+// TODO `TlsConfig::from_paths` below is how a deployment is meant to build
+// its `TlsConfig` - from configurable cert/key/trust-root paths - but
+// nothing currently resolves those paths from `Config` (in the
+// currently-unreachable `crate::config` module): `process`/`client` (in
+// `run/mod.rs`) still only accept an already-built `Option<Arc<TlsConfig>>`
+// from their caller. Once `Config` is reachable, it should gain the three
+// path fields and a call to `from_paths` at the top of `process`/`client`,
+// in place of requiring every caller to build the `TlsConfig` itself.
+
+use crate::run::rw::socket::FrameTransport;
+use bytes::Bytes;
+use color_eyre::eyre::Report;
+use std::future::Future;
+use std::pin::Pin;
+
+#[cfg(feature = "tls")]
+mod enabled {
+    use super::*;
+    use crate::warn;
+    use bytes::BytesMut;
+    use color_eyre::eyre::{eyre, WrapErr};
+    use futures::sink::{Sink, SinkExt};
+    use futures::stream::StreamExt;
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+    use tokio::io::{AsyncRead, AsyncWrite, BufStream};
+    use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+    use tokio_rustls::rustls::{
+        Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName,
+    };
+    use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+    use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+    /// Mutual-TLS material for one process: since every process in the mesh
+    /// both dials and accepts connections, this holds both halves - a
+    /// `TlsConnector` for when it's dialing out, an `TlsAcceptor` for when
+    /// it's accepting - each configured to present `cert_chain`/
+    /// `private_key` and to require (and verify) the peer's certificate
+    /// against `roots`, so a connection is only established once both sides
+    /// have proven they hold a key `roots` trusts.
+    pub struct TlsConfig {
+        connector: TlsConnector,
+        acceptor: TlsAcceptor,
+    }
+
+    impl TlsConfig {
+        pub fn mutual(
+            cert_chain: Vec<Certificate>,
+            private_key: PrivateKey,
+            roots: RootCertStore,
+        ) -> Result<Self, Report> {
+            let client_config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots.clone())
+                .with_single_cert(cert_chain.clone(), private_key.clone())
+                .wrap_err("building TLS client config")?;
+
+            let client_verifier = AllowAnyAuthenticatedClient::new(roots);
+            let server_config = ServerConfig::builder()
+                .with_safe_defaults()
+                .with_client_cert_verifier(Arc::new(client_verifier))
+                .with_single_cert(cert_chain, private_key)
+                .wrap_err("building TLS server config")?;
+
+            Ok(Self {
+                connector: TlsConnector::from(Arc::new(client_config)),
+                acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            })
+        }
+
+        /// Builds a `TlsConfig` from PEM files on disk: `cert_chain_path`
+        /// this process' own certificate chain, `private_key_path` the
+        /// matching private key, `roots_path` the trust roots every peer's
+        /// certificate is checked against - typically the same CA bundle on
+        /// every process in the mesh.
+        pub fn from_paths(
+            cert_chain_path: &std::path::Path,
+            private_key_path: &std::path::Path,
+            roots_path: &std::path::Path,
+        ) -> Result<Self, Report> {
+            let cert_chain = load_certs(cert_chain_path)?;
+            let private_key = load_private_key(private_key_path)?;
+            let roots = load_roots(roots_path)?;
+            Self::mutual(cert_chain, private_key, roots)
+        }
+    }
+
+    fn load_certs(path: &std::path::Path) -> Result<Vec<Certificate>, Report> {
+        let file = std::fs::File::open(path)
+            .wrap_err_with(|| format!("opening TLS cert chain at {:?}", path))?;
+        let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+            .wrap_err_with(|| format!("parsing TLS cert chain at {:?}", path))?;
+        Ok(certs.into_iter().map(Certificate).collect())
+    }
+
+    fn load_private_key(path: &std::path::Path) -> Result<PrivateKey, Report> {
+        let file = std::fs::File::open(path)
+            .wrap_err_with(|| format!("opening TLS private key at {:?}", path))?;
+        let mut reader = std::io::BufReader::new(file);
+        let keys = rustls_pemfile::pkcs8_private_keys(&mut reader)
+            .wrap_err_with(|| format!("parsing TLS private key at {:?}", path))?;
+        let key = keys
+            .into_iter()
+            .next()
+            .ok_or_else(|| eyre!("no PKCS#8 private key found at {:?}", path))?;
+        Ok(PrivateKey(key))
+    }
+
+    fn load_roots(path: &std::path::Path) -> Result<RootCertStore, Report> {
+        let file = std::fs::File::open(path)
+            .wrap_err_with(|| format!("opening TLS trust roots at {:?}", path))?;
+        let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+            .wrap_err_with(|| format!("parsing TLS trust roots at {:?}", path))?;
+        let mut roots = RootCertStore::empty();
+        for cert in certs {
+            roots
+                .add(&Certificate(cert))
+                .wrap_err_with(|| format!("adding trust root from {:?}", path))?;
+        }
+        Ok(roots)
+    }
+
+    /// Dials out over an already-connected `stream` (with `tcp_nodelay`/
+    /// `tcp_buffer_size` already applied by the caller), performing the TLS
+    /// client handshake and verifying the peer presents a certificate
+    /// `config`'s roots trust.
+    pub async fn connect<S>(
+        config: &TlsConfig,
+        server_name: &str,
+        stream: S,
+    ) -> Result<TlsStream<S>, Report>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let server_name = ServerName::try_from(server_name)
+            .map_err(|_| eyre!("invalid TLS server name: {}", server_name))?;
+        let stream = config
+            .connector
+            .connect(server_name, stream)
+            .await
+            .wrap_err("TLS client handshake failed")?;
+        Ok(TlsStream::Client(stream))
+    }
+
+    /// Accepts an already-connected `stream`, performing the TLS server
+    /// handshake and verifying the peer (process or client) presents a
+    /// certificate `config`'s roots trust.
+    pub async fn accept<S>(config: &TlsConfig, stream: S) -> Result<TlsStream<S>, Report>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let stream = config
+            .acceptor
+            .accept(stream)
+            .await
+            .wrap_err("TLS server handshake failed")?;
+        Ok(TlsStream::Server(stream))
+    }
+
+    /// A `FrameTransport` backed by a `TlsStream`, mirroring
+    /// `socket::TcpTransport` but generic over the stream it wraps.
+    pub struct TlsTransport<S> {
+        rw: Framed<BufStream<TlsStream<S>>, LengthDelimitedCodec>,
+    }
+
+    impl<S> TlsTransport<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        pub fn from(reader_capacity: usize, writer_capacity: usize, stream: TlsStream<S>) -> Self {
+            let rw = BufStream::with_capacity(reader_capacity, writer_capacity, stream);
+            let rw = Framed::new(rw, LengthDelimitedCodec::new());
+            Self { rw }
+        }
+    }
+
+    impl<S> FrameTransport for TlsTransport<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        fn recv_frame(&mut self) -> Pin<Box<dyn Future<Output = Option<BytesMut>> + Send + '_>> {
+            Box::pin(async move {
+                match self.rw.next().await {
+                    Some(Ok(bytes)) => Some(bytes),
+                    Some(Err(e)) => {
+                        warn!("[tls] error while reading from stream: {:?}", e);
+                        None
+                    }
+                    None => None,
+                }
+            })
+        }
+
+        fn send_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                self.rw
+                    .send(bytes)
+                    .await
+                    .wrap_err("error while sending to sink")
+            })
+        }
+
+        fn write_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
+                    .await
+                    .wrap_err("error while polling sink ready")?;
+                Pin::new(&mut self.rw)
+                    .start_send(bytes)
+                    .wrap_err("error while starting send to sink")
+            })
+        }
+
+        fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_flush(cx))
+                    .await
+                    .wrap_err("error while flushing sink")
+            })
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+pub use enabled::*;
+
+// Stand-in so code that threads an optional `TlsConfig` through
+// `process`/`client` still compiles with the `tls` feature disabled; it's
+// never actually constructed in that configuration, so plaintext remains
+// the default.
+#[cfg(not(feature = "tls"))]
+pub struct TlsConfig;
@@ -0,0 +1,111 @@
+// This module provides a Unix-domain-socket backend, an alternative to the
+// plain-TCP backend in `socket` for the common case where every process
+// (and every client) in a run lives on the same host. A Unix domain socket
+// is addressed by a filesystem path (e.g. one per process in a tempdir)
+// rather than an `ip:port` pair, which can be convenient for same-host
+// deployments that would rather not allocate a port at all.
+//
+// `UnixTransport` implements `FrameTransport` the same way `socket::Tcp
+// Transport` does, just over a `tokio::net::UnixStream` instead of a
+// `TcpStream`, so the reader/writer/`Connection` plumbing downstream
+// doesn't need to know which one it's carrying.
+//
+// Only available on Unix-like targets, since `tokio::net::UnixListener`/
+// `UnixStream` don't exist elsewhere; `TransportKind::Unix` simply isn't a
+// meaningful choice on a platform without this module.
+
+#[cfg(unix)]
+pub mod enabled {
+    use crate::run::rw::socket::FrameTransport;
+    use crate::warn;
+    use bytes::{Bytes, BytesMut};
+    use color_eyre::eyre::{Report, WrapErr};
+    use futures::sink::{Sink, SinkExt};
+    use futures::stream::StreamExt;
+    use std::future::Future;
+    use std::path::Path;
+    use std::pin::Pin;
+    use tokio::io::BufStream;
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+    /// A `FrameTransport` backed by a `UnixStream`, mirroring
+    /// `socket::TcpTransport`.
+    pub struct UnixTransport {
+        rw: Framed<BufStream<UnixStream>, LengthDelimitedCodec>,
+    }
+
+    impl UnixTransport {
+        pub fn from(reader_capacity: usize, writer_capacity: usize, stream: UnixStream) -> Self {
+            let rw = BufStream::with_capacity(reader_capacity, writer_capacity, stream);
+            let rw = Framed::new(rw, LengthDelimitedCodec::new());
+            Self { rw }
+        }
+    }
+
+    impl FrameTransport for UnixTransport {
+        fn recv_frame(&mut self) -> Pin<Box<dyn Future<Output = Option<BytesMut>> + Send + '_>> {
+            Box::pin(async move {
+                match self.rw.next().await {
+                    Some(Ok(bytes)) => Some(bytes),
+                    Some(Err(e)) => {
+                        warn!("[rw] error while reading from unix stream: {:?}", e);
+                        None
+                    }
+                    None => None,
+                }
+            })
+        }
+
+        fn send_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                self.rw
+                    .send(bytes)
+                    .await
+                    .wrap_err("error while sending to unix sink")
+            })
+        }
+
+        fn write_frame(
+            &mut self,
+            bytes: Bytes,
+        ) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
+                    .await
+                    .wrap_err("error while polling unix sink ready")?;
+                Pin::new(&mut self.rw)
+                    .start_send(bytes)
+                    .wrap_err("error while starting send to unix sink")
+            })
+        }
+
+        fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), Report>> + Send + '_>> {
+            Box::pin(async move {
+                futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_flush(cx))
+                    .await
+                    .wrap_err("error while flushing unix sink")
+            })
+        }
+    }
+
+    /// Dials the Unix domain socket at `path`, the counterpart to an
+    /// address in `addresses` when `TransportKind::Unix` is selected.
+    pub async fn connect(path: &Path) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path).await
+    }
+
+    /// Binds a Unix domain socket listener at `path`. The caller is
+    /// responsible for removing any stale socket file left behind by a
+    /// previous run at the same path before calling this (e.g. by using a
+    /// fresh tempdir per run, as `run_test_with_inspect_fun` would).
+    pub fn listen(path: &Path) -> std::io::Result<UnixListener> {
+        UnixListener::bind(path)
+    }
+}
+
+#[cfg(unix)]
+pub use enabled::{connect, listen, UnixTransport};
@@ -0,0 +1,122 @@
+// This module decouples the *framing* a `Rw`/`Connection` does (length
+// header, schema-version byte, heartbeat tag - see `rw`'s module-level doc
+// comment) from the *encoding* of the payload bytes carried inside each
+// frame, by routing every (de)serialization through the `WireFormat` trait
+// below rather than calling `bincode` directly. Bincode remains the default
+// and is what every in-process channel (`ReaderToWorkers`, `WorkerTo
+// Executors`, the client's `CommandSender`/`CommandResultReceiver`) keeps
+// using, since those are Rust-to-Rust only and bincode is the cheapest
+// encoding for that. `Protobuf` is the pluggable alternative this opens the
+// door to: a stable, versioned, cross-language schema an external,
+// non-Rust client or tool could submit `Command`s and read `CommandResult`s
+// against, instead of having to match a Rust binary's bincode layout.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Which `WireFormat` `serialize_client_data` dumps the run's aggregated
+/// `ClientData` as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientDataFormat {
+    /// The long-standing default: a bincode blob only a matching Rust
+    /// binary can read back.
+    Bincode,
+    /// A language-neutral metrics file, readable by tooling not written in
+    /// Rust, once `ClientData` has a generated Protobuf counterpart (see
+    /// this module's and `proto/wire.proto`'s doc comments).
+    Protobuf,
+}
+
+impl Default for ClientDataFormat {
+    fn default() -> Self {
+        ClientDataFormat::Bincode
+    }
+}
+
+/// How the payload bytes inside a frame (or a standalone dump like
+/// `serialize_client_data`'s metrics file) are encoded. Every message type
+/// that crosses the wire already needs to round-trip (the sender's type is
+/// the receiver's type), so `V` is bound on both directions at once rather
+/// than splitting `encode`/`decode` into separate traits.
+pub trait WireFormat<V>
+where
+    V: Serialize + DeserializeOwned,
+{
+    fn encode(value: &V) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> V;
+}
+
+/// The long-standing default: every message type already derives
+/// `Serialize`/`Deserialize`, so this is a zero-cost wrapper around
+/// `bincode`.
+#[derive(Debug, Default)]
+pub struct Bincode;
+
+impl<V> WireFormat<V> for Bincode
+where
+    V: Serialize + DeserializeOwned,
+{
+    fn encode(value: &V) -> Vec<u8> {
+        bincode::serialize(value).expect("[wire_format] bincode encode should work")
+    }
+
+    fn decode(bytes: &[u8]) -> V {
+        bincode::deserialize(bytes).expect("[wire_format] bincode decode should work")
+    }
+}
+
+/// A MessagePack-backed format via `rmp-serde`: more compact and
+/// self-describing than bincode, and readable by non-Rust tooling without
+/// matching a Rust binary's exact struct layout - at some serialization
+/// cost relative to bincode. Unlike `Protobuf`, selectable per connection
+/// at runtime (see `rw::Codec`) instead of requiring a recompile, since
+/// every `Serialize`/`Deserialize` type already round-trips through it with
+/// no generated counterpart needed.
+#[derive(Debug, Default)]
+pub struct MessagePack;
+
+impl<V> WireFormat<V> for MessagePack
+where
+    V: Serialize + DeserializeOwned,
+{
+    fn encode(value: &V) -> Vec<u8> {
+        rmp_serde::to_vec(value)
+            .expect("[wire_format] messagepack encode should work")
+    }
+
+    fn decode(bytes: &[u8]) -> V {
+        rmp_serde::from_slice(bytes)
+            .expect("[wire_format] messagepack decode should work")
+    }
+}
+
+/// A Protobuf-backed format, built from a `.proto` schema compiled by
+/// `build.rs` (see `proto/wire.proto`) the way `prost` expects: usable for
+/// any `V` that has a generated Protobuf twin, i.e. derives `prost::Message`
+/// itself or is converted into/from one at the call site.
+///
+/// TODO `Command`/`CommandResult`/`ClientData` (defined in `crate::command`
+/// and `crate::client::data`) don't derive `prost::Message` in this tree, so
+/// nothing actually constructs a `Protobuf` format over them yet. Once one
+/// of those gains a generated counterpart mirroring `proto/wire.proto`,
+/// implementing `From`/`TryFrom` between the two (the same bridge
+/// `serialize_client_data` would need) makes it a drop-in `WireFormat`
+/// alternative to `Bincode` at that call site, no framing code changes
+/// required.
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Default)]
+pub struct Protobuf;
+
+#[cfg(feature = "protobuf")]
+impl<V> WireFormat<V> for Protobuf
+where
+    V: Serialize + DeserializeOwned + prost::Message + Default,
+{
+    fn encode(value: &V) -> Vec<u8> {
+        value.encode_to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> V {
+        V::decode(bytes).expect("[wire_format] protobuf decode should work")
+    }
+}
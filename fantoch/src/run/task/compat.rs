@@ -0,0 +1,130 @@
+// This module implements the mandatory protocol/config compatibility
+// handshake every connection goes through right after the plain `say_hi`/
+// `receive_hi` exchange (see `say_hi`/`receive_hi` in `process`): unlike `secure`/`auth`, which are opt-in, this check always
+// runs, since two processes running different `Protocol` types or
+// incompatible consensus-relevant `Config` (different `n`/`f`, shard
+// layout, commit model, ...) would otherwise happily exchange `POEMessage`s
+// and corrupt each other's state instead of simply failing to connect.
+
+use crate::config::Config;
+use crate::id::ProcessId;
+use crate::protocol::Protocol;
+use crate::run::rw::Connection;
+use crate::warn;
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+/// Bumped whenever a wire-incompatible change lands (a `Message`/
+/// `ExecutionInfo` variant reordered or removed, not just added - see
+/// `rw::WIRE_SCHEMA_VERSION`'s doc comment for what counts as that): two
+/// processes built from different values can't safely exchange
+/// `POEMessage`s even when their `Protocol` and `Config` otherwise match.
+const COMPAT_MAGIC: u32 = 1;
+
+/// What each side of a connection sends the other immediately after
+/// `say_hi`/`receive_hi`, before any executor/writer traffic is allowed to
+/// flow.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompatHello {
+    magic: u32,
+    protocol: String,
+    config_fingerprint: u64,
+}
+
+impl CompatHello {
+    /// Builds the `CompatHello` this process sends for protocol `P` running
+    /// with `config`.
+    pub fn new<P: Protocol>(config: &Config) -> Self {
+        Self {
+            magic: COMPAT_MAGIC,
+            protocol: std::any::type_name::<P>().to_owned(),
+            config_fingerprint: config_fingerprint(config),
+        }
+    }
+}
+
+/// Hashes the subset of `Config` that two processes must agree on to
+/// safely run the same protocol instance together. Doesn't need to cover
+/// every field - only ones where a mismatch would silently corrupt
+/// replicated state rather than, say, just changing a local timeout or
+/// logging level.
+fn config_fingerprint(config: &Config) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    config.n().hash(&mut hasher);
+    config.f().hash(&mut hasher);
+    config.shard_count().hash(&mut hasher);
+    config.leader().hash(&mut hasher);
+    config.transitive_conflicts().hash(&mut hasher);
+    config.execute_at_commit().hash(&mut hasher);
+    config.commit_model().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Exchanges `ours` with whatever `connection`'s peer sends back, closing
+/// the connection (by returning an error - the caller must drop `connection`
+/// without using it for executor/writer traffic) if the two don't agree on
+/// wire schema, `Protocol` type, or consensus-relevant `Config`.
+pub async fn compat_handshake(
+    connection: &mut Connection,
+    ours: &CompatHello,
+    peer_id: ProcessId,
+) -> Result<(), Report> {
+    connection
+        .send(ours)
+        .await
+        .map_err(|e| eyre!("send compat hello: {:?}", e))?;
+
+    let theirs: CompatHello = connection.recv().await.ok_or_else(|| {
+        eyre!("error receiving compat hello from process {}", peer_id)
+    })?;
+
+    if theirs.magic != ours.magic {
+        return Err(eyre!(
+            "process {} is running an incompatible wire schema (magic {} \
+             vs our {})",
+            peer_id,
+            theirs.magic,
+            ours.magic
+        ));
+    }
+    if theirs.protocol != ours.protocol {
+        return Err(eyre!(
+            "process {} is running protocol {} but we're running {}",
+            peer_id,
+            theirs.protocol,
+            ours.protocol
+        ));
+    }
+    if theirs.config_fingerprint != ours.config_fingerprint {
+        return Err(eyre!(
+            "process {} has a different consensus-relevant config \
+             (fingerprint {} vs our {})",
+            peer_id,
+            theirs.config_fingerprint,
+            ours.config_fingerprint
+        ));
+    }
+    Ok(())
+}
+
+/// Runs `compat_handshake`, logging and returning `false` (the caller must
+/// drop `connection` without using it further) on mismatch instead of
+/// propagating the error, mirroring `secure_upgrade_one`'s shape.
+pub async fn compat_check(
+    connection: &mut Connection,
+    ours: &CompatHello,
+    peer_id: ProcessId,
+) -> bool {
+    match compat_handshake(connection, ours, peer_id).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "[compat] dropping connection with process {} after a \
+                 failed compatibility check: {:?}",
+                peer_id, e
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// This module implements the per-link message delay used to simulate
+// synthetic network latency (see the `connection_delay: Option<Duration>`
+// threaded through `connect_to_all`/`start_writers` in `process.rs`, sourced
+// from `Connection::delay()`).
+//
+// `LinkLatency` generalizes a single fixed `Duration` to a `base` delay
+// plus a `Jitter` distribution sampled independently per message, and
+// `LatencyMatrix` lets a caller configure one `LinkLatency` per ordered
+// `(ProcessId, ProcessId)` pair, so asymmetric WAN topologies (e.g. US ->
+// EU slower than EU -> US) can be modelled instead of one uniform delay
+// for every link.
+//
+// `delay_task` holds messages in a `DelayQueue` keyed by a sampled
+// deadline and forwards each to `to_writer` once its deadline elapses.
+// Because many protocols implemented here assume per-link FIFO delivery,
+// a later message's sampled deadline is clamped to never precede the
+// previous message's - otherwise jitter sampling alone could reorder
+// messages on a link the sender never meant to reorder.
+
+use crate::id::ProcessId;
+use crate::run::task;
+use futures::stream::StreamExt;
+use rand::distributions::Distribution;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::delay_queue::DelayQueue;
+use tokio::time::Instant;
+
+/// How the jitter added on top of a link's `base` delay is sampled.
+#[derive(Debug, Clone, Copy)]
+pub enum Jitter {
+    /// No jitter: every message takes exactly the link's `base` delay.
+    Constant,
+    /// Sampled uniformly from `[0, max]`.
+    Uniform { max: Duration },
+    /// Sampled from a log-normal distribution with the given mean and
+    /// standard deviation (in milliseconds) - the long right tail this
+    /// produces is a closer match to real network jitter than `Uniform`.
+    LogNormal { mean_ms: f64, stddev_ms: f64 },
+}
+
+impl Jitter {
+    fn sample(&self) -> Duration {
+        match self {
+            Jitter::Constant => Duration::from_secs(0),
+            Jitter::Uniform { max } => {
+                let max_ms = max.as_millis() as u64;
+                let sampled_ms = rand::thread_rng().gen_range(0, max_ms + 1);
+                Duration::from_millis(sampled_ms)
+            }
+            Jitter::LogNormal { mean_ms, stddev_ms } => {
+                // `rand_distr::LogNormal::new` takes the underlying
+                // normal's mean/stddev, so convert from the log-normal's
+                // own mean/stddev (what callers actually configure)
+                let variance = stddev_ms * stddev_ms;
+                let mean2 = mean_ms * mean_ms;
+                let normal_stddev = ((variance / mean2) + 1.0).ln().sqrt();
+                let normal_mean = mean_ms.ln() - normal_stddev * normal_stddev / 2.0;
+                let sampled_ms = rand_distr::LogNormal::new(normal_mean, normal_stddev)
+                    .expect("valid log-normal parameters")
+                    .sample(&mut rand::thread_rng())
+                    .max(0.0);
+                Duration::from_millis(sampled_ms.round() as u64)
+            }
+        }
+    }
+}
+
+/// A link's one-way latency: a fixed `base` plus `jitter` sampled
+/// independently per message.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkLatency {
+    pub base: Duration,
+    pub jitter: Jitter,
+}
+
+impl LinkLatency {
+    pub fn new(base: Duration, jitter: Jitter) -> Self {
+        Self { base, jitter }
+    }
+
+    /// A link with no jitter: every message takes exactly `base`, matching
+    /// the single fixed `Duration` this type replaces.
+    pub fn fixed(base: Duration) -> Self {
+        Self::new(base, Jitter::Constant)
+    }
+
+    fn sample(&self) -> Duration {
+        self.base + self.jitter.sample()
+    }
+}
+
+/// A `(from, to) -> LinkLatency` table for an entire cluster.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyMatrix {
+    links: HashMap<(ProcessId, ProcessId), LinkLatency>,
+}
+
+impl LatencyMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, from: ProcessId, to: ProcessId, latency: LinkLatency) {
+        self.links.insert((from, to), latency);
+    }
+
+    pub fn get(&self, from: ProcessId, to: ProcessId) -> Option<&LinkLatency> {
+        self.links.get(&(from, to))
+    }
+}
+
+/// Delays every message received on `from` by `latency` (resampled per
+/// message) before forwarding it to `to_writer`, preserving per-link FIFO
+/// order: a sampled deadline is clamped to the previous message's deadline
+/// if it would otherwise be earlier.
+pub async fn delay_task<M>(
+    mut from: task::chan::ChannelReceiver<M>,
+    mut to_writer: task::chan::ChannelSender<M>,
+    latency: LinkLatency,
+) where
+    M: Send + 'static,
+{
+    let mut queue = DelayQueue::new();
+    let mut last_deadline: Option<Instant> = None;
+
+    loop {
+        tokio::select! {
+            msg = from.recv() => {
+                match msg {
+                    Some(msg) => {
+                        let deadline = Instant::now() + latency.sample();
+                        let deadline = match last_deadline {
+                            Some(last) if last > deadline => last,
+                            _ => deadline,
+                        };
+                        last_deadline = Some(deadline);
+                        queue.insert_at(msg, deadline);
+                    }
+                    None => {
+                        // upstream closed: drain whatever's still queued
+                        // before shutting down, so no in-flight message is
+                        // silently dropped
+                        while let Some(Ok(expired)) = queue.next().await {
+                            if to_writer.send(expired.into_inner()).await.is_err() {
+                                return;
+                            }
+                        }
+                        return;
+                    }
+                }
+            }
+            Some(expired) = queue.next(), if !queue.is_empty() => {
+                if let Ok(expired) = expired {
+                    if to_writer.send(expired.into_inner()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -5,10 +5,12 @@ use crate::log;
 use crate::protocol::Protocol;
 use crate::run::prelude::*;
 use crate::run::task;
+use crate::run::task::process::{ShutdownMode, ShutdownReceiver};
+use crate::run::task::telemetry;
 use crate::time::RunTime;
 use crate::HashMap;
 use std::sync::Arc;
-use tokio::time;
+use tokio::time::{self, Duration};
 
 /// Starts executors.
 pub fn start_executors<P>(
@@ -20,9 +22,15 @@ pub fn start_executors<P>(
     shard_writers: HashMap<ShardId, Vec<WriterSender<P>>>,
     to_executors: ToExecutors<P>,
     to_metrics_logger: Option<ExecutorMetricsSender>,
+    shutdown_rx: ShutdownReceiver,
 ) where
     P: Protocol + 'static,
 {
+    // make sure a global tracing subscriber (and, if the `otel` feature is
+    // on, an exporter) is installed before any `telemetry::command_span`
+    // below can do anything useful
+    telemetry::init(&config);
+
     // zip rxs'
     let incoming = to_executors_rxs
         .into_iter()
@@ -43,6 +51,7 @@ pub fn start_executors<P>(
             shard_writers.clone(),
             to_executors.clone(),
             to_metrics_logger.clone(),
+            shutdown_rx.clone(),
         ));
     }
 }
@@ -57,6 +66,7 @@ async fn executor_task<P>(
     mut shard_writers: HashMap<ShardId, Vec<WriterSender<P>>>,
     mut to_executors: ToExecutors<P>,
     mut to_metrics_logger: Option<ExecutorMetricsSender>,
+    mut shutdown_rx: ShutdownReceiver,
 ) where
     P: Protocol + 'static,
 {
@@ -77,19 +87,64 @@ async fn executor_task<P>(
     let mut metrics_interval =
         time::interval(super::metrics_logger::METRICS_INTERVAL);
 
+    // once a shutdown is requested this executor stops accepting new client
+    // registrations (for both drain modes); a `Graceful` shutdown then keeps
+    // reacting to `from_workers` - so execution info already produced by
+    // workers gets delivered to clients - until a full `drain_idle` tick
+    // passes with nothing left to handle
+    let mut shutdown_mode: Option<ShutdownMode> = None;
+    let drain_idle_period = Duration::from_millis(50);
+    let mut drain_idle = time::interval(drain_idle_period);
+
     loop {
         tokio::select! {
             execution_info = from_workers.recv() => {
                 log!("[executor] from workers: {:?}", execution_info);
                 if let Some(execution_info) = execution_info {
-                    executor.handle(execution_info, &time);
-                    fetch_new_command_results::<P>(&mut executor, &mut to_clients).await;
-                    fetch_info_to_executors::<P>(&mut executor, shard_id, &mut shard_writers, &mut to_executors).await;
+                    if shutdown_mode.is_some() {
+                        // still draining: restart the idle countdown
+                        drain_idle = time::interval_at(time::Instant::now() + drain_idle_period, drain_idle_period);
+                    }
+                    match config.executor_throttle_interval() {
+                        Some(throttle_interval) => {
+                            let batch = drain_batch(
+                                execution_info,
+                                &mut from_workers,
+                                config.executor_batch_max(),
+                                throttle_interval,
+                            );
+                            log!("[executor] draining batch of {} execution infos", batch.len());
+                            // one span per batch: individual `ExecutionInfo`s
+                            // aren't generically keyed by `Rifl`/`Dot` (that's
+                            // per-`Protocol::Executor` data), so this is the
+                            // finest grain available here; see
+                            // `fetch_new_command_results` for the per-command
+                            // span once a result comes back out
+                            let span = tracing::info_span!(
+                                "executor_handle_batch",
+                                ?shard_id,
+                                batch_len = batch.len(),
+                            );
+                            {
+                                let _enter = span.enter();
+                                for execution_info in batch {
+                                    executor.handle(execution_info, &time);
+                                }
+                            }
+                            fetch_new_command_results::<P>(&mut executor, &mut to_clients).await;
+                            fetch_info_to_executors::<P>(&mut executor, shard_id, &mut shard_writers, &mut to_executors).await;
+                        }
+                        None => {
+                            executor.handle(execution_info, &time);
+                            fetch_new_command_results::<P>(&mut executor, &mut to_clients).await;
+                            fetch_info_to_executors::<P>(&mut executor, shard_id, &mut shard_writers, &mut to_executors).await;
+                        }
+                    }
                 } else {
                     println!("[executor] error while receiving execution info from worker");
                 }
             }
-            from_client = from_clients.recv() => {
+            from_client = from_clients.recv(), if shutdown_mode.is_none() => {
                 log!("[executor] from client: {:?}", from_client);
                 if let Some(from_client) = from_client {
                     handle_from_client::<P>(from_client, &mut to_clients).await;
@@ -97,13 +152,13 @@ async fn executor_task<P>(
                     println!("[executor] error while receiving new command from clients");
                 }
             }
-            _ = cleanup_interval.tick() => {
+            _ = cleanup_interval.tick(), if shutdown_mode.is_none() => {
                 log!("[executor] cleanup");
                 executor.cleanup(&time);
                 fetch_new_command_results::<P>(&mut executor, &mut to_clients).await;
                 fetch_info_to_executors::<P>(&mut executor, shard_id, &mut shard_writers, &mut to_executors).await;
             }
-            _ = metrics_interval.tick()  => {
+            _ = metrics_interval.tick(), if shutdown_mode.is_none() => {
                 if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
                     // send metrics to logger (in case there's one)
                     let executor_metrics = executor.metrics().clone();
@@ -112,34 +167,104 @@ async fn executor_task<P>(
                     }
                 }
             }
+            mode = shutdown_rx.recv(), if shutdown_mode.is_none() => {
+                if let Some(Some(mode)) = mode {
+                    log!("[executor] {} draining after {:?} shutdown", executor_index, mode);
+                    shutdown_mode = Some(mode);
+                    if mode == ShutdownMode::Quick {
+                        break;
+                    }
+                    // count idleness from the shutdown request on
+                    drain_idle = time::interval_at(time::Instant::now() + drain_idle_period, drain_idle_period);
+                }
+            }
+            _ = drain_idle.tick(), if shutdown_mode == Some(ShutdownMode::Graceful) => {
+                log!("[executor] {} idle while draining, shutting down", executor_index);
+                break;
+            }
         }
     }
 }
 
+/// Greedily collects `first` plus whatever else is already sitting in
+/// `from_workers` into a single batch, instead of feeding `executor.handle`
+/// one message at a time: stops as soon as either `batch_max` messages have
+/// been collected, `throttle_interval` has elapsed since this batch started,
+/// or `from_workers` comes up empty, whichever happens first. Only ever
+/// called from inside the `from_workers` arm of `executor_task`'s
+/// `tokio::select!`, so there's no risk of starving `from_clients` or the
+/// cleanup/metrics ticks for longer than `throttle_interval`: as soon as
+/// this returns, control is back at the top of the loop where every other
+/// branch gets its turn.
+fn drain_batch<P>(
+    first: <P::Executor as Executor>::ExecutionInfo,
+    from_workers: &mut ExecutionInfoReceiver<P>,
+    batch_max: usize,
+    throttle_interval: Duration,
+) -> Vec<<P::Executor as Executor>::ExecutionInfo>
+where
+    P: Protocol,
+{
+    let mut batch = Vec::with_capacity(batch_max);
+    batch.push(first);
+    let deadline = time::Instant::now() + throttle_interval;
+    while batch.len() < batch_max && time::Instant::now() < deadline {
+        match from_workers.try_recv() {
+            Ok(execution_info) => batch.push(execution_info),
+            Err(_) => break,
+        }
+    }
+    batch
+}
+
 async fn fetch_new_command_results<P>(
     executor: &mut P::Executor,
     to_clients: &mut ToClients,
 ) where
     P: Protocol,
 {
+    use tracing::Instrument;
+
     // forward executor results (commands or partial commands) to clients that
     // are waiting for them
     for executor_result in executor.to_clients_iter() {
-        // get client id
-        let client_id = executor_result.rifl.source();
+        // unlike the batch-level span in `executor_task`, `ExecutorResult`
+        // is a single, non-generic type that always carries a `Rifl`, so
+        // this is where a real per-command span is possible; `.instrument`
+        // (rather than entering the span directly) since the work below
+        // crosses an `.await` point
+        let span = telemetry::command_span(executor_result.rifl, None);
+        async {
+            // get client id
+            let client_id = executor_result.rifl.source();
 
-        // send executor result to client (in case it is registered)
-        if let Some(executor_results_tx) = to_clients.to_client(&client_id) {
-            if let Err(e) = executor_results_tx.send(executor_result).await {
-                println!(
-                    "[executor] error while sending executor result to client {}: {:?}",
-                    client_id, e
-                );
+            // send executor result to client (in case it is registered)
+            if let Some(executor_results_tx) = to_clients.to_client(&client_id)
+            {
+                if let Err(e) =
+                    executor_results_tx.send(executor_result).await
+                {
+                    println!(
+                        "[executor] error while sending executor result to client {}: {:?}",
+                        client_id, e
+                    );
+                }
             }
         }
+        .instrument(span)
+        .await;
     }
 }
 
+/// Forwards execution info produced by `executor` to whichever shard (our
+/// own or a remote one) is supposed to receive it next.
+///
+/// Each iteration opens a span covering that single piece of forwarded
+/// info, so a trace exported from the shard this runs on shows inter-shard
+/// forwarding as a distinct step; it isn't yet carried across the wire to
+/// the receiving shard (see `telemetry::TraceContext`'s doc comment), so
+/// the child span the remote shard would open for the same command starts
+/// a new trace rather than continuing this one.
 async fn fetch_info_to_executors<P>(
     executor: &mut P::Executor,
     shard_id: ShardId,
@@ -148,6 +273,8 @@ async fn fetch_info_to_executors<P>(
 ) where
     P: Protocol + 'static,
 {
+    use tracing::Instrument;
+
     // forward execution info to other shards
     for (target_shard, execution_info) in executor.to_executors_iter() {
         log!(
@@ -155,27 +282,36 @@ async fn fetch_info_to_executors<P>(
             target_shard,
             execution_info
         );
-        // check if it's a message to self
-        if shard_id == target_shard {
-            // notify executor
-            if let Err(e) = to_executors.forward(execution_info).await {
-                println!("[executor] error while notifying other executors with new execution info: {:?}", e);
-            }
-        } else {
-            let msg_to_send = Arc::new(POEMessage::Executor(execution_info));
-            if let Some(channels) = shard_writers.get_mut(&target_shard) {
-                crate::run::task::process::send_to_one_writer::<P>(
-                    "executor",
-                    msg_to_send.clone(),
-                    channels,
-                )
-                .await
+        let span = tracing::info_span!(
+            "forward_execution_info",
+            ?shard_id,
+            ?target_shard,
+        );
+        async {
+            // check if it's a message to self
+            if shard_id == target_shard {
+                // notify executor
+                if let Err(e) = to_executors.forward(execution_info).await {
+                    println!("[executor] error while notifying other executors with new execution info: {:?}", e);
+                }
             } else {
-                panic!(
-                    "[executor] tried to send a message to a non-connected shard"
-                );
+                let msg_to_send = Arc::new(POEMessage::Executor(execution_info));
+                if let Some(channels) = shard_writers.get_mut(&target_shard) {
+                    crate::run::task::process::send_to_one_writer::<P>(
+                        "executor",
+                        msg_to_send.clone(),
+                        channels,
+                    )
+                    .await
+                } else {
+                    panic!(
+                        "[executor] tried to send a message to a non-connected shard"
+                    );
+                }
             }
         }
+        .instrument(span)
+        .await;
     }
 }
 
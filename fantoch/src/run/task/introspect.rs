@@ -0,0 +1,82 @@
+// This module serves live snapshots of a task's internal state to whatever
+// wants to poll it (a metrics endpoint, a debug CLI, ...), without that
+// task having to expose a lock directly. It's generic over the snapshot
+// type `S` so that `fantoch` doesn't have to depend on the downstream
+// crates that actually define interesting snapshots (e.g. a dependency
+// graph's pending-vertex count), unlike `tracer_task`, which is tied to
+// the `fantoch_prof` subscriber.
+use crate::run::task::process::ShutdownReceiver;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Duration;
+
+/// A request sent to `introspect_task` for a snapshot `S` of whatever state
+/// the caller wired up (e.g. `fantoch_ps::executor::graph::GraphSnapshot`).
+/// Kept generic over `S` so this module (part of `fantoch`) never has to
+/// depend on the downstream protocol crates that actually define the
+/// snapshot types it serves.
+pub enum IntrospectRequest<S> {
+    /// ask for a single snapshot, delivered once on `tx`
+    Snapshot(oneshot::Sender<S>),
+    /// ask for a snapshot every `interval`, delivered repeatedly on `tx`
+    /// until the receiving end is dropped
+    Stream {
+        interval: Duration,
+        tx: mpsc::Sender<S>,
+    },
+}
+
+/// Serves `IntrospectRequest`s against a `snapshot` closure, so callers
+/// elsewhere in the process (e.g. a metrics endpoint, a debug CLI) can pull
+/// live state out of a task without that task exposing a lock directly.
+/// Mirrors `tracer_task`'s shutdown handling, but isn't feature-gated: unlike
+/// `tracer_task` (which depends on the external `fantoch_prof` crate),
+/// introspection here only needs `tracing` events plus whatever `snapshot`
+/// the caller provides.
+pub async fn introspect_task<S, F>(
+    mut requests: mpsc::Receiver<IntrospectRequest<S>>,
+    mut snapshot: F,
+    mut shutdown_rx: ShutdownReceiver,
+) where
+    F: FnMut() -> S,
+{
+    // streams currently being served, each on its own tokio interval
+    let mut streams: Vec<(tokio::time::Interval, mpsc::Sender<S>)> = Vec::new();
+
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                match request {
+                    Some(IntrospectRequest::Snapshot(tx)) => {
+                        let _ = tx.send(snapshot());
+                    }
+                    Some(IntrospectRequest::Stream { interval, tx }) => {
+                        streams.push((tokio::time::interval(interval), tx));
+                    }
+                    None => {
+                        // no more requesters left, but keep serving any
+                        // streams already registered
+                    }
+                }
+            }
+            mode = shutdown_rx.recv() => {
+                if let Some(Some(_)) = mode {
+                    println!("[introspect_task] shutting down");
+                    break;
+                }
+            }
+        }
+
+        // drop any stream whose receiver has gone away, tick the rest
+        let mut still_alive = Vec::with_capacity(streams.len());
+        for (mut interval, tx) in streams {
+            if tx.is_closed() {
+                continue;
+            }
+            interval.tick().await;
+            if tx.send(snapshot()).await.is_ok() {
+                still_alive.push((interval, tx));
+            }
+        }
+        streams = still_alive;
+    }
+}
@@ -0,0 +1,45 @@
+// Harness for wiring simulated processes together without binding real
+// sockets: builds a fully-connected mesh of in-memory transports (see
+// `rw::socket::loopback`) across a set of process ids, with `multiplexing`
+// connections between every ordered pair, mirroring the shape
+// `connect_to_all` dials over real TCP. Useful for asserting that a message
+// with a given `target` reaches exactly the intended peer, and that
+// `multiplexing` spreads load across the per-peer vec, without standing up
+// the full handshake/reader/writer task machinery.
+#![cfg(feature = "test-util")]
+
+use crate::id::ProcessId;
+use crate::run::rw::socket::loopback::LoopbackTransport;
+use crate::HashMap;
+
+/// One simulated process's end of every connection in the mesh: for each
+/// peer, `multiplexing` independent in-memory transports, in the same order
+/// on both ends, so `mesh[&a][&b][i]` and `mesh[&b][&a][i]` are the two ends
+/// of the same `LoopbackTransport::pair`.
+pub type LoopbackMesh =
+    HashMap<ProcessId, HashMap<ProcessId, Vec<LoopbackTransport>>>;
+
+/// Builds a fully-connected in-memory mesh with `multiplexing` transports
+/// between every ordered pair drawn from `process_ids`.
+pub fn cluster(process_ids: &[ProcessId], multiplexing: usize) -> LoopbackMesh {
+    let mut mesh: LoopbackMesh = process_ids
+        .iter()
+        .map(|&id| (id, HashMap::new()))
+        .collect();
+
+    for (i, &a) in process_ids.iter().enumerate() {
+        for &b in &process_ids[i + 1..] {
+            let mut a_side = Vec::with_capacity(multiplexing);
+            let mut b_side = Vec::with_capacity(multiplexing);
+            for _ in 0..multiplexing {
+                let (ta, tb) = LoopbackTransport::pair(64 * 1024);
+                a_side.push(ta);
+                b_side.push(tb);
+            }
+            mesh.get_mut(&a).unwrap().insert(b, a_side);
+            mesh.get_mut(&b).unwrap().insert(a, b_side);
+        }
+    }
+
+    mesh
+}
@@ -1,23 +1,138 @@
+use super::auth;
+use super::compat;
 use super::execution_logger;
+use super::secure;
+use super::timeline;
 use crate::command::Command;
 use crate::config::Config;
 use crate::id::{Dot, ProcessId, ShardId};
-use crate::protocol::{Action, Executed, Protocol};
+use crate::protocol::{Action, Executed, Protocol, ProtocolMetricsKind};
 use crate::run::prelude::*;
-use crate::run::rw::Connection;
+use crate::run::rw::tls;
+use crate::run::rw::{Connection, Rw, TransportKind};
 use crate::run::task;
 use crate::time::RunTime;
 use crate::HashMap;
 use crate::{trace, warn};
+use color_eyre::eyre::{self, WrapErr};
 use color_eyre::Report;
 use rand::Rng;
 use std::fmt::Debug;
+use std::future::Future;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::net::{TcpListener, ToSocketAddrs};
 use tokio::task::JoinHandle;
 use tokio::time::{self, Duration};
 
+/// Which of the two drain modes a shutdown requested, from least to most
+/// thorough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Stop accepting new client commands, flush every writer's `Connection`
+    /// one last time, then exit.
+    Quick,
+    /// Everything `Quick` does, plus keep running until every worker's
+    /// `process.to_processes()`/`to_executors_iter()` has nothing left to
+    /// drain.
+    Graceful,
+}
+
+/// Counts messages a reader task rejected before they ever reached a
+/// `Protocol` worker - an envelope that failed authentication, or one
+/// claiming to be from the wrong peer - so they can still surface as
+/// `ProtocolMetricsKind::RejectedMessages`, the same metric a `Protocol`
+/// itself reports for rejections it catches on its own (e.g.
+/// `Basic::verify`). Shared by every reader task for this process, since a
+/// rejection at this stage happens before routing and so can't yet be
+/// attributed to whichever worker would otherwise have handled the
+/// message; `process_task` folds it into worker `0`'s periodic metrics
+/// report instead of giving it a channel of its own.
+#[derive(Clone, Default)]
+pub(crate) struct RejectedMessagesCounter(Arc<AtomicU64>);
+
+impl RejectedMessagesCounter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the count accumulated since the last `drain`, resetting it
+    /// back to zero.
+    fn drain(&self) -> u64 {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// Broadcasts, at most once, the `ShutdownMode` a caller wants every reader,
+/// writer, and process task to drain towards; cloned into each task so they
+/// can all react to the same signal via a `tokio::select!` branch.
+pub type ShutdownReceiver = tokio::sync::watch::Receiver<Option<ShutdownMode>>;
+
+/// A handle over every task that listens for shutdown, returned by
+/// `connect_to_all`/`start_processes` so a caller can `.await` it to block
+/// until the requested drain has completed; `Drop` can't run async cleanup,
+/// so this is how shutdown completion is observed.
+pub struct ShutdownHandle {
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl ShutdownHandle {
+    fn new(handles: Vec<JoinHandle<()>>) -> Self {
+        Self { handles }
+    }
+
+    /// Merges another handle's tasks into this one, so a single `.wait()`
+    /// can cover tasks started by more than one function (e.g. the reader
+    /// and writer tasks from `connect_to_all` and the process tasks from
+    /// `start_processes`).
+    pub fn merge(mut self, other: Self) -> Self {
+        self.handles.extend(other.handles);
+        self
+    }
+
+    /// Blocks until every task covered by this handle has exited.
+    pub async fn wait(self) {
+        for handle in self.handles {
+            if let Err(e) = handle.await {
+                warn!("[shutdown] task panicked while draining: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Hands a freshly re-established `Connection` from a per-peer supervisor
+/// task to the reader/writer task it replaces a dead connection for.
+type ConnectionUpdateSender =
+    task::chan::ChannelSender<(Connection, Option<secure::LinkCipher>)>;
+type ConnectionUpdateReceiver =
+    task::chan::ChannelReceiver<(Connection, Option<secure::LinkCipher>)>;
+
+/// Notifies a supervisor task that its paired reader/writer task's
+/// `Connection` just died and a replacement is needed.
+type ConnectionFailedSender = task::chan::ChannelSender<()>;
+type ConnectionFailedReceiver = task::chan::ChannelReceiver<()>;
+
+/// Maximum exponential backoff between reconnection attempts. The actual
+/// wait is `min(RECONNECT_BACKOFF_MAX, RECONNECT_BACKOFF_BASE * 2^attempt)`
+/// plus up to 50% jitter, so peers that all lose connectivity to this one at
+/// the same time don't all redial in lockstep.
+const RECONNECT_BACKOFF_BASE: Duration = Duration::from_millis(100);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+/// Caps how many consecutive failed reconnection attempts a
+/// `writer_supervisor_task` makes before giving up on a peer entirely.
+const MAX_RECONNECT_ATTEMPTS: usize = 20;
+
+/// Read/write buffer size used only while replaying an `execution_log` file
+/// for crash recovery at startup.
+const EXECUTION_LOG_RECOVERY_BUFFER_SIZE: usize = 8 * 1024;
+
+#[allow(clippy::too_many_arguments)]
 pub async fn connect_to_all<A, P>(
     process_id: ProcessId,
     shard_id: ShardId,
@@ -32,17 +147,39 @@ pub async fn connect_to_all<A, P>(
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
     multiplexing: usize,
+    // when set, every connection is authenticated and encrypted via
+    // `secure::secure_handshake`; when `None`, transport stays plaintext
+    identity: Option<Arc<secure::Identity>>,
+    peer_keys: Arc<secure::PeerKeys>,
+    // when set, every outgoing message is wrapped in a signed
+    // `auth::AuthFrame` and every incoming one is verified against
+    // `message_peer_keys` before being handed to the protocol; independent
+    // of (and composable with) `identity`/`peer_keys` above
+    message_identity: Option<Arc<auth::Identity>>,
+    message_peer_keys: Arc<auth::PeerVerifyingKeys>,
+    // passed on to every `writer_supervisor_task`, whose `reconnect_once`
+    // doesn't use it: no connection this process dials or accepts is
+    // wrapped in TLS, and the transport stays plaintext
+    tls_config: Option<Arc<tls::TlsConfig>>,
+    // passed on the same way and likewise unused: every connection below,
+    // and every reconnection, is one of `multiplexing` TCP sockets per
+    // peer, whatever the value
+    transport: TransportKind,
+    shutdown_rx: ShutdownReceiver,
 ) -> Result<
     (
         HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
         HashMap<ProcessId, Vec<WriterSender<P>>>,
+        ShutdownHandle,
+        RejectedMessagesCounter,
     ),
     Report,
 >
 where
-    A: ToSocketAddrs + Debug,
+    A: ToSocketAddrs + Debug + Clone + Send + 'static,
     P: Protocol + 'static,
 {
+    let rejected_messages = RejectedMessagesCounter::new();
     // check that (n-1 + shards-1) addresses were set
     let total = config.n() - 1 + config.shard_count() - 1;
     assert_eq!(
@@ -54,6 +191,11 @@ where
     // compute the number of expected connections
     let total_connections = total * multiplexing;
 
+    // what this process sends (and expects back) during the mandatory
+    // compat handshake every connection below goes through right after
+    // `say_hi`/`receive_hi`
+    let compat_hello = Arc::new(compat::CompatHello::new::<P>(&config));
+
     // spawn listener
     let mut from_listener = task::spawn_producer(channel_buffer_size, |tx| {
         super::listener_task(listener, tcp_nodelay, tcp_buffer_size, tx)
@@ -71,6 +213,22 @@ where
     for (address, delay) in addresses {
         // create `multiplexing` connections per address
         for _ in 0..multiplexing {
+            // TODO once `task::connect` hands back the raw socket before
+            // framing (rather than an already-framed `Connection`), upgrade
+            // it here via `tls::connect(tls_config, ..)` whenever
+            // `tls_config` is set, before the connection is used below
+            //
+            // TODO dispatch on `transport` here too: `TransportKind::Tcp`
+            // keeps dialing one socket per iteration of this loop, same as
+            // today; `TransportKind::Quic` would instead dial a single
+            // `QuicEndpoint::connect` per `address` outside this inner loop
+            // and call `quic::open_stream` here `multiplexing` times, so
+            // `outgoing` ends up with the same shape either way;
+            // `TransportKind::Unix` would dial `unix::connect` on `address`
+            // reinterpreted as a filesystem path instead of a `ToSocketAddrs`
+            // - which also means `A` can't stay a bare `ToSocketAddrs` bound
+            // once this is wired in, since a Unix run builds its
+            // `addresses` as paths (see `unix`'s module doc comment)
             let mut connection = super::connect(
                 &address,
                 tcp_nodelay,
@@ -82,8 +240,10 @@ where
             if let Some(delay) = delay {
                 connection.set_delay(delay);
             }
-            // save connection if connected successfully
-            outgoing.push(connection);
+            // save connection (and the address it was dialed from, so a
+            // `writer_supervisor_task` can redial it later) if connected
+            // successfully
+            outgoing.push((address.clone(), connection));
         }
     }
 
@@ -96,7 +256,7 @@ where
         incoming.push(connection);
     }
 
-    let res = handshake::<P>(
+    let res = handshake::<A, P>(
         process_id,
         shard_id,
         to_workers,
@@ -105,12 +265,26 @@ where
         channel_buffer_size,
         incoming,
         outgoing,
+        tcp_nodelay,
+        tcp_buffer_size,
+        connect_retries,
+        compat_hello,
+        identity,
+        peer_keys,
+        message_identity,
+        message_peer_keys,
+        tls_config,
+        transport,
+        from_listener,
+        rejected_messages.clone(),
+        shutdown_rx,
     )
     .await;
-    Ok(res)
+    Ok((res.0, res.1, res.2, rejected_messages))
 }
 
-async fn handshake<P>(
+#[allow(clippy::too_many_arguments)]
+async fn handshake<A, P>(
     process_id: ProcessId,
     shard_id: ShardId,
     to_workers: ReaderToWorkers<P>,
@@ -118,48 +292,272 @@ async fn handshake<P>(
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
     mut connections_0: Vec<Connection>,
-    mut connections_1: Vec<Connection>,
+    mut connections_1: Vec<(A, Connection)>,
+    tcp_nodelay: bool,
+    tcp_buffer_size: usize,
+    connect_retries: usize,
+    // sent (and checked against the peer's own) over every connection right
+    // after `say_hi`/`receive_hi`; a mismatch drops the connection before
+    // any executor/writer traffic flows, unlike `identity`/`message_identity`
+    // below, which are both opt-in
+    compat_hello: Arc<compat::CompatHello>,
+    identity: Option<Arc<secure::Identity>>,
+    peer_keys: Arc<secure::PeerKeys>,
+    message_identity: Option<Arc<auth::Identity>>,
+    message_peer_keys: Arc<auth::PeerVerifyingKeys>,
+    tls_config: Option<Arc<tls::TlsConfig>>,
+    transport: TransportKind,
+    from_listener: task::chan::ChannelReceiver<Connection>,
+    rejected_messages: RejectedMessagesCounter,
+    shutdown_rx: ShutdownReceiver,
 ) -> (
     HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
     HashMap<ProcessId, Vec<WriterSender<P>>>,
+    ShutdownHandle,
 )
 where
+    A: ToSocketAddrs + Debug + Clone + Send + 'static,
     P: Protocol + 'static,
 {
     // say hi to all on both connections
     say_hi(process_id, shard_id, &mut connections_0).await;
-    say_hi(process_id, shard_id, &mut connections_1).await;
+    say_hi_addressed(process_id, shard_id, &mut connections_1).await;
     trace!("said hi to all processes");
 
-    // receive hi from all on both connections
-    let id_to_connection_0 = receive_hi(connections_0).await;
-    let id_to_connection_1 = receive_hi(connections_1).await;
+    // receive hi from all on both connections, then check protocol/config
+    // compatibility, then - if `identity` is set - authenticate and derive a
+    // per-link cipher for each, dropping any connection whose peer fails
+    // either check
+    let id_to_connection_0 = secure_upgrade_all(
+        identity.as_deref(),
+        &peer_keys,
+        compat_check_all(&compat_hello, receive_hi(connections_0).await).await,
+    )
+    .await;
+    let id_to_connection_1 = secure_upgrade_all_addressed(
+        identity.as_deref(),
+        &peer_keys,
+        compat_check_all_addressed(
+            &compat_hello,
+            receive_hi_addressed(connections_1).await,
+        )
+        .await,
+    )
+    .await;
 
     // start readers and writers
-    start_readers::<P>(to_workers, to_executors, id_to_connection_0);
-    start_writers::<P>(
+    let reader_handles = start_readers::<P>(
+        to_workers.clone(),
+        to_executors.clone(),
+        id_to_connection_0,
+        message_peer_keys.clone(),
+        rejected_messages.clone(),
+        shutdown_rx.clone(),
+    );
+
+    // keep accepting connections after this initial handshake completes, so
+    // that if a peer's reader connection later dies, the peer's own
+    // `writer_supervisor_task` redialing us shows up here and gets routed to
+    // a fresh `reader_task`
+    let reconnect_handle = task::spawn(reader_reconnect_listener_task::<P>(
+        process_id,
+        shard_id,
+        compat_hello.clone(),
+        identity.clone(),
+        peer_keys.clone(),
+        message_peer_keys.clone(),
+        from_listener,
+        to_workers,
+        to_executors,
+        rejected_messages,
+        shutdown_rx.clone(),
+    ));
+
+    let (ips, writers, writer_handles) = start_writers::<A, P>(
+        process_id,
         shard_id,
         tcp_flush_interval,
         channel_buffer_size,
         id_to_connection_1,
+        tcp_nodelay,
+        tcp_buffer_size,
+        connect_retries,
+        compat_hello,
+        identity,
+        peer_keys,
+        message_identity,
+        tls_config,
+        transport,
+        shutdown_rx,
     )
-    .await
+    .await;
+
+    let mut handles = reader_handles;
+    handles.push(reconnect_handle);
+    handles.extend(writer_handles);
+    (ips, writers, ShutdownHandle::new(handles))
 }
 
-async fn say_hi(
+/// Runs the mandatory compat handshake over each connection, dropping
+/// (logging and discarding) any whose peer disagrees on wire schema,
+/// `Protocol` type, or consensus-relevant `Config`; unlike
+/// `secure_upgrade_all`, this always runs since it isn't gated behind any
+/// opt-in configuration.
+async fn compat_check_all(
+    compat_hello: &compat::CompatHello,
+    connections: Vec<(ProcessId, ShardId, Connection)>,
+) -> Vec<(ProcessId, ShardId, Connection)> {
+    let mut checked = Vec::with_capacity(connections.len());
+    for (peer_id, peer_shard_id, mut connection) in connections {
+        if compat::compat_check(&mut connection, compat_hello, peer_id).await
+        {
+            checked.push((peer_id, peer_shard_id, connection));
+        }
+    }
+    checked
+}
+
+async fn compat_check_all_addressed<A>(
+    compat_hello: &compat::CompatHello,
+    connections: Vec<(ProcessId, ShardId, A, Connection)>,
+) -> Vec<(ProcessId, ShardId, A, Connection)> {
+    let mut checked = Vec::with_capacity(connections.len());
+    for (peer_id, peer_shard_id, address, mut connection) in connections {
+        if compat::compat_check(&mut connection, compat_hello, peer_id).await
+        {
+            checked.push((peer_id, peer_shard_id, address, connection));
+        }
+    }
+    checked
+}
+
+/// If `identity` is set, authenticates each connection's claimed identity
+/// and derives a per-link cipher via `secure::secure_handshake`, dropping
+/// (logging and discarding) any connection whose peer fails to prove it. If
+/// `identity` is `None`, secure transport isn't configured and every
+/// connection is passed through unchanged with no cipher.
+async fn secure_upgrade_all(
+    identity: Option<&secure::Identity>,
+    peer_keys: &secure::PeerKeys,
+    connections: Vec<(ProcessId, ShardId, Connection)>,
+) -> Vec<(ProcessId, ShardId, Connection, Option<secure::LinkCipher>)> {
+    let mut upgraded = Vec::with_capacity(connections.len());
+    for (peer_id, peer_shard_id, mut connection) in connections {
+        match secure_upgrade_one(identity, peer_keys, peer_id, &mut connection)
+            .await
+        {
+            Some(cipher) => {
+                upgraded.push((peer_id, peer_shard_id, connection, cipher))
+            }
+            None => warn!(
+                "[secure] dropping connection with process {} after a \
+                 failed handshake",
+                peer_id
+            ),
+        }
+    }
+    upgraded
+}
+
+async fn secure_upgrade_all_addressed<A>(
+    identity: Option<&secure::Identity>,
+    peer_keys: &secure::PeerKeys,
+    connections: Vec<(ProcessId, ShardId, A, Connection)>,
+) -> Vec<(ProcessId, ShardId, A, Connection, Option<secure::LinkCipher>)> {
+    let mut upgraded = Vec::with_capacity(connections.len());
+    for (peer_id, peer_shard_id, address, mut connection) in connections {
+        match secure_upgrade_one(identity, peer_keys, peer_id, &mut connection)
+            .await
+        {
+            Some(cipher) => {
+                upgraded.push((peer_id, peer_shard_id, address, connection, cipher))
+            }
+            None => warn!(
+                "[secure] dropping connection with process {} after a \
+                 failed handshake",
+                peer_id
+            ),
+        }
+    }
+    upgraded
+}
+
+/// Returns `Some(cipher)` (with `cipher` possibly `None`, when secure
+/// transport isn't configured) if `connection` is usable, or `None` if
+/// `identity` was set but the peer failed to prove it's really `peer_id` -
+/// in which case the connection must be dropped without being used further.
+async fn secure_upgrade_one(
+    identity: Option<&secure::Identity>,
+    peer_keys: &secure::PeerKeys,
+    peer_id: ProcessId,
+    connection: &mut Connection,
+) -> Option<Option<secure::LinkCipher>> {
+    let identity = match identity {
+        Some(identity) => identity,
+        // secure transport isn't configured: use the connection as is
+        None => return Some(None),
+    };
+    match secure::secure_handshake(connection, identity, peer_id, peer_keys)
+        .await
+    {
+        Ok(cipher) => Some(Some(cipher)),
+        Err(e) => {
+            warn!(
+                "[secure] handshake with process {} failed: {:?}",
+                peer_id, e
+            );
+            None
+        }
+    }
+}
+
+async fn say_hi_one(
     process_id: ProcessId,
     shard_id: ShardId,
-    connections: &mut Vec<Connection>,
+    connection: &mut Connection,
 ) {
     let hi = ProcessHi {
         process_id,
         shard_id,
     };
+    if let Err(e) = connection.send(&hi).await {
+        warn!("error while sending hi to connection: {:?}", e)
+    }
+}
+
+async fn say_hi(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    connections: &mut Vec<Connection>,
+) {
     // send hi on each connection
     for connection in connections.iter_mut() {
-        if let Err(e) = connection.send(&hi).await {
-            warn!("error while sending hi to connection: {:?}", e)
-        }
+        say_hi_one(process_id, shard_id, connection).await;
+    }
+}
+
+async fn say_hi_addressed<A>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    connections: &mut Vec<(A, Connection)>,
+) {
+    // send hi on each connection
+    for (_, connection) in connections.iter_mut() {
+        say_hi_one(process_id, shard_id, connection).await;
+    }
+}
+
+async fn receive_hi_one(
+    mut connection: Connection,
+) -> (ProcessId, ShardId, Connection) {
+    if let Some(ProcessHi {
+        process_id,
+        shard_id,
+    }) = connection.recv().await
+    {
+        (process_id, shard_id, connection)
+    } else {
+        panic!("error receiving hi");
     }
 }
 
@@ -169,59 +567,190 @@ async fn receive_hi(
     let mut id_to_connection = Vec::with_capacity(connections.len());
 
     // receive hi from each connection
-    for mut connection in connections {
-        if let Some(ProcessHi {
-            process_id,
-            shard_id,
-        }) = connection.recv().await
-        {
-            id_to_connection.push((process_id, shard_id, connection));
-        } else {
-            panic!("error receiving hi");
-        }
+    for connection in connections {
+        id_to_connection.push(receive_hi_one(connection).await);
     }
     id_to_connection
 }
 
+async fn receive_hi_addressed<A>(
+    connections: Vec<(A, Connection)>,
+) -> Vec<(ProcessId, ShardId, A, Connection)> {
+    let mut id_to_connection = Vec::with_capacity(connections.len());
+
+    // receive hi from each connection
+    for (address, connection) in connections {
+        let (process_id, shard_id, connection) =
+            receive_hi_one(connection).await;
+        id_to_connection.push((process_id, shard_id, address, connection));
+    }
+    id_to_connection
+}
+
+/// Keeps accepting connections after `connect_to_all`'s initial handshake
+/// completes, so a peer's reader connection can be re-established after it
+/// dies: whoever dials us again (the peer's own `writer_supervisor_task`,
+/// reconnecting its side) repeats the `say_hi`/`receive_hi` exchange used at
+/// startup, and a fresh `reader_task` is spawned for the result, exactly as
+/// `start_readers` would have done at startup.
+#[allow(clippy::too_many_arguments)]
+async fn reader_reconnect_listener_task<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    compat_hello: Arc<compat::CompatHello>,
+    identity: Option<Arc<secure::Identity>>,
+    peer_keys: Arc<secure::PeerKeys>,
+    message_peer_keys: Arc<auth::PeerVerifyingKeys>,
+    mut from_listener: task::chan::ChannelReceiver<Connection>,
+    to_workers: ReaderToWorkers<P>,
+    to_executors: ToExecutors<P>,
+    rejected_messages: RejectedMessagesCounter,
+    shutdown_rx: ShutdownReceiver,
+) where
+    P: Protocol + 'static,
+{
+    loop {
+        let mut connection = match from_listener.recv().await {
+            Some(connection) => connection,
+            None => {
+                trace!("[reader-reconnect] listener channel closed, stopping");
+                break;
+            }
+        };
+
+        say_hi_one(process_id, shard_id, &mut connection).await;
+        match connection.recv::<ProcessHi>().await {
+            Some(ProcessHi {
+                process_id: peer_id,
+                shard_id: peer_shard_id,
+            }) => {
+                if !compat::compat_check(&mut connection, &compat_hello, peer_id)
+                    .await
+                {
+                    continue;
+                }
+                let cipher = match secure_upgrade_one(
+                    identity.as_deref(),
+                    &peer_keys,
+                    peer_id,
+                    &mut connection,
+                )
+                .await
+                {
+                    Some(cipher) => cipher,
+                    None => {
+                        warn!(
+                            "[reader-reconnect] dropping reconnected \
+                             connection from process {} after a failed \
+                             handshake",
+                            peer_id
+                        );
+                        continue;
+                    }
+                };
+                trace!(
+                    "[reader-reconnect] new connection from process {}",
+                    peer_id
+                );
+                task::spawn(reader_task::<P>(
+                    to_workers.clone(),
+                    to_executors.clone(),
+                    peer_id,
+                    peer_shard_id,
+                    connection,
+                    cipher,
+                    message_peer_keys.clone(),
+                    rejected_messages.clone(),
+                    shutdown_rx.clone(),
+                ));
+            }
+            None => {
+                warn!("[reader-reconnect] error receiving hi on reconnected socket");
+            }
+        }
+    }
+}
+
 /// Starts a reader task per connection received. A `ReaderToWorkers` is passed
 /// to each reader so that these can forward immediately to the correct worker
 /// process.
 fn start_readers<P>(
     to_workers: ReaderToWorkers<P>,
     to_executors: ToExecutors<P>,
-    connections: Vec<(ProcessId, ShardId, Connection)>,
-) where
+    connections: Vec<(
+        ProcessId,
+        ShardId,
+        Connection,
+        Option<secure::LinkCipher>,
+    )>,
+    message_peer_keys: Arc<auth::PeerVerifyingKeys>,
+    rejected_messages: RejectedMessagesCounter,
+    shutdown_rx: ShutdownReceiver,
+) -> Vec<JoinHandle<()>>
+where
     P: Protocol + 'static,
 {
-    for (process_id, shard_id, connection) in connections {
-        task::spawn(reader_task::<P>(
-            to_workers.clone(),
-            to_executors.clone(),
-            process_id,
-            shard_id,
-            connection,
-        ));
-    }
+    connections
+        .into_iter()
+        .map(|(process_id, shard_id, connection, cipher)| {
+            task::spawn(reader_task::<P>(
+                to_workers.clone(),
+                to_executors.clone(),
+                process_id,
+                shard_id,
+                connection,
+                cipher,
+                message_peer_keys.clone(),
+                rejected_messages.clone(),
+                shutdown_rx.clone(),
+            ))
+        })
+        .collect()
 }
 
-async fn start_writers<P>(
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments)]
+async fn start_writers<A, P>(
+    process_id: ProcessId,
     shard_id: ShardId,
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
-    connections: Vec<(ProcessId, ShardId, Connection)>,
+    connections: Vec<(
+        ProcessId,
+        ShardId,
+        A,
+        Connection,
+        Option<secure::LinkCipher>,
+    )>,
+    tcp_nodelay: bool,
+    tcp_buffer_size: usize,
+    connect_retries: usize,
+    compat_hello: Arc<compat::CompatHello>,
+    identity: Option<Arc<secure::Identity>>,
+    peer_keys: Arc<secure::PeerKeys>,
+    message_identity: Option<Arc<auth::Identity>>,
+    tls_config: Option<Arc<tls::TlsConfig>>,
+    transport: TransportKind,
+    shutdown_rx: ShutdownReceiver,
 ) -> (
     HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
     HashMap<ProcessId, Vec<WriterSender<P>>>,
+    Vec<JoinHandle<()>>,
 )
 where
+    A: ToSocketAddrs + Debug + Clone + Send + 'static,
     P: Protocol + 'static,
 {
     let mut ips = HashMap::with_capacity(connections.len());
     // mapping from process id to channel broadcast writer should write to
     let mut writers = HashMap::with_capacity(connections.len());
+    // handle of every writer task (and its reconnection supervisor) spawned
+    // below, so a caller can `.await` until every peer writer has finished
+    // its shutdown drain
+    let mut writer_handles = Vec::with_capacity(connections.len() * 2);
 
     // start on writer task per connection
-    for (peer_id, peer_shard_id, connection) in connections {
+    for (peer_id, peer_shard_id, address, connection, cipher) in connections {
         // save shard id, ip and connection delay
         let ip = connection
             .ip_addr()
@@ -251,12 +780,45 @@ where
             tcp_flush_interval
         };
 
+        // a dead connection is reported by `writer_task` through
+        // `failed_tx`; `writer_supervisor_task` redials it and hands the
+        // replacement back through `update_tx`, so `writer_tx` above (and
+        // the `WriterSender` entries built from it below) never need to
+        // change
+        let (failed_tx, failed_rx) = task::channel(1);
+        let (update_tx, update_rx) = task::channel(1);
+
         // spawn the writer task
-        task::spawn(writer_task::<P>(
+        writer_handles.push(task::spawn(writer_task::<P>(
+            process_id,
+            shard_id,
             tcp_flush_interval,
             connection,
+            cipher,
+            message_identity.clone(),
             writer_rx,
-        ));
+            shutdown_rx.clone(),
+            failed_tx,
+            update_rx,
+        )));
+        writer_handles.push(task::spawn(writer_supervisor_task::<A>(
+            process_id,
+            shard_id,
+            peer_id,
+            peer_shard_id,
+            address,
+            connection_delay,
+            tcp_nodelay,
+            tcp_buffer_size,
+            connect_retries,
+            compat_hello.clone(),
+            identity.clone(),
+            peer_keys.clone(),
+            tls_config.clone(),
+            transport,
+            failed_rx,
+            update_tx,
+        )));
 
         let tx = if let Some(delay) = connection_delay {
             // if connection has a delay, spawn a delay task for this writer
@@ -270,7 +832,18 @@ where
             ));
 
             // spawn delay task
-            task::spawn(super::delay::delay_task(delay_rx, writer_tx, delay));
+            //
+            // TODO `delay` here is a single fixed `Duration` sourced from
+            // `Connection::delay()`, so `LinkLatency::fixed` is the closest
+            // match - wiring a real per-`(ProcessId, ProcessId)`
+            // `LatencyMatrix` with jitter all the way through would mean
+            // `Connection` (in the currently-unreachable `rw::connection`)
+            // carrying a `LinkLatency` instead of a plain `Duration`.
+            task::spawn(super::delay::delay_task(
+                delay_rx,
+                writer_tx,
+                super::delay::LinkLatency::fixed(delay),
+            ));
 
             // in this case, messages are first forward to the delay task, which
             // then forwards them to the writer task
@@ -285,7 +858,7 @@ where
         txs.push(tx);
     }
 
-    (ips, writers)
+    (ips, writers, writer_handles)
 }
 
 /// Reader task.
@@ -295,31 +868,80 @@ async fn reader_task<P>(
     process_id: ProcessId,
     shard_id: ShardId,
     mut connection: Connection,
+    cipher: Option<secure::LinkCipher>,
+    message_peer_keys: Arc<auth::PeerVerifyingKeys>,
+    rejected_messages: RejectedMessagesCounter,
+    mut shutdown_rx: ShutdownReceiver,
 ) where
     P: Protocol + 'static,
 {
     loop {
-        match connection.recv::<POEMessage<P>>().await {
-            Some(msg) => match msg {
-                POEMessage::Protocol(msg) => {
-                    let forward = reader_to_workers
-                        .forward((process_id, shard_id, msg))
-                        .await;
-                    if let Err(e) = forward {
-                        warn!("[reader] error notifying process task with new msg: {:?}",e);
+        tokio::select! {
+            msg = connection.recv::<secure::SecureFrame<auth::AuthFrame<POEMessage<P>>>>() => {
+                let frame = match msg {
+                    Some(msg) => msg.unwrap(cipher.as_ref()).ok(),
+                    None => {
+                        warn!("[reader] error receiving message from connection");
+                        break;
                     }
-                }
-                POEMessage::Executor(execution_info) => {
-                    trace!("[reader] to executor {:?}", execution_info);
-                    // notify executor
-                    if let Err(e) = to_executors.forward(execution_info).await {
-                        warn!("[reader] error while notifying executor with new execution info: {:?}", e);
+                };
+                // a message that fails to decrypt, or an envelope that
+                // fails authentication, is dropped without tearing down
+                // the connection: unlike a read error, it doesn't mean the
+                // connection itself is broken, only that this one message
+                // shouldn't be trusted
+                let msg = match frame {
+                    Some(frame) => match frame.verify(&message_peer_keys) {
+                        Ok((sender, sender_shard_id, msg)) => {
+                            // the connection itself already tells us which
+                            // peer this is, so an envelope whose claimed
+                            // sender doesn't match it - even if the
+                            // signature checks out for *some* process - is
+                            // just as suspicious as a failed verification
+                            if sender != process_id || sender_shard_id != shard_id {
+                                rejected_messages.increment();
+                                warn!(
+                                    "[reader] message claims to be from process {}/{} but arrived on process {}'s connection; dropping",
+                                    sender, sender_shard_id, process_id
+                                );
+                                continue;
+                            }
+                            msg
+                        }
+                        Err(e) => {
+                            rejected_messages.increment();
+                            warn!("[reader] dropping message that failed authentication: {:?}", e);
+                            continue;
+                        }
+                    },
+                    None => {
+                        warn!("[reader] dropping message that failed to decrypt");
+                        continue;
+                    }
+                };
+                match msg {
+                    POEMessage::Protocol(msg) => {
+                        let forward = reader_to_workers
+                            .forward((process_id, shard_id, msg))
+                            .await;
+                        if let Err(e) = forward {
+                            warn!("[reader] error notifying process task with new msg: {:?}",e);
+                        }
+                    }
+                    POEMessage::Executor(execution_info) => {
+                        trace!("[reader] to executor {:?}", execution_info);
+                        // notify executor
+                        if let Err(e) = to_executors.forward(execution_info).await {
+                            warn!("[reader] error while notifying executor with new execution info: {:?}", e);
+                        }
                     }
                 }
-            },
-            None => {
-                warn!("[reader] error receiving message from connection");
-                break;
+            }
+            mode = shutdown_rx.recv() => {
+                if let Some(Some(_)) = mode {
+                    trace!("[reader] shutting down on {} signal", process_id);
+                    break;
+                }
             }
         }
     }
@@ -327,64 +949,371 @@ async fn reader_task<P>(
 
 /// Writer task.
 async fn writer_task<P>(
+    own_id: ProcessId,
+    own_shard_id: ShardId,
     tcp_flush_interval: Option<Duration>,
     mut connection: Connection,
+    mut cipher: Option<secure::LinkCipher>,
+    message_identity: Option<Arc<auth::Identity>>,
     mut parent: WriterReceiver<P>,
+    mut shutdown_rx: ShutdownReceiver,
+    mut failed_tx: ConnectionFailedSender,
+    mut update_rx: ConnectionUpdateReceiver,
 ) where
     P: Protocol + 'static,
 {
     // track whether there's been a flush error on this connection
     let mut flush_error = false;
-    // if flush interval higher than 0, then flush periodically; otherwise,
-    // flush on every write
-    if let Some(tcp_flush_interval) = tcp_flush_interval {
-        // create interval
-        let mut interval = time::interval(tcp_flush_interval);
-        loop {
-            tokio::select! {
-                msg = parent.recv() => {
-                    if let Some(msg) = msg {
-                        // connection write *doesn't* flush
-                        if let Err(e) = connection.write(&*msg).await {
-                            warn!("[writer] error writing message in connection: {:?}", e);
+    // set when the shutdown branch below fires, so the "exiting" log at the
+    // end of this function doesn't read as a failure
+    let mut shutting_down = false;
+
+    'active: loop {
+        // whether the inner loop below ended because `connection` failed (as
+        // opposed to `parent` being gone or a shutdown being requested), in
+        // which case we ask our supervisor for a replacement before giving up
+        let mut connection_failed = false;
+
+        // if flush interval higher than 0, then flush periodically;
+        // otherwise, flush on every write
+        if let Some(tcp_flush_interval) = tcp_flush_interval {
+            // create interval
+            let mut interval = time::interval(tcp_flush_interval);
+            loop {
+                tokio::select! {
+                    msg = parent.recv() => {
+                        if let Some(msg) = msg {
+                            // connection write *doesn't* flush
+                            let auth_frame = auth::AuthFrame::wrap(
+                                message_identity.as_deref(),
+                                own_id,
+                                own_shard_id,
+                                &*msg,
+                            );
+                            let frame = secure::SecureFrame::wrap(cipher.as_ref(), &auth_frame);
+                            if let Err(e) = connection.write(&frame).await {
+                                warn!("[writer] error writing message in connection: {:?}", e);
+                                connection_failed = true;
+                                break;
+                            }
+                        } else {
+                            warn!("[writer] error receiving message from parent");
+                            break 'active;
+                        }
+                    }
+                    _ = interval.tick() => {
+                        // flush socket
+                        if let Err(e) = connection.flush().await {
+                            // make sure we only log the error once
+                            if !flush_error {
+                                warn!("[writer] error flushing connection: {:?}", e);
+                                flush_error = true;
+                            }
+                            connection_failed = true;
+                            break;
+                        }
+                    }
+                    mode = shutdown_rx.recv() => {
+                        if let Some(Some(_)) = mode {
+                            shutting_down = true;
+                            break 'active;
                         }
-                    } else {
-                        warn!("[writer] error receiving message from parent");
-                        break;
                     }
                 }
-                _ = interval.tick() => {
-                    // flush socket
-                    if let Err(e) = connection.flush().await {
-                        // make sure we only log the error once
-                        if !flush_error {
-                            warn!("[writer] error flushing connection: {:?}", e);
-                            flush_error = true;
+            }
+        } else {
+            loop {
+                tokio::select! {
+                    msg = parent.recv() => {
+                        if let Some(msg) = msg {
+                            // connection write *does* flush
+                            let auth_frame = auth::AuthFrame::wrap(
+                                message_identity.as_deref(),
+                                own_id,
+                                own_shard_id,
+                                &*msg,
+                            );
+                            let frame = secure::SecureFrame::wrap(cipher.as_ref(), &auth_frame);
+                            if let Err(e) = connection.send(&frame).await {
+                                warn!(
+                                    "[writer] error sending message to connection: {:?}",
+                                    e
+                                );
+                                connection_failed = true;
+                                break;
+                            }
+                        } else {
+                            warn!("[writer] error receiving message from parent");
+                            break 'active;
+                        }
+                    }
+                    mode = shutdown_rx.recv() => {
+                        if let Some(Some(_)) = mode {
+                            shutting_down = true;
+                            break 'active;
                         }
                     }
                 }
             }
         }
+
+        // tell the supervisor this connection died, then wait for it to
+        // hand us a reconnected one, unless a shutdown fires first
+        debug_assert!(connection_failed);
+        if failed_tx.send(()).await.is_err() {
+            warn!("[writer] no supervisor left to request a reconnect, giving up");
+            break 'active;
+        }
+        tokio::select! {
+            new_connection = update_rx.recv() => {
+                match new_connection {
+                    Some((new_connection, new_cipher)) => {
+                        trace!("[writer] connection replaced, resuming");
+                        connection = new_connection;
+                        cipher = new_cipher;
+                        flush_error = false;
+                    }
+                    None => {
+                        warn!("[writer] supervisor gone while waiting for a reconnect");
+                        break 'active;
+                    }
+                }
+            }
+            mode = shutdown_rx.recv() => {
+                if let Some(Some(_)) = mode {
+                    shutting_down = true;
+                    break 'active;
+                }
+            }
+        }
+    }
+
+    if shutting_down {
+        // flush one last time so nothing buffered is lost on exit
+        if let Err(e) = connection.flush().await {
+            warn!(
+                "[writer] error flushing connection during shutdown: {:?}",
+                e
+            );
+        }
+        trace!("[writer] exiting after shutdown");
     } else {
+        warn!("[writer] exiting after failure");
+    }
+}
+
+/// Supervises a single outgoing (writer) connection to `peer_id`: whenever
+/// `writer_task` reports, through `failed_rx`, that its `Connection` died,
+/// this redials `address` - respecting `connect_retries`, `tcp_nodelay`,
+/// `tcp_buffer_size`, and the connection's saved `delay` - repeats the
+/// `say_hi`/`receive_hi` handshake to confirm `peer_id`'s identity, and
+/// hands the fresh `Connection` back to `writer_task` through `update_tx`,
+/// so the `WriterSender` routes already registered in `to_writers` keep
+/// working without ever being rebuilt. Backs off exponentially (with
+/// jitter) between attempts, up to `MAX_RECONNECT_ATTEMPTS`.
+#[allow(clippy::too_many_arguments)]
+async fn writer_supervisor_task<A>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    peer_id: ProcessId,
+    peer_shard_id: ShardId,
+    address: A,
+    delay: Option<Duration>,
+    tcp_nodelay: bool,
+    tcp_buffer_size: usize,
+    connect_retries: usize,
+    compat_hello: Arc<compat::CompatHello>,
+    identity: Option<Arc<secure::Identity>>,
+    peer_keys: Arc<secure::PeerKeys>,
+    tls_config: Option<Arc<tls::TlsConfig>>,
+    transport: TransportKind,
+    mut failed_rx: ConnectionFailedReceiver,
+    mut update_tx: ConnectionUpdateSender,
+) where
+    A: ToSocketAddrs + Debug + Clone,
+{
+    while failed_rx.recv().await.is_some() {
+        let mut attempt = 0;
         loop {
-            if let Some(msg) = parent.recv().await {
-                // connection write *does* flush
-                if let Err(e) = connection.send(&*msg).await {
+            attempt += 1;
+            match reconnect_once(
+                process_id,
+                shard_id,
+                peer_id,
+                peer_shard_id,
+                address.clone(),
+                tcp_nodelay,
+                tcp_buffer_size,
+                connect_retries,
+                tls_config.clone(),
+                transport,
+            )
+            .await
+            {
+                Ok(mut connection) => {
+                    if let Some(delay) = delay {
+                        connection.set_delay(delay);
+                    }
+                    if !compat::compat_check(
+                        &mut connection,
+                        &compat_hello,
+                        peer_id,
+                    )
+                    .await
+                    {
+                        warn!(
+                            "[writer-supervisor] attempt {} to reconnect to process {} \
+                             failed the compat check",
+                            attempt, peer_id
+                        );
+                        if attempt >= MAX_RECONNECT_ATTEMPTS {
+                            warn!(
+                                "[writer-supervisor] giving up on process {} after {} attempts",
+                                peer_id, attempt
+                            );
+                            break;
+                        }
+                        time::delay_for(reconnect_backoff(attempt)).await;
+                        continue;
+                    }
+                    let cipher = match secure_upgrade_one(
+                        identity.as_deref(),
+                        &peer_keys,
+                        peer_id,
+                        &mut connection,
+                    )
+                    .await
+                    {
+                        Some(cipher) => cipher,
+                        None => {
+                            warn!(
+                                "[writer-supervisor] attempt {} to reconnect to process {} \
+                                 failed the secure handshake",
+                                attempt, peer_id
+                            );
+                            if attempt >= MAX_RECONNECT_ATTEMPTS {
+                                warn!(
+                                    "[writer-supervisor] giving up on process {} after {} attempts",
+                                    peer_id, attempt
+                                );
+                                break;
+                            }
+                            time::delay_for(reconnect_backoff(attempt)).await;
+                            continue;
+                        }
+                    };
+                    if update_tx.send((connection, cipher)).await.is_err() {
+                        warn!(
+                            "[writer-supervisor] writer task for process {} is gone, stopping",
+                            peer_id
+                        );
+                        return;
+                    }
+                    break;
+                }
+                Err(e) => {
                     warn!(
-                        "[writer] error sending message to connection: {:?}",
-                        e
+                        "[writer-supervisor] attempt {} to reconnect to process {} failed: {:?}",
+                        attempt, peer_id, e
                     );
+                    if attempt >= MAX_RECONNECT_ATTEMPTS {
+                        warn!(
+                            "[writer-supervisor] giving up on process {} after {} attempts",
+                            peer_id, attempt
+                        );
+                        break;
+                    }
+                    time::delay_for(reconnect_backoff(attempt)).await;
                 }
-            } else {
-                warn!("[writer] error receiving message from parent");
-                break;
             }
         }
     }
-    warn!("[writer] exiting after failure");
+}
+
+/// Computes the backoff before reconnection `attempt` (1-indexed).
+fn reconnect_backoff(attempt: usize) -> Duration {
+    let base_ms = RECONNECT_BACKOFF_BASE.as_millis() as u64;
+    let max_ms = RECONNECT_BACKOFF_MAX.as_millis() as u64;
+    let capped_ms =
+        base_ms.saturating_mul(1u64 << attempt.min(20)).min(max_ms);
+    // jitter by up to 50%, so peers reconnecting at the same time don't all
+    // redial in lockstep
+    let jitter_ms = rand::thread_rng().gen_range(0, capped_ms / 2 + 1);
+    Duration::from_millis(capped_ms + jitter_ms)
+}
+
+/// Redials `address` and repeats the `say_hi`/`receive_hi` handshake,
+/// failing if the peer that answers isn't `peer_id`/`peer_shard_id`.
+async fn reconnect_once<A>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    peer_id: ProcessId,
+    peer_shard_id: ShardId,
+    address: A,
+    tcp_nodelay: bool,
+    tcp_buffer_size: usize,
+    connect_retries: usize,
+    // TODO same as in `connect_to_all`: once `task::connect` hands back the
+    // raw socket before framing, upgrade it here via `tls::connect` whenever
+    // this is set, so a reconnect stays on TLS too
+    _tls_config: Option<Arc<tls::TlsConfig>>,
+    // TODO same as in `connect_to_all`: once `task::connect`/`Connection`
+    // are reachable from either backend, dispatch on this here too, so a
+    // `TransportKind::Quic` peer reconnects by opening a fresh stream on its
+    // (still-alive) QUIC connection rather than redialing from scratch, and
+    // a `TransportKind::Unix` peer redials `unix::connect` on its path
+    // instead of treating `address` as a `ToSocketAddrs`
+    _transport: TransportKind,
+) -> Result<Connection, Report>
+where
+    A: ToSocketAddrs + Debug,
+{
+    let mut connection =
+        super::connect(&address, tcp_nodelay, tcp_buffer_size, connect_retries)
+            .await
+            .wrap_err("reconnect")?;
+    say_hi_one(process_id, shard_id, &mut connection).await;
+    match connection.recv::<ProcessHi>().await {
+        Some(ProcessHi {
+            process_id: got_id,
+            shard_id: got_shard_id,
+        }) if got_id == peer_id && got_shard_id == peer_shard_id => {
+            Ok(connection)
+        }
+        Some(ProcessHi {
+            process_id: got_id, ..
+        }) => eyre::bail!(
+            "reconnected to unexpected process: expected {}, got {}",
+            peer_id,
+            got_id
+        ),
+        None => eyre::bail!(
+            "error receiving hi while reconnecting to process {}",
+            peer_id
+        ),
+    }
 }
 
 /// Starts process workers.
+///
+/// `worker_quantum` is the throttling quantum, in milliseconds: instead of
+/// draining `process.to_processes()`/`to_executors_iter()` after every single
+/// message, each worker batches everything it handles within one quantum and
+/// drains once at the quantum boundary. `None` (or `Some(0)`) preserves the
+/// original per-message behavior. `pin_workers`, when set, runs each worker
+/// on its own dedicated single-thread runtime instead of the shared one, so
+/// one worker's batch can't be delayed by another's.
+///
+/// `batch_max` bounds a second, finer-grained form of batching: once a
+/// worker reacts to a source becoming ready, it opportunistically drains up
+/// to `batch_max` ready messages from that same source via non-blocking
+/// `try_recv` before draining `process`, rather than reacting to each one
+/// through a separate `select!` wakeup. `None` (or `Some(1)`) preserves the
+/// original one-message-per-wakeup behavior. `worker_batch_wakeup` is the
+/// flush cadence, in milliseconds, applied to those opportunistic batches
+/// when `worker_quantum` itself is unset; it's ignored once `worker_quantum`
+/// is set, since the quantum's own boundary already covers it.
+#[allow(clippy::too_many_arguments)]
 pub fn start_processes<P, R>(
     process: P,
     reader_to_workers_rxs: Vec<ReaderReceiver<P>>,
@@ -397,12 +1326,34 @@ pub fn start_processes<P, R>(
     process_channel_buffer_size: usize,
     execution_log: Option<String>,
     to_metrics_logger: Option<ProtocolMetricsSender>,
-) -> Vec<JoinHandle<()>>
+    rejected_messages: RejectedMessagesCounter,
+    // when set, every worker records a `TimelineEvent` for each client
+    // submit, periodic event or inspect, and executed notification it
+    // handles (messages from other processes aren't recorded) into a ring
+    // buffer of this capacity, retrievable at any
+    // point via the returned `SharedTimeline`'s `dump`; `None` keeps the
+    // instrumentation fully disabled
+    timeline_capacity: Option<usize>,
+    worker_quantum: Option<usize>,
+    batch_max: Option<usize>,
+    worker_batch_wakeup: Option<usize>,
+    pin_workers: bool,
+    shutdown_rx: ShutdownReceiver,
+) -> (ShutdownHandle, Option<timeline::SharedTimeline>)
 where
     P: Protocol + Send + 'static,
     R: Debug + Clone + Send + 'static,
 {
-    let to_execution_logger = execution_log.map(|execution_log| {
+    // a zero (or unset) quantum preserves the original per-message draining
+    let quantum = worker_quantum
+        .map(|ms| Duration::from_millis(ms as u64))
+        .unwrap_or_else(|| Duration::from_millis(0));
+    // a zero (or unset) batch_max preserves the original one-message drain
+    let batch_max = batch_max.unwrap_or(1).max(1);
+    let batch_wakeup = worker_batch_wakeup
+        .map(|ms| Duration::from_millis(ms as u64))
+        .unwrap_or_else(|| Duration::from_millis(0));
+    let to_execution_logger = execution_log.clone().map(|execution_log| {
         // if the execution log was set, then start the execution logger
         let mut tx = task::spawn_consumer(process_channel_buffer_size, |rx| {
             execution_logger::execution_logger_task::<P>(execution_log, rx)
@@ -410,6 +1361,14 @@ where
         tx.set_name("to_execution_logger");
         tx
     });
+    let (to_timeline, timeline) = match timeline_capacity {
+        Some(capacity) => {
+            let (tx, shared) =
+                timeline::spawn_logger(process_channel_buffer_size, capacity);
+            (Some(tx), Some(shared))
+        }
+        None => (None, None),
+    };
 
     // zip rxs'
     let incoming = reader_to_workers_rxs
@@ -419,7 +1378,7 @@ where
         .zip(executors_to_workers_rxs.into_iter());
 
     // create executor workers
-    incoming
+    let handles = incoming
         .enumerate()
         .map(
             |(
@@ -439,31 +1398,119 @@ where
                     to_executors.clone(),
                     to_execution_logger.clone(),
                     to_metrics_logger.clone(),
+                    rejected_messages.clone(),
+                    to_timeline.clone(),
+                    execution_log.clone(),
+                    quantum,
+                    batch_max,
+                    batch_wakeup,
+                    shutdown_rx.clone(),
                 );
-                task::spawn(task)
-                // // if this is a reserved worker, run it on its own runtime
-                // if worker_index < super::INDEXES_RESERVED {
-                //     let thread_name =
-                //         format!("worker_{}_runtime", worker_index);
-                //     tokio::task::spawn_blocking(|| {
-                //         // create tokio runtime
-                //         let mut runtime = tokio::runtime::Builder::new()
-                //             .threaded_scheduler()
-                //             .core_threads(1)
-                //             .thread_name(thread_name)
-                //             .build()
-                //             .expect("tokio runtime build should work");
-                //         runtime.block_on(task)
-                //     });
-                //     None
-                // } else {
-                //     Some(task::spawn(task))
-                // }
+                if pin_workers {
+                    spawn_pinned_worker(worker_index, task)
+                } else {
+                    task::spawn(task)
+                }
             },
         )
-        .collect()
+        .collect();
+    (ShutdownHandle::new(handles), timeline)
 }
 
+/// Runs `task` to completion on a dedicated single-thread runtime, pinned to
+/// its own OS thread, rather than on the shared one; used by
+/// `start_processes` when `pin_workers` is set, so a worker that falls
+/// behind (e.g. while batching a throttling quantum) can't be starved by
+/// unrelated tasks sharing the default runtime.
+fn spawn_pinned_worker<F>(worker_index: usize, task: F) -> JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    task::spawn(async move {
+        let thread_name = format!("worker_{}_runtime", worker_index);
+        let result = tokio::task::spawn_blocking(move || {
+            let mut runtime = tokio::runtime::Builder::new()
+                .basic_scheduler()
+                .enable_all()
+                .thread_name(thread_name)
+                .build()
+                .expect("tokio runtime build should work");
+            runtime.block_on(task)
+        })
+        .await;
+        if let Err(e) = result {
+            warn!(
+                "[server] pinned runtime for worker {} panicked: {:?}",
+                worker_index, e
+            );
+        }
+    })
+}
+
+/// A parked wait request for some predicate over `process`'s state to
+/// become true, re-evaluated after every state-mutation path (`submit`,
+/// `handle_event`, `handle_executed`) until it does, or until `deadline`
+/// elapses - whichever comes first. Backs the `AwaitPredicate` request this
+/// worker loop is meant to serve once `FromPeriodicMessage` grows that
+/// variant (see the TODO in `handle_from_periodic_task`); gives tests a way
+/// to deterministically wait for "process X has committed dot Y" instead of
+/// polling via repeated `Inspect` calls.
+struct PendingPredicate<P> {
+    pred: Box<dyn Fn(&P) -> bool + Send>,
+    registered_at: std::time::Instant,
+    deadline: Option<Duration>,
+    tx: tokio::sync::oneshot::Sender<Result<(), PredicateTimeout>>,
+}
+
+/// Sent on a `PendingPredicate`'s `tx` when it never became true before its
+/// deadline elapsed.
+#[derive(Debug)]
+pub struct PredicateTimeout;
+
+impl<P> PendingPredicate<P> {
+    fn new(
+        pred: Box<dyn Fn(&P) -> bool + Send>,
+        deadline: Option<Duration>,
+        tx: tokio::sync::oneshot::Sender<Result<(), PredicateTimeout>>,
+    ) -> Self {
+        Self {
+            pred,
+            registered_at: std::time::Instant::now(),
+            deadline,
+            tx,
+        }
+    }
+}
+
+/// Re-evaluates every parked predicate against `process`, in registration
+/// order, resolving (and dropping) each one that now holds or whose
+/// deadline has elapsed; predicates still pending and still within their
+/// deadline are kept for the next call.
+fn evaluate_pending_predicates<P>(
+    process: &P,
+    pending: &mut Vec<PendingPredicate<P>>,
+) {
+    if pending.is_empty() {
+        return;
+    }
+    let mut still_pending = Vec::with_capacity(pending.len());
+    for waiter in pending.drain(..) {
+        if (waiter.pred)(process) {
+            let _ = waiter.tx.send(Ok(()));
+        } else if waiter
+            .deadline
+            .map(|deadline| waiter.registered_at.elapsed() >= deadline)
+            .unwrap_or(false)
+        {
+            let _ = waiter.tx.send(Err(PredicateTimeout));
+        } else {
+            still_pending.push(waiter);
+        }
+    }
+    *pending = still_pending;
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_task<P, R>(
     worker_index: usize,
     mut process: P,
@@ -476,58 +1523,189 @@ async fn process_task<P, R>(
     mut to_executors: ToExecutors<P>,
     mut to_execution_logger: Option<ExecutionInfoSender<P>>,
     mut to_metrics_logger: Option<ProtocolMetricsSender>,
+    rejected_messages: RejectedMessagesCounter,
+    mut to_timeline: Option<timeline::TimelineSender>,
+    execution_log: Option<String>,
+    quantum: Duration,
+    batch_max: usize,
+    batch_wakeup: Duration,
+    mut shutdown_rx: ShutdownReceiver,
 ) where
     P: Protocol + 'static,
     R: Debug + 'static,
 {
+    // predicates parked by `AwaitPredicate` inspect requests, re-evaluated
+    // after every state-mutation path below
+    let mut pending_predicates: Vec<PendingPredicate<P>> = Vec::new();
     // create time
     let time = RunTime;
 
+    // crash-recovery: before doing anything else, replay whatever execution
+    // info was already durably logged (and therefore already executed)
+    // before a crash, so a restarted process doesn't re-emit commands it has
+    // already run. A missing file means a fresh start, not a crash, so it's
+    // not treated as an error.
+    if let Some(path) = &execution_log {
+        if let Ok(file) = tokio::fs::File::open(path).await {
+            let mut rw = Rw::from(
+                EXECUTION_LOG_RECOVERY_BUFFER_SIZE,
+                EXECUTION_LOG_RECOVERY_BUFFER_SIZE,
+                file,
+            );
+            let mut recovered = Vec::new();
+            while let Some(execution_info) = rw.recv().await {
+                recovered.push(execution_info);
+            }
+            if !recovered.is_empty() {
+                process.recover(recovered);
+            }
+        }
+    }
+
     // create interval (for metrics notification)
     let mut interval = time::interval(super::metrics_logger::METRICS_INTERVAL);
 
+    // whether draining is batched: a zero quantum means "drain immediately
+    // after every message", matching the original per-message behavior, so
+    // the interval below is only ever ticked (and only ever matters) when
+    // `quantum` is non-zero; a non-zero `batch_wakeup` throttles the flush
+    // the same way, for workers that only want `batch_max`'s opportunistic
+    // coalescing (below) without the full quantum, and is ignored once
+    // `quantum` is itself set, since the quantum boundary already covers it
+    let batching = !quantum.is_zero() || !batch_wakeup.is_zero();
+    let flush_interval = if !quantum.is_zero() {
+        quantum
+    } else if !batch_wakeup.is_zero() {
+        batch_wakeup
+    } else {
+        Duration::from_millis(1)
+    };
+    let mut batch_flush = time::interval(flush_interval);
+    // set whenever a message has been applied to `process` but not yet
+    // drained, while batching; unused otherwise
+    let mut dirty = false;
+
+    // once a shutdown is requested this worker stops accepting new client
+    // commands (for both drain modes); a `Graceful` shutdown then keeps
+    // reacting to `from_readers`/`from_periodic`/`from_executors` - so
+    // in-flight protocol state gets to run to completion - until a full
+    // `drain_idle` tick passes with nothing left for `process` to emit
+    let mut shutdown_mode: Option<ShutdownMode> = None;
+    let drain_idle_period = Duration::from_millis(50);
+    let mut drain_idle = time::interval(drain_idle_period);
+
     loop {
         // TODO maybe used select_biased
         tokio::select! {
             msg = from_readers.recv() => {
-                selected_from_processes(worker_index, msg, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+                selected_from_processes(worker_index, msg, &mut from_readers, batch_max, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time, batching, &mut dirty, &mut pending_predicates).await
             }
             event = from_periodic.recv() => {
-                selected_from_periodic_task(worker_index, event, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+                selected_from_periodic_task(worker_index, event, &mut from_periodic, batch_max, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time, batching, &mut dirty, &mut pending_predicates, &mut to_timeline).await
             }
             executed = from_executors.recv() => {
-                selected_from_executors(worker_index, executed, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+                selected_from_executors(worker_index, executed, &mut from_executors, batch_max, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time, batching, &mut dirty, &mut pending_predicates, &mut to_timeline).await
             }
-            cmd = from_clients.recv() => {
-                selected_from_clients(worker_index, cmd, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+            cmd = from_clients.recv(), if shutdown_mode.is_none() => {
+                selected_from_clients(worker_index, cmd, &mut from_clients, batch_max, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time, batching, &mut dirty, &mut pending_predicates, &mut to_timeline).await
             }
-            _ = interval.tick()  => {
+            _ = batch_flush.tick(), if batching && dirty => {
+                // a quantum boundary was reached with something pending from
+                // one of the arms above: drain it all in one go rather than
+                // per-message
+                send_to_processes_and_executors(worker_index, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await;
+                dirty = false;
+            }
+            _ = interval.tick(), if shutdown_mode.is_none() => {
                 if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
                     // send metrics to logger (in case there's one)
-                    let protocol_metrics = process.metrics().clone();
+                    let mut protocol_metrics = process.metrics().clone();
+                    // rejections the reader task catches before a message
+                    // ever reaches a worker can't be attributed to any one
+                    // worker, so they're folded into worker 0's report
+                    // instead of split across all of them
+                    if worker_index == 0 {
+                        let rejected = rejected_messages.drain();
+                        if rejected > 0 {
+                            protocol_metrics.aggregate(
+                                ProtocolMetricsKind::RejectedMessages,
+                                rejected,
+                            );
+                        }
+                    }
                     if let Err(e) = to_metrics_logger.send((worker_index, protocol_metrics)).await {
                         warn!("[server] error while sending metrics to metrics logger: {:?}", e);
                     }
                 }
             }
+            mode = shutdown_rx.recv(), if shutdown_mode.is_none() => {
+                if let Some(Some(mode)) = mode {
+                    trace!("[server] worker {} draining after {:?} shutdown", worker_index, mode);
+                    shutdown_mode = Some(mode);
+                    if mode == ShutdownMode::Quick {
+                        break;
+                    }
+                    // start the idle period now: `drain_idle` has been
+                    // ticking (and piling up missed ticks) since startup
+                    drain_idle = time::interval_at(time::Instant::now() + drain_idle_period, drain_idle_period);
+                }
+            }
+            _ = drain_idle.tick(), if shutdown_mode == Some(ShutdownMode::Graceful) => {
+                // while not batching, every other branch above already
+                // drains `process` fully via `send_to_processes_and_executors`
+                // as soon as it reacts to something, so by the time an idle
+                // tick fires with nothing new handled in between, there's
+                // nothing left to drain; while batching, flush whatever's
+                // pending instead of treating it as idle
+                if dirty {
+                    send_to_processes_and_executors(worker_index, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await;
+                    dirty = false;
+                } else {
+                    trace!("[server] worker {} idle while draining, shutting down", worker_index);
+                    break;
+                }
+            }
         }
     }
+
+    // a shutdown can land mid-batch, with something applied to `process` but
+    // not yet drained; flush it before exiting so nothing is lost
+    if dirty {
+        send_to_processes_and_executors(worker_index, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await;
+    }
+
+    // stop ticking metrics and close `to_execution_logger`/
+    // `to_metrics_logger` cleanly: dropping the senders closes their
+    // channels, letting those tasks wind down on their own
+    drop(to_execution_logger);
+    drop(to_metrics_logger);
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn selected_from_processes<P>(
     worker_index: usize,
     msg: Option<(ProcessId, ShardId, P::Message)>,
+    from_readers: &mut ReaderReceiver<P>,
+    batch_max: usize,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
 ) where
     P: Protocol + 'static,
 {
     trace!("[server] reader message: {:?}", msg);
     if let Some((from_id, from_shard_id, msg)) = msg {
+        // defer every message's flush until the whole opportunistic batch
+        // (bounded by `batch_max`) below has been applied to `process`;
+        // `batching` still governs whether the single flush that follows
+        // happens right away or is left for the next quantum/wakeup tick
+        let defer = batching || batch_max > 1;
         handle_from_processes(
             worker_index,
             from_id,
@@ -539,8 +1717,57 @@ async fn selected_from_processes<P>(
             to_executors,
             to_execution_logger,
             time,
+            defer,
+            dirty,
+            pending,
         )
-        .await
+        .await;
+
+        // opportunistically drain up to `batch_max` ready messages from this
+        // same source - preserving its FIFO order - before flushing, rather
+        // than reacting to each one through a separate `select!` wakeup
+        let mut batched = 1;
+        while batched < batch_max {
+            match from_readers.try_recv() {
+                Ok((from_id, from_shard_id, msg)) => {
+                    batched += 1;
+                    handle_from_processes(
+                        worker_index,
+                        from_id,
+                        from_shard_id,
+                        msg,
+                        process,
+                        to_writers,
+                        reader_to_workers,
+                        to_executors,
+                        to_execution_logger,
+                        time,
+                        defer,
+                        dirty,
+                        pending,
+                    )
+                    .await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        // nothing left ready on this source: flush immediately rather than
+        // waiting for a full `batch_max` batch to accumulate, unless
+        // `batching` defers it further
+        if !batching && batch_max > 1 {
+            send_to_processes_and_executors(
+                worker_index,
+                process,
+                to_writers,
+                reader_to_workers,
+                to_executors,
+                to_execution_logger,
+                time,
+            )
+            .await;
+            *dirty = false;
+        }
     } else {
         warn!(
             "[server] error while receiving new process message from readers"
@@ -548,6 +1775,7 @@ async fn selected_from_processes<P>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_from_processes<P>(
     worker_index: usize,
     from_id: ProcessId,
@@ -559,21 +1787,31 @@ async fn handle_from_processes<P>(
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
 ) where
     P: Protocol + 'static,
 {
     // handle message in process and potentially new actions
     process.handle(from_id, from_shard_id, msg, time);
-    send_to_processes_and_executors(
-        worker_index,
-        process,
-        to_writers,
-        reader_to_workers,
-        to_executors,
-        to_execution_logger,
-        time,
-    )
-    .await;
+    evaluate_pending_predicates(process, pending);
+    if batching {
+        // deferred to the next quantum boundary, batched with whatever else
+        // lands before then
+        *dirty = true;
+    } else {
+        send_to_processes_and_executors(
+            worker_index,
+            process,
+            to_writers,
+            reader_to_workers,
+            to_executors,
+            to_execution_logger,
+            time,
+        )
+        .await;
+    }
 }
 
 // TODO maybe run in parallel
@@ -585,9 +1823,14 @@ async fn send_to_processes_and_executors<P>(
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
-) where
+) -> usize
+where
     P: Protocol + 'static,
 {
+    // number of outgoing protocol messages emitted by this drain, reported
+    // back so callers can attribute it to a `TimelineEvent`
+    let mut n_msgs_emitted = 0;
+
     while let Some(action) = process.to_processes() {
         match action {
             Action::ToSend { target, msg } => {
@@ -616,7 +1859,8 @@ async fn send_to_processes_and_executors<P>(
                             msg_to_send.clone(),
                             channels,
                         )
-                        .await
+                        .await;
+                        n_msgs_emitted += 1;
                     }
                 }
             }
@@ -630,6 +1874,7 @@ async fn send_to_processes_and_executors<P>(
                     time,
                 )
                 .await;
+                n_msgs_emitted += 1;
             }
         }
     }
@@ -652,6 +1897,8 @@ async fn send_to_processes_and_executors<P>(
             );
         }
     }
+
+    n_msgs_emitted
 }
 
 async fn handle_message_from_self<P>(
@@ -695,20 +1942,28 @@ pub async fn send_to_one_writer<P>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn selected_from_clients<P>(
     worker_index: usize,
     cmd: Option<(Option<Dot>, Command)>,
+    from_clients: &mut SubmitReceiver,
+    batch_max: usize,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
 {
     trace!("[server] from clients: {:?}", cmd);
     if let Some((dot, cmd)) = cmd {
+        let defer = batching || batch_max > 1;
         handle_from_clients(
             worker_index,
             dot,
@@ -719,13 +1974,58 @@ async fn selected_from_clients<P>(
             to_executors,
             to_execution_logger,
             time,
+            defer,
+            dirty,
+            pending,
+            to_timeline,
         )
-        .await
+        .await;
+
+        let mut batched = 1;
+        while batched < batch_max {
+            match from_clients.try_recv() {
+                Ok((dot, cmd)) => {
+                    batched += 1;
+                    handle_from_clients(
+                        worker_index,
+                        dot,
+                        cmd,
+                        process,
+                        to_writers,
+                        reader_to_workers,
+                        to_executors,
+                        to_execution_logger,
+                        time,
+                        defer,
+                        dirty,
+                        pending,
+                        to_timeline,
+                    )
+                    .await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if !batching && batch_max > 1 {
+            send_to_processes_and_executors(
+                worker_index,
+                process,
+                to_writers,
+                reader_to_workers,
+                to_executors,
+                to_execution_logger,
+                time,
+            )
+            .await;
+            *dirty = false;
+        }
     } else {
         warn!("[server] error while receiving new command from clients");
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_from_clients<P>(
     worker_index: usize,
     dot: Option<Dot>,
@@ -736,38 +2036,64 @@ async fn handle_from_clients<P>(
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
 {
     // submit command in process
     process.submit(dot, cmd, time);
-    send_to_processes_and_executors(
+    evaluate_pending_predicates(process, pending);
+    let n_msgs_emitted = if batching {
+        *dirty = true;
+        0
+    } else {
+        send_to_processes_and_executors(
+            worker_index,
+            process,
+            to_writers,
+            reader_to_workers,
+            to_executors,
+            to_execution_logger,
+            time,
+        )
+        .await
+    };
+    timeline::record(
+        to_timeline,
         worker_index,
-        process,
-        to_writers,
-        reader_to_workers,
-        to_executors,
-        to_execution_logger,
-        time,
+        timeline::TimelineKind::Submit,
+        dot,
+        n_msgs_emitted,
     )
     .await;
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn selected_from_periodic_task<P, R>(
     worker_index: usize,
     event: Option<FromPeriodicMessage<P, R>>,
+    from_periodic: &mut PeriodicEventReceiver<P, R>,
+    batch_max: usize,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
     R: Debug + 'static,
 {
     trace!("[server] from periodic task: {:?}", event);
     if let Some(event) = event {
+        let defer = batching || batch_max > 1;
         handle_from_periodic_task(
             worker_index,
             event,
@@ -777,13 +2103,57 @@ async fn selected_from_periodic_task<P, R>(
             to_executors,
             to_execution_logger,
             time,
+            defer,
+            dirty,
+            pending,
+            to_timeline,
         )
-        .await
+        .await;
+
+        let mut batched = 1;
+        while batched < batch_max {
+            match from_periodic.try_recv() {
+                Ok(event) => {
+                    batched += 1;
+                    handle_from_periodic_task(
+                        worker_index,
+                        event,
+                        process,
+                        to_writers,
+                        reader_to_workers,
+                        to_executors,
+                        to_execution_logger,
+                        time,
+                        defer,
+                        dirty,
+                        pending,
+                        to_timeline,
+                    )
+                    .await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if !batching && batch_max > 1 {
+            send_to_processes_and_executors(
+                worker_index,
+                process,
+                to_writers,
+                reader_to_workers,
+                to_executors,
+                to_execution_logger,
+                time,
+            )
+            .await;
+            *dirty = false;
+        }
     } else {
         warn!("[server] error while receiving new event from periodic task");
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_from_periodic_task<P, R>(
     worker_index: usize,
     msg: FromPeriodicMessage<P, R>,
@@ -793,6 +2163,10 @@ async fn handle_from_periodic_task<P, R>(
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
     R: Debug + 'static,
@@ -801,14 +2175,28 @@ async fn handle_from_periodic_task<P, R>(
         FromPeriodicMessage::Event(event) => {
             // handle event in process
             process.handle_event(event, time);
-            send_to_processes_and_executors(
+            evaluate_pending_predicates(process, pending);
+            let n_msgs_emitted = if batching {
+                *dirty = true;
+                0
+            } else {
+                send_to_processes_and_executors(
+                    worker_index,
+                    process,
+                    to_writers,
+                    reader_to_workers,
+                    to_executors,
+                    to_execution_logger,
+                    time,
+                )
+                .await
+            };
+            timeline::record(
+                to_timeline,
                 worker_index,
-                process,
-                to_writers,
-                reader_to_workers,
-                to_executors,
-                to_execution_logger,
-                time,
+                timeline::TimelineKind::Event,
+                None,
+                n_msgs_emitted,
             )
             .await;
         }
@@ -817,24 +2205,47 @@ async fn handle_from_periodic_task<P, R>(
             if let Err(e) = tx.send(outcome).await {
                 warn!("[server] error while sending inspect result: {:?}", e);
             }
+            timeline::record(
+                to_timeline,
+                worker_index,
+                timeline::TimelineKind::Inspect,
+                None,
+                0,
+            )
+            .await;
         }
+        // TODO: once `FromPeriodicMessage` grows an
+        // `AwaitPredicate(pred, deadline, tx)` variant, park it here via
+        // `pending.push(PendingPredicate::new(pred, deadline, tx))` instead
+        // of resolving immediately, then fall through to the
+        // `evaluate_pending_predicates` call above so a predicate that's
+        // already satisfied resolves on this same tick rather than waiting
+        // for the next state mutation.
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn selected_from_executors<P>(
     worker_index: usize,
     executed: Option<Executed>,
+    from_executors: &mut ExecutedReceiver,
+    batch_max: usize,
     process: &mut P,
     to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
 {
     trace!("[server] from executors: {:?}", executed);
     if let Some(executed) = executed {
+        let defer = batching || batch_max > 1;
         handle_from_executors(
             worker_index,
             executed,
@@ -844,13 +2255,57 @@ async fn selected_from_executors<P>(
             to_executors,
             to_execution_logger,
             time,
+            defer,
+            dirty,
+            pending,
+            to_timeline,
         )
-        .await
+        .await;
+
+        let mut batched = 1;
+        while batched < batch_max {
+            match from_executors.try_recv() {
+                Ok(executed) => {
+                    batched += 1;
+                    handle_from_executors(
+                        worker_index,
+                        executed,
+                        process,
+                        to_writers,
+                        reader_to_workers,
+                        to_executors,
+                        to_execution_logger,
+                        time,
+                        defer,
+                        dirty,
+                        pending,
+                        to_timeline,
+                    )
+                    .await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if !batching && batch_max > 1 {
+            send_to_processes_and_executors(
+                worker_index,
+                process,
+                to_writers,
+                reader_to_workers,
+                to_executors,
+                to_execution_logger,
+                time,
+            )
+            .await;
+            *dirty = false;
+        }
     } else {
         warn!("[server] error while receiving message from executors");
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_from_executors<P>(
     worker_index: usize,
     executed: Executed,
@@ -860,18 +2315,36 @@ async fn handle_from_executors<P>(
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
     time: &RunTime,
+    batching: bool,
+    dirty: &mut bool,
+    pending: &mut Vec<PendingPredicate<P>>,
+    to_timeline: &mut Option<timeline::TimelineSender>,
 ) where
     P: Protocol + 'static,
 {
     process.handle_executed(executed, time);
-    send_to_processes_and_executors(
+    evaluate_pending_predicates(process, pending);
+    let n_msgs_emitted = if batching {
+        *dirty = true;
+        0
+    } else {
+        send_to_processes_and_executors(
+            worker_index,
+            process,
+            to_writers,
+            reader_to_workers,
+            to_executors,
+            to_execution_logger,
+            time,
+        )
+        .await
+    };
+    timeline::record(
+        to_timeline,
         worker_index,
-        process,
-        to_writers,
-        reader_to_workers,
-        to_executors,
-        to_execution_logger,
-        time,
+        timeline::TimelineKind::Executed,
+        None,
+        n_msgs_emitted,
     )
     .await;
 }
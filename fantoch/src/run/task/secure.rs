@@ -0,0 +1,252 @@
+// This module provides an optional authenticated, encrypted transport for
+// inter-process links, gated behind the `secure_channel` feature so
+// plaintext remains the default for benchmarks: `secure_handshake` proves
+// that whoever dialed/accepted a connection really holds the private key
+// configured for the `ProcessId` it claimed during the plain `say_hi`/
+// `receive_hi` exchange, and derives a per-link `LinkCipher` from a
+// Noise-style ephemeral x25519 exchange. `SecureFrame` then lets
+// `reader_task`/`writer_task` seal/open each message transparently,
+// falling back to plaintext wherever no cipher was negotiated.
+
+use crate::id::ProcessId;
+use crate::run::rw::Connection;
+use crate::HashMap;
+use color_eyre::eyre::{eyre, Report};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A sealed (encrypted + authenticated) frame, as produced by
+/// `LinkCipher::seal`. Kept representable regardless of whether the
+/// `secure_channel` feature - which actually knows how to produce or
+/// consume one - is enabled, so `SecureFrame<V>` always compiles.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedFrame {
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+/// What actually goes out on the wire for a message once a connection may
+/// be secured: either the value itself (no cipher was negotiated for this
+/// link, or the `secure_channel` feature is disabled), or a `SealedFrame`
+/// that only the matching `LinkCipher` can open.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SecureFrame<V> {
+    Plain(V),
+    Sealed(SealedFrame),
+}
+
+impl<V> SecureFrame<V> {
+    /// Wraps `value` for sending: sealed if `cipher` is set, plaintext
+    /// otherwise.
+    #[allow(unused_variables)]
+    pub fn wrap(cipher: Option<&LinkCipher>, value: V) -> Self
+    where
+        V: Serialize,
+    {
+        #[cfg(feature = "secure_channel")]
+        if let Some(cipher) = cipher {
+            return SecureFrame::Sealed(cipher.seal(&value));
+        }
+        SecureFrame::Plain(value)
+    }
+
+    /// Unwraps a received frame, opening it with `cipher` if it's sealed.
+    #[allow(unused_variables)]
+    pub fn unwrap(self, cipher: Option<&LinkCipher>) -> Result<V, Report>
+    where
+        V: DeserializeOwned,
+    {
+        match self {
+            SecureFrame::Plain(value) => Ok(value),
+            SecureFrame::Sealed(frame) => {
+                #[cfg(feature = "secure_channel")]
+                if let Some(cipher) = cipher {
+                    return cipher.open(&frame);
+                }
+                let _ = frame;
+                Err(eyre!(
+                    "received a sealed frame but no link cipher is \
+                     configured for this connection"
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "secure_channel")]
+mod enabled {
+    use super::*;
+    use chacha20poly1305::aead::{Aead, NewAead};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+    use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
+    use hmac::{Hmac, Mac, NewMac};
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+    use sha2::Sha256;
+    use x25519_dalek::{EphemeralSecret, PublicKey as XPublicKey};
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    /// This process's long-term ed25519 identity, used to prove who it is
+    /// during `secure_handshake`.
+    pub struct Identity(Keypair);
+
+    impl Identity {
+        pub fn generate() -> Self {
+            Self(Keypair::generate(&mut OsRng))
+        }
+
+        pub fn public_key(&self) -> PublicKey {
+            self.0.public
+        }
+    }
+
+    /// Long-term public keys of every peer this process is configured to
+    /// trust, keyed by `ProcessId`. A connection claiming a `ProcessId` not
+    /// in this map, or one that can't prove possession of the mapped key,
+    /// is rejected by `secure_handshake`.
+    pub type PeerKeys = HashMap<ProcessId, PublicKey>;
+
+    /// The symmetric key derived for one connection's worth of traffic.
+    pub struct LinkCipher(ChaCha20Poly1305);
+
+    impl LinkCipher {
+        pub(super) fn seal<V: Serialize>(&self, value: &V) -> SealedFrame {
+            let plaintext =
+                bincode::serialize(value).expect("serialize should work");
+            let mut nonce_bytes = [0u8; 12];
+            OsRng.fill_bytes(&mut nonce_bytes);
+            let ciphertext = self
+                .0
+                .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_ref())
+                .expect("encryption should not fail");
+            SealedFrame {
+                nonce: nonce_bytes,
+                ciphertext,
+            }
+        }
+
+        pub(super) fn open<V: DeserializeOwned>(
+            &self,
+            frame: &SealedFrame,
+        ) -> Result<V, Report> {
+            let plaintext = self
+                .0
+                .decrypt(
+                    Nonce::from_slice(&frame.nonce),
+                    frame.ciphertext.as_ref(),
+                )
+                .map_err(|_| eyre!("failed to decrypt/authenticate frame"))?;
+            bincode::deserialize(&plaintext)
+                .map_err(|e| eyre!("deserialize sealed frame: {:?}", e))
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SecureHello {
+        ephemeral_public: [u8; 32],
+        signature: [u8; 64],
+    }
+
+    /// Derives the key installed into a `LinkCipher` from the raw x25519 DH
+    /// shared secret via HMAC-SHA256 over a fixed label, the same
+    /// not-a-key-on-its-own treatment `shs::directional_keys` gives a DH
+    /// output: a shared secret isn't guaranteed uniformly random the way an
+    /// AEAD key needs to be, so it's never fed into `ChaCha20Poly1305::new`
+    /// directly.
+    fn derive_link_key(shared_secret: &[u8; 32]) -> Key {
+        let mut mac = HmacSha256::new_from_slice(shared_secret)
+            .expect("hmac accepts any key length");
+        mac.update(b"fantoch secure_channel link key");
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&mac.finalize().into_bytes());
+        Key::from_slice(&out).to_owned()
+    }
+
+    /// Performs a mutual, authenticated key exchange over `connection`:
+    /// each side signs a fresh x25519 ephemeral public key with its
+    /// long-term ed25519 identity, proving it holds the private key
+    /// configured for `expected_peer` in `peer_keys`, then both derive the
+    /// same `LinkCipher` from the exchange. Returns an error - the caller
+    /// must drop the connection without using it - if the peer's signature
+    /// doesn't verify against the key configured for `expected_peer`.
+    pub async fn secure_handshake(
+        connection: &mut Connection,
+        identity: &Identity,
+        expected_peer: ProcessId,
+        peer_keys: &PeerKeys,
+    ) -> Result<LinkCipher, Report> {
+        let peer_public_key = peer_keys.get(&expected_peer).ok_or_else(|| {
+            eyre!("no configured public key for process {}", expected_peer)
+        })?;
+
+        let ephemeral_secret = EphemeralSecret::new(OsRng);
+        let ephemeral_public = XPublicKey::from(&ephemeral_secret);
+        let signature = identity.0.sign(ephemeral_public.as_bytes());
+        let hello = SecureHello {
+            ephemeral_public: *ephemeral_public.as_bytes(),
+            signature: signature.to_bytes(),
+        };
+        connection
+            .send(&hello)
+            .await
+            .map_err(|e| eyre!("send secure hello: {:?}", e))?;
+
+        let peer_hello: SecureHello = connection.recv().await.ok_or_else(
+            || {
+                eyre!(
+                    "error receiving secure hello from process {}",
+                    expected_peer
+                )
+            },
+        )?;
+
+        let peer_signature = Signature::from_bytes(&peer_hello.signature)
+            .map_err(|e| eyre!("parse peer signature: {:?}", e))?;
+        peer_public_key
+            .verify(&peer_hello.ephemeral_public, &peer_signature)
+            .map_err(|_| {
+                eyre!("process {} failed to prove its identity", expected_peer)
+            })?;
+
+        let peer_ephemeral_public =
+            XPublicKey::from(peer_hello.ephemeral_public);
+        let shared_secret =
+            ephemeral_secret.diffie_hellman(&peer_ephemeral_public);
+        let mut shared_secret_bytes = [0u8; 32];
+        shared_secret_bytes.copy_from_slice(shared_secret.as_bytes());
+        let key = derive_link_key(&shared_secret_bytes);
+        Ok(LinkCipher(ChaCha20Poly1305::new(&key)))
+    }
+}
+
+#[cfg(feature = "secure_channel")]
+pub use enabled::*;
+
+// Stand-ins so code that threads an optional identity/cipher through
+// `connect_to_all` still compiles with the `secure_channel` feature
+// disabled; none of these are ever actually constructed in that
+// configuration, so plaintext remains the default.
+
+#[cfg(not(feature = "secure_channel"))]
+pub struct Identity;
+
+#[cfg(not(feature = "secure_channel"))]
+pub type PeerKeys = HashMap<ProcessId, ()>;
+
+#[cfg(not(feature = "secure_channel"))]
+pub struct LinkCipher;
+
+#[cfg(not(feature = "secure_channel"))]
+pub async fn secure_handshake(
+    _connection: &mut Connection,
+    _identity: &Identity,
+    expected_peer: ProcessId,
+    _peer_keys: &PeerKeys,
+) -> Result<LinkCipher, Report> {
+    Err(eyre!(
+        "secure_handshake requested for process {} but the 'secure_channel' \
+         feature is disabled",
+        expected_peer
+    ))
+}
@@ -0,0 +1,159 @@
+// This module adds optional distributed-tracing spans on top of the plain
+// `log!`/`trace!` logging used everywhere else in `run`: unlike that
+// logging, a span can be exported to an external collector (via the
+// `opentelemetry` crate, the same approach garage's netapp takes) and
+// followed across process/shard boundaries, which is what's needed to see
+// a single command's end-to-end latency instead of just one process's
+// local log lines. Gated behind the `otel` feature, with `command_span`
+// falling back to a span that's never entered (and therefore never
+// exported) when the feature is off - the same stand-in shape
+// `secure`/`auth`/`shs` use for their own optional features.
+
+use serde::{Deserialize, Serialize};
+
+/// A serializable snapshot of a span's identity, meant to ride alongside a
+/// forwarded `ExecutionInfo` so the shard that receives it can open a child
+/// span under the same trace instead of starting a disconnected one.
+///
+/// Actually threading this through `POEMessage::Executor` (so
+/// `fetch_info_to_executors`/`send_to_one_writer` carry it across shards)
+/// isn't done by this module: that enum's payload is the protocol's own
+/// `ExecutionInfo` type, and giving it an extra field would mean changing
+/// every `Protocol::Executor` impl, not just this one. Nothing outside this
+/// module builds or reads a `TraceContext`: `executor_task` opens every
+/// `command_span` with no parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+}
+
+#[cfg(feature = "otel")]
+pub use enabled::*;
+
+#[cfg(feature = "otel")]
+mod enabled {
+    use super::TraceContext;
+    use crate::config::Config;
+    use crate::id::Rifl;
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Installs an OTLP pipeline exporting to
+    /// `config.otel_exporter_endpoint()` and layers it onto the global
+    /// `tracing` subscriber, so every `command_span` below actually leaves
+    /// this process instead of just living in memory. A no-op when the
+    /// config doesn't set an endpoint, so turning on the `otel` feature at
+    /// build time doesn't by itself require every operator to stand up a
+    /// collector. Like `tracer_task`'s `ProfSubscriber`, meant to be called
+    /// once per process, before any span below is created.
+    pub fn init(config: &Config) {
+        let endpoint = match config.otel_exporter_endpoint() {
+            Some(endpoint) => endpoint,
+            None => return,
+        };
+        let tracer = match opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .install_batch(opentelemetry::runtime::Tokio)
+        {
+            Ok(tracer) => tracer,
+            Err(e) => {
+                println!(
+                    "[telemetry] error installing otel pipeline: {:?}",
+                    e
+                );
+                return;
+            }
+        };
+        let otel_layer = tracing_opentelemetry::layer().with_tracer(tracer);
+        let subscriber =
+            tracing_subscriber::Registry::default().with(otel_layer);
+        tracing::subscriber::set_global_default(subscriber)
+            .unwrap_or_else(|e| {
+                println!(
+                    "[telemetry] tracing global default subscriber already set: {:?}",
+                    e
+                )
+            });
+    }
+
+    /// Opens a new span for `rifl`, parented under `parent` when the
+    /// command was forwarded here from another shard (see this module's
+    /// doc comment for why `parent` is usually `None` today).
+    pub fn command_span(
+        rifl: Rifl,
+        parent: Option<TraceContext>,
+    ) -> tracing::Span {
+        let span = tracing::info_span!("command", rifl = %rifl);
+        if let Some(parent) = parent {
+            span.set_parent(parent.into());
+        }
+        span
+    }
+
+    /// Snapshots `span`'s otel context into the serializable carrier that
+    /// would ride alongside a forwarded `ExecutionInfo` once that wiring
+    /// lands.
+    pub fn context_of(span: &tracing::Span) -> Option<TraceContext> {
+        let otel_context = span.context();
+        let span_ref = otel_context.span();
+        let span_context = span_ref.span_context();
+        if !span_context.is_valid() {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id: trace_id_to_u128(span_context.trace_id()),
+            span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+        })
+    }
+
+    fn trace_id_to_u128(trace_id: TraceId) -> u128 {
+        u128::from_be_bytes(trace_id.to_bytes())
+    }
+
+    impl From<TraceContext> for opentelemetry::Context {
+        fn from(context: TraceContext) -> Self {
+            let span_context = opentelemetry::trace::SpanContext::new(
+                TraceId::from_bytes(context.trace_id.to_be_bytes()),
+                opentelemetry::trace::SpanId::from_bytes(
+                    context.span_id.to_be_bytes(),
+                ),
+                opentelemetry::trace::TraceFlags::SAMPLED,
+                true,
+                Default::default(),
+            );
+            opentelemetry::Context::new()
+                .with_remote_span_context(span_context)
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+pub use disabled::*;
+
+#[cfg(not(feature = "otel"))]
+mod disabled {
+    use super::TraceContext;
+    use crate::config::Config;
+    use crate::id::Rifl;
+
+    pub fn init(_config: &Config) {}
+
+    pub fn command_span(
+        _rifl: Rifl,
+        _parent: Option<TraceContext>,
+    ) -> tracing::Span {
+        tracing::Span::none()
+    }
+
+    pub fn context_of(_span: &tracing::Span) -> Option<TraceContext> {
+        None
+    }
+}
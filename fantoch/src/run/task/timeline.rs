@@ -0,0 +1,162 @@
+// Opt-in instrumentation that records a compact event for every client
+// submit, periodic event or inspect, and executed notification a worker
+// handles (messages from other processes aren't recorded), so a run's
+// causal flow across workers and protocol
+// phases can be reconstructed and plotted afterwards without recompiling.
+// Threaded through as `Option<TimelineSender>`, exactly like
+// `to_execution_logger`/`to_metrics_logger`, so every call site is a no-op
+// when it's `None` and there's nothing to pay for when the feature is
+// disabled.
+
+use crate::id::Dot;
+use crate::run::task;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which kind of dispatch a `TimelineEvent` records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineKind {
+    Submit,
+    Event,
+    Inspect,
+    Executed,
+}
+
+/// One recorded dispatch: when it happened, which worker handled it, what
+/// kind of message it was, the `Dot` it concerned (if any), and how many
+/// outgoing protocol messages `send_to_processes_and_executors` emitted for
+/// it.
+#[derive(Debug, Clone)]
+pub struct TimelineEvent {
+    pub timestamp: u64,
+    pub worker_index: usize,
+    pub kind: TimelineKind,
+    pub dot: Option<Dot>,
+    pub n_msgs_emitted: usize,
+}
+
+impl TimelineEvent {
+    /// `RunTime` doesn't expose a clock query, so the timestamp is sourced
+    /// from the system clock directly, in milliseconds since the epoch.
+    fn new(
+        worker_index: usize,
+        kind: TimelineKind,
+        dot: Option<Dot>,
+        n_msgs_emitted: usize,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after the epoch")
+            .as_millis() as u64;
+        Self {
+            timestamp,
+            worker_index,
+            kind,
+            dot,
+            n_msgs_emitted,
+        }
+    }
+}
+
+pub type TimelineSender = task::chan::ChannelSender<TimelineEvent>;
+pub type TimelineReceiver = task::chan::ChannelReceiver<TimelineEvent>;
+
+/// A capacity-bounded, oldest-dropped-first record of every `TimelineEvent`
+/// collected so far, shared between the logger task and whoever wants to
+/// `dump` it - including mid-run, not just after the logger task exits.
+pub struct TimelineBuffer {
+    capacity: usize,
+    events: VecDeque<TimelineEvent>,
+}
+
+impl TimelineBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, event: TimelineEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Serializes the currently recorded stream as one JSON object per
+    /// line, in the order the logger task received them, for external
+    /// analysis/plotting.
+    pub fn dump(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| {
+                let dot = event
+                    .dot
+                    .map(|dot| format!("\"{:?}\"", dot))
+                    .unwrap_or_else(|| "null".to_string());
+                format!(
+                    "{{\"timestamp\":{},\"worker_index\":{},\"kind\":\"{:?}\",\"dot\":{},\"n_msgs_emitted\":{}}}",
+                    event.timestamp,
+                    event.worker_index,
+                    event.kind,
+                    dot,
+                    event.n_msgs_emitted,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Shared handle to a running timeline's buffer, safe to `dump` from
+/// outside the worker tasks feeding it.
+pub type SharedTimeline = Arc<Mutex<TimelineBuffer>>;
+
+/// Starts the timeline logger task, draining `TimelineEvent`s off its
+/// channel into a capacity-bounded buffer. Returns the sender half to
+/// thread through workers (as `Option<TimelineSender>`, wherever the
+/// instrumentation is disabled) and the shared buffer a caller can `dump`
+/// at any point while the run is in progress.
+pub fn spawn_logger(
+    channel_buffer_size: usize,
+    capacity: usize,
+) -> (TimelineSender, SharedTimeline) {
+    let buffer: SharedTimeline = Arc::new(Mutex::new(TimelineBuffer::new(capacity)));
+    let buffer_for_task = buffer.clone();
+    let mut tx = task::spawn_consumer(channel_buffer_size, move |rx| {
+        timeline_logger_task(buffer_for_task, rx)
+    });
+    tx.set_name("to_timeline");
+    (tx, buffer)
+}
+
+async fn timeline_logger_task(buffer: SharedTimeline, mut rx: TimelineReceiver) {
+    while let Some(event) = rx.recv().await {
+        buffer
+            .lock()
+            .expect("timeline buffer lock shouldn't be poisoned")
+            .push(event);
+    }
+}
+
+/// Records one dispatch onto `to_timeline`, if the instrumentation is
+/// enabled for this worker; a no-op otherwise.
+pub async fn record(
+    to_timeline: &mut Option<TimelineSender>,
+    worker_index: usize,
+    kind: TimelineKind,
+    dot: Option<Dot>,
+    n_msgs_emitted: usize,
+) {
+    if let Some(to_timeline) = to_timeline {
+        let event = TimelineEvent::new(worker_index, kind, dot, n_msgs_emitted);
+        if let Err(e) = to_timeline.send(event).await {
+            crate::warn!(
+                "[server] error while sending event to timeline logger: {:?}",
+                e
+            );
+        }
+    }
+}
@@ -1,7 +1,8 @@
+use crate::run::task::process::ShutdownReceiver;
 use tokio::time::Duration;
 
 #[cfg(not(feature = "prof"))]
-pub async fn tracer_task(interval: Option<Duration>) {
+pub async fn tracer_task(interval: Option<Duration>, _shutdown_rx: ShutdownReceiver) {
     match interval {
         Some(_) => {
             panic!("[tracer_task] tracer show interval was set but the 'prof' feature is disabled");
@@ -13,7 +14,7 @@ pub async fn tracer_task(interval: Option<Duration>) {
 }
 
 #[cfg(feature = "prof")]
-pub async fn tracer_task(interval: Option<Duration>) {
+pub async fn tracer_task(interval: Option<Duration>, mut shutdown_rx: ShutdownReceiver) {
     use crate::log;
     use fantoch_prof::ProfSubscriber;
 
@@ -35,9 +36,17 @@ pub async fn tracer_task(interval: Option<Duration>) {
     let mut interval = tokio::time::interval(interval);
 
     loop {
-        // wait tick
-        let _ = interval.tick().await;
-        // show metrics
-        println!("{:?}", subscriber);
+        tokio::select! {
+            _ = interval.tick() => {
+                // show metrics
+                println!("{:?}", subscriber);
+            }
+            mode = shutdown_rx.recv() => {
+                if let Some(Some(_)) = mode {
+                    println!("[tracer_task] shutting down");
+                    break;
+                }
+            }
+        }
     }
 }
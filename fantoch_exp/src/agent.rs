@@ -0,0 +1,420 @@
+// An optional replacement for the ssh-spawn-plus-log-grep control plane
+// `util`/`bench` otherwise use: `wait_process_started` loops grepping a log
+// file over ssh, `stop_processes` parses `lsof | grep -v PID` columns, and
+// `check_no_dstat` scrapes `ps -aux` - all fragile text scraping of state a
+// process running on someone else's machine happens to expose. This module
+// instead defines a small RPC protocol for a persistent agent daemon meant
+// to run once per VM: the orchestrator asks it to start/signal/wait on a
+// process and fetch a file back, and the agent answers from state it
+// actually owns (the `tokio::process::Child` handle, its exit status, and
+// whether a readiness pattern has shown up in its captured output) instead
+// of re-deriving it from ssh'd-in shell commands every poll. Modeled on the
+// persistent-agent-over-a-typed-transport approach p9cpu takes, rather than
+// a fresh ssh invocation per action. A `Signal` request is always answered
+// with an error: see `signal_process`.
+//
+// The wire format is a length-prefixed `bincode` frame (a `u32` big-endian
+// byte count followed by the encoded value) directly over a `TcpStream`,
+// the simpler of the two transports the request considered - adding
+// tonic/gRPC's protobuf build step isn't worth it for five request/response
+// shapes this small.
+//
+// `AgentHost` (a `RemoteHost` impl backed by this protocol, so `bench`
+// could pick it over `TsunamiHost` without caring which control plane is
+// underneath) isn't added here: `RemoteHost::spawn` returns a local
+// `tokio::process::Child`, which only makes sense when the command actually
+// runs as a child of the orchestrator's own process - an agent-started
+// process runs on the remote VM instead, so it needs a different handle
+// type than `RemoteHost` was built around. Reshaping that trait is left as
+// a follow-up once there's a second backend that actually needs it.
+
+use color_eyre::eyre::{self, WrapErr};
+use color_eyre::Report;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+/// A process id assigned by `start_process`, unique across every connection
+/// to the same agent - not the OS pid (the agent never needs to expose
+/// that).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AgentPid(u32);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AgentSignal {
+    Terminate,
+    Kill,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum AgentRequest {
+    /// Starts `command` in the background. `ready_pattern`, if set, is a
+    /// substring `wait_ready` waits to see in the child's combined
+    /// stdout/stderr (the same "process N started"/"all clients ended"
+    /// markers `wait_process_started`/`wait_client_ended` already grep for).
+    StartProcess {
+        command: String,
+        ready_pattern: Option<String>,
+    },
+    WaitReady(AgentPid),
+    Signal(AgentPid, AgentSignal),
+    WaitExited(AgentPid),
+    FetchFile(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum AgentResponse {
+    Pid(AgentPid),
+    Ready,
+    Signaled,
+    Exited(Option<i32>),
+    File(Vec<u8>),
+    Error(String),
+}
+
+async fn write_frame<T>(stream: &mut TcpStream, value: &T) -> Result<(), Report>
+where
+    T: Serialize,
+{
+    let bytes = bincode::serialize(value).wrap_err("agent encode")?;
+    let len = u32::try_from(bytes.len()).wrap_err("agent frame too large")?;
+    stream
+        .write_all(&len.to_be_bytes())
+        .await
+        .wrap_err("agent write length")?;
+    stream.write_all(&bytes).await.wrap_err("agent write frame")?;
+    Ok(())
+}
+
+async fn read_frame<T>(stream: &mut TcpStream) -> Result<T, Report>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let mut len_bytes = [0u8; 4];
+    stream
+        .read_exact(&mut len_bytes)
+        .await
+        .wrap_err("agent read length")?;
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    let mut bytes = vec![0u8; len];
+    stream
+        .read_exact(&mut bytes)
+        .await
+        .wrap_err("agent read frame")?;
+    bincode::deserialize(&bytes).wrap_err("agent decode")
+}
+
+/// A connection to one VM's agent daemon. Every method is one request/
+/// response round-trip; callers needing several in-flight operations
+/// against the same agent should open one `AgentClient` per operation (a
+/// single `TcpStream` isn't multiplexed).
+pub struct AgentClient {
+    stream: TcpStream,
+}
+
+impl AgentClient {
+    pub async fn connect(addr: &str) -> Result<Self, Report> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .wrap_err("agent connect")?;
+        Ok(Self { stream })
+    }
+
+    async fn call(&mut self, request: AgentRequest) -> Result<AgentResponse, Report> {
+        write_frame(&mut self.stream, &request).await?;
+        let response = read_frame(&mut self.stream).await?;
+        if let AgentResponse::Error(error) = &response {
+            eyre::bail!("agent error: {}", error);
+        }
+        Ok(response)
+    }
+
+    pub async fn start_process(
+        &mut self,
+        command: String,
+        ready_pattern: Option<String>,
+    ) -> Result<AgentPid, Report> {
+        match self
+            .call(AgentRequest::StartProcess {
+                command,
+                ready_pattern,
+            })
+            .await?
+        {
+            AgentResponse::Pid(pid) => Ok(pid),
+            other => eyre::bail!("unexpected agent response: {:?}", other),
+        }
+    }
+
+    pub async fn wait_ready(&mut self, pid: AgentPid) -> Result<(), Report> {
+        match self.call(AgentRequest::WaitReady(pid)).await? {
+            AgentResponse::Ready => Ok(()),
+            other => eyre::bail!("unexpected agent response: {:?}", other),
+        }
+    }
+
+    pub async fn signal(
+        &mut self,
+        pid: AgentPid,
+        signal: AgentSignal,
+    ) -> Result<(), Report> {
+        match self.call(AgentRequest::Signal(pid, signal)).await? {
+            AgentResponse::Signaled => Ok(()),
+            other => eyre::bail!("unexpected agent response: {:?}", other),
+        }
+    }
+
+    pub async fn wait_exited(&mut self, pid: AgentPid) -> Result<Option<i32>, Report> {
+        match self.call(AgentRequest::WaitExited(pid)).await? {
+            AgentResponse::Exited(code) => Ok(code),
+            other => eyre::bail!("unexpected agent response: {:?}", other),
+        }
+    }
+
+    pub async fn fetch_file(&mut self, path: String) -> Result<Vec<u8>, Report> {
+        match self.call(AgentRequest::FetchFile(path)).await? {
+            AgentResponse::File(bytes) => Ok(bytes),
+            other => eyre::bail!("unexpected agent response: {:?}", other),
+        }
+    }
+}
+
+/// A child's exit status, or the absence of one yet - `Option<i32>` alone
+/// can't distinguish "still running" from "exited without a code" (e.g.
+/// killed by a signal), so this needs its own `Running` state.
+#[derive(Debug, Clone, Copy)]
+enum ExitState {
+    Running,
+    Exited(Option<i32>),
+}
+
+/// A process the agent started: the readiness flag is flipped by a task
+/// tailing the child's captured output for `ready_pattern`, and the exit
+/// status is filled in once by a task awaiting the child directly - both
+/// watched via a `watch` channel the same way `run::spawn_shutdown_listener`
+/// broadcasts its own one-shot signal, so any number of `WaitReady`/
+/// `WaitExited` requests (even ones that arrive after the fact) observe it.
+struct ManagedProcess {
+    ready_rx: tokio::sync::watch::Receiver<bool>,
+    exited_rx: tokio::sync::watch::Receiver<ExitState>,
+}
+
+type Processes = Arc<Mutex<HashMap<AgentPid, ManagedProcess>>>;
+
+/// Runs the agent daemon, accepting connections on `bind_addr` until the
+/// process is killed. Meant to be the entire body of a small `agent`
+/// binary installed on each VM (the orchestrator side is `AgentClient`).
+pub async fn serve(bind_addr: &str) -> Result<(), Report> {
+    let listener = TcpListener::bind(bind_addr)
+        .await
+        .wrap_err("agent bind")?;
+    let processes: Processes = Arc::new(Mutex::new(HashMap::new()));
+
+    loop {
+        let (stream, _) = listener.accept().await.wrap_err("agent accept")?;
+        let processes = processes.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, processes).await {
+                tracing::warn!("agent connection error: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    processes: Processes,
+) -> Result<(), Report> {
+    loop {
+        let request: AgentRequest = match read_frame(&mut stream).await {
+            Ok(request) => request,
+            // the orchestrator closed the connection; nothing left to do
+            Err(_) => return Ok(()),
+        };
+
+        let response = match request {
+            AgentRequest::StartProcess {
+                command,
+                ready_pattern,
+            } => match start_process(command, ready_pattern).await {
+                Ok(process) => {
+                    // `processes` is shared by every connection and never
+                    // shrinks, so its length is a pid no connection has
+                    // been handed before
+                    let mut processes = processes.lock().await;
+                    let pid = AgentPid(processes.len() as u32);
+                    processes.insert(pid, process);
+                    AgentResponse::Pid(pid)
+                }
+                Err(e) => AgentResponse::Error(format!("{:?}", e)),
+            },
+            AgentRequest::WaitReady(pid) => {
+                match wait_ready(&processes, pid).await {
+                    Ok(()) => AgentResponse::Ready,
+                    Err(e) => AgentResponse::Error(format!("{:?}", e)),
+                }
+            }
+            AgentRequest::Signal(pid, signal) => {
+                match signal_process(pid, signal) {
+                    Ok(()) => AgentResponse::Signaled,
+                    Err(e) => AgentResponse::Error(format!("{:?}", e)),
+                }
+            }
+            AgentRequest::WaitExited(pid) => {
+                match wait_exited(&processes, pid).await {
+                    Ok(code) => AgentResponse::Exited(code),
+                    Err(e) => AgentResponse::Error(format!("{:?}", e)),
+                }
+            }
+            AgentRequest::FetchFile(path) => match tokio::fs::read(&path).await {
+                Ok(bytes) => AgentResponse::File(bytes),
+                Err(e) => AgentResponse::Error(format!("{:?}", e)),
+            },
+        };
+
+        write_frame(&mut stream, &response).await?;
+    }
+}
+
+async fn start_process(
+    command: String,
+    ready_pattern: Option<String>,
+) -> Result<ManagedProcess, Report> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .wrap_err("agent spawn")?;
+
+    let (ready_tx, ready_rx) = tokio::sync::watch::channel(false);
+    let (exited_tx, exited_rx) = tokio::sync::watch::channel(ExitState::Running);
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    tokio::spawn(watch_ready(stdout, stderr, ready_pattern, ready_tx));
+
+    tokio::spawn(async move {
+        let code = child.wait().await.ok().and_then(|status| status.code());
+        let _ = exited_tx.broadcast(ExitState::Exited(code));
+    });
+
+    Ok(ManagedProcess {
+        ready_rx,
+        exited_rx,
+    })
+}
+
+/// Tails `stdout`/`stderr` line by line, broadcasting readiness as soon as
+/// `ready_pattern` shows up in either - or immediately, if no pattern was
+/// given (the command doesn't need a readiness check).
+async fn watch_ready(
+    stdout: Option<tokio::process::ChildStdout>,
+    stderr: Option<tokio::process::ChildStderr>,
+    ready_pattern: Option<String>,
+    ready_tx: tokio::sync::watch::Sender<bool>,
+) {
+    let pattern = match ready_pattern {
+        Some(pattern) => pattern,
+        None => {
+            let _ = ready_tx.broadcast(true);
+            return;
+        }
+    };
+
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    let mut stdout_lines = stdout.map(|s| BufReader::new(s).lines());
+    let mut stderr_lines = stderr.map(|s| BufReader::new(s).lines());
+
+    loop {
+        let stdout_next = async {
+            match &mut stdout_lines {
+                Some(lines) => lines.next_line().await,
+                None => futures::future::pending().await,
+            }
+        };
+        let stderr_next = async {
+            match &mut stderr_lines {
+                Some(lines) => lines.next_line().await,
+                None => futures::future::pending().await,
+            }
+        };
+        let (line, from_stdout) = tokio::select! {
+            line = stdout_next => (line, true),
+            line = stderr_next => (line, false),
+        };
+        match line {
+            Ok(Some(line)) => {
+                if line.contains(&pattern) {
+                    let _ = ready_tx.broadcast(true);
+                    return;
+                }
+            }
+            // a stream closed or errored: stop reading it and keep draining
+            // the other one; once both are gone without the pattern, the
+            // process closed its output before announcing readiness, so
+            // return, dropping `ready_tx`, which makes `wait_ready` report
+            // that instead of waiting forever
+            Ok(None) | Err(_) => {
+                if from_stdout {
+                    stdout_lines = None;
+                } else {
+                    stderr_lines = None;
+                }
+                if stdout_lines.is_none() && stderr_lines.is_none() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn wait_ready(processes: &Processes, pid: AgentPid) -> Result<(), Report> {
+    let mut ready_rx = {
+        let processes = processes.lock().await;
+        let process = processes
+            .get(&pid)
+            .ok_or_else(|| eyre::eyre!("unknown pid: {:?}", pid))?;
+        process.ready_rx.clone()
+    };
+    while let Some(ready) = ready_rx.recv().await {
+        if ready {
+            return Ok(());
+        }
+    }
+    eyre::bail!("process {:?} exited without ever becoming ready", pid)
+}
+
+async fn wait_exited(
+    processes: &Processes,
+    pid: AgentPid,
+) -> Result<Option<i32>, Report> {
+    let mut exited_rx = {
+        let processes = processes.lock().await;
+        let process = processes
+            .get(&pid)
+            .ok_or_else(|| eyre::eyre!("unknown pid: {:?}", pid))?;
+        process.exited_rx.clone()
+    };
+    while let Some(state) = exited_rx.recv().await {
+        if let ExitState::Exited(code) = state {
+            return Ok(code);
+        }
+    }
+    eyre::bail!("agent shut down before process {:?} exited", pid)
+}
+
+fn signal_process(pid: AgentPid, signal: AgentSignal) -> Result<(), Report> {
+    // sending a signal to an arbitrary already-spawned `tokio::process::Child`
+    // by pid isn't exposed by `tokio` 0.2's `Child` beyond `kill()` (always
+    // `SIGKILL`), and nix-level `libc::kill` plumbing is more than this
+    // module needs for `stop_processes`'s current two uses (`kill` and,
+    // transitively, `AgentSignal::Kill`); left as a follow-up alongside
+    // actually wiring an agent-backed control plane into `bench`.
+    eyre::bail!("sending {:?} to {:?} is not supported by this agent", signal, pid)
+}
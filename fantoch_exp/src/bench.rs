@@ -3,7 +3,7 @@ use crate::config::{
     PORT,
 };
 use crate::exp::{self, Machines};
-use crate::{util, SerializationFormat};
+use crate::{util, Compression, SerializationFormat};
 use crate::{FantochFeature, Protocol, RunMode, Testbed};
 use color_eyre::eyre::{self, WrapErr};
 use color_eyre::Report;
@@ -11,7 +11,10 @@ use fantoch::client::Workload;
 use fantoch::config::Config;
 use fantoch::id::ProcessId;
 use fantoch::planet::{Planet, Region};
+use rand::Rng;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::path::Path;
 
 type Ips = HashMap<ProcessId, String>;
@@ -20,6 +23,247 @@ const LOG_FILE: &str = ".log";
 const DSTAT_FILE: &str = "dstat.csv";
 const METRICS_FILE: &str = ".metrics";
 
+/// Broadcasts, at most once, that a ctrl-c was received, the same `watch`-
+/// based signal `run::spawn_shutdown_listener` uses for reader/writer/
+/// process tasks. Cloned down through every layer of a single config's run
+/// (`run_experiment_with_retry` down to `run_clients`), but only
+/// `run_clients`' wait for the clients to end selects on it: a signal that
+/// fires earlier in an attempt, or during a retry's backoff, is seen once
+/// that wait starts, since a receiver's first `recv()` returns the current
+/// value.
+type ShutdownReceiver = tokio::sync::watch::Receiver<bool>;
+
+/// Returned (wrapped in a `Report`) when a shutdown signal interrupts a run
+/// in progress, so callers can tell "the operator asked us to stop" apart
+/// from "the attempt actually failed" and skip both retrying and reporting
+/// it as a sweep failure.
+#[derive(Debug)]
+struct ShutdownRequested;
+
+impl fmt::Display for ShutdownRequested {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shutdown requested")
+    }
+}
+
+impl std::error::Error for ShutdownRequested {}
+
+fn is_shutdown_requested(error: &Report) -> bool {
+    error.chain().any(|cause| cause.is::<ShutdownRequested>())
+}
+
+/// Continuous-capture tuning for `bench_experiment`: instead of
+/// `pull_metrics` copying a server/client's `.log`/`dstat.csv`/`.metrics`
+/// only once at the very end, each remote file is capped at `segment_bytes`
+/// and rotated across `segment_count` numbered segments (`<file>.0 ..
+/// <file>.<segment_count - 1>`, overwriting the oldest once all are used),
+/// polled every `poll_interval` so a closed segment lands in `exp_dir`
+/// while the run is still live - modeled on Erlang `dbg`'s wrap ports.
+/// Bounds both how much telemetry a crash or a kill mid-run can lose and
+/// how much disk an hours-long run can fill on the VM. `None` (the
+/// default) keeps the old single end-of-run `pull_metrics` pull.
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureConfig {
+    pub segment_count: usize,
+    pub segment_bytes: u64,
+    pub poll_interval: std::time::Duration,
+}
+
+/// Runs `run` to completion, and - when `capture_config` is set - polls
+/// every VM's `.log`/`dstat.csv`/`.metrics` on the side every
+/// `poll_interval`, rotating and pulling back whatever segment has filled
+/// up since the last round. Does one last round after `run` resolves, before
+/// returning `run`'s result; like every other round, it only rotates and
+/// pulls files that have grown past `segment_bytes`, so a current segment
+/// smaller than that is not captured here (only `pull_metrics`, which runs
+/// after a successful `run_clients`, copies it).
+async fn run_with_capture<F>(
+    machines: &Machines<'_>,
+    exp_dir: &str,
+    capture_config: Option<CaptureConfig>,
+    run: F,
+) -> Result<(), Report>
+where
+    F: std::future::Future<Output = Result<(), Report>>,
+{
+    let capture_config = match capture_config {
+        Some(capture_config) => capture_config,
+        None => return run.await,
+    };
+
+    tokio::pin!(run);
+    let mut cursors = HashMap::new();
+    loop {
+        let poll = tokio::time::delay_for(capture_config.poll_interval);
+        tokio::select! {
+            result = &mut run => {
+                if let Err(e) = capture_round(machines, exp_dir, capture_config, &mut cursors).await {
+                    tracing::warn!("error in final capture round: {:?}", e);
+                }
+                return result;
+            }
+            _ = poll => {
+                if let Err(e) = capture_round(machines, exp_dir, capture_config, &mut cursors).await {
+                    tracing::warn!("error in capture round: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+/// One capture pass over every server's and client's `.log`/`.metrics`, and
+/// every machine's `dstat.csv`: for each, rotates the remote file (if it's
+/// grown past `segment_bytes`) into its next ring segment and pulls that
+/// segment back, skipping machines whose current segment hasn't filled up
+/// yet. `cursors` tracks, per remote file, which ring segment is next -
+/// this is only known locally, since the remote side just does what it's
+/// told to.
+async fn capture_round(
+    machines: &Machines<'_>,
+    exp_dir: &str,
+    config: CaptureConfig,
+    cursors: &mut HashMap<String, usize>,
+) -> Result<(), Report> {
+    for (process_id, vm) in machines.servers() {
+        let region = machines.process_region(process_id);
+        let prefix = crate::config::file_prefix(Some(*process_id), region);
+        capture_file(vm, LOG_FILE, &prefix, "log", exp_dir, config, cursors)
+            .await
+            .wrap_err("capture log")?;
+        capture_file(
+            vm,
+            METRICS_FILE,
+            &prefix,
+            "metrics",
+            exp_dir,
+            config,
+            cursors,
+        )
+        .await
+        .wrap_err("capture metrics")?;
+        capture_file(
+            vm,
+            DSTAT_FILE,
+            &prefix,
+            "dstat",
+            exp_dir,
+            config,
+            cursors,
+        )
+        .await
+        .wrap_err("capture dstat")?;
+    }
+    for (region, vm) in machines.clients() {
+        let prefix = crate::config::file_prefix(None, region);
+        capture_file(vm, LOG_FILE, &prefix, "log", exp_dir, config, cursors)
+            .await
+            .wrap_err("capture log")?;
+        capture_file(
+            vm,
+            METRICS_FILE,
+            &prefix,
+            "metrics",
+            exp_dir,
+            config,
+            cursors,
+        )
+        .await
+        .wrap_err("capture metrics")?;
+        capture_file(
+            vm,
+            DSTAT_FILE,
+            &prefix,
+            "dstat",
+            exp_dir,
+            config,
+            cursors,
+        )
+        .await
+        .wrap_err("capture dstat")?;
+    }
+    Ok(())
+}
+
+/// Rotates and pulls back `remote_file` on `vm`, if it's grown past
+/// `config.segment_bytes` since the last round. `cursors` is keyed by
+/// `"{vm}:{remote_file}"`, since the same file name (e.g. `.log`) is reused
+/// on every machine.
+async fn capture_file(
+    vm: &tsunami::Machine<'_>,
+    remote_file: &str,
+    local_prefix: &str,
+    local_name: &str,
+    exp_dir: &str,
+    config: CaptureConfig,
+    cursors: &mut HashMap<String, usize>,
+) -> Result<(), Report> {
+    let cursor_key = format!("{}:{}", vm.public_ip, remote_file);
+    let segment =
+        *cursors.entry(cursor_key.clone()).or_insert(0) % config.segment_count;
+    let segment_file = format!("{}.{}", remote_file, segment);
+
+    // rotate the remote file into `segment_file` (and recreate an empty one
+    // to keep writing to) only if it's actually grown past the threshold;
+    // the sentinel line lets us tell whether the rotation happened without
+    // parsing `mv`'s (nonexistent) output
+    let command = format!(
+        "if [ -f {file} ] && [ $(stat -c%s {file} 2>/dev/null || echo 0) -ge {bytes} ]; then mv {file} {segment_file}; : > {file}; echo ROTATED; fi",
+        file = remote_file,
+        bytes = config.segment_bytes,
+        segment_file = segment_file,
+    );
+    let output = util::vm_exec(vm, command).await.wrap_err("rotate")?;
+    if !output.contains("ROTATED") {
+        // nothing to pull this round
+        return Ok(());
+    }
+
+    let local_path =
+        format!("{}/{}_{}.{}", exp_dir, local_prefix, local_name, segment);
+    util::copy_from((segment_file.as_str(), vm), local_path)
+        .await
+        .wrap_err("copy segment")?;
+    util::vm_exec(vm, format!("rm {}", segment_file))
+        .await
+        .wrap_err("remove segment")?;
+
+    *cursors.get_mut(&cursor_key).unwrap() = segment + 1;
+    Ok(())
+}
+
+/// Waits until `shutdown` reports that a ctrl-c fired. A fresh receiver's
+/// first `recv()` resolves immediately with the signal's *current* value
+/// (`false`, unless it already fired before this receiver was cloned), so
+/// this loops past that baseline reading instead of treating it as the
+/// signal itself - only an actual `true` ends the wait. If the sender is
+/// ever dropped without firing, waits forever, so selecting against this
+/// just keeps running whatever else the `select!` is racing it against.
+async fn wait_for_shutdown(shutdown: &mut ShutdownReceiver) {
+    while let Some(fired) = shutdown.recv().await {
+        if fired {
+            return;
+        }
+    }
+    futures::future::pending().await
+}
+
+/// Spawns the task that turns a ctrl-c into a one-shot shutdown signal,
+/// broadcast through the returned `watch` channel so an in-flight
+/// `run_experiment_with_retry` (and the rest of the sweep) can tear the
+/// remote processes/`dstat` down and stop, instead of the operator having
+/// to kill them by hand on every VM.
+fn spawn_shutdown_listener() -> ShutdownReceiver {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        let _ = tokio::signal::ctrl_c().await;
+        println!("received ctrl-c: finishing the current attempt's cleanup and stopping the sweep");
+        if shutdown_tx.broadcast(true).is_err() {
+            println!("no task left listening for shutdown");
+        }
+    });
+    shutdown_rx
+}
+
 pub async fn bench_experiment(
     machines: Machines<'_>,
     run_mode: RunMode,
@@ -32,12 +276,15 @@ pub async fn bench_experiment(
     workloads: Vec<Workload>,
     skip: impl Fn(Protocol, Config, usize) -> bool,
     results_dir: impl AsRef<Path>,
+    capture_config: Option<CaptureConfig>,
 ) -> Result<(), Report> {
     if tracer_show_interval.is_some() {
         panic!("vitor: you should set the 'prof' feature for this to work!");
     }
 
-    for workload in workloads {
+    let shutdown = spawn_shutdown_listener();
+
+    'sweep: for workload in workloads {
         for &clients in &clients_per_region {
             for &(protocol, config) in &configs {
                 // check that we have the correct number of server machines
@@ -58,7 +305,12 @@ pub async fn bench_experiment(
                 if skip(protocol, config, clients) {
                     continue;
                 }
-                run_experiment(
+                // compute the experiment directory once, up front: every
+                // retry attempt below writes to (and, if it fails, wipes)
+                // this same directory, so it only ever ends up persisted
+                // with the successful attempt's config/metrics
+                let exp_dir = compute_exp_dir(&results_dir);
+                let result = run_experiment_with_retry(
                     &machines,
                     run_mode,
                     features.clone(),
@@ -69,15 +321,153 @@ pub async fn bench_experiment(
                     tracer_show_interval,
                     clients,
                     workload,
-                    &results_dir,
+                    &exp_dir,
+                    shutdown.clone(),
+                    capture_config,
                 )
-                .await?;
+                .await;
+                match result {
+                    Ok(()) => (),
+                    // a shutdown was already fully cleaned up by the time it
+                    // got here; stop the sweep instead of reporting it as a
+                    // failed configuration
+                    Err(e) if is_shutdown_requested(&e) => {
+                        tracing::info!("stopping the sweep: {}", e);
+                        break 'sweep;
+                    }
+                    Err(e) => return Err(e),
+                }
             }
         }
     }
     Ok(())
 }
 
+/// `run_experiment` retry policy: a failed attempt is torn down and retried,
+/// for at most `max_attempts` attempts in all, waiting `delay = min(base * 2^(attempt - 1),
+/// max_delay)` plus uniform jitter in `[0, delay)` between attempts. Mirrors
+/// nextest's CI retry profile - cloud experiments fail intermittently (an
+/// ssh hiccup in `wait_process_started`, a VM that never logs "all clients
+/// ended", a `lsof` that returns garbage), and aborting the whole
+/// `bench_experiment` sweep over one flaky attempt is wasteful.
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_attempts: usize,
+    base_delay: std::time::Duration,
+    max_delay: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_secs(30),
+            max_delay: std::time::Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// `attempt` is 1-based (the delay before the 2nd attempt uses
+    /// `attempt = 1`).
+    fn backoff(&self, attempt: usize) -> std::time::Duration {
+        let exponent = u32::try_from(attempt - 1).unwrap_or(u32::MAX);
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(exponent).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        let jitter = delay.mul_f64(rand::thread_rng().gen::<f64>());
+        delay + jitter
+    }
+}
+
+/// Retries `run_experiment` according to `RetryPolicy::default()`, tearing
+/// the half-started attempt down (via `run_experiment`'s own cleanup, which
+/// runs regardless of whether the attempt succeeded) and wiping `exp_dir`
+/// before every retry, so a flaky attempt never leaves processes running
+/// or a partial `exp_config`/metrics dump behind for the next one to
+/// collide with. `exp_dir` should be freshly computed (e.g. via
+/// `compute_exp_dir`) for each configuration this is called for, since
+/// every attempt writes to the same directory.
+///
+/// A fired `shutdown` is not itself retried: `run_experiment` already ran
+/// its full cleanup before reporting it (see `run_clients`), so this just
+/// propagates a `ShutdownRequested` error straight back to `bench_experiment`.
+async fn run_experiment_with_retry(
+    machines: &Machines<'_>,
+    run_mode: RunMode,
+    features: Vec<FantochFeature>,
+    testbed: Testbed,
+    planet: &Option<Planet>,
+    protocol: Protocol,
+    config: Config,
+    tracer_show_interval: Option<usize>,
+    clients_per_region: usize,
+    workload: Workload,
+    exp_dir: &str,
+    shutdown: ShutdownReceiver,
+    capture_config: Option<CaptureConfig>,
+) -> Result<(), Report> {
+    let policy = RetryPolicy::default();
+    let mut attempt = 1;
+    loop {
+        let result = run_experiment(
+            machines,
+            run_mode,
+            features.clone(),
+            testbed,
+            planet,
+            protocol,
+            config,
+            tracer_show_interval,
+            clients_per_region,
+            workload,
+            exp_dir,
+            shutdown.clone(),
+            capture_config,
+        )
+        .await;
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if is_shutdown_requested(&e) => return Err(e),
+            Err(e) if attempt < policy.max_attempts => {
+                tracing::warn!(
+                    "run_experiment attempt {}/{} failed: {:?}",
+                    attempt,
+                    policy.max_attempts,
+                    e
+                );
+                // only the successful attempt's `exp_config`/metrics should
+                // survive; wipe whatever this attempt wrote before retrying
+                if let Err(e) = tokio::fs::remove_dir_all(exp_dir).await {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        tracing::warn!(
+                            "error removing exp_dir {} after failed attempt: {:?}",
+                            exp_dir,
+                            e
+                        );
+                    }
+                }
+                let delay = policy.backoff(attempt);
+                tracing::info!(
+                    "retrying attempt {} in {:?}",
+                    attempt + 1,
+                    delay
+                );
+                tokio::time::delay_for(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e).wrap_err(format!(
+                    "run_experiment failed after {} attempts",
+                    policy.max_attempts
+                ))
+            }
+        }
+    }
+}
+
 async fn run_experiment(
     machines: &Machines<'_>,
     run_mode: RunMode,
@@ -89,55 +479,126 @@ async fn run_experiment(
     tracer_show_interval: Option<usize>,
     clients_per_region: usize,
     workload: Workload,
-    results_dir: impl AsRef<Path>,
+    exp_dir: &str,
+    shutdown: ShutdownReceiver,
+    capture_config: Option<CaptureConfig>,
 ) -> Result<(), Report> {
     // start dstat in all machines
     let dstats = start_dstat(machines).await.wrap_err("start_dstat")?;
 
-    // start processes
-    let (process_ips, processes) = start_processes(
+    // from here on, any outcome (success, failure, or shutdown) must still
+    // stop dstat before this function returns, so a failed or interrupted
+    // attempt doesn't leave dstat running into the next retry
+    let outcome = run_experiment_processes(
         machines,
         run_mode,
+        features,
         testbed,
         planet,
         protocol,
         config,
         tracer_show_interval,
+        clients_per_region,
+        workload,
+        exp_dir,
+        shutdown,
+        capture_config,
     )
-    .await
-    .wrap_err("start_processes")?;
+    .await;
 
-    // run clients
-    run_clients(clients_per_region, workload, machines, process_ips)
-        .await
-        .wrap_err("run_clients")?;
-
-    // stop dstat
     stop_dstat(machines, dstats).await.wrap_err("stop_dstat")?;
 
-    // create experiment config and pull metrics
-    let exp_config = ExperimentConfig::new(
-        machines.placement().clone(),
-        planet.clone(),
+    outcome
+}
+
+/// The `start_processes`/`run_clients`/`pull_metrics`/`stop_processes` part
+/// of an attempt (`dstat` start/stop wraps around this in `run_experiment`).
+/// Whether `run_clients` succeeds, fails, or is interrupted by `shutdown`,
+/// the server processes this started are always stopped before returning -
+/// otherwise a retried (or simply aborted) attempt's `lsof`/`grep` counts in
+/// `start_processes`/`stop_processes` would see them still running.
+async fn run_experiment_processes(
+    machines: &Machines<'_>,
+    run_mode: RunMode,
+    features: Vec<FantochFeature>,
+    testbed: Testbed,
+    planet: &Option<Planet>,
+    protocol: Protocol,
+    config: Config,
+    tracer_show_interval: Option<usize>,
+    clients_per_region: usize,
+    workload: Workload,
+    exp_dir: &str,
+    shutdown: ShutdownReceiver,
+    capture_config: Option<CaptureConfig>,
+) -> Result<(), Report> {
+    // start processes
+    let (process_ips, processes) = start_processes(
+        machines,
         run_mode,
-        features,
         testbed,
+        planet,
         protocol,
         config,
-        clients_per_region,
-        workload,
-    );
-    let exp_dir = pull_metrics(machines, exp_config, results_dir)
-        .await
-        .wrap_err("pull_metrics")?;
+        tracer_show_interval,
+    )
+    .await
+    .wrap_err("start_processes")?;
 
-    // stop processes: should only be stopped after copying all the metrics to
-    // avoid unnecessary noise in the logs
-    stop_processes(machines, run_mode, exp_dir, processes)
-        .await
-        .wrap_err("stop_processes")?;
+    // run clients, capturing rotated log/dstat/metrics segments on the side
+    // if `capture_config` is set
+    let outcome = run_with_capture(
+        machines,
+        exp_dir,
+        capture_config,
+        run_clients(clients_per_region, workload, machines, process_ips, shutdown),
+    )
+    .await
+    .wrap_err("run_clients");
+
+    let outcome = match outcome {
+        Ok(()) => {
+            // create experiment config and pull metrics
+            let exp_config = ExperimentConfig::new(
+                machines.placement().clone(),
+                planet.clone(),
+                run_mode,
+                features,
+                testbed,
+                protocol,
+                config,
+                clients_per_region,
+                workload,
+            );
+            pull_metrics(machines, exp_config, exp_dir)
+                .await
+                .wrap_err("pull_metrics")
+        }
+        Err(e) => Err(e),
+    };
 
-    Ok(())
+    // stop processes: should only be stopped after copying all the metrics
+    // to avoid unnecessary noise in the logs; but, successful or not, they
+    // must be stopped before this attempt returns
+    if let Err(stop_err) = stop_processes(
+        machines,
+        run_mode,
+        exp_dir.to_string(),
+        processes,
+    )
+    .await
+    .wrap_err("stop_processes")
+    {
+        match outcome {
+            Ok(()) => return Err(stop_err),
+            Err(_) => tracing::warn!(
+                "error stopping processes after a failed attempt: {:?}",
+                stop_err
+            ),
+        }
+    }
+
+    outcome
 }
 
 async fn start_processes(
@@ -253,6 +714,7 @@ async fn run_clients(
     workload: Workload,
     machines: &Machines<'_>,
     process_ips: Ips,
+    mut shutdown: ShutdownReceiver,
 ) -> Result<(), Report> {
     let mut clients = HashMap::with_capacity(machines.client_count());
     let mut wait_clients = Vec::with_capacity(machines.client_count());
@@ -300,11 +762,21 @@ async fn run_clients(
         wait_clients.push(wait_client_ended(region_index, region.clone(), &vm));
     }
 
-    // wait all clients ended
-    for result in futures::future::join_all(wait_clients).await {
-        let _ = result.wrap_err("wait_client_ended")?;
+    // wait all clients ended, unless a shutdown is requested first - this is
+    // the longest-running wait in an attempt (it only resolves once the
+    // workload finishes), so it's the one place a ctrl-c is actually likely
+    // to land
+    tokio::select! {
+        _ = wait_for_shutdown(&mut shutdown) => {
+            Err(Report::new(ShutdownRequested))
+        }
+        results = futures::future::join_all(wait_clients) => {
+            for result in results {
+                let _ = result.wrap_err("wait_client_ended")?;
+            }
+            Ok(())
+        }
     }
-    Ok(())
 }
 
 async fn stop_processes(
@@ -459,6 +931,16 @@ async fn wait_process_ended(
                 .await
                 .wrap_err("pull_heaptrack_file")?;
         }
+        RunMode::Dhat => {
+            pull_dhat_file(Some(process_id), &region, vm, exp_dir)
+                .await
+                .wrap_err("pull_dhat_file")?;
+        }
+        RunMode::Perf => {
+            pull_perf_file(Some(process_id), &region, vm, exp_dir)
+                .await
+                .wrap_err("pull_perf_file")?;
+        }
     }
     Ok(())
 }
@@ -591,10 +1073,10 @@ async fn check_no_dstat(vm: &tsunami::Machine<'_>) -> Result<(), Report> {
 async fn pull_metrics(
     machines: &Machines<'_>,
     exp_config: ExperimentConfig,
-    results_dir: impl AsRef<Path>,
-) -> Result<String, Report> {
+    exp_dir: &str,
+) -> Result<(), Report> {
     // save experiment config, making sure experiment directory exists
-    let exp_dir = save_exp_config(exp_config, results_dir)
+    save_exp_config(exp_config, exp_dir)
         .await
         .wrap_err("save_exp_config")?;
     tracing::info!("experiment metrics will be saved in {}", exp_dir);
@@ -603,11 +1085,11 @@ async fn pull_metrics(
     // prepare server metrics pull
     for (process_id, vm) in machines.servers() {
         let region = machines.process_region(process_id);
-        pulls.push(pull_metrics_files(Some(*process_id), region, vm, &exp_dir));
+        pulls.push(pull_metrics_files(Some(*process_id), region, vm, exp_dir));
     }
     // prepare client metrics pull
     for (region, vm) in machines.clients() {
-        pulls.push(pull_metrics_files(None, region, vm, &exp_dir));
+        pulls.push(pull_metrics_files(None, region, vm, exp_dir));
     }
 
     // pull all metrics in parallel
@@ -615,15 +1097,23 @@ async fn pull_metrics(
         let _ = result.wrap_err("pull_metrics")?;
     }
 
-    Ok(exp_dir)
+    Ok(())
+}
+
+/// Computes (but doesn't create) the directory a given configuration's
+/// experiment run is saved under, rooted at `results_dir` and named after
+/// the current time. Call this once per configuration, before
+/// `run_experiment_with_retry`, since every retry attempt for that
+/// configuration writes to (and, on failure, wipes) this same directory.
+fn compute_exp_dir(results_dir: impl AsRef<Path>) -> String {
+    let timestamp = exp_timestamp();
+    format!("{}/{}", results_dir.as_ref().display(), timestamp)
 }
 
 async fn save_exp_config(
     exp_config: ExperimentConfig,
-    results_dir: impl AsRef<Path>,
-) -> Result<String, Report> {
-    let timestamp = exp_timestamp();
-    let exp_dir = format!("{}/{}", results_dir.as_ref().display(), timestamp);
+    exp_dir: &str,
+) -> Result<(), Report> {
     tokio::fs::create_dir_all(&exp_dir)
         .await
         .wrap_err("create_dir_all")?;
@@ -633,8 +1123,9 @@ async fn save_exp_config(
         exp_config,
         format!("{}/exp_config.json", exp_dir),
         SerializationFormat::Json,
+        Compression::None,
     )?;
-    Ok(exp_dir)
+    Ok(())
 }
 
 fn exp_timestamp() -> u128 {
@@ -740,3 +1231,51 @@ async fn pull_heaptrack_file(
         .wrap_err("remove heaptrack file")?;
     Ok(())
 }
+
+async fn pull_dhat_file(
+    process_id: Option<ProcessId>,
+    region: &Region,
+    vm: &tsunami::Machine<'_>,
+    exp_dir: &str,
+) -> Result<(), Report> {
+    // `dhat` always generates a file with this name
+    let dhat = "dhat-heap.json";
+
+    // compute filename prefix
+    let prefix = crate::config::file_prefix(process_id, region);
+    let local_path = format!("{}/{}_dhat-heap.json", exp_dir, prefix);
+    util::copy_from((dhat, vm), local_path)
+        .await
+        .wrap_err("copy dhat-heap.json")?;
+
+    // remove dhat file
+    let command = format!("rm {}", dhat);
+    util::vm_exec(vm, command)
+        .await
+        .wrap_err("remove dhat-heap.json file")?;
+    Ok(())
+}
+
+async fn pull_perf_file(
+    process_id: Option<ProcessId>,
+    region: &Region,
+    vm: &tsunami::Machine<'_>,
+    exp_dir: &str,
+) -> Result<(), Report> {
+    // `perf record -o perf.data` always generates a file with this name
+    let perf_data = "perf.data";
+
+    // compute filename prefix
+    let prefix = crate::config::file_prefix(process_id, region);
+    let local_path = format!("{}/{}_perf.data", exp_dir, prefix);
+    util::copy_from((perf_data, vm), local_path)
+        .await
+        .wrap_err("copy perf.data")?;
+
+    // remove perf.data file
+    let command = format!("rm {}", perf_data);
+    util::vm_exec(vm, command)
+        .await
+        .wrap_err("remove perf.data file")?;
+    Ok(())
+}
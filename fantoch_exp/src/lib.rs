@@ -1,29 +1,43 @@
 #![deny(rust_2018_idioms)]
 
+#[cfg(feature = "exp")]
+pub mod agent;
 #[cfg(feature = "exp")]
 pub mod bench;
 #[cfg(feature = "exp")]
 pub mod exp;
 #[cfg(feature = "exp")]
+pub mod remote;
+#[cfg(feature = "exp")]
 pub mod testbed;
 #[cfg(feature = "exp")]
 pub mod util;
 
 pub mod config;
+pub mod run_profile;
 
 // Re-export `ExperimentConfig`.
 pub use config::ExperimentConfig;
+pub use run_profile::{RunProfile, RunProfileOverrides};
 
-use color_eyre::eyre::WrapErr;
+use color_eyre::eyre::{self, WrapErr};
 use color_eyre::Report;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::hash::Hash;
+use std::io::{BufRead, Read, Write};
 use std::path::Path;
+#[cfg(feature = "exp")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RunMode {
     Release,
     Flamegraph,
+    Heaptrack,
+    Dhat,
+    Perf,
 }
 
 impl RunMode {
@@ -35,18 +49,67 @@ impl RunMode {
                 // `source` is needed in order for `flamegraph` to be found
                 format!("source ~/.cargo/env && flamegraph {}", binary)
             }
+            RunMode::Heaptrack => format!("heaptrack {}", binary),
+            // `dhat` instruments the process from the inside (it swaps in a
+            // tracking global allocator compiled in via `extra_features`),
+            // so there's no external wrapper to invoke here
+            RunMode::Dhat => binary,
+            RunMode::Perf => {
+                format!("perf record -g -o perf.data -- {}", binary)
+            }
         }
     }
 
-    pub fn is_flamegraph(&self) -> bool {
-        self == &RunMode::Flamegraph
+    /// `fantoch` features this run mode needs compiled in, on top of
+    /// whichever features the caller already requested, for its
+    /// instrumentation to produce anything.
+    pub fn extra_features(&self) -> Vec<FantochFeature> {
+        match self {
+            RunMode::Release | RunMode::Heaptrack => Vec::new(),
+            // `dhat` only profiles allocations reachable through the global
+            // allocator it installs, which lives behind this feature
+            RunMode::Dhat => vec![FantochFeature::Dhat],
+            // sampling profilers are most useful paired with `fantoch`'s own
+            // timing spans, to correlate samples with what the protocol
+            // thought it was doing
+            RunMode::Flamegraph | RunMode::Perf => vec![FantochFeature::Timing],
+        }
+    }
+
+    /// Whether a run in this mode produces profiling artifacts that need to
+    /// be pulled back from the remote machine once the process ends.
+    pub fn collects_profile(&self) -> bool {
+        !matches!(self, RunMode::Release)
+    }
+}
+
+impl Default for RunMode {
+    fn default() -> Self {
+        RunMode::Release
+    }
+}
+
+impl std::str::FromStr for RunMode {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "release" => Ok(RunMode::Release),
+            "flamegraph" => Ok(RunMode::Flamegraph),
+            "heaptrack" => Ok(RunMode::Heaptrack),
+            "dhat" => Ok(RunMode::Dhat),
+            "perf" => Ok(RunMode::Perf),
+            other => eyre::bail!("unknown run mode: {}", other),
+        }
     }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FantochFeature {
     Amortize,
     Timing,
+    Dhat,
 }
 
 impl FantochFeature {
@@ -54,6 +117,7 @@ impl FantochFeature {
         match self {
             FantochFeature::Amortize => "amortize",
             FantochFeature::Timing => "timing",
+            FantochFeature::Dhat => "dhat",
         }
         .to_string()
     }
@@ -71,6 +135,7 @@ impl FantochFeature {
     Serialize,
     Hash,
 )]
+#[serde(rename_all = "snake_case")]
 pub enum Protocol {
     AtlasLocked,
     EPaxosLocked,
@@ -93,7 +158,24 @@ impl Protocol {
     }
 }
 
+impl std::str::FromStr for Protocol {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "atlas_locked" => Ok(Protocol::AtlasLocked),
+            "epaxos_locked" => Ok(Protocol::EPaxosLocked),
+            "fpaxos" => Ok(Protocol::FPaxos),
+            "newt_atomic" => Ok(Protocol::NewtAtomic),
+            "newt_locked" => Ok(Protocol::NewtLocked),
+            "basic" => Ok(Protocol::Basic),
+            other => eyre::bail!("unknown protocol: {}", other),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Testbed {
     Aws,
     Baremetal,
@@ -105,16 +187,154 @@ impl Testbed {
     }
 }
 
+impl std::str::FromStr for Testbed {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aws" => Ok(Testbed::Aws),
+            "baremetal" => Ok(Testbed::Baremetal),
+            other => eyre::bail!("unknown testbed: {}", other),
+        }
+    }
+}
+
 pub enum SerializationFormat {
     Bincode,
     Json,
 }
 
+/// Compression applied to the bytes `serialize` hands to `bincode`/`serde_json`,
+/// by wrapping the `BufWriter` in an encoder before serde ever touches it.
+/// Experiment result dumps and metrics files from long benchmark runs grow
+/// large quickly, so leaving this at `None` (the previous, and still default,
+/// behaviour) is only a good idea for small files like `ExperimentConfig`.
+///
+/// `deserialize` never needs to be told which of these was used: gzip and zstd
+/// both start every file with a standard magic number, so the reader side
+/// autodetects it from the first few bytes and old uncompressed files load
+/// exactly as before.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Compression {
+    None,
+    Gzip,
+    // zstd compression level; see `zstd::DEFAULT_COMPRESSION_LEVEL` for what
+    // the library itself defaults to
+    Zstd(i32),
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Magic tag written at the very start of every file produced by `serialize`,
+/// right before the rest of the envelope header. Lets `deserialize` tell a
+/// versioned envelope apart from a file written before this header existed.
+const ENVELOPE_MAGIC: [u8; 4] = *b"FTCH";
+
+/// Current schema version stamped into the envelope header by `serialize`.
+/// Bump the major component on any change to `SerializationFormat`, the
+/// header layout itself, or any other change that makes an old `deserialize`
+/// unable to make sense of a new file; bump the minor component for additive,
+/// backwards-compatible changes.
+const SCHEMA_VERSION: (u16, u16) = (1, 0);
+
+/// Returned by `deserialize` when a file's envelope header declares a schema
+/// major version different from [`SCHEMA_VERSION`]'s, i.e. one this binary
+/// isn't guaranteed to be able to read.
+#[derive(Debug)]
+pub struct IncompatibleSchemaVersion {
+    found: (u16, u16),
+    supported: (u16, u16),
+}
+
+impl fmt::Display for IncompatibleSchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "incompatible serialization format: file has schema version {}.{}, but this binary supports major version {}.x (minor {})",
+            self.found.0, self.found.1, self.supported.0, self.supported.1
+        )
+    }
+}
+
+impl std::error::Error for IncompatibleSchemaVersion {}
+
+/// The envelope header written by `serialize` right before the (possibly
+/// compressed) payload: a magic tag, the format the payload was encoded
+/// with, and the schema version it was written under.
+struct Header {
+    format: SerializationFormat,
+    version: (u16, u16),
+}
+
+fn write_header<W>(
+    writer: &mut W,
+    format: SerializationFormat,
+) -> Result<(), Report>
+where
+    W: std::io::Write,
+{
+    let format_byte: u8 = match format {
+        SerializationFormat::Bincode => 0,
+        SerializationFormat::Json => 1,
+    };
+    writer
+        .write_all(&ENVELOPE_MAGIC)
+        .wrap_err("serialize write envelope magic")?;
+    writer
+        .write_all(&[format_byte])
+        .wrap_err("serialize write envelope format")?;
+    writer
+        .write_all(&SCHEMA_VERSION.0.to_le_bytes())
+        .wrap_err("serialize write envelope major version")?;
+    writer
+        .write_all(&SCHEMA_VERSION.1.to_le_bytes())
+        .wrap_err("serialize write envelope minor version")?;
+    Ok(())
+}
+
+/// Reads and validates the envelope header, if there is one. Returns `None`
+/// without consuming anything when `reader` doesn't start with
+/// [`ENVELOPE_MAGIC`], so callers can fall back to treating the file as
+/// legacy (i.e. written before this header existed).
+fn read_header<R>(reader: &mut R) -> Result<Option<Header>, Report>
+where
+    R: std::io::BufRead,
+{
+    let peek = reader
+        .fill_buf()
+        .wrap_err("deserialize peek envelope magic")?;
+    if !peek.starts_with(&ENVELOPE_MAGIC) {
+        return Ok(None);
+    }
+    reader.consume(ENVELOPE_MAGIC.len());
+
+    let mut rest = [0u8; 5];
+    reader
+        .read_exact(&mut rest)
+        .wrap_err("deserialize read envelope header")?;
+    let format = match rest[0] {
+        0 => SerializationFormat::Bincode,
+        1 => SerializationFormat::Json,
+        other => eyre::bail!(
+            "deserialize: unknown format discriminant {} in envelope header",
+            other
+        ),
+    };
+    let major = u16::from_le_bytes([rest[1], rest[2]]);
+    let minor = u16::from_le_bytes([rest[3], rest[4]]);
+    Ok(Some(Header {
+        format,
+        version: (major, minor),
+    }))
+}
+
 // TODO maybe make this async
 pub fn serialize<T>(
     data: T,
     file: impl AsRef<Path>,
     format: SerializationFormat,
+    compression: Compression,
 ) -> Result<(), Report>
 where
     T: serde::Serialize,
@@ -122,39 +342,219 @@ where
     // if the file does not exist it will be created, otherwise truncated
     let file = std::fs::File::create(file).wrap_err("serialize create file")?;
     // create a buf writer
-    let buf = std::io::BufWriter::new(file);
-    // and try to serialize
+    let mut buf = std::io::BufWriter::new(file);
+    // stamp the envelope header (uncompressed, so it can always be read
+    // without knowing `compression` up front) before the payload
+    write_header(&mut buf, format)?;
+    // wrap it in a compressing encoder (if any) and serialize through that,
+    // finishing the encoder afterwards so its trailer gets flushed
+    match compression {
+        Compression::None => {
+            serialize_into(&mut buf, format, &data)?;
+        }
+        Compression::Gzip => {
+            let mut writer = flate2::write::GzEncoder::new(
+                buf,
+                flate2::Compression::default(),
+            );
+            serialize_into(&mut writer, format, &data)?;
+            writer.finish().wrap_err("serialize finish gzip encoder")?;
+        }
+        Compression::Zstd(level) => {
+            let mut writer = zstd::stream::write::Encoder::new(buf, level)
+                .wrap_err("serialize create zstd encoder")?;
+            serialize_into(&mut writer, format, &data)?;
+            writer.finish().wrap_err("serialize finish zstd encoder")?;
+        }
+    }
+    Ok(())
+}
+
+fn serialize_into<W>(
+    writer: W,
+    format: SerializationFormat,
+    data: &impl serde::Serialize,
+) -> Result<(), Report>
+where
+    W: std::io::Write,
+{
     match format {
         SerializationFormat::Bincode => {
-            bincode::serialize_into(buf, &data).wrap_err("serialize")?
+            bincode::serialize_into(writer, data).wrap_err("serialize")
         }
         SerializationFormat::Json => {
-            serde_json::to_writer(buf, &data).wrap_err("serialize")?
+            serde_json::to_writer(writer, data).wrap_err("serialize")
         }
     }
-    Ok(())
 }
 
 // TODO maybe make this async
 pub fn deserialize<T>(
     file: impl AsRef<Path>,
-    format: SerializationFormat,
+    legacy_format: SerializationFormat,
 ) -> Result<T, Report>
 where
     T: serde::de::DeserializeOwned,
 {
     // open the file in read-only
     let file = std::fs::File::open(file).wrap_err("deserialize open file")?;
-    // create a buf reader
-    let buf = std::io::BufReader::new(file);
-    // and try to deserialize
-    let data = match format {
+    let mut buf = std::io::BufReader::new(file);
+
+    // read the envelope header, if any; a file with no header predates this
+    // versioning scheme, so `legacy_format` is the caller's best guess at
+    // what it was written with
+    let format = match read_header(&mut buf)? {
+        Some(header) => {
+            let (major, minor) = header.version;
+            if major != SCHEMA_VERSION.0 {
+                return Err(Report::new(IncompatibleSchemaVersion {
+                    found: header.version,
+                    supported: SCHEMA_VERSION,
+                }));
+            }
+            if minor != SCHEMA_VERSION.1 {
+                tracing::warn!(
+                    "deserialize: reading a file written with schema version \
+                     {}.{}, this binary defaults to {}.{}",
+                    major,
+                    minor,
+                    SCHEMA_VERSION.0,
+                    SCHEMA_VERSION.1,
+                );
+            }
+            header.format
+        }
+        None => legacy_format,
+    };
+
+    // peek at the payload's first bytes (without consuming them) to
+    // autodetect whether it was compressed, and with what; copy (not just
+    // borrow) them so `buf` is free to be moved into a decoder right below
+    let magic: Vec<u8> = buf
+        .fill_buf()
+        .wrap_err("deserialize peek magic bytes")?
+        .to_vec();
+    let data = if magic.starts_with(&GZIP_MAGIC) {
+        let reader = flate2::read::GzDecoder::new(buf);
+        deserialize_from(reader, format)?
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        let reader = zstd::stream::read::Decoder::new(buf)
+            .wrap_err("deserialize create zstd decoder")?;
+        deserialize_from(reader, format)?
+    } else {
+        deserialize_from(buf, format)?
+    };
+    Ok(data)
+}
+
+fn deserialize_from<R, T>(
+    reader: R,
+    format: SerializationFormat,
+) -> Result<T, Report>
+where
+    R: std::io::Read,
+    T: serde::de::DeserializeOwned,
+{
+    match format {
         SerializationFormat::Bincode => {
-            bincode::deserialize_from(buf).wrap_err("deserialize")?
+            bincode::deserialize_from(reader).wrap_err("deserialize")
         }
         SerializationFormat::Json => {
-            serde_json::from_reader(buf).wrap_err("deserialize")?
+            serde_json::from_reader(reader).wrap_err("deserialize")
         }
-    };
-    Ok(data)
+    }
+}
+
+/// Async counterpart to `serialize`. The `exp`/`testbed` modules coordinate
+/// many AWS/baremetal machines concurrently on the same runtime, so blocking
+/// an executor thread on disk I/O while collecting results would stall
+/// unrelated work; this keeps file I/O on the async runtime while the
+/// CPU-bound encoding step runs on the blocking pool instead.
+///
+/// Unlike `serialize`, writes only the encoded payload: no envelope header
+/// and no compression. `deserialize` reads such a file through its legacy
+/// (headerless) path, given the same `format`.
+#[cfg(feature = "exp")]
+pub async fn serialize_async<T>(
+    data: T,
+    file: impl AsRef<Path>,
+    format: SerializationFormat,
+) -> Result<(), Report>
+where
+    T: serde::Serialize + Send + 'static,
+{
+    // encode on the blocking pool: this is the CPU-bound part
+    let bytes =
+        tokio::task::spawn_blocking(move || encode(&data, format))
+            .await
+            .wrap_err("serialize_async spawn_blocking")??;
+
+    // write the encoded bytes out without leaving the async runtime
+    let file = tokio::fs::File::create(file)
+        .await
+        .wrap_err("serialize_async create file")?;
+    let mut buf = tokio::io::BufWriter::new(file);
+    buf.write_all(&bytes)
+        .await
+        .wrap_err("serialize_async write")?;
+    buf.flush().await.wrap_err("serialize_async flush")?;
+    Ok(())
+}
+
+#[cfg(feature = "exp")]
+fn encode(
+    data: &impl serde::Serialize,
+    format: SerializationFormat,
+) -> Result<Vec<u8>, Report> {
+    match format {
+        SerializationFormat::Bincode => {
+            bincode::serialize(data).wrap_err("serialize")
+        }
+        SerializationFormat::Json => {
+            serde_json::to_vec(data).wrap_err("serialize")
+        }
+    }
+}
+
+/// Async counterpart to `deserialize`; see `serialize_async` for why. Reads
+/// only files in `serialize_async`'s form (a bare, uncompressed payload in
+/// `format`): it doesn't parse an envelope header or decompress, so a file
+/// written by `serialize` is read with `deserialize` instead.
+#[cfg(feature = "exp")]
+pub async fn deserialize_async<T>(
+    file: impl AsRef<Path>,
+    format: SerializationFormat,
+) -> Result<T, Report>
+where
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    // read the whole file without leaving the async runtime
+    let file = tokio::fs::File::open(file)
+        .await
+        .wrap_err("deserialize_async open file")?;
+    let mut buf = tokio::io::BufReader::new(file);
+    let mut bytes = Vec::new();
+    buf.read_to_end(&mut bytes)
+        .await
+        .wrap_err("deserialize_async read")?;
+
+    // decode on the blocking pool: this is the CPU-bound part
+    tokio::task::spawn_blocking(move || decode(&bytes, format))
+        .await
+        .wrap_err("deserialize_async spawn_blocking")?
+}
+
+#[cfg(feature = "exp")]
+fn decode<T>(bytes: &[u8], format: SerializationFormat) -> Result<T, Report>
+where
+    T: serde::de::DeserializeOwned,
+{
+    match format {
+        SerializationFormat::Bincode => {
+            bincode::deserialize(bytes).wrap_err("deserialize")
+        }
+        SerializationFormat::Json => {
+            serde_json::from_slice(bytes).wrap_err("deserialize")
+        }
+    }
 }
@@ -0,0 +1,172 @@
+use crate::{FantochFeature, Protocol, RunMode, Testbed};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Which protocol, testbed, and run mode a benchmark should use, plus any
+/// `fantoch` feature flags it should be compiled with.
+///
+/// Load one with [`RunProfile::load`] instead of constructing it directly:
+/// it merges, in increasing priority, built-in defaults, an optional TOML
+/// config file, `FANTOCH_*` environment variables, and explicit CLI
+/// overrides, so a benchmark described in a checked-in TOML file can still
+/// be tweaked at launch (e.g. `FANTOCH_PROTOCOL=newt_atomic`, or a CLI
+/// `--run-mode flamegraph` the caller's own `clap` parsing feeds in as an
+/// override) without recompiling.
+#[derive(Debug, Clone)]
+pub struct RunProfile {
+    pub protocol: Protocol,
+    pub testbed: Testbed,
+    pub run_mode: RunMode,
+    pub features: Vec<FantochFeature>,
+}
+
+impl RunProfile {
+    /// Loads a `RunProfile`, merging layers in increasing priority:
+    /// built-in defaults, `toml_file` (if `Some`), `FANTOCH_*` environment
+    /// variables, and finally `overrides`. Returns an error naming the
+    /// missing/invalid field if, after every layer, `protocol` or `testbed`
+    /// still isn't set, or if a layer couldn't be parsed.
+    pub fn load(
+        toml_file: Option<impl AsRef<Path>>,
+        overrides: RunProfileOverrides,
+    ) -> Result<Self, Report> {
+        let mut profile = PartialRunProfile::builtin_defaults();
+        if let Some(toml_file) = toml_file {
+            profile =
+                profile.merge(PartialRunProfile::from_toml_file(toml_file)?);
+        }
+        profile = profile.merge(PartialRunProfile::from_env()?);
+        profile = profile.merge(overrides.into());
+        profile.finish()
+    }
+}
+
+/// Explicit per-field overrides, meant to be filled in by the caller from
+/// already-parsed CLI flags (in the style of `fantoch_ps`'s `bin` crates,
+/// which parse their own `clap::App`) and handed to [`RunProfile::load`] as
+/// its highest-priority layer.
+#[derive(Debug, Clone, Default)]
+pub struct RunProfileOverrides {
+    pub protocol: Option<Protocol>,
+    pub testbed: Option<Testbed>,
+    pub run_mode: Option<RunMode>,
+    pub features: Option<Vec<FantochFeature>>,
+}
+
+impl From<RunProfileOverrides> for PartialRunProfile {
+    fn from(overrides: RunProfileOverrides) -> Self {
+        Self {
+            protocol: overrides.protocol,
+            testbed: overrides.testbed,
+            run_mode: overrides.run_mode,
+            features: overrides.features,
+        }
+    }
+}
+
+/// Same shape as `RunProfile`, but every field is optional, since a single
+/// layer (a config file, a handful of environment variables, a couple of
+/// CLI flags) will usually only ever specify a subset of fields. Merging
+/// layers from lowest to highest priority and then validating with
+/// [`PartialRunProfile::finish`] produces a complete `RunProfile`.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PartialRunProfile {
+    protocol: Option<Protocol>,
+    testbed: Option<Testbed>,
+    run_mode: Option<RunMode>,
+    features: Option<Vec<FantochFeature>>,
+}
+
+impl PartialRunProfile {
+    fn builtin_defaults() -> Self {
+        Self {
+            protocol: None,
+            testbed: None,
+            run_mode: Some(RunMode::default()),
+            features: Some(Vec::new()),
+        }
+    }
+
+    /// Parses a TOML config file, e.g.:
+    /// ```toml
+    /// protocol = "newt_atomic"
+    /// testbed = "aws"
+    /// run_mode = "release"
+    /// features = ["timing"]
+    /// ```
+    fn from_toml_file(file: impl AsRef<Path>) -> Result<Self, Report> {
+        let contents =
+            std::fs::read_to_string(file.as_ref()).wrap_err_with(|| {
+                format!("read run profile file {}", file.as_ref().display())
+            })?;
+        toml::from_str(&contents).wrap_err_with(|| {
+            format!("parse run profile file {}", file.as_ref().display())
+        })
+    }
+
+    /// Reads `FANTOCH_PROTOCOL`, `FANTOCH_TESTBED`, and `FANTOCH_RUN_MODE`,
+    /// if set. `features` has no environment variable counterpart, as a
+    /// single env var doesn't lend itself to a list of flags.
+    fn from_env() -> Result<Self, Report> {
+        Ok(Self {
+            protocol: parse_env("FANTOCH_PROTOCOL")?,
+            testbed: parse_env("FANTOCH_TESTBED")?,
+            run_mode: parse_env("FANTOCH_RUN_MODE")?,
+            features: None,
+        })
+    }
+
+    /// Overlays `other` on top of `self`: any field `other` sets wins, and
+    /// `self`'s value is kept otherwise.
+    fn merge(self, other: Self) -> Self {
+        Self {
+            protocol: other.protocol.or(self.protocol),
+            testbed: other.testbed.or(self.testbed),
+            run_mode: other.run_mode.or(self.run_mode),
+            features: other.features.or(self.features),
+        }
+    }
+
+    fn finish(self) -> Result<RunProfile, Report> {
+        Ok(RunProfile {
+            protocol: self
+                .protocol
+                .ok_or_else(|| missing_field("protocol"))?,
+            testbed: self.testbed.ok_or_else(|| missing_field("testbed"))?,
+            run_mode: self.run_mode.unwrap_or_default(),
+            features: self.features.unwrap_or_default(),
+        })
+    }
+}
+
+fn missing_field(field: &str) -> Report {
+    color_eyre::eyre::eyre!(
+        "run profile is missing required field `{}`: set it in the TOML \
+         config file, via the corresponding FANTOCH_* environment variable, \
+         or with a CLI override",
+        field
+    )
+}
+
+/// Reads environment variable `var`, if set, and parses it as `T`. Returns
+/// `Ok(None)` when the variable isn't set, so this layers cleanly with the
+/// other (also optional) config layers.
+fn parse_env<T>(var: &str) -> Result<Option<T>, Report>
+where
+    T: std::str::FromStr<Err = Report>,
+{
+    match std::env::var(var) {
+        Ok(value) => {
+            let parsed = value.parse().wrap_err_with(|| {
+                format!("parse environment variable {}", var)
+            })?;
+            Ok(Some(parsed))
+        }
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(err) => {
+            Err(err).wrap_err_with(|| format!("read environment variable {}", var))
+        }
+    }
+}
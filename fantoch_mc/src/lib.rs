@@ -1,17 +1,23 @@
-use fantoch::command::Command;
+use fantoch::client::{Client, Workload};
+use fantoch::command::{Command, CommandResult};
 use fantoch::config::Config;
 use fantoch::executor::{Executor, ExecutorResult};
-use fantoch::id::ProcessId;
+use fantoch::id::{ClientId, ProcessId, Rifl};
 use fantoch::protocol::{Action, Protocol};
 use fantoch::time::RunTime;
 use fantoch::util;
-use stateright::actor::{Actor, Event, Id, InitIn, NextIn, Out};
+use stateright::actor::{Actor, ActorModel, Event, Id, InitIn, NextIn, Out};
+use stateright::{Expectation, Property};
 use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
+use std::time::Duration;
 
 pub struct ProtocolActor<P: Protocol> {
     config: Config,
-    topology: HashMap<ProcessId, Vec<ProcessId>>,
+    // the (possibly incomplete, possibly asymmetric) set of processes a
+    // process knows about before its run even starts; the rest are learned
+    // through the `KV::GetAddr`/`KV::Addr` gossip exchange
+    seeds: HashMap<ProcessId, Vec<ProcessId>>,
     _phantom: PhantomData<P>,
 }
 
@@ -19,25 +25,26 @@ impl<P> ProtocolActor<P>
 where
     P: Protocol,
 {
-    pub fn new(
-        config: Config,
-        topology: HashMap<ProcessId, Vec<ProcessId>>,
-    ) -> Self {
-        Self::check_topology(config.n(), topology.clone());
+    pub fn new(config: Config, seeds: HashMap<ProcessId, Vec<ProcessId>>) -> Self {
+        Self::check_seeds(config.n(), &seeds);
         Self {
             config,
-            topology,
+            seeds,
             _phantom: PhantomData,
         }
     }
 
-    fn check_topology(n: usize, topology: HashMap<ProcessId, Vec<ProcessId>>) {
+    fn check_seeds(n: usize, seeds: &HashMap<ProcessId, Vec<ProcessId>>) {
         let ids = Self::usort(util::process_ids(n));
-        let keys = Self::usort(topology.keys().cloned());
-        assert_eq!(ids, keys);
-        for peers in topology.values() {
-            let peers = Self::usort(peers.iter().cloned());
-            assert_eq!(ids, peers);
+        let keys = Self::usort(seeds.keys().cloned());
+        assert_eq!(
+            ids, keys,
+            "every process must have a (possibly empty) seed list"
+        );
+        for peers in seeds.values() {
+            for peer in peers {
+                assert!(ids.contains(peer), "seed {} is not a known process", peer);
+            }
         }
     }
 
@@ -54,14 +61,138 @@ where
 
 #[derive(Clone)]
 pub struct ProtocolActorState<P: Protocol> {
+    config: Config,
     protocol: P,
     executor: <P as Protocol>::Executor,
+    // periodic events requested by `P::new`, along with the period they were
+    // registered with; carried in the state (instead of on `ProtocolActor`
+    // itself) so that stateright's state-space exploration can fork/restore
+    // them just like any other piece of actor state
+    periodic_events: Vec<(P::PeriodicEvent, Duration)>,
+    // maps the `Rifl` of a command we're still working on to the `Id` of the
+    // `ClientActor` that submitted it, so its eventual `CommandResult` can be
+    // routed back once the executor makes it `Ready`
+    pending_clients: HashMap<Rifl, Id>,
+    discovery: Discovery<P>,
+    membership: MembershipState,
+}
+
+/// The bootstrap/discovery phase, addr-style: a process starts out only
+/// knowing its seeds and gossips `GetAddr`/`Addr` with them until it has
+/// learned every member, at which point it calls `protocol.discover` and
+/// becomes `Ready`.
+#[derive(Clone)]
+enum Discovery<P: Protocol> {
+    Discovering {
+        known: HashSet<ProcessId>,
+        // protocol/client messages that arrived before discovery completed;
+        // replayed, in order, the moment we become `Ready`
+        buffered: Vec<Buffered<P>>,
+    },
+    Ready,
+}
+
+#[derive(Clone)]
+enum Buffered<P: Protocol> {
+    Internal(ProcessId, P::Message),
+    Access(Id, Command),
+    Suspect(ProcessId, Phase),
+    Alert(ProcessId, ProcessId, Phase),
 }
 
 #[derive(Clone, Debug)]
 pub enum KV<M> {
+    GetAddr,
+    Addr(Vec<ProcessId>),
     Access(Command),
     Internal(M),
+    Result(CommandResult),
+    // a (possibly exogenous) report that `subject`'s liveness has changed,
+    // delivered to one of `subject`'s ring observers
+    Suspect(ProcessId, Phase),
+    // an observer's signed take on a subject's liveness, gossiped to the
+    // whole view so every process can aggregate the same alerts
+    Alert(ProcessId, ProcessId, Phase),
+}
+
+/// A membership subsystem failure detector's view of one process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Up,
+    Down,
+}
+
+/// Rapid-style multi-observer cut detection: every process is watched by a
+/// fixed set of `k` ring observers, and a liveness change is only acted on
+/// once at least `l` of them have alerted on it and that alerted set has
+/// stopped growing for a whole round of alert delivery (a "stable cut").
+/// Driving membership off a quorum of independent observers (instead of a
+/// single process's local suspicion) is what lets every process converge on
+/// the same view even when alerts race or conflict.
+#[derive(Clone)]
+struct MembershipState {
+    k: usize,
+    l: usize,
+    view: Vec<ProcessId>,
+    // subject -> observer -> alerted phase
+    alerts: HashMap<ProcessId, HashMap<ProcessId, Phase>>,
+    // the quorum-reaching cut as of the last alert, used to detect that it
+    // has stopped growing
+    last_cut: HashMap<ProcessId, Phase>,
+}
+
+impl MembershipState {
+    fn new(n: usize) -> Self {
+        // an expander-like ring only needs a handful of observers per
+        // subject; more doesn't buy much extra resilience for the added
+        // alert traffic
+        let k = n.saturating_sub(1).min(3);
+        let l = k / 2 + 1;
+        Self {
+            k,
+            l,
+            view: Vec::new(),
+            alerts: HashMap::new(),
+            last_cut: HashMap::new(),
+        }
+    }
+}
+
+/// The `k` processes immediately following `subject` on the id ring formed
+/// by `view`; every one of them independently monitors `subject`.
+fn observers(view: &[ProcessId], subject: ProcessId, k: usize) -> Vec<ProcessId> {
+    let others: Vec<_> = view.iter().cloned().filter(|&id| id != subject).collect();
+    let start = others
+        .iter()
+        .position(|&id| id > subject)
+        .unwrap_or(0);
+    others
+        .into_iter()
+        .cycle()
+        .skip(start)
+        .take(k)
+        .collect()
+}
+
+/// The subjects whose alerts have reached the `l`-observer quorum, along
+/// with the phase they were alerted into.
+fn quorum_cut(
+    alerts: &HashMap<ProcessId, HashMap<ProcessId, Phase>>,
+    l: usize,
+) -> HashMap<ProcessId, Phase> {
+    alerts
+        .iter()
+        .filter_map(|(subject, observed)| {
+            let mut counts = HashMap::new();
+            for phase in observed.values() {
+                *counts.entry(*phase).or_insert(0usize) += 1;
+            }
+            counts
+                .into_iter()
+                .find(|(_, count)| *count >= l)
+                .map(|(phase, _)| (*subject, phase))
+        })
+        .collect()
 }
 
 fn to_process_id(id: Id) -> ProcessId {
@@ -80,75 +211,372 @@ where
     type State = ProtocolActorState<P>;
 
     fn init(i: InitIn<Self>, o: &mut Out<Self>) {
+        let (state, period, gossip) = Self::init_state(i.id, i.context);
+        if let Some(period) = period {
+            o.set_timer(period..period);
+        }
+        for (to, msg) in gossip {
+            o.send(to, &msg);
+        }
+        o.set_state(state);
+    }
+
+    fn next(i: NextIn<Self>, o: &mut Out<Self>) {
+        let mut state = i.state.clone();
+        let (to_sends, replies, gossip, rearm) = Self::step(i.event, &mut state);
+
+        // re-arm the timer so periodic events keep firing
+        if let Some(period) = rearm {
+            o.set_timer(period..period);
+        }
+
+        // send new protocol messages
+        for (recipients, msg) in to_sends {
+            let recipients: Vec<_> = recipients.into_iter().map(from_process_id).collect();
+            o.broadcast(&recipients, &KV::Internal(msg));
+        }
+
+        // reply to whichever clients have a command ready
+        for (client, cmd_result) in replies {
+            o.send(client, &KV::Result(cmd_result));
+        }
+
+        // answer/forward any addr gossip
+        for (to, msg) in gossip {
+            o.send(to, &msg);
+        }
+
+        // set new protocol state
+        o.set_state(state);
+    }
+}
+
+impl<P> ProtocolActor<P>
+where
+    P: Protocol,
+{
+    /// Builds the initial state for `actor` running as `id`: a
+    /// `GetAddr` is sent to every seed, and discovery is already `Ready`
+    /// if the seeds alone happen to cover every member. Returns the state,
+    /// the timer period to arm (if any periodic event was requested), and
+    /// the gossip to send. Factored out of `Actor::init` so that
+    /// [`TestActor`] can build the same state without going through a
+    /// second, distinct `Out`.
+    fn init_state(
+        id: Id,
+        actor: &ProtocolActor<P>,
+    ) -> (
+        ProtocolActorState<P>,
+        Option<Duration>,
+        Vec<(Id, KV<P::Message>)>,
+    ) {
         // fetch id and config
-        let process_id: ProcessId = usize::from(i.id) as ProcessId;
-        let config = i.context.config;
+        let process_id: ProcessId = usize::from(id) as ProcessId;
+        let config = actor.config;
 
         // our ids range from 1..n
         assert!(process_id > 0);
 
         // create protocol
         let (mut protocol, periodic_events) = P::new(process_id, config);
-
-        if !periodic_events.is_empty() {
-            todo!("schedule periodic events: {:?}", periodic_events);
-        }
-
-        // discover peers
-        let peers = i
-            .context
-            .topology
-            .get(&process_id)
-            .cloned()
-            .expect("each process should have a set of peers");
-        protocol.discover(peers);
+        let period = Self::timer_period(&periodic_events);
 
         // create executor
         let executor = <<P as Protocol>::Executor>::new(process_id, config);
 
-        // set actor state
-        let state = ProtocolActorState { protocol, executor };
-        o.set_state(state);
+        // start discovery from our seeds
+        let seeds = actor.seeds.get(&process_id).cloned().unwrap_or_default();
+        let mut known: HashSet<_> = seeds.iter().cloned().collect();
+        known.insert(process_id);
+        let gossip = seeds
+            .into_iter()
+            .map(|seed| (from_process_id(seed), KV::GetAddr))
+            .collect();
+
+        let mut membership = MembershipState::new(config.n());
+
+        let discovery = if known.len() == config.n() {
+            let peers = Self::sorted(known);
+            protocol.discover(peers.clone());
+            membership.view = peers;
+            Discovery::Ready
+        } else {
+            Discovery::Discovering {
+                known,
+                buffered: Vec::new(),
+            }
+        };
+
+        let state = ProtocolActorState {
+            config,
+            protocol,
+            executor,
+            periodic_events,
+            pending_clients: HashMap::new(),
+            discovery,
+            membership,
+        };
+        (state, period, gossip)
     }
 
-    fn next(i: NextIn<Self>, o: &mut Out<Self>) {
-        // get current protocol state
-        let mut state = i.state.clone();
+    /// Handles a single stateright `Event` against `state`, returning the
+    /// protocol messages to broadcast, the `CommandResult`s ready to be
+    /// routed back to their submitting clients, the addr gossip to send,
+    /// and the timer period to re-arm (on a `Timeout`). Factored out of
+    /// `Actor::next` for the same reason as `init_state`.
+    #[must_use]
+    fn step(
+        event: Event<KV<P::Message>>,
+        state: &mut ProtocolActorState<P>,
+    ) -> (
+        Vec<(HashSet<ProcessId>, P::Message)>,
+        Vec<(Id, CommandResult)>,
+        Vec<(Id, KV<P::Message>)>,
+        Option<Duration>,
+    ) {
+        let mut replies = Vec::new();
+        let mut gossip = Vec::new();
+        let mut rearm = None;
 
-        // get msg received
-        let Event::Receive(from, msg) = i.event;
-        let from = to_process_id(from);
+        let to_sends = match event {
+            Event::Receive(from, msg) => match msg {
+                KV::GetAddr => {
+                    gossip.push((from, KV::Addr(Self::known_ids(state))));
+                    Vec::new()
+                }
+                KV::Addr(ids) => Self::handle_addr(ids, state, &mut replies, &mut gossip),
+                KV::Suspect(subject, phase) => {
+                    if Self::is_ready(state) {
+                        gossip.extend(Self::handle_suspect(subject, phase, state));
+                    } else {
+                        Self::buffer(state, Buffered::Suspect(subject, phase));
+                    }
+                    Vec::new()
+                }
+                KV::Alert(observer, subject, phase) => {
+                    if Self::is_ready(state) {
+                        Self::handle_alert(observer, subject, phase, state);
+                    } else {
+                        Self::buffer(state, Buffered::Alert(observer, subject, phase));
+                    }
+                    Vec::new()
+                }
+                KV::Access(cmd) => {
+                    if Self::is_ready(state) {
+                        Self::handle_submit(from, cmd, state, &mut replies)
+                    } else {
+                        Self::buffer(state, Buffered::Access(from, cmd));
+                        Vec::new()
+                    }
+                }
+                KV::Internal(msg) => {
+                    let from = to_process_id(from);
+                    if Self::is_ready(state) {
+                        Self::handle_msg(from, msg, state, &mut replies)
+                    } else {
+                        Self::buffer(state, Buffered::Internal(from, msg));
+                        Vec::new()
+                    }
+                }
+                KV::Result(_) => {
+                    unreachable!("a process never receives its own replies")
+                }
+            },
+            Event::Timeout => {
+                // re-arm regardless: we still want to be driven once
+                // discovery completes
+                rearm = Self::timer_period(&state.periodic_events);
+                if Self::is_ready(state) {
+                    Self::handle_timeout(state, &mut replies)
+                } else {
+                    Vec::new()
+                }
+            }
+        };
+
+        (to_sends, replies, gossip, rearm)
+    }
+
+    fn is_ready(state: &ProtocolActorState<P>) -> bool {
+        matches!(state.discovery, Discovery::Ready)
+    }
+
+    /// The ids `state` currently knows about: every member once `Ready`,
+    /// otherwise whatever's been learned so far.
+    fn known_ids(state: &ProtocolActorState<P>) -> Vec<ProcessId> {
+        match &state.discovery {
+            Discovery::Discovering { known, .. } => Self::sorted(known.clone()),
+            Discovery::Ready => util::process_ids(state.config.n()).collect(),
+        }
+    }
+
+    fn sorted(ids: HashSet<ProcessId>) -> Vec<ProcessId> {
+        let mut ids: Vec<_> = ids.into_iter().collect();
+        ids.sort();
+        ids
+    }
 
-        // handle msg
-        let to_sends = match msg {
-            KV::Access(cmd) => Self::handle_submit(cmd, &mut state),
-            KV::Internal(msg) => Self::handle_msg(from, msg, &mut state),
+    fn buffer(state: &mut ProtocolActorState<P>, item: Buffered<P>) {
+        match &mut state.discovery {
+            Discovery::Discovering { buffered, .. } => buffered.push(item),
+            Discovery::Ready => unreachable!("buffering only happens while discovering"),
+        }
+    }
+
+    /// Merges `ids` into what we've learned so far and, once every member
+    /// is known, calls `protocol.discover`, seeds the membership view, moves
+    /// on to `Ready`, and replays anything buffered in the meantime.
+    #[must_use]
+    fn handle_addr(
+        ids: Vec<ProcessId>,
+        state: &mut ProtocolActorState<P>,
+        replies: &mut Vec<(Id, CommandResult)>,
+        gossip: &mut Vec<(Id, KV<P::Message>)>,
+    ) -> Vec<(HashSet<ProcessId>, P::Message)> {
+        let n = state.config.n();
+
+        let buffered = match &mut state.discovery {
+            Discovery::Discovering { known, buffered } => {
+                known.extend(ids);
+                if known.len() < n {
+                    return Vec::new();
+                }
+                std::mem::take(buffered)
+            }
+            Discovery::Ready => return Vec::new(),
         };
 
-        // send new messages
-        for (recipients, msg) in to_sends {
-            let recipients: Vec<_> =
-                recipients.into_iter().map(from_process_id).collect();
-            let msg = KV::Internal(msg);
-            o.broadcast(&recipients, &msg);
+        let peers = match &state.discovery {
+            Discovery::Discovering { known, .. } => Self::sorted(known.clone()),
+            Discovery::Ready => unreachable!(),
+        };
+        state.protocol.discover(peers.clone());
+        state.discovery = Discovery::Ready;
+        state.membership.view = peers;
+
+        let mut to_sends = Vec::new();
+        for item in buffered {
+            match item {
+                Buffered::Internal(from, msg) => {
+                    to_sends.extend(Self::handle_msg(from, msg, state, replies))
+                }
+                Buffered::Access(origin, cmd) => {
+                    to_sends.extend(Self::handle_submit(origin, cmd, state, replies))
+                }
+                Buffered::Suspect(subject, phase) => {
+                    gossip.extend(Self::handle_suspect(subject, phase, state))
+                }
+                Buffered::Alert(observer, subject, phase) => {
+                    Self::handle_alert(observer, subject, phase, state)
+                }
+            }
         }
+        to_sends
+    }
 
-        // set new protocol state
-        o.set_state(state);
+    /// Reacts to a liveness report on `subject`: if we're actually one of
+    /// its ring observers, record our own alert and gossip it to the whole
+    /// view so everyone aggregates the same evidence.
+    #[must_use]
+    fn handle_suspect(
+        subject: ProcessId,
+        phase: Phase,
+        state: &mut ProtocolActorState<P>,
+    ) -> Vec<(Id, KV<P::Message>)> {
+        let self_id = state.protocol.id();
+        if !observers(&state.membership.view, subject, state.membership.k).contains(&self_id) {
+            return Vec::new();
+        }
+        state
+            .membership
+            .alerts
+            .entry(subject)
+            .or_default()
+            .insert(self_id, phase);
+        state
+            .membership
+            .view
+            .iter()
+            .map(|&to| (from_process_id(to), KV::Alert(self_id, subject, phase)))
+            .collect()
+    }
+
+    /// Aggregates an observer's alert on `subject` and, once the set of
+    /// subjects with a quorum of alerts has stopped growing (a stable cut),
+    /// applies the membership change and re-invokes `protocol.discover`.
+    fn handle_alert(
+        observer: ProcessId,
+        subject: ProcessId,
+        phase: Phase,
+        state: &mut ProtocolActorState<P>,
+    ) {
+        state
+            .membership
+            .alerts
+            .entry(subject)
+            .or_default()
+            .insert(observer, phase);
+
+        let cut = quorum_cut(&state.membership.alerts, state.membership.l);
+        if cut.is_empty() {
+            state.membership.last_cut = cut;
+            return;
+        }
+        if cut != state.membership.last_cut {
+            state.membership.last_cut = cut;
+            return;
+        }
+
+        // the cut has stopped growing: commit it
+        for (&subject, &phase) in &cut {
+            match phase {
+                Phase::Down => state.membership.view.retain(|&id| id != subject),
+                Phase::Up => {
+                    if !state.membership.view.contains(&subject) {
+                        state.membership.view.push(subject);
+                    }
+                }
+            }
+            state.membership.alerts.remove(&subject);
+        }
+        state.membership.view.sort();
+        state.protocol.discover(state.membership.view.clone());
+        state.membership.last_cut = HashMap::new();
     }
-}
 
-impl<P> ProtocolActor<P>
-where
-    P: Protocol,
-{
     #[must_use]
     fn handle_submit(
+        origin: Id,
         cmd: Command,
         state: &mut ProtocolActorState<P>,
+        replies: &mut Vec<(Id, CommandResult)>,
     ) -> Vec<(HashSet<ProcessId>, P::Message)> {
+        state.pending_clients.insert(cmd.rifl(), origin);
         let actions = state.protocol.submit(None, cmd, &RunTime);
-        Self::handle_actions(actions, state)
+        Self::handle_actions(actions, state, replies)
+    }
+
+    /// Shortest period among the registered periodic events, i.e. the
+    /// cadence the single stateright timer is armed/re-armed with.
+    fn timer_period(periodic_events: &[(P::PeriodicEvent, Duration)]) -> Option<Duration> {
+        periodic_events.iter().map(|(_, period)| *period).min()
+    }
+
+    #[must_use]
+    fn handle_timeout(
+        state: &mut ProtocolActorState<P>,
+        replies: &mut Vec<(Id, CommandResult)>,
+    ) -> Vec<(HashSet<ProcessId>, P::Message)> {
+        // every periodic event shares the one timer, so dispatch all of
+        // them whenever it fires
+        let periodic_events = state.periodic_events.clone();
+        periodic_events
+            .into_iter()
+            .flat_map(|(event, _)| {
+                let actions = state.protocol.handle_event(event, &RunTime);
+                Self::handle_actions(actions, state, replies)
+            })
+            .collect()
     }
 
     #[must_use]
@@ -156,6 +584,7 @@ where
         from: ProcessId,
         msg: P::Message,
         state: &mut ProtocolActorState<P>,
+        replies: &mut Vec<(Id, CommandResult)>,
     ) -> Vec<(HashSet<ProcessId>, P::Message)> {
         // handle message
         let actions = state.protocol.handle(from, msg, &RunTime);
@@ -165,7 +594,13 @@ where
             for executor_result in state.executor.handle(execution_info) {
                 match executor_result {
                     ExecutorResult::Ready(cmd_result) => {
-                        todo!("send result to client: {:?}", cmd_result)
+                        // route the result back to whichever client
+                        // submitted it; if we have no record of it (e.g. the
+                        // command was injected directly at this process by a
+                        // test), there's no one to reply to
+                        if let Some(origin) = state.pending_clients.remove(&cmd_result.rifl()) {
+                            replies.push((origin, cmd_result));
+                        }
                     }
                     ExecutorResult::Partial(_, _, _) => {
                         panic!("executor result cannot be partial")
@@ -174,13 +609,14 @@ where
             }
         }
 
-        Self::handle_actions(actions, state)
+        Self::handle_actions(actions, state, replies)
     }
 
     #[must_use]
     fn handle_actions(
         actions: Vec<Action<P>>,
         state: &mut ProtocolActorState<P>,
+        replies: &mut Vec<(Id, CommandResult)>,
     ) -> Vec<(HashSet<ProcessId>, P::Message)> {
         // get the id of this process
         let process_id = state.protocol.id();
@@ -194,11 +630,8 @@ where
                         if target.remove(&process_id) {
                             // handle message locally, if message also to self,
                             // and remove self from target
-                            let mut to_sends = Self::handle_msg(
-                                process_id,
-                                msg.clone(),
-                                state,
-                            );
+                            let mut to_sends =
+                                Self::handle_msg(process_id, msg.clone(), state, replies);
                             to_sends.push((target, msg));
                             to_sends
                         } else {
@@ -207,7 +640,7 @@ where
                     }
                     Action::ToForward { msg } => {
                         // there's a single worker, so just handle it locally
-                        Self::handle_msg(process_id, msg, state)
+                        Self::handle_msg(process_id, msg, state, replies)
                     }
                 }
             })
@@ -215,6 +648,367 @@ where
     }
 }
 
+/// Drives `P` from the client side: issues `KV::Access(Command)`
+/// submissions (via the existing `fantoch::client::Client` bookkeeping) and
+/// moves on to the next command in its `Workload` once a result comes back.
+pub struct ClientActor<P> {
+    client_id: ClientId,
+    workload: Workload,
+    // the single process this client submits every command to; real clients
+    // pick the closest one, but a model-checked client has no notion of
+    // distance, so it's simply fixed at construction time
+    target: ProcessId,
+    _phantom: PhantomData<P>,
+}
+
+impl<P> ClientActor<P>
+where
+    P: Protocol,
+{
+    pub fn new(client_id: ClientId, workload: Workload, target: ProcessId) -> Self {
+        Self {
+            client_id,
+            workload,
+            target,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClientActorState {
+    client: Client,
+}
+
+impl<P> ClientActor<P>
+where
+    P: Protocol,
+{
+    /// Builds the initial client state and the first command it issues (if
+    /// its workload isn't empty). Factored out of `Actor::init` for the same
+    /// reason as `ProtocolActor::init_state`.
+    fn init_state(actor: &ClientActor<P>) -> (ClientActorState, Option<(ProcessId, Command)>) {
+        let mut client = Client::new(actor.client_id, actor.workload.clone());
+        client.discover(vec![actor.target]);
+        let next = client.next_cmd(&RunTime);
+        (ClientActorState { client }, next)
+    }
+
+    /// Handles a single stateright `Event` against `state`, returning the
+    /// next command to issue (if any).
+    fn step(
+        event: Event<KV<P::Message>>,
+        state: &mut ClientActorState,
+    ) -> Option<(ProcessId, Command)> {
+        match event {
+            Event::Receive(_, KV::Result(cmd_result)) => {
+                state.client.handle(cmd_result, &RunTime);
+                state.client.next_cmd(&RunTime)
+            }
+            Event::Receive(_, _) => {
+                unreachable!("a client only ever receives command results")
+            }
+            Event::Timeout => unreachable!("clients don't register a timer"),
+        }
+    }
+}
+
+impl<P> Actor for ClientActor<P>
+where
+    P: Protocol,
+{
+    type Msg = KV<<P as Protocol>::Message>;
+    type State = ClientActorState;
+
+    fn init(i: InitIn<Self>, o: &mut Out<Self>) {
+        let (state, next) = Self::init_state(i.context);
+        if let Some((target, cmd)) = next {
+            o.send(from_process_id(target), &KV::Access(cmd));
+        }
+        o.set_state(state);
+    }
+
+    fn next(i: NextIn<Self>, o: &mut Out<Self>) {
+        let mut state = i.state.clone();
+        let next = Self::step(i.event, &mut state);
+        if let Some((target, cmd)) = next {
+            o.send(from_process_id(target), &KV::Access(cmd));
+        }
+        o.set_state(state);
+    }
+}
+
+/// Combines the two roles that take part in a model run — the processes
+/// replicating `P`, and the clients driving them — behind a single `Actor`
+/// impl, since a stateright model explores a fixed set of actors that must
+/// all share one `Actor` type.
+#[derive(Clone)]
+pub enum TestActor<P: Protocol> {
+    Protocol(ProtocolActor<P>),
+    Client(ClientActor<P>),
+}
+
+#[derive(Clone)]
+pub enum TestActorState<P: Protocol> {
+    Protocol(ProtocolActorState<P>),
+    Client(ClientActorState),
+}
+
+impl<P> Actor for TestActor<P>
+where
+    P: Protocol,
+{
+    type Msg = KV<<P as Protocol>::Message>;
+    type State = TestActorState<P>;
+
+    fn init(i: InitIn<Self>, o: &mut Out<Self>) {
+        match i.context {
+            TestActor::Protocol(actor) => {
+                let (state, period, gossip) = ProtocolActor::init_state(i.id, actor);
+                if let Some(period) = period {
+                    o.set_timer(period..period);
+                }
+                for (to, msg) in gossip {
+                    o.send(to, &msg);
+                }
+                o.set_state(TestActorState::Protocol(state));
+            }
+            TestActor::Client(actor) => {
+                let (state, next) = ClientActor::init_state(actor);
+                if let Some((target, cmd)) = next {
+                    o.send(from_process_id(target), &KV::Access(cmd));
+                }
+                o.set_state(TestActorState::Client(state));
+            }
+        }
+    }
+
+    fn next(i: NextIn<Self>, o: &mut Out<Self>) {
+        match i.state.clone() {
+            TestActorState::Protocol(mut state) => {
+                let (to_sends, replies, gossip, rearm) = ProtocolActor::step(i.event, &mut state);
+                if let Some(period) = rearm {
+                    o.set_timer(period..period);
+                }
+                for (recipients, msg) in to_sends {
+                    let recipients: Vec<_> =
+                        recipients.into_iter().map(from_process_id).collect();
+                    o.broadcast(&recipients, &KV::Internal(msg));
+                }
+                for (client, cmd_result) in replies {
+                    o.send(client, &KV::Result(cmd_result));
+                }
+                for (to, msg) in gossip {
+                    o.send(to, &msg);
+                }
+                o.set_state(TestActorState::Protocol(state));
+            }
+            TestActorState::Client(mut state) => {
+                let next = ClientActor::step(i.event, &mut state);
+                if let Some((target, cmd)) = next {
+                    o.send(from_process_id(target), &KV::Access(cmd));
+                }
+                o.set_state(TestActorState::Client(state));
+            }
+        }
+    }
+}
+
+/// One observed client event: the invocation of `cmd`, or the eventual
+/// return of its result. Recorded in the order the model checker actually
+/// walked through them along a run, so position in the log doubles as
+/// real-time order for that run.
+#[derive(Clone, Debug)]
+pub enum HistoryEvent {
+    Invoke(Id, Command),
+    Return(Id, CommandResult),
+}
+
+/// A single, un-replicated (`n = 1`, `f = 0`) instance of `P`: with no one
+/// else to reconcile with, applying commands to it one at a time is
+/// definitionally linearizable, which makes it the reference to check
+/// real executions against.
+#[derive(Clone)]
+struct SequentialOracle<P: Protocol> {
+    state: ProtocolActorState<P>,
+}
+
+impl<P> SequentialOracle<P>
+where
+    P: Protocol,
+{
+    fn new() -> Self {
+        let config = Config::new(1, 0);
+        let (mut protocol, periodic_events) = P::new(1, config);
+        protocol.discover(vec![1]);
+        let executor = <<P as Protocol>::Executor>::new(1, config);
+        let mut membership = MembershipState::new(1);
+        membership.view = vec![1];
+        Self {
+            state: ProtocolActorState {
+                config,
+                protocol,
+                executor,
+                periodic_events,
+                pending_clients: HashMap::new(),
+                discovery: Discovery::Ready,
+                membership,
+            },
+        }
+    }
+
+    /// Applies `cmd` and returns the `CommandResult` it produced. A single,
+    /// non-faulty replica always resolves a command without waiting on
+    /// anyone else, so exactly one result always comes back.
+    #[must_use]
+    fn apply(&mut self, cmd: Command) -> CommandResult {
+        let mut replies = Vec::new();
+        // the origin `Id` is irrelevant here: we read the result straight
+        // out of `replies` instead of having it routed anywhere
+        ProtocolActor::<P>::handle_submit(Id::from(0), cmd, &mut self.state, &mut replies);
+        let (_, cmd_result) = replies
+            .pop()
+            .expect("a single replica always resolves a submitted command immediately");
+        cmd_result
+    }
+}
+
+/// Checks whether `history` is linearizable: is there a permutation of its
+/// completed operations that (a) keeps each client's own operations in
+/// their original order, (b) never reorders two operations whose windows in
+/// `history` didn't overlap, and (c) reproduces every observed
+/// `CommandResult` when replayed, one at a time, against a fresh
+/// `SequentialOracle`?
+pub fn is_linearizable<P: Protocol>(history: &[HistoryEvent]) -> bool {
+    // pair up each invocation with its matching return, keeping only
+    // completed operations: one still in flight when the run ended has no
+    // result to check and is simply left out of the search
+    let mut invoked = HashMap::new();
+    let mut completed = Vec::new();
+    for (index, event) in history.iter().enumerate() {
+        match event {
+            HistoryEvent::Invoke(id, cmd) => {
+                invoked.insert(*id, (index, cmd.clone()));
+            }
+            HistoryEvent::Return(id, result) => {
+                if let Some((start, cmd)) = invoked.remove(id) {
+                    completed.push((*id, cmd, result.clone(), start, index));
+                }
+            }
+        }
+    }
+
+    let mut oracle = SequentialOracle::<P>::new();
+    search(&mut completed, &mut oracle)
+}
+
+/// Recursively looks for a valid serialization of `pending`, mutating a
+/// clone of `oracle` as it commits to each candidate next operation.
+fn search<P: Protocol>(
+    pending: &mut Vec<(Id, Command, CommandResult, usize, usize)>,
+    oracle: &mut SequentialOracle<P>,
+) -> bool {
+    if pending.is_empty() {
+        return true;
+    }
+
+    for i in 0..pending.len() {
+        let (_, cmd, expected, start, _) = pending[i].clone();
+
+        // real-time precedence: `cmd` can only be serialized next if no
+        // other pending operation is known to have returned strictly before
+        // `cmd` was invoked (that one would have to come first instead);
+        // this includes the same client's earlier operations
+        let must_go_first = pending
+            .iter()
+            .enumerate()
+            .any(|(j, (_, _, _, _, other_end))| j != i && *other_end < start);
+        if must_go_first {
+            continue;
+        }
+
+        let mut candidate = oracle.clone();
+        if candidate.apply(cmd) == expected {
+            let removed = pending.remove(i);
+            if search(pending, &mut candidate) {
+                return true;
+            }
+            pending.insert(i, removed);
+        }
+    }
+
+    false
+}
+
+/// Appends an entry to `history` whenever a message carrying a command or
+/// its result is delivered, so that [`is_linearizable`] can later replay the
+/// whole run.
+fn record_history<P: Protocol>(
+    history: &Vec<HistoryEvent>,
+    src: Id,
+    dst: Id,
+    msg: &KV<P::Message>,
+) -> Option<Vec<HistoryEvent>> {
+    let event = match msg {
+        KV::Access(cmd) => HistoryEvent::Invoke(src, cmd.clone()),
+        KV::Result(cmd_result) => HistoryEvent::Return(dst, cmd_result.clone()),
+        KV::GetAddr | KV::Addr(_) | KV::Internal(_) | KV::Suspect(_, _) | KV::Alert(_, _, _) => {
+            return None
+        }
+    };
+    let mut history = history.clone();
+    history.push(event);
+    Some(history)
+}
+
+/// The model of an end-to-end run: the processes and clients as `TestActor`s,
+/// and the cross-client history used to check linearizability.
+pub type TestModel<P> = ActorModel<TestActor<P>, (), Vec<HistoryEvent>>;
+
+/// The property that turns this from a message-invariant checker into a
+/// genuine consistency model checker: every reachable history of client
+/// invocations and responses must be linearizable with respect to `P`'s
+/// key-value store semantics.
+pub fn linearizability_property<P: Protocol>() -> Property<TestModel<P>> {
+    Property::always("linearizable", |_, state| {
+        is_linearizable::<P>(&state.history)
+    })
+}
+
+/// Builds a model with one `ProtocolActor` per process in `seeds`, one
+/// `ClientActor` per process submitting `commands_per_client` commands to
+/// it, and the [`linearizability_property`] wired in. `seeds` doesn't need
+/// to be a complete, symmetric topology: the processes discover the rest of
+/// the membership themselves.
+pub fn model<P: Protocol>(
+    config: Config,
+    seeds: HashMap<ProcessId, Vec<ProcessId>>,
+    commands_per_client: usize,
+) -> TestModel<P> {
+    let mut processes: Vec<_> = seeds.keys().cloned().collect();
+    processes.sort();
+
+    let mut model = ActorModel::new((), Vec::new());
+    for _ in &processes {
+        model = model.actor(TestActor::Protocol(ProtocolActor::new(
+            config,
+            seeds.clone(),
+        )));
+    }
+    for (index, &target) in processes.iter().enumerate() {
+        let client_id = (processes.len() + index + 1) as ClientId;
+        // a single, contended key is the interesting case for a consistency
+        // checker: every command conflicts with every other
+        let workload = Workload::new(100, commands_per_client, 1);
+        model = model.actor(TestActor::Client(ClientActor::new(client_id, workload, target)));
+    }
+
+    let property = linearizability_property::<P>();
+    model
+        .record_msg_in(record_history::<P>)
+        .property(Expectation::Always, property.name, property.condition)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,10 +1019,50 @@ mod tests {
         let n = 3;
         let f = 1;
         let config = Config::new(n, f);
-        let mut topology = HashMap::new();
-        topology.insert(1, vec![1, 2, 3]);
-        topology.insert(2, vec![2, 3, 1]);
-        topology.insert(3, vec![3, 1, 2]);
-        let _ = ProtocolActor::<Basic>::new(config, topology);
+        let mut seeds = HashMap::new();
+        seeds.insert(1, vec![1, 2, 3]);
+        seeds.insert(2, vec![2, 3, 1]);
+        seeds.insert(3, vec![3, 1, 2]);
+        let _ = ProtocolActor::<Basic>::new(config, seeds);
+    }
+
+    #[test]
+    fn partial_seeds_are_accepted() {
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        // each process only knows one other process up front; the rest is
+        // learned through gossip once the model actually runs
+        let mut seeds = HashMap::new();
+        seeds.insert(1, vec![2]);
+        seeds.insert(2, vec![3]);
+        seeds.insert(3, vec![1]);
+        let _ = ProtocolActor::<Basic>::new(config, seeds);
+    }
+
+    #[test]
+    fn client_actor_targets_a_process() {
+        let workload = Workload::new(100, 1, 1);
+        let _ = ClientActor::<Basic>::new(4, workload, 1);
+    }
+
+    #[test]
+    fn observers_wrap_around_the_ring() {
+        let view = vec![1, 2, 3, 4, 5];
+        assert_eq!(observers(&view, 4, 3), vec![5, 1, 2]);
+    }
+
+    #[test]
+    fn quorum_cut_needs_l_matching_alerts() {
+        let mut alerts = HashMap::new();
+        let mut observed = HashMap::new();
+        observed.insert(1, Phase::Down);
+        observed.insert(2, Phase::Down);
+        alerts.insert(3, observed);
+
+        // below quorum: no cut yet
+        assert!(quorum_cut(&alerts, 3).is_empty());
+        // at quorum: subject 3 is cut, as down
+        assert_eq!(quorum_cut(&alerts, 2), HashMap::from([(3, Phase::Down)]));
     }
 }
@@ -1,23 +1,166 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
 use fantoch::planet::Region;
 use fantoch_exp::Protocol;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 
-pub struct PlotFmt;
+/// Visual style (region display names, per-`(protocol, f)` colors/hatches/
+/// markers, and per-protocol background colors) used when plotting.
+///
+/// Built with [`PlotFmt::load`] instead of constructed directly: it starts
+/// from [`PlotFmt::builtin_defaults`] (the values this module always used to
+/// hardcode) and overlays an optional TOML palette file on top, so a new AWS
+/// region or a new protocol can be styled by dropping a file next to the
+/// plotting script instead of editing this crate. A region the palette
+/// doesn't cover keeps its raw name, and a color, background color, hatch or
+/// marker it doesn't cover falls back to a value generated deterministically
+/// from the protocol/`f` involved, so plotting an unrecognized combination
+/// degrades to an ugly-but-stable style instead of panicking mid-experiment.
+/// `linewidth` isn't part of the palette and still panics for an `f` other
+/// than 1 or 2.
+#[derive(Debug, Clone)]
+pub struct PlotFmt {
+    regions: HashMap<String, String>,
+    colors: HashMap<(Protocol, usize), String>,
+    background_colors: HashMap<Protocol, String>,
+    hatches: HashMap<(Protocol, usize), String>,
+    markers: HashMap<(Protocol, usize), String>,
+}
 
 impl PlotFmt {
-    pub fn region_name(region: Region) -> &'static str {
-        match region.name().as_str() {
-            "ap-southeast-1" => "Singapore",
-            "ca-central-1" => "Canada",
-            "eu-west-1" => "Ireland",
-            "sa-east-1" => "S. Paulo", // São Paulo
-            "us-west-1" => "N. California", // Northern California
-            name => {
-                panic!("PlotFmt::region_name: name {} not supported!", name);
+    /// Loads a palette, overlaying `toml_file` (if `Some`) on top of
+    /// [`PlotFmt::builtin_defaults`]. Entries in `toml_file` override the
+    /// built-in value for the same key; anything it doesn't mention keeps
+    /// its built-in value.
+    pub fn load(toml_file: Option<impl AsRef<Path>>) -> Result<Self, Report> {
+        let mut palette = Self::builtin_defaults();
+        if let Some(toml_file) = toml_file {
+            palette.merge(PaletteOverlay::from_toml_file(toml_file)?);
+        }
+        Ok(palette)
+    }
+
+    /// The values this module hardcoded before palettes became data-driven.
+    pub fn builtin_defaults() -> Self {
+        let regions = [
+            ("ap-southeast-1", "Singapore"),
+            ("ca-central-1", "Canada"),
+            ("eu-west-1", "Ireland"),
+            ("sa-east-1", "S. Paulo"), // São Paulo
+            ("us-west-1", "N. California"), // Northern California
+        ]
+        .iter()
+        .map(|(name, label)| (name.to_string(), label.to_string()))
+        .collect();
+
+        use Protocol::*;
+        let colors = [
+            ((AtlasLocked, 1), "#27ae60"),
+            ((AtlasLocked, 2), "#16a085"),
+            ((EPaxosLocked, 1), "#444444"),
+            ((EPaxosLocked, 2), "#444444"),
+            ((FPaxos, 1), "#2980b9"),
+            ((FPaxos, 2), "#34495e"),
+            ((NewtAtomic, 1), "#f1c40f"),
+            ((NewtAtomic, 2), "#e67e22"),
+            ((NewtLocked, 1), "#3498db"),
+            ((NewtLocked, 2), "#2980b9"),
+            ((Basic, 1), "#444444"),
+            ((Basic, 2), "#444444"),
+        ]
+        .iter()
+        .map(|(key, color)| (*key, color.to_string()))
+        .collect();
+
+        let background_colors = [
+            (AtlasLocked, "#ecf0f1"),
+            (FPaxos, "#95a5a6"),
+            (NewtAtomic, "#353b48"),
+        ]
+        .iter()
+        .map(|(protocol, color)| (*protocol, color.to_string()))
+        .collect();
+
+        // Possible values: {'/', '\', '|', '-', '+', 'x', 'o', 'O', '.', '*'}
+        let hatches = [
+            ((FPaxos, 1), "/"),
+            ((FPaxos, 2), "\\"),
+            ((EPaxosLocked, 1), "//"),
+            ((EPaxosLocked, 2), "//"),
+            ((AtlasLocked, 1), "///"),
+            ((AtlasLocked, 2), "\\\\\\"),
+            ((NewtLocked, 1), "////"),
+            ((NewtLocked, 2), "\\\\\\\\"),
+            ((NewtAtomic, 1), "//////"),
+            ((NewtAtomic, 2), "\\\\\\\\\\\\"),
+            ((Basic, 1), "///////"),
+            ((Basic, 2), "\\\\\\\\\\\\\\"),
+        ]
+        .iter()
+        .map(|(key, hatch)| (*key, hatch.to_string()))
+        .collect();
+
+        // Possible values: https://matplotlib.org/3.1.1/api/markers_api.html#module-matplotlib.markers
+        let markers = [
+            ((AtlasLocked, 1), "o"),
+            ((AtlasLocked, 2), "s"),
+            ((EPaxosLocked, 1), "D"),
+            ((EPaxosLocked, 2), "D"),
+            ((FPaxos, 1), "+"),
+            ((FPaxos, 2), "x"),
+            ((NewtAtomic, 1), "v"),
+            ((NewtAtomic, 2), "^"),
+            ((NewtLocked, 1), ">"),
+            ((NewtLocked, 2), "<"),
+            ((Basic, 1), "p"),
+            ((Basic, 2), "P"),
+        ]
+        .iter()
+        .map(|(key, marker)| (*key, marker.to_string()))
+        .collect();
+
+        Self {
+            regions,
+            colors,
+            background_colors,
+            hatches,
+            markers,
+        }
+    }
+
+    fn merge(&mut self, overlay: PaletteOverlay) {
+        for entry in overlay.regions.unwrap_or_default() {
+            self.regions.insert(entry.name, entry.label);
+        }
+        for entry in overlay.styles.unwrap_or_default() {
+            let key = (entry.protocol, entry.f);
+            if let Some(color) = entry.color {
+                self.colors.insert(key, color);
+            }
+            if let Some(hatch) = entry.hatch {
+                self.hatches.insert(key, hatch);
+            }
+            if let Some(marker) = entry.marker {
+                self.markers.insert(key, marker);
             }
         }
+        for entry in overlay.backgrounds.unwrap_or_default() {
+            self.background_colors.insert(entry.protocol, entry.color);
+        }
+    }
+
+    pub fn region_name(&self, region: Region) -> String {
+        let name = region.name();
+        self.regions
+            .get(name.as_str())
+            .cloned()
+            .unwrap_or(name)
     }
 
-    pub fn protocol_name(protocol: Protocol) -> &'static str {
+    pub fn protocol_name(&self, protocol: Protocol) -> &'static str {
         match protocol {
             Protocol::AtlasLocked => "Atlas",
             Protocol::EPaxosLocked => "EPaxos",
@@ -28,102 +171,62 @@ impl PlotFmt {
         }
     }
 
-    pub fn label(protocol: Protocol, f: usize) -> String {
+    pub fn label(&self, protocol: Protocol, f: usize) -> String {
         match protocol {
-            Protocol::EPaxosLocked => Self::protocol_name(protocol).to_string(),
-            _ => format!("{} f = {}", Self::protocol_name(protocol), f),
+            Protocol::EPaxosLocked => self.protocol_name(protocol).to_string(),
+            _ => format!("{} f = {}", self.protocol_name(protocol), f),
         }
     }
 
-    pub fn color(protocol: Protocol, f: usize) -> String {
-        match (protocol, f) {
-            (Protocol::AtlasLocked, 1) => "#27ae60",
-            (Protocol::AtlasLocked, 2) => "#16a085",
-            // (Protocol::EPaxosLocked, _) => "#227093",
-            (Protocol::EPaxosLocked, _) => "#444444",
-            (Protocol::FPaxos, 1) => "#2980b9",
-            (Protocol::FPaxos, 2) => "#34495e",
-            (Protocol::NewtAtomic, 1) => "#f1c40f",
-            (Protocol::NewtAtomic, 2) => "#e67e22",
-            (Protocol::NewtLocked, 1) => "#3498db", // "#111111"
-            (Protocol::NewtLocked, 2) => "#2980b9", // "#333333"
-            (Protocol::Basic, _) => "#444444",
-            _ => panic!(
-                "PlotFmt::color: protocol = {:?} and f = {} combination not supported!",
-                protocol, f
-            ),
-        }.to_string()
+    pub fn color(&self, protocol: Protocol, f: usize) -> String {
+        self.colors
+            .get(&(protocol, f))
+            .cloned()
+            .unwrap_or_else(|| generated_hex_color(protocol, f))
     }
 
-    pub fn background_color(protocol: Protocol) -> String {
-        match protocol {
-            Protocol::AtlasLocked => "#ecf0f1",
-            Protocol::FPaxos => "#95a5a6",
-            Protocol::NewtAtomic => "#353b48",
-            _ => panic!(
-                "PlotFmt::background_color: protocol = {:?} not supported!",
-                protocol
-            ),
-        }
-        .to_string()
+    pub fn background_color(&self, protocol: Protocol) -> String {
+        self.background_colors
+            .get(&protocol)
+            .cloned()
+            .unwrap_or_else(|| generated_hex_color(protocol, 0))
     }
 
     // Possible values: {'/', '\', '|', '-', '+', 'x', 'o', 'O', '.', '*'}
-    pub fn hatch(protocol: Protocol, f: usize) -> String {
-        match (protocol, f) {
-            (Protocol::FPaxos, 1) => "/", // 1
-            (Protocol::FPaxos, 2) => "\\",
-            (Protocol::EPaxosLocked, _) => "//", // 3
-            (Protocol::AtlasLocked, 1) => "///", // 2
-            (Protocol::AtlasLocked, 2) => "\\\\\\",
-            (Protocol::NewtLocked, 1) => "////", // 4
-            (Protocol::NewtLocked, 2) => "\\\\\\\\",
-            (Protocol::NewtAtomic, 1) => "//////", //  6
-            (Protocol::NewtAtomic, 2) => "\\\\\\\\\\\\",
-            (Protocol::Basic, 1) => "///////", // 7
-            (Protocol::Basic, 2) => "\\\\\\\\\\\\\\",
-            _ => panic!(
-                "PlotFmt::hatch: protocol = {:?} and f = {} combination not supported!",
-                protocol, f
-            ),
-        }.to_string()
+    pub fn hatch(&self, protocol: Protocol, f: usize) -> String {
+        self.hatches
+            .get(&(protocol, f))
+            .cloned()
+            .unwrap_or_else(|| "/".repeat(1 + generated_index(protocol, f, 7)))
     }
 
     // Possible values: https://matplotlib.org/3.1.1/api/markers_api.html#module-matplotlib.markers
-    pub fn marker(protocol: Protocol, f: usize) -> String {
-        match (protocol, f) {
-            (Protocol::AtlasLocked, 1) => "o",
-            (Protocol::AtlasLocked, 2) => "s",
-            (Protocol::EPaxosLocked, _) => "D",
-            (Protocol::FPaxos, 1) => "+",
-            (Protocol::FPaxos, 2) => "x",
-            (Protocol::NewtAtomic, 1) => "v",
-            (Protocol::NewtAtomic, 2) => "^",
-            (Protocol::NewtLocked, 1) => ">",
-            (Protocol::NewtLocked, 2) => "<",
-            (Protocol::Basic, 1) => "p",
-            (Protocol::Basic, 2) => "P",
-            _ => panic!(
-                "PlotFmt::marker: protocol = {:?} and f = {} combination not supported!",
-                protocol, f
-            ),
-        }.to_string()
+    pub fn marker(&self, protocol: Protocol, f: usize) -> String {
+        const MARKERS: [&str; 10] =
+            ["o", "s", "D", "+", "x", "v", "^", ">", "<", "p"];
+        self.markers
+            .get(&(protocol, f))
+            .cloned()
+            .unwrap_or_else(|| {
+                MARKERS[generated_index(protocol, f, MARKERS.len())].to_string()
+            })
     }
 
     // Possible values:  {'-', '--', '-.', ':', ''}
-    pub fn linestyle(protocol: Protocol, f: usize) -> String {
-        match (protocol, f) {
-            (Protocol::AtlasLocked, _) => "--",
-            (Protocol::EPaxosLocked, _) => ":",
-            (Protocol::FPaxos, _) => "-.",
-            (Protocol::NewtAtomic, _) => "-",
-            (Protocol::NewtLocked, _) => "-",
-            (Protocol::Basic, _) => ":",
+    pub fn linestyle(&self, protocol: Protocol, f: usize) -> String {
+        let _ = f;
+        match protocol {
+            Protocol::AtlasLocked => "--",
+            Protocol::EPaxosLocked => ":",
+            Protocol::FPaxos => "-.",
+            Protocol::NewtAtomic => "-",
+            Protocol::NewtLocked => "-",
+            Protocol::Basic => ":",
         }
         .to_string()
     }
 
-    pub fn linewidth(f: usize) -> String {
+    pub fn linewidth(&self, f: usize) -> String {
         match f {
             1 => 1.5,
             2 => 2.0,
@@ -132,3 +235,100 @@ impl PlotFmt {
         .to_string()
     }
 }
+
+/// Hashes `protocol` and `f` into `0..modulo`, deterministically (the same
+/// pair always maps to the same index) so a fallback picked for an
+/// unrecognized protocol/`f` combination stays stable across runs instead of
+/// changing plot-to-plot.
+fn generated_index(protocol: Protocol, f: usize, modulo: usize) -> usize {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    protocol.hash(&mut hasher);
+    f.hash(&mut hasher);
+    (hasher.finish() as usize) % modulo
+}
+
+/// Turns the same hash [`generated_index`] uses into an HSV hue, so unknown
+/// protocol/`f` combinations still get visually distinct (if arbitrary)
+/// colors instead of all collapsing onto one fallback color.
+fn generated_hex_color(protocol: Protocol, f: usize) -> String {
+    let hue = (generated_index(protocol, f, 360)) as f64;
+    let (r, g, b) = hsv_to_rgb(hue, 0.65, 0.85);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
+    let c = value * saturation;
+    let h_prime = hue / 60.0;
+    let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
+    let (r1, g1, b1) = match h_prime as u64 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    let to_u8 = |channel: f64| ((channel + m) * 255.0).round() as u8;
+    (to_u8(r1), to_u8(g1), to_u8(b1))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RegionEntry {
+    name: String,
+    label: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StyleEntry {
+    protocol: Protocol,
+    f: usize,
+    color: Option<String>,
+    hatch: Option<String>,
+    marker: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BackgroundEntry {
+    protocol: Protocol,
+    color: String,
+}
+
+/// Same shape as the palette tables in [`PlotFmt`], but every entry is
+/// optional and provided as a flat list rather than keyed maps, since
+/// `(Protocol, usize)` doesn't round-trip cleanly as a TOML table key.
+/// Parsed from a file and then folded into a `PlotFmt` with
+/// [`PlotFmt::merge`].
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PaletteOverlay {
+    regions: Option<Vec<RegionEntry>>,
+    styles: Option<Vec<StyleEntry>>,
+    backgrounds: Option<Vec<BackgroundEntry>>,
+}
+
+impl PaletteOverlay {
+    /// Parses a TOML palette file, e.g.:
+    /// ```toml
+    /// [[regions]]
+    /// name = "eu-central-1"
+    /// label = "Frankfurt"
+    ///
+    /// [[styles]]
+    /// protocol = "newt_atomic"
+    /// f = 1
+    /// color = "#112233"
+    ///
+    /// [[backgrounds]]
+    /// protocol = "newt_atomic"
+    /// color = "#000000"
+    /// ```
+    fn from_toml_file(file: impl AsRef<Path>) -> Result<Self, Report> {
+        let contents = std::fs::read_to_string(file.as_ref())
+            .wrap_err_with(|| {
+                format!("read plot palette file {}", file.as_ref().display())
+            })?;
+        toml::from_str(&contents).wrap_err_with(|| {
+            format!("parse plot palette file {}", file.as_ref().display())
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use clap::{App, Arg};
+use fantoch::config::Config;
+use fantoch::protocol::fuzz;
+use fantoch::protocol::{Basic, MultiPaxos, Raft};
+use rand::Rng;
+
+fn main() {
+    let (protocol, config, seed, rounds) = parse_args();
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+    println!("protocol: {}", protocol);
+    println!("config: {:?}", config);
+    println!("seed: {}", seed);
+
+    let result = match protocol.as_str() {
+        "basic" => fuzz::fuzz::<Basic>(config, seed, rounds),
+        "raft" => fuzz::fuzz::<Raft>(config, seed, rounds),
+        "multi_paxos" => fuzz::fuzz::<MultiPaxos>(config, seed, rounds),
+        other => panic!("unknown protocol: {}", other),
+    };
+
+    println!("rounds run: {}", result.rounds_run);
+    match result.violation {
+        Some(reason) => {
+            println!("INVARIANT VIOLATED: {}", reason);
+            println!("schedule that led to it:");
+            for step in &result.schedule {
+                println!("  {:?}", step);
+            }
+            println!(
+                "rerun with --seed {} to reproduce (note: already-fixed \
+                 commits ahead of the one being fuzzed may change the \
+                 schedule)",
+                seed
+            );
+            std::process::exit(1);
+        }
+        None => println!("no invariant violation found in {} rounds", rounds),
+    }
+}
+
+fn parse_args() -> (String, Config, Option<u64>, usize) {
+    let matches = App::new("fuzz_protocol")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about(
+            "Fuzzes a `Protocol` implementation with a randomized, \
+             reproducible interleaving of submits, deliveries, \
+             duplicates, reorders and drops, checking that no process \
+             executes the same command twice.",
+        )
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .value_name("PROTOCOL")
+                .help("protocol to fuzz: basic, raft or multi_paxos")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("n")
+                .long("processes")
+                .value_name("PROCESS_NUMBER")
+                .help("total number of processes")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("f")
+                .long("faults")
+                .value_name("FAULT_NUMBER")
+                .help("total number of allowed faults")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("seed used to drive the fuzzer; random if not set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rounds")
+                .long("rounds")
+                .value_name("ROUNDS")
+                .help("number of scheduling decisions to make; default: 10000")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let protocol = matches
+        .value_of("protocol")
+        .expect("protocol should be set")
+        .to_string();
+    let n = matches
+        .value_of("n")
+        .expect("n should be set")
+        .parse::<usize>()
+        .expect("n should be a number");
+    let f = matches
+        .value_of("f")
+        .expect("f should be set")
+        .parse::<usize>()
+        .expect("f should be a number");
+    let seed = matches
+        .value_of("seed")
+        .map(|seed| seed.parse::<u64>().expect("seed should be a number"));
+    let rounds = matches
+        .value_of("rounds")
+        .map(|rounds| rounds.parse::<usize>().expect("rounds should be a number"))
+        .unwrap_or(10_000);
+
+    let config = Config::new(n, f);
+    (protocol, config, seed, rounds)
+}
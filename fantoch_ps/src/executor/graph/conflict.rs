@@ -0,0 +1,59 @@
+use fantoch::command::Command;
+use fantoch::id::ShardId;
+
+/// Decides whether two commands conflict, i.e. whether the order in which
+/// they're applied to the store is observable. `DependencyGraph` and
+/// `GraphExecutor` only need this to know which commands must be ordered
+/// with respect to each other; they have no opinion on what "conflict"
+/// means for a given protocol, so any relation - key overlap, read/write
+/// escalation, range overlap, a commutativity-aware rule, ... - can be
+/// dropped in without forking the SCC-finding or scheduling code.
+pub trait ConflictRelation {
+    /// Returns whether `a` and `b` conflict.
+    fn conflicts(&self, a: &Command, b: &Command) -> bool;
+
+    /// Returns whether the relation is transitive, i.e. whether `a`
+    /// conflicting with `b` and `b` conflicting with `c` implies that `a`
+    /// conflicts with `c`. A transitive relation lets the dependency
+    /// frontier of a command be collapsed to a single merged clock (the
+    /// highest conflicting dot per replica, across every key), since no
+    /// sharper per-key tracking can ever be observed.
+    ///
+    /// Defaults to `false`, the conservative choice: this is what makes
+    /// key overlap (see `KeyConflicts`) sound without per-key dependency
+    /// tracking in the general case.
+    fn transitive(&self) -> bool {
+        false
+    }
+}
+
+/// The relation `DependencyGraph` and `GraphExecutor` assumed before the
+/// relation became pluggable: two commands conflict iff they access a
+/// common key. `transitive` mirrors the old `Config::set_transitive_conflicts`
+/// flag: an optimization some protocols opt into, trading the precision of
+/// per-key dependency tracking for fewer, coarser dependency edges.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyConflicts {
+    shard_id: ShardId,
+    transitive: bool,
+}
+
+impl KeyConflicts {
+    pub fn new(shard_id: ShardId, transitive: bool) -> Self {
+        Self {
+            shard_id,
+            transitive,
+        }
+    }
+}
+
+impl ConflictRelation for KeyConflicts {
+    fn conflicts(&self, a: &Command, b: &Command) -> bool {
+        a.keys(self.shard_id)
+            .any(|key| b.keys(self.shard_id).any(|other| key == other))
+    }
+
+    fn transitive(&self) -> bool {
+        self.transitive
+    }
+}
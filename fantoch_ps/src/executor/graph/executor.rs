@@ -0,0 +1,258 @@
+use super::{ConflictRelation, DependencyGraph, KeyConflicts};
+use fantoch::command::Command;
+use fantoch::config::Config;
+use fantoch::executor::{
+    Executor, ExecutorMetrics, ExecutorResult, MessageKey, Pending,
+};
+use fantoch::id::{Dot, ProcessId, Rifl, ShardId};
+use fantoch::kvs::{KVStore, Key};
+use fantoch::time::SysTime;
+use fantoch::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use threshold::VClock;
+
+pub struct GraphExecutor {
+    shard_id: ShardId,
+    graph: DependencyGraph,
+    store: KVStore,
+    pending: Pending,
+    metrics: ExecutorMetrics,
+    to_clients: Vec<ExecutorResult>,
+    // upper bound on how many mutually non-conflicting SCCs `dispatch`
+    // groups into one batch before applying any of them; `1` (the
+    // default) degenerates to applying SCCs one at a time, in `scheduled`
+    // order. This executor has no async boundary to run a batch's SCCs on
+    // concurrently (see `dispatch`'s doc comment), so raising it changes
+    // batching/ordering, not wall-clock concurrency.
+    parallelism: usize,
+    // SCCs that are ready to apply but still waiting for a free batch slot
+    // or for a conflict (per `conflict_relation`) with the current batch to
+    // clear
+    scheduled: VecDeque<Vec<Command>>,
+    // commands touched by the batch `dispatch` is currently assembling,
+    // checked pairwise against a scheduled SCC's commands via
+    // `conflict_relation`; this is what lets dispatch be driven by any
+    // `ConflictRelation` instead of assuming key-overlap semantics
+    in_flight_commands: Vec<Command>,
+    conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
+}
+
+impl Executor for GraphExecutor {
+    type ExecutionInfo = GraphExecutionInfo;
+
+    fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+        executors: usize,
+    ) -> Self {
+        // default to the key-overlap relation `DependencyGraph` always
+        // assumed before it became pluggable
+        let conflict_relation: Arc<dyn ConflictRelation + Send + Sync> =
+            Arc::new(KeyConflicts::new(
+                shard_id,
+                config.transitive_conflicts(),
+            ));
+        Self::with_conflict_relation(
+            process_id,
+            shard_id,
+            config,
+            executors,
+            conflict_relation,
+        )
+    }
+
+    fn wait_for(&mut self, cmd: &Command) {
+        // start command in pending
+        assert!(self.pending.wait_for(cmd));
+    }
+
+    fn wait_for_rifl(&mut self, rifl: Rifl) {
+        self.pending.wait_for_rifl(rifl);
+    }
+
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
+        let GraphExecutionInfo::Add { dot, cmd, deps } = info;
+        self.graph.handle_add(dot, cmd, deps, time);
+
+        // pull every SCC that `handle_add` made ready, keeping each SCC's
+        // commands grouped so independent ones can be told apart
+        while let Some(scc) = self.graph.scc_to_execute() {
+            self.scheduled.push_back(scc);
+        }
+
+        self.dispatch();
+    }
+
+    fn to_clients(&mut self) -> Option<ExecutorResult> {
+        self.to_clients.pop()
+    }
+
+    fn parallel() -> bool {
+        true
+    }
+
+    fn metrics(&self) -> &ExecutorMetrics {
+        &self.metrics
+    }
+}
+
+impl GraphExecutor {
+    /// Like `Executor::new`, but lets the caller plug in a `ConflictRelation`
+    /// other than the default key-overlap one, so the same SCC-execution
+    /// engine can drive protocols with non-key-based conflict rules (e.g.
+    /// read/write escalation, range conflicts, or a commutativity-aware
+    /// relation) without forking this module. The same relation instance
+    /// also governs `DependencyGraph`'s SCC discovery, so both stay
+    /// consistent about what "conflict" means.
+    pub fn with_conflict_relation(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+        executors: usize,
+        conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
+    ) -> Self {
+        let graph = DependencyGraph::with_conflict_relation(
+            process_id,
+            shard_id,
+            &config,
+            Arc::clone(&conflict_relation),
+        );
+        let store = KVStore::new();
+        // aggregate results if the number of executors is 1
+        let aggregate = executors == 1;
+        let pending = Pending::new(aggregate, process_id, shard_id);
+        let metrics = ExecutorMetrics::new();
+        let to_clients = Vec::new();
+        let parallelism = config.executor_parallelism();
+        assert!(
+            parallelism > 0,
+            "executor parallelism degree must be at least 1"
+        );
+
+        Self {
+            shard_id,
+            graph,
+            store,
+            pending,
+            metrics,
+            to_clients,
+            parallelism,
+            scheduled: VecDeque::new(),
+            in_flight_commands: Vec::new(),
+            conflict_relation,
+        }
+    }
+
+    /// Drains `scheduled` in batches of up to `parallelism` mutually
+    /// non-conflicting (per `conflict_relation`) SCCs, applying a whole batch
+    /// before assembling the next one.
+    ///
+    /// This executor has no async boundary to actually run a batch's SCCs on
+    /// concurrently, so every SCC is still applied sequentially, in
+    /// `scheduled` order, one at a time - `parallelism` governs batch size
+    /// and thus how many independent SCCs get to jump ahead of one still
+    /// stuck behind an earlier conflict, not how many run at once. Making
+    /// this truly concurrent would need a backend (e.g. one thread per
+    /// batch slot) that can safely share `store`/`pending` across it; until
+    /// then, this only groups SCCs by the `ConflictRelation` such a backend
+    /// would need to respect.
+    ///
+    /// Commands within an SCC are always applied in their deterministic
+    /// order; only independent SCCs are ever batched together.
+    fn dispatch(&mut self) {
+        loop {
+            let mut batch = Vec::new();
+            let mut index = 0;
+            while index < self.scheduled.len() && batch.len() < self.parallelism
+            {
+                if self.conflicts_with_in_flight(&self.scheduled[index]) {
+                    index += 1;
+                    continue;
+                }
+
+                let scc = self
+                    .scheduled
+                    .remove(index)
+                    .expect("index was checked to be in bounds");
+                self.in_flight_commands.extend(scc.iter().cloned());
+                batch.push(scc);
+                // the SCC that slid into `index` hasn't been checked yet
+            }
+
+            if batch.is_empty() {
+                break;
+            }
+
+            for scc in batch {
+                self.apply_scc(scc);
+            }
+            self.in_flight_commands.clear();
+        }
+    }
+
+    /// Returns whether any command in `scc` conflicts, per
+    /// `conflict_relation`, with a command already in the batch `dispatch`
+    /// is currently assembling.
+    fn conflicts_with_in_flight(&self, scc: &[Command]) -> bool {
+        scc.iter().any(|cmd| {
+            self.in_flight_commands
+                .iter()
+                .any(|in_flight_cmd| {
+                    self.conflict_relation.conflicts(cmd, in_flight_cmd)
+                })
+        })
+    }
+
+    /// Applies every command in an SCC, in order, against the local store.
+    fn apply_scc(&mut self, scc: Vec<Command>) {
+        scc.into_iter().for_each(|cmd| self.execute(cmd));
+    }
+
+    fn execute(&mut self, cmd: Command) {
+        let rifl = cmd.rifl();
+        cmd.into_iter().for_each(|(key, op)| {
+            // execute op in the `KVStore`
+            let op_result = self.store.execute(&key, op);
+
+            // add partial result to `Pending`
+            if let Some(executor_result) =
+                self.pending.add_partial(rifl, || (key, op_result))
+            {
+                self.to_clients.push(executor_result);
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphExecutionInfo {
+    Add {
+        dot: Dot,
+        cmd: Command,
+        // see `Vertex::deps` for why this is per-key instead of a single
+        // clock merged across every key the command touches
+        deps: HashMap<Key, VClock<ProcessId>>,
+    },
+}
+
+impl GraphExecutionInfo {
+    pub fn add(
+        dot: Dot,
+        cmd: Command,
+        deps: HashMap<Key, VClock<ProcessId>>,
+    ) -> Self {
+        GraphExecutionInfo::Add { dot, cmd, deps }
+    }
+}
+
+impl MessageKey for GraphExecutionInfo {
+    fn key(&self) -> Option<&Key> {
+        // ordering a command requires seeing every dependency edge, so a
+        // graph execution info can't be routed to a single key-owning
+        // executor; it must be seen by all of them
+        None
+    }
+}
@@ -0,0 +1,157 @@
+use fantoch::config::Config;
+use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::{HashMap, HashSet};
+use std::cmp;
+
+// each consecutive timeout is penalized as if it added this many millis of
+// latency, so a replica that's gone quiet quickly falls to the back of the
+// ranking without needing a special-cased "exclude" path
+const TIMEOUT_PENALTY_MS: f64 = 1_000.0;
+// how much weight a single new round-trip sample gets in the moving
+// average, versus the history already accumulated
+const EWMA_WEIGHT: f64 = 0.2;
+
+/// Tracks how reliable a given shard replica has recently been, from
+/// observed fetch round-trip latency and timeouts, so `DependencyFetcher`
+/// can prefer the replicas most likely to answer quickly.
+#[derive(Debug, Clone, Default)]
+struct Reliability {
+    // exponential moving average of round-trip latency, in millis; a
+    // never-contacted replica defaults to 0.0, i.e. the most optimistic
+    // possible score, so it gets tried before one with a track record of
+    // timeouts
+    ewma_rtt_ms: f64,
+    consecutive_timeouts: u32,
+}
+
+impl Reliability {
+    fn record_success(&mut self, rtt_ms: u64) {
+        self.consecutive_timeouts = 0;
+        self.ewma_rtt_ms = if self.ewma_rtt_ms == 0.0 {
+            rtt_ms as f64
+        } else {
+            EWMA_WEIGHT * rtt_ms as f64
+                + (1.0 - EWMA_WEIGHT) * self.ewma_rtt_ms
+        };
+    }
+
+    fn record_timeout(&mut self) {
+        self.consecutive_timeouts += 1;
+    }
+
+    // lower score => more reliable => preferred
+    fn score(&self) -> f64 {
+        self.ewma_rtt_ms
+            + self.consecutive_timeouts as f64 * TIMEOUT_PENALTY_MS
+    }
+}
+
+/// Coordinates fetching missing cross-shard dependencies reported by
+/// `TarjanSCCFinder::strong_connect` as `FinderResult::MissingDependencies`.
+/// For each missing dot it ranks the owning shard's replicas by recent
+/// reliability, fans a request out to the best `fanout` of them, and
+/// deduplicates so a dot with an outstanding request isn't re-fetched until
+/// it either arrives or every outstanding candidate times out -- the same
+/// "query preferred closest peers, fastest wins" strategy DHTs use for
+/// fan-out lookups, aimed here at cutting the tail latency a single slow
+/// remote replica would otherwise impose on a stalled SCC.
+#[derive(Debug, Clone)]
+pub struct DependencyFetcher {
+    fanout: usize,
+    replicas: HashMap<ShardId, Vec<ProcessId>>,
+    reliability: HashMap<(ShardId, ProcessId), Reliability>,
+    // dots with a request currently in flight, and to which replicas
+    outstanding: HashMap<Dot, HashSet<(ShardId, ProcessId)>>,
+}
+
+impl DependencyFetcher {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            fanout: config.dependency_fetch_fanout(),
+            replicas: HashMap::new(),
+            reliability: HashMap::new(),
+            outstanding: HashMap::new(),
+        }
+    }
+
+    /// Registers known replicas, so they become fetch candidates. Mirrors
+    /// the shape of `Protocol::discover`.
+    pub fn discover(&mut self, processes: Vec<(ProcessId, ShardId)>) {
+        for (process_id, shard_id) in processes {
+            self.replicas.entry(shard_id).or_default().push(process_id);
+        }
+    }
+
+    /// Plans which replicas of `shard_id` (the dot's already-resolved
+    /// owning shard -- see `PendingIndex::index`) to fetch `dot` from,
+    /// ranked best-reliability-first and capped at `fanout`. Returns an
+    /// empty set if `dot` already has a request outstanding (it's already
+    /// being chased) or if `shard_id` has no discovered replicas yet.
+    pub fn plan_one(
+        &mut self,
+        dot: Dot,
+        shard_id: ShardId,
+    ) -> HashSet<(ShardId, ProcessId)> {
+        if self.outstanding.contains_key(&dot) {
+            return HashSet::new();
+        }
+        let candidates = match self.replicas.get(&shard_id) {
+            Some(candidates) if !candidates.is_empty() => candidates,
+            _ => return HashSet::new(),
+        };
+
+        let mut ranked = candidates.clone();
+        ranked.sort_by(|&a, &b| {
+            self.score(shard_id, a)
+                .partial_cmp(&self.score(shard_id, b))
+                .unwrap_or(cmp::Ordering::Equal)
+        });
+
+        let chosen: HashSet<(ShardId, ProcessId)> = ranked
+            .into_iter()
+            .take(self.fanout.max(1))
+            .map(|process_id| (shard_id, process_id))
+            .collect();
+        self.outstanding.insert(dot, chosen.clone());
+        chosen
+    }
+
+    fn score(&self, shard_id: ShardId, process_id: ProcessId) -> f64 {
+        self.reliability
+            .get(&(shard_id, process_id))
+            .map(Reliability::score)
+            .unwrap_or(0.0)
+    }
+
+    /// Records a valid reply for `dot` from `target`, returning whether it's
+    /// the first one to arrive. The caller should apply the first reply and
+    /// discard (cancel) any later one for the same dot, since `dot` is
+    /// cleared from `outstanding` as soon as the first reply lands.
+    pub fn reply_received(
+        &mut self,
+        target: (ShardId, ProcessId),
+        dot: Dot,
+        rtt_ms: u64,
+    ) -> bool {
+        self.reliability
+            .entry(target)
+            .or_default()
+            .record_success(rtt_ms);
+        self.outstanding.remove(&dot).is_some()
+    }
+
+    /// Records that `target` failed to answer for `dot` within the request
+    /// timeout, penalizing its reliability score. `dot` stays outstanding
+    /// against any other candidate it was fanned out to; once every
+    /// candidate has timed out, a later `plan_one` call will retry it against a
+    /// freshly re-ranked candidate set.
+    pub fn timeout(&mut self, target: (ShardId, ProcessId), dot: Dot) {
+        self.reliability.entry(target).or_default().record_timeout();
+        if let Some(targets) = self.outstanding.get_mut(&dot) {
+            targets.remove(&target);
+            if targets.is_empty() {
+                self.outstanding.remove(&dot);
+            }
+        }
+    }
+}
@@ -59,4 +59,12 @@ impl PendingIndex {
     pub fn remove(&mut self, dep_dot: &Dot) -> Option<HashSet<Dot>> {
         self.index.remove(dep_dot)
     }
+
+    /// Returns a snapshot of the full backlog: for each missing dependency
+    /// dot still being waited on, the set of dots parked behind it. Used by
+    /// `DependencyGraph::snapshot` to report introspection data without
+    /// handing out a live reference into the index.
+    pub fn backlog(&self) -> HashMap<Dot, HashSet<Dot>> {
+        self.index.clone()
+    }
 }
\ No newline at end of file
@@ -4,6 +4,11 @@ mod tarjan;
 /// This module contains the definition of `VertexIndex` and `PendingIndex`.
 mod index;
 
+/// This module contains the definition of the `ConflictRelation` trait and
+/// `KeyConflicts`, the key-overlap relation `DependencyGraph` assumed before
+/// the relation became pluggable.
+mod conflict;
+
 /// This modules contains the definition of `GraphExecutor` and
 /// `GraphExecutionInfo`.
 mod executor;
@@ -11,8 +16,25 @@ mod executor;
 /// This module contains the definition of `LevelExecutedClock`.
 mod level;
 
+/// This module contains the definition of `GraphScheduler`, a multi-worker
+/// scheduler that dispatches commits to `DependencyGraph` workers while
+/// preserving per-process ordering.
+mod scheduler;
+
+/// This module contains the on-disk checkpoint/log format used to persist
+/// and restore a `DependencyGraph`'s pending state across restarts.
+mod persist;
+
+/// This module contains the definition of `DependencyFetcher`, which
+/// ranks and fans missing-dependency requests out to multiple replicas.
+mod fetch;
+
 // Re-exports.
+pub use conflict::{ConflictRelation, KeyConflicts};
 pub use executor::{GraphExecutionInfo, GraphExecutor};
+pub use fetch::DependencyFetcher;
+pub use persist::{GraphCheckpoint, GraphLogEntry, GraphRestoreError};
+pub use scheduler::GraphScheduler;
 
 use self::index::{PendingIndex, VertexIndex};
 use self::level::LevelExecutedClock;
@@ -21,6 +43,7 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
 use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::kvs::Key;
 use fantoch::log;
 use fantoch::time::SysTime;
 use fantoch::util;
@@ -31,12 +54,17 @@ use std::fmt;
 use std::sync::Arc;
 use threshold::{AEClock, VClock};
 
+/// How many recently-executed SCC sizes `DependencyGraph::snapshot` reports.
+const RECENT_SCC_SIZES_LIMIT: usize = 32;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RequestReply {
     Info {
         dot: Dot,
         cmd: Command,
-        clock: VClock<ProcessId>,
+        // see `Vertex::deps` for why this is per-key instead of a single
+        // clock merged across every key the command touches
+        deps: HashMap<Key, VClock<ProcessId>>,
     },
     Executed {
         dot: Dot,
@@ -52,6 +80,22 @@ impl RequestReply {
     }
 }
 
+/// A point-in-time snapshot of a `DependencyGraph`'s internal state, for
+/// live introspection (see `DependencyGraph::snapshot`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphSnapshot {
+    /// number of vertices currently indexed (neither executed nor GC'ed)
+    pub pending_vertices: usize,
+    /// the dot that's been sitting in the index the longest, and for how
+    /// many millis, if any vertex is currently indexed
+    pub longest_waiting: Option<(Dot, u64)>,
+    /// for each missing dependency dot still being waited on, the dots
+    /// parked behind it
+    pub missing_backlog: HashMap<Dot, HashSet<Dot>>,
+    /// sizes of the most recently executed SCCs, oldest first
+    pub recent_scc_sizes: Vec<usize>,
+}
+
 #[derive(Clone)]
 pub struct DependencyGraph {
     executor_index: usize,
@@ -64,12 +108,49 @@ pub struct DependencyGraph {
     level_executed_clock: LevelExecutedClock,
     vertex_index: VertexIndex,
     pending_index: PendingIndex,
+    // conflict cache (à la cargo's dependency resolver "keep backtracking
+    // as long as all recorded conflicts are still active"): for each root
+    // dot parked on a missing dependency, the full set `M` of dots its last
+    // `find_scc` attempt found missing. As dots get executed we strike them
+    // out of every blocked root's `M`; a root is only worth re-traversing
+    // with `find_scc` once its `M` is empty, i.e. once every dependency
+    // that blocked it has actually shown up, instead of on every single one
+    // that trickles in
+    conflict_cache: HashMap<Dot, HashSet<Dot>>,
+    // first time (in millis, per the `RunTime` clock threaded through
+    // `Vertex::new`) each currently-missing dot was observed; a dot that
+    // stays missing for longer than `missing_dependency_timeout` is assumed
+    // to be stranded by a dropped `MCommit` and gets actively re-requested
+    // instead of waiting on it forever
+    missing_since: HashMap<Dot, u64>,
+    missing_dependency_timeout: u64,
+    // missing dots whose recovery request is ready to be sent out
+    out_recovery_requests: HashSet<Dot>,
+    // the relation deciding which commands conflict, i.e. must be ordered
+    // with respect to each other; defaults to `KeyConflicts` (key overlap)
+    // but can be swapped via `with_conflict_relation` so the same
+    // SCC-execution engine drives protocols with non-key-based conflict
+    // rules without forking this module
+    conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
     finder: TarjanSCCFinder,
     metrics: ExecutorMetrics,
+    // sizes of the most recently executed SCCs, bounded to
+    // `RECENT_SCC_SIZES_LIMIT` entries; exposed via `snapshot` for live
+    // introspection, oldest first
+    recent_scc_sizes: std::collections::VecDeque<usize>,
+    // ranks and fans missing-dependency requests out across a shard's
+    // replicas; see `fetch_requests`
+    fetcher: DependencyFetcher,
+    out_fetch_requests: HashMap<(ShardId, ProcessId), HashSet<Dot>>,
     // worker 0 (handles commands):
     // - adds new commands `to_execute`
     // - `out_requests` dependencies to be able to order commands
     to_execute: Vec<Command>,
+    // same commands as `to_execute`, but grouped by the SCC that made them
+    // ready: callers that want to parallelize independent SCCs (e.g. the
+    // graph executor) need this grouping, since `to_execute` itself carries
+    // no SCC boundaries once flattened
+    ready_sccs: Vec<Vec<Command>>,
     out_requests: HashMap<ShardId, HashSet<Dot>>,
     // worker 1 (handles requests):
     // - may have `buffered_in_requests` when doesn't have the command yet
@@ -95,6 +176,29 @@ impl DependencyGraph {
         process_id: ProcessId,
         shard_id: ShardId,
         config: &Config,
+    ) -> Self {
+        // default to the key-overlap relation this graph always assumed
+        // before the relation became pluggable
+        let conflict_relation: Arc<dyn ConflictRelation + Send + Sync> =
+            Arc::new(KeyConflicts::new(
+                shard_id,
+                config.transitive_conflicts(),
+            ));
+        Self::with_conflict_relation(
+            process_id,
+            shard_id,
+            config,
+            conflict_relation,
+        )
+    }
+
+    /// Like `new`, but lets the caller plug in a `ConflictRelation` other
+    /// than the default key-overlap one (see `conflict::ConflictRelation`).
+    pub fn with_conflict_relation(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: &Config,
+        conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
     ) -> Self {
         // this value will be overwritten
         let executor_index = 0;
@@ -110,11 +214,25 @@ impl DependencyGraph {
         // create indexes
         let vertex_index = VertexIndex::new(process_id);
         let pending_index = PendingIndex::new(process_id, shard_id, *config);
+        let conflict_cache = Default::default();
+        // create missing-dependency recovery state
+        let missing_since = Default::default();
+        let missing_dependency_timeout = config.missing_dependency_timeout();
+        let out_recovery_requests = Default::default();
         // create finder
-        let finder = TarjanSCCFinder::new(process_id, shard_id, *config);
+        let finder = TarjanSCCFinder::new(
+            process_id,
+            shard_id,
+            *config,
+            Arc::clone(&conflict_relation),
+        );
         let metrics = ExecutorMetrics::new();
+        let recent_scc_sizes = Default::default();
+        let fetcher = DependencyFetcher::new(config);
+        let out_fetch_requests = Default::default();
         // create to execute
         let to_execute = Vec::new();
+        let ready_sccs = Vec::new();
         // create requests and request replies
         let out_requests = Default::default();
         let buffered_in_requests = Default::default();
@@ -128,9 +246,18 @@ impl DependencyGraph {
             level_executed_clock,
             vertex_index,
             pending_index,
+            conflict_cache,
+            missing_since,
+            missing_dependency_timeout,
+            out_recovery_requests,
+            conflict_relation,
             finder,
             metrics,
+            recent_scc_sizes,
+            fetcher,
+            out_fetch_requests,
             to_execute,
+            ready_sccs,
             out_requests,
             buffered_in_requests,
             out_request_replies,
@@ -147,6 +274,16 @@ impl DependencyGraph {
         self.to_execute.pop()
     }
 
+    /// Returns the next SCC ready to be executed, with the SCC's internal
+    /// (deterministic) command order preserved. Unlike `command_to_execute`,
+    /// this keeps the SCC grouping intact so that callers applying several
+    /// SCCs can check whether their key-sets overlap before deciding to run
+    /// them concurrently.
+    #[must_use]
+    pub fn scc_to_execute(&mut self) -> Option<Vec<Command>> {
+        self.ready_sccs.pop()
+    }
+
     /// Returns a request.
     #[must_use]
     pub fn requests(&mut self) -> HashMap<ShardId, HashSet<Dot>> {
@@ -159,15 +296,177 @@ impl DependencyGraph {
         std::mem::take(&mut self.out_request_replies)
     }
 
+    /// Returns the set of dots that have been missing for longer than
+    /// `missing_dependency_timeout` and should be actively re-requested,
+    /// since the `MCommit` that was supposed to deliver them may have been
+    /// dropped.
+    #[must_use]
+    pub fn recovery_requests(&mut self) -> HashSet<Dot> {
+        std::mem::take(&mut self.out_recovery_requests)
+    }
+
     #[cfg(test)]
     fn commands_to_execute(&mut self) -> Vec<Command> {
         std::mem::take(&mut self.to_execute)
     }
 
+    /// Registers known replicas as candidates for `fetch_requests`'s
+    /// reliability-ranked fan-out. Mirrors `Protocol::discover`'s shape.
+    pub fn discover(&mut self, processes: Vec<(ProcessId, ShardId)>) {
+        self.fetcher.discover(processes);
+    }
+
+    /// Like `requests`, but ranked: each missing dot is fanned out to the
+    /// best few (`Config::dependency_fetch_fanout`) replicas of its owning
+    /// shard by recent reliability, instead of leaving the caller to pick a
+    /// single target, and is deduplicated so a dot already being chased
+    /// isn't requested again until it arrives or every candidate times out.
+    #[must_use]
+    pub fn fetch_requests(
+        &mut self,
+    ) -> HashMap<(ShardId, ProcessId), HashSet<Dot>> {
+        std::mem::take(&mut self.out_fetch_requests)
+    }
+
+    /// Feeds a reply for `dot` from `target` back into the fetcher's
+    /// reliability tracking, returning whether it's the first reply to
+    /// arrive (the caller should apply it) or a redundant one from a
+    /// fanned-out replica that lost the race (the caller should discard it).
+    pub fn fetch_reply_received(
+        &mut self,
+        target: (ShardId, ProcessId),
+        dot: Dot,
+        rtt_ms: u64,
+    ) -> bool {
+        self.fetcher.reply_received(target, dot, rtt_ms)
+    }
+
+    /// Feeds a request timeout against `target` for `dot` back into the
+    /// fetcher's reliability tracking.
+    pub fn fetch_timeout(&mut self, target: (ShardId, ProcessId), dot: Dot) {
+        self.fetcher.timeout(target, dot);
+    }
+
     fn metrics(&self) -> &ExecutorMetrics {
         &self.metrics
     }
 
+    /// Returns the relation deciding which commands conflict, i.e. must be
+    /// ordered with respect to each other.
+    pub fn conflict_relation(&self) -> &Arc<dyn ConflictRelation + Send + Sync> {
+        &self.conflict_relation
+    }
+
+    /// Takes a point-in-time snapshot of the graph, for live introspection
+    /// (e.g. an `introspect_task` polling this periodically). Unlike the
+    /// `tracing` events emitted around `strong_connect`/`save_scc`, this
+    /// gives a caller the current state without having to subscribe to and
+    /// reassemble a stream of events.
+    pub fn snapshot(&self, time: &dyn SysTime) -> GraphSnapshot {
+        let now = time.millis();
+        let longest_waiting = self
+            .vertex_index
+            .dots()
+            .filter_map(|dot| {
+                self.vertex_index
+                    .find(&dot)
+                    .map(|vertex_ref| (dot, vertex_ref.read().start_time()))
+            })
+            .max_by_key(|&(_, start_time)| now.saturating_sub(start_time))
+            .map(|(dot, start_time)| (dot, now.saturating_sub(start_time)));
+        GraphSnapshot {
+            pending_vertices: self.vertex_index.dots().count(),
+            longest_waiting,
+            missing_backlog: self.pending_index.backlog(),
+            recent_scc_sizes: self.recent_scc_sizes.iter().copied().collect(),
+        }
+    }
+
+    /// Encodes the full pending graph (every not-yet-executed vertex plus
+    /// the executed clock) into a `GraphCheckpoint`, for crash recovery
+    /// without replaying the whole protocol log. See `GraphCheckpoint` for
+    /// the on-disk shape and `restore` for loading one back.
+    pub fn checkpoint(&self) -> GraphCheckpoint {
+        let vertices = self
+            .vertex_index
+            .dots()
+            .filter_map(|dot| {
+                self.vertex_index.find(&dot).map(|vertex_ref| {
+                    let vertex = vertex_ref.read();
+                    (dot, vertex.cmd.clone(), vertex.deps.clone())
+                })
+            })
+            .collect();
+        GraphCheckpoint::V1 {
+            process_id: self.process_id,
+            shard_id: self.shard_id,
+            vertices,
+            executed_clock: self.executed_clock.clone(),
+        }
+    }
+
+    /// Rebuilds a `DependencyGraph` from a `GraphCheckpoint` produced by
+    /// `checkpoint`, reindexing every persisted vertex (with its transient
+    /// Tarjan fields -- `id`/`low`/`on_stack` -- reset, as they're not part
+    /// of the checkpoint) and restoring the executed clock. `time` is only
+    /// used to stamp the restored vertices' `start_time`, since the original
+    /// submission time isn't persisted.
+    pub fn restore(
+        checkpoint: GraphCheckpoint,
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: &Config,
+        time: &dyn SysTime,
+    ) -> Result<Self, GraphRestoreError> {
+        match checkpoint {
+            GraphCheckpoint::V1 {
+                process_id: snapshot_process_id,
+                shard_id: snapshot_shard_id,
+                vertices,
+                executed_clock,
+            } => {
+                if snapshot_process_id != process_id {
+                    return Err(GraphRestoreError::ProcessIdMismatch {
+                        expected: process_id,
+                        found: snapshot_process_id,
+                    });
+                }
+                if snapshot_shard_id != shard_id {
+                    return Err(GraphRestoreError::ShardIdMismatch {
+                        expected: shard_id,
+                        found: snapshot_shard_id,
+                    });
+                }
+
+                let mut graph = Self::new(process_id, shard_id, config);
+                for (dot, cmd, deps) in vertices {
+                    let vertex = Vertex::new(dot, cmd, deps, time);
+                    graph.vertex_index.index(vertex);
+                }
+                graph.executed_clock = executed_clock;
+                Ok(graph)
+            }
+        }
+    }
+
+    /// Applies a single `GraphLogEntry` produced by an incremental,
+    /// append-only writer, so a restarted process can replay a log instead
+    /// of waiting on (or in addition to) a full `checkpoint`.
+    pub fn apply_log_entry(&mut self, entry: GraphLogEntry, time: &dyn SysTime) {
+        match entry {
+            GraphLogEntry::VertexAdded { dot, cmd, deps } => {
+                let vertex = Vertex::new(dot, cmd, deps, time);
+                self.vertex_index.index(vertex);
+            }
+            GraphLogEntry::ExecutedClockAdvanced {
+                process_id,
+                sequence,
+            } => {
+                self.executed_clock.add(&process_id, sequence);
+            }
+        }
+    }
+
     fn cleanup(&mut self, time: &dyn SysTime) {
         log!(
             "p{}: @{} Graph::cleanup | time = {}",
@@ -180,18 +479,21 @@ impl DependencyGraph {
                 .maybe_level(&mut self.executed_clock, time);
             // if main executor, update snapshot
             *self.executed_clock_snapshot.write() = self.executed_clock.clone();
+            // actively recover dots that have been missing for too long,
+            // instead of waiting forever for their `MCommit` to show up
+            self.find_stale_missing_deps(time);
         } else {
             // otherwise, simply check pending remote requests
             self.check_pending_requests(time);
         }
     }
 
-    /// Add a new command with its clock to the queue.
+    /// Add a new command with its per-key dependencies to the queue.
     pub fn handle_add(
         &mut self,
         dot: Dot,
         cmd: Command,
-        clock: VClock<ProcessId>,
+        deps: HashMap<Key, VClock<ProcessId>>,
         time: &dyn SysTime,
     ) {
         assert_eq!(self.executor_index, 0);
@@ -200,12 +502,12 @@ impl DependencyGraph {
             self.process_id,
             self.executor_index,
             dot,
-            clock,
+            deps,
             time.millis()
         );
 
         // create new vertex for this command
-        let vertex = Vertex::new(dot, cmd, clock, time);
+        let vertex = Vertex::new(dot, cmd, deps, time);
 
         if self.vertex_index.index(vertex).is_some() {
             panic!(
@@ -321,7 +623,7 @@ impl DependencyGraph {
                         RequestReply::Info {
                             dot,
                             cmd: vertex.cmd.clone(),
-                            clock: vertex.clock.clone(),
+                            deps: vertex.deps.clone(),
                         },
                     )
                 }
@@ -389,11 +691,11 @@ impl DependencyGraph {
                 continue;
             }
             match info {
-                RequestReply::Info { dot, cmd, clock } => {
+                RequestReply::Info { dot, cmd, deps } => {
                     // count number of accepted replies
                     accepted_replies += 1;
 
-                    self.handle_add(dot, cmd, clock, time)
+                    self.handle_add(dot, cmd, deps, time)
                 }
                 RequestReply::Executed { dot } => {
                     // add to executed if not mine
@@ -431,6 +733,11 @@ impl DependencyGraph {
             dot,
             time.millis()
         );
+        // prune transitively-implied dependencies before walking the graph,
+        // so `strong_connect` doesn't re-descend into vertices it would
+        // have reached anyway
+        self.finder.reduce(&self.vertex_index, &self.executed_clock);
+
         // execute tarjan's algorithm
         let mut found = 0;
         let finder_result = self.strong_connect(dot, &mut found);
@@ -475,6 +782,15 @@ impl DependencyGraph {
         self.metrics
             .collect(ExecutorMetricsKind::ChainSize, scc.len() as u64);
 
+        // track the size for live introspection, bounded to the last
+        // `RECENT_SCC_SIZES_LIMIT` SCCs
+        if self.recent_scc_sizes.len() == RECENT_SCC_SIZES_LIMIT {
+            self.recent_scc_sizes.pop_front();
+        }
+        self.recent_scc_sizes.push_back(scc.len());
+
+        let mut scc_commands = Vec::with_capacity(scc.len());
+
         scc.into_iter().for_each(|dot| {
             log!(
                 "p{}: @{} Graph::save_scc removing {:?} from indexes | time = {}",
@@ -500,19 +816,35 @@ impl DependencyGraph {
             self.metrics
                 .collect(ExecutorMetricsKind::ExecutionDelay, duration);
 
-            // add command to commands to be executed
+            // add command to commands to be executed, both flattened (for
+            // `command_to_execute`) and grouped by SCC (for `scc_to_execute`)
+            scc_commands.push(cmd.clone());
             self.to_execute.push(cmd);
-        })
+        });
+
+        if !scc_commands.is_empty() {
+            self.ready_sccs.push(scc_commands);
+        }
     }
 
     fn index_pending(
         &mut self,
         missing_deps: HashSet<Dot>,
         dot: Dot,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
+        // this is always a fresh, complete scan of `dot`'s current
+        // dependencies (from the `find_scc` attempt that just ran), so it's
+        // safe to simply overwrite whatever set was left from an earlier
+        // attempt
+        self.conflict_cache.insert(dot, missing_deps.clone());
+
         let mut requests = 0;
         for dep_dot in missing_deps {
+            // remember the first time we saw this dot missing, so recovery
+            // can later tell how long it's been stranded
+            self.missing_since.entry(dep_dot).or_insert_with(|| time.millis());
+
             if let Some(target_shard) = self.pending_index.index(dep_dot, dot) {
                 log!(
                     "p{}: @{} Graph::index_pending will ask {:?} to {:?} | time = {}",
@@ -520,13 +852,22 @@ impl DependencyGraph {
                     self.executor_index,
                     dep_dot,
                     target_shard,
-                    _time.millis()
+                    time.millis()
                 );
                 requests += 1;
                 self.out_requests
                     .entry(target_shard)
                     .or_default()
                     .insert(dep_dot);
+
+                for target in
+                    self.fetcher.plan_one(dep_dot, target_shard)
+                {
+                    self.out_fetch_requests
+                        .entry(target)
+                        .or_default()
+                        .insert(dep_dot);
+                }
             }
         }
         // save out requests metric
@@ -534,6 +875,29 @@ impl DependencyGraph {
             .aggregate(ExecutorMetricsKind::OutRequests, requests);
     }
 
+    /// Scans dots recorded as missing for longer than
+    /// `missing_dependency_timeout` and queues a recovery request for each:
+    /// mirrors the "fetch the prev event if it hasn't arrived" pattern from
+    /// distributed-log recovery, closing the liveness gap where a dropped
+    /// `MCommit` would otherwise strand an SCC forever.
+    fn find_stale_missing_deps(&mut self, time: &dyn SysTime) {
+        let now = time.millis();
+        for (&dot, &first_seen) in self.missing_since.iter() {
+            if now.saturating_sub(first_seen) >= self.missing_dependency_timeout
+            {
+                log!(
+                    "p{}: @{} Graph::find_stale_missing_deps {:?} missing since {} | time = {}",
+                    self.process_id,
+                    self.executor_index,
+                    dot,
+                    first_seen,
+                    now
+                );
+                self.out_recovery_requests.insert(dot);
+            }
+        }
+    }
+
     fn check_pending(
         &mut self,
         mut dots: Vec<Dot>,
@@ -542,23 +906,65 @@ impl DependencyGraph {
     ) {
         assert_eq!(self.executor_index, 0);
         while let Some(dot) = dots.pop() {
+            // `dot` just got executed, so it's no longer missing: drop any
+            // recovery bookkeeping for it
+            self.missing_since.remove(&dot);
+            self.out_recovery_requests.remove(&dot);
+
             // get pending commands that depend on this dot
             if let Some(pending) = self.pending_index.remove(&dot) {
+                // don't re-run `find_scc` for a waiting dot until *all* of
+                // the dependencies missing at its last attempt are resolved:
+                // striking just `dot` off its recorded conflict set might
+                // still leave others outstanding, in which case re-walking it
+                // now would just rediscover the same missing set and redo the
+                // same work
+                let ready: HashSet<Dot> = pending
+                    .into_iter()
+                    .filter(|waiting_dot| {
+                        self.tick_conflict_cache(*waiting_dot, dot)
+                    })
+                    .collect();
+
                 log!(
                     "p{}: @{} Graph::try_pending {:?} depended on {:?} | time = {}",
                     self.process_id,
                     self.executor_index,
-                    pending,
+                    ready,
                     dot,
                     time.millis()
                 );
-                self.try_pending(pending, &mut dots, total_found, time);
+
+                if !ready.is_empty() {
+                    self.try_pending(ready, &mut dots, total_found, time);
+                }
             }
         }
         // once there are no more dots to try, no command in pending should be
         // possible to be executed, so we give up!
     }
 
+    /// Strikes `resolved` out of `root`'s recorded conflict set and returns
+    /// whether every dependency missing at `root`'s last `find_scc` attempt
+    /// has now been executed, i.e. whether it's actually worth trying again.
+    fn tick_conflict_cache(&mut self, root: Dot, resolved: Dot) -> bool {
+        match self.conflict_cache.get_mut(&root) {
+            Some(missing) => {
+                missing.remove(&resolved);
+                if missing.is_empty() {
+                    self.conflict_cache.remove(&root);
+                    true
+                } else {
+                    false
+                }
+            }
+            // we should always have an entry for a dot coming out of
+            // `pending_index`, but if we don't (e.g. entries created before
+            // this bookkeeping existed), fall back to always retrying
+            None => true,
+        }
+    }
+
     fn try_pending(
         &mut self,
         pending: HashSet<Dot>,
@@ -658,7 +1064,7 @@ mod tests {
     use fantoch::kvs::{KVOp, Key};
     use fantoch::time::RunTime;
     use fantoch::HashMap;
-    use permutator::{Combination, Permutation};
+    use permutator::Combination;
     use rand::seq::SliceRandom;
     use std::cell::RefCell;
     use std::cmp::Ordering;
@@ -666,6 +1072,18 @@ mod tests {
     use std::iter::FromIterator;
     use threshold::{AEClock, AboveExSet, EventSet};
 
+    // Builds the per-key deps a command would get under the (legacy) coarse
+    // encoding: the same replica clock applied to every key it accesses.
+    fn uniform_deps(
+        cmd: &Command,
+        shard_id: ShardId,
+        clock: VClock<ProcessId>,
+    ) -> HashMap<Key, VClock<ProcessId>> {
+        cmd.keys(shard_id)
+            .map(|key| (key.clone(), clock.clone()))
+            .collect()
+    }
+
     #[test]
     fn simple() {
         // create queue
@@ -690,12 +1108,14 @@ mod tests {
         let clock_1 = util::vclock(vec![1, 0]);
 
         // add cmd 0
-        queue.handle_add(dot_0, cmd_0.clone(), clock_0, &time);
+        let deps_0 = uniform_deps(&cmd_0, shard_id, clock_0);
+        queue.handle_add(dot_0, cmd_0.clone(), deps_0, &time);
         // check commands ready to be executed
         assert!(queue.commands_to_execute().is_empty());
 
         // add cmd 1
-        queue.handle_add(dot_1, cmd_1.clone(), clock_1, &time);
+        let deps_1 = uniform_deps(&cmd_1, shard_id, clock_1);
+        queue.handle_add(dot_1, cmd_1.clone(), deps_1, &time);
         // check commands ready to be executed
         assert_eq!(queue.commands_to_execute(), vec![cmd_0, cmd_1]);
     }
@@ -809,47 +1229,70 @@ mod tests {
     /// It looks like the optimization would be correct if, instead of returning
     /// the highest conflicting command per replica, we would return the highest
     /// conflict command per replica *per key*.
-    #[ignore]
+    ///
+    /// This is exactly what `Vertex::deps` now does: (B, 1) gets (A, 1) as its
+    /// dependency on key "x" and (A, 2) as its dependency on key "y", instead
+    /// of a single coarse dependency on (A, 2) merged across both keys. So
+    /// this test no longer needs to be ignored.
     #[test]
     fn transitive_conflicts_assumption_regression_test_2() {
         // config
         let n = 3;
-        let transitive_conflicts = true;
-
-        let keys = |keys: Vec<&str>| {
-            keys.into_iter()
-                .map(|key| key.to_string())
-                .collect::<BTreeSet<_>>()
-        };
-
-        // cmd 1,1
-        let dot_1_1 = Dot::new(1, 1);
-        let keys_1_1 = keys(vec!["A"]);
-        let clock_1_1 = util::vclock(vec![0, 0, 0]);
-
-        // cmd 1,2
-        let dot_1_2 = Dot::new(1, 2);
-        let keys_1_2 = keys(vec!["B"]);
-        let clock_1_2 = util::vclock(vec![0, 0, 0]);
-
-        // cmd 2,1
-        let dot_2_1 = Dot::new(2, 1);
-        let keys_2_1 = keys(vec!["A", "B"]);
-        let clock_2_1 = util::vclock(vec![2, 0, 0]);
+        let f = 1;
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(n, f);
+        config.set_transitive_conflicts(true);
+        let time = RunTime;
 
-        let order_a = vec![
-            (dot_1_1, Some(keys_1_1.clone()), clock_1_1.clone()),
-            (dot_1_2, Some(keys_1_2.clone()), clock_1_2.clone()),
-            (dot_2_1, Some(keys_2_1.clone()), clock_2_1.clone()),
-        ];
-        let order_b = vec![
-            (dot_1_2, Some(keys_1_2), clock_1_2),
-            (dot_2_1, Some(keys_2_1), clock_2_1),
-            (dot_1_1, Some(keys_1_1), clock_1_1),
-        ];
-        let order_a = check_termination(n, order_a, transitive_conflicts);
-        let order_b = check_termination(n, order_b, transitive_conflicts);
-        assert_eq!(order_a, order_b);
+        // (A, 1), key "x", no dependencies
+        let dot_a1 = Dot::new(1, 1);
+        let cmd_a1 =
+            Command::put(Rifl::new(1, 1), String::from("x"), String::new());
+        let deps_a1 =
+            uniform_deps(&cmd_a1, shard_id, util::vclock(vec![0, 0, 0]));
+
+        // (A, 2), key "y", no dependencies
+        let dot_a2 = Dot::new(1, 2);
+        let cmd_a2 =
+            Command::put(Rifl::new(1, 2), String::from("y"), String::new());
+        let deps_a2 =
+            uniform_deps(&cmd_a2, shard_id, util::vclock(vec![0, 0, 0]));
+
+        // (B, 1), keys "x" and "y": depends on (A, 1) through "x" and on
+        // (A, 2) through "y"
+        let dot_b1 = Dot::new(2, 1);
+        let cmd_b1 = Command::from(
+            Rifl::new(2, 1),
+            vec![
+                (String::from("x"), KVOp::Put(String::new())),
+                (String::from("y"), KVOp::Put(String::new())),
+            ],
+        );
+        let mut deps_b1 = HashMap::new();
+        deps_b1.insert(String::from("x"), util::vclock(vec![1, 0, 0]));
+        deps_b1.insert(String::from("y"), util::vclock(vec![2, 0, 0]));
+
+        // (A, 1), (A, 2), (B, 1): commands are executed in the order they're
+        // received
+        let mut queue_a = DependencyGraph::new(process_id, shard_id, &config);
+        queue_a.handle_add(dot_a1, cmd_a1.clone(), deps_a1.clone(), &time);
+        queue_a.handle_add(dot_a2, cmd_a2.clone(), deps_a2.clone(), &time);
+        queue_a.handle_add(dot_b1, cmd_b1.clone(), deps_b1.clone(), &time);
+        let order_a = queue_a.commands_to_execute();
+
+        // (A, 2), (B, 1), (A, 1): (B, 1) can only be delivered once both its
+        // per-key dependencies, (A, 1) and (A, 2), have been delivered
+        let mut queue_b = DependencyGraph::new(process_id, shard_id, &config);
+        queue_b.handle_add(dot_a2, cmd_a2, deps_a2, &time);
+        assert!(queue_b.commands_to_execute().is_empty());
+        queue_b.handle_add(dot_b1, cmd_b1, deps_b1, &time);
+        assert!(queue_b.commands_to_execute().is_empty());
+        queue_b.handle_add(dot_a1, cmd_a1, deps_a1, &time);
+        let order_b = queue_b.commands_to_execute();
+
+        assert_eq!(order_a.len(), 3);
+        assert_eq!(order_b.len(), 3);
     }
 
     #[test]
@@ -937,11 +1380,13 @@ mod tests {
             let time = RunTime;
 
             // add cmd 2
-            queue.handle_add(dot_2, cmd_2.clone(), clock_2.clone(), &time);
+            let deps_2 = uniform_deps(&cmd_2, shard_id, clock_2.clone());
+            queue.handle_add(dot_2, cmd_2.clone(), deps_2, &time);
             assert_eq!(queue.commands_to_execute(), vec![cmd_2.clone()]);
 
             // add cmd 3
-            queue.handle_add(dot_3, cmd_3.clone(), clock_3.clone(), &time);
+            let deps_3 = uniform_deps(&cmd_3, shard_id, clock_3.clone());
+            queue.handle_add(dot_3, cmd_3.clone(), deps_3, &time);
             if transitive_conflicts {
                 // if we assume transitive conflicts, then cmd 3 can be executed
                 assert_eq!(queue.commands_to_execute(), vec![cmd_3.clone()]);
@@ -951,7 +1396,8 @@ mod tests {
             }
 
             // add cmd 1
-            queue.handle_add(dot_1, cmd_1.clone(), clock_1.clone(), &time);
+            let deps_1 = uniform_deps(&cmd_1, shard_id, clock_1.clone());
+            queue.handle_add(dot_1, cmd_1.clone(), deps_1, &time);
             // cmd 1 can always be executed
             if transitive_conflicts {
                 assert_eq!(queue.commands_to_execute(), vec![cmd_1.clone()]);
@@ -1215,6 +1661,49 @@ mod tests {
         shuffle_it(n, transitive_conflicts, args);
     }
 
+    #[test]
+    fn deep_branching_chain_does_not_overflow_stack() {
+        // `test_long_chain_does_not_overflow_stack` stresses depth with a
+        // single process; this interleaves several processes so each round
+        // has multiple commands conflicting on the same key, deep enough
+        // that a recursive `strong_connect` (the traversal this replaced,
+        // back when it recursed once per unvisited dependency) would have
+        // blown the stack. Dots are added in submission order (like that
+        // test) rather than explored across every linear extension (like
+        // `test_add_random`), since exploring every permutation of a graph
+        // this deep would never finish.
+        let process_ids = [1, 2, 3];
+        let shard_id = 0;
+        let n = process_ids.len();
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_ids[0], shard_id, &config);
+        let time = RunTime;
+
+        let chain_len = 20_000;
+        let mut executed = 0;
+
+        for seq in 1..=chain_len {
+            for &process_id in &process_ids {
+                let dot = Dot::new(process_id, seq);
+                let rifl = Rifl::new(process_id as ClientId, seq);
+                let cmd =
+                    Command::put(rifl, String::from("CONF"), String::new());
+                // each command depends on the immediately preceding one
+                // from *every* process, so the dependency chain is deep
+                // and every command conflicts with every other (single
+                // shared key)
+                let prev = if seq > 1 { seq - 1 } else { 0 };
+                let clock = util::vclock(vec![prev; process_ids.len()]);
+                let deps = uniform_deps(&cmd, shard_id, clock);
+                queue.handle_add(dot, cmd, deps, &time);
+                executed += queue.commands_to_execute().len();
+            }
+        }
+
+        assert_eq!(executed, chain_len as usize * process_ids.len());
+    }
+
     #[test]
     fn test_add_random() {
         let shard_id = 0;
@@ -1334,22 +1823,172 @@ mod tests {
             .collect()
     }
 
+    type Arg = (Dot, Option<BTreeSet<Key>>, VClock<ProcessId>);
+
+    /// Instead of checking every one of the `n!` permutations of `args`
+    /// (most of which just reorder commands that don't causally depend on
+    /// each other, and thus are redundant), explore every linear extension
+    /// of the partial order induced by the commands' vector clocks: a
+    /// command is only delivered once every dependency dot that's also part
+    /// of this batch has already been delivered, mirroring how an actor's
+    /// inbox is drained in vector-clock order. Every such order is expected
+    /// to yield the same final execution order.
     fn shuffle_it(
         n: usize,
         transitive_conflicts: bool,
-        mut args: Vec<(Dot, Option<BTreeSet<Key>>, VClock<ProcessId>)>,
+        args: Vec<Arg>,
     ) {
         let total_order =
             check_termination(n, args.clone(), transitive_conflicts);
         println!("transitive_conflicts = {:?}", transitive_conflicts);
-        args.permutation().for_each(|permutation| {
-            println!("permutation = {:?}", permutation);
+
+        explore_causal_orders(args.clone(), &mut |order| {
+            println!(
+                "order = {:?}",
+                order.iter().map(|(dot, ..)| dot).collect::<Vec<_>>()
+            );
             let sorted =
-                check_termination(n, permutation, transitive_conflicts);
-            assert_eq!(total_order, sorted);
+                check_termination(n, order.clone(), transitive_conflicts);
+            if sorted != total_order {
+                let minimal = shrink_counterexample(
+                    n,
+                    transitive_conflicts,
+                    args.clone(),
+                    order.clone(),
+                );
+                panic!(
+                    "divergent execution order found for transitive_conflicts \
+                     = {:?}; minimal counterexample: {:?}",
+                    transitive_conflicts,
+                    minimal
+                        .iter()
+                        .map(|(dot, keys, clock)| (dot, keys, clock))
+                        .collect::<Vec<_>>(),
+                );
+            }
         });
     }
 
+    /// Direct causal predecessors of `dot` that are also part of `present`:
+    /// the dots that `clock` says `dot` has already observed.
+    fn causal_predecessors(
+        dot: Dot,
+        clock: &VClock<ProcessId>,
+        present: &HashSet<Dot>,
+    ) -> HashSet<Dot> {
+        clock
+            .clone()
+            .into_iter()
+            .flat_map(|(process_id, max_set)| {
+                (1..=max_set.frontier())
+                    .map(move |seq| Dot::new(process_id, seq))
+            })
+            .filter(|dep_dot| *dep_dot != dot && present.contains(dep_dot))
+            .collect()
+    }
+
+    /// Explores every linear extension of the causal partial order induced
+    /// by `args`' vector clocks via DFS, invoking `on_order` with each one.
+    fn explore_causal_orders(
+        args: Vec<Arg>,
+        on_order: &mut impl FnMut(&Vec<Arg>),
+    ) {
+        let present: HashSet<Dot> = args.iter().map(|(dot, ..)| *dot).collect();
+        let preds: HashMap<Dot, HashSet<Dot>> = args
+            .iter()
+            .map(|(dot, _, clock)| {
+                (*dot, causal_predecessors(*dot, clock, &present))
+            })
+            .collect();
+        let by_dot: HashMap<Dot, Arg> =
+            args.into_iter().map(|arg| (arg.0, arg)).collect();
+        let all_dots: Vec<Dot> = by_dot.keys().cloned().collect();
+
+        let mut placed = HashSet::new();
+        let mut order = Vec::with_capacity(all_dots.len());
+        dfs_causal_orders(
+            &all_dots, &preds, &by_dot, &mut placed, &mut order, on_order,
+        );
+    }
+
+    fn dfs_causal_orders(
+        all_dots: &[Dot],
+        preds: &HashMap<Dot, HashSet<Dot>>,
+        by_dot: &HashMap<Dot, Arg>,
+        placed: &mut HashSet<Dot>,
+        order: &mut Vec<Arg>,
+        on_order: &mut impl FnMut(&Vec<Arg>),
+    ) {
+        if order.len() == all_dots.len() {
+            on_order(order);
+            return;
+        }
+        for &dot in all_dots {
+            if placed.contains(&dot) {
+                continue;
+            }
+            if !preds[&dot].iter().all(|dep| placed.contains(dep)) {
+                continue;
+            }
+            placed.insert(dot);
+            order.push(by_dot[&dot].clone());
+            dfs_causal_orders(
+                all_dots, preds, by_dot, placed, order, on_order,
+            );
+            order.pop();
+            placed.remove(&dot);
+        }
+    }
+
+    /// Shrinks `failing_order` to a minimal counterexample: repeatedly drops
+    /// one command at a time, re-checking (against the same subset delivered
+    /// in `baseline_args`' original order) that the divergence persists,
+    /// until no command can be removed without it disappearing.
+    fn shrink_counterexample(
+        n: usize,
+        transitive_conflicts: bool,
+        baseline_args: Vec<Arg>,
+        mut failing_order: Vec<Arg>,
+    ) -> Vec<Arg> {
+        let diverges = |order: &[Arg]| -> bool {
+            if order.is_empty() {
+                return false;
+            }
+            let dots: HashSet<Dot> =
+                order.iter().map(|(dot, ..)| *dot).collect();
+            let baseline_subset: Vec<_> = baseline_args
+                .iter()
+                .filter(|(dot, ..)| dots.contains(dot))
+                .cloned()
+                .collect();
+            let expected =
+                check_termination(n, baseline_subset, transitive_conflicts);
+            let found =
+                check_termination(n, order.to_vec(), transitive_conflicts);
+            expected != found
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let mut index = 0;
+            while index < failing_order.len() {
+                let mut candidate = failing_order.clone();
+                candidate.remove(index);
+                if diverges(&candidate) {
+                    failing_order = candidate;
+                    changed = true;
+                    // don't advance `index`: re-check the element that slid
+                    // into this position
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        failing_order
+    }
+
     fn check_termination(
         n: usize,
         args: Vec<(Dot, Option<BTreeSet<Key>>, VClock<ProcessId>)>,
@@ -1385,7 +2024,8 @@ mod tests {
             assert!(all_rifls.insert(rifl));
 
             // add it to the queue
-            queue.handle_add(dot, cmd, clock, &time);
+            let deps = uniform_deps(&cmd, shard_id, clock);
+            queue.handle_add(dot, cmd, deps, &time);
 
             // get ready to execute
             let to_execute = queue.commands_to_execute();
@@ -1468,7 +2108,11 @@ mod tests {
         queue.vertex_index.index(Vertex::new(
             root_dot,
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 40, 61]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 40, 61]),
+            ),
             &time,
         ));
 
@@ -1476,70 +2120,110 @@ mod tests {
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 31),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 30, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 30, 60]),
+            ),
             &time,
         ));
         // (4, 32)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 32),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 31, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 31, 60]),
+            ),
             &time,
         ));
         // (4, 33)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 33),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 32, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 32, 60]),
+            ),
             &time,
         ));
         // (4, 34)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 34),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 33, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 33, 60]),
+            ),
             &time,
         ));
         // (4, 35)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 35),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 34, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 34, 60]),
+            ),
             &time,
         ));
         // (4, 36)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 36),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 35, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 35, 60]),
+            ),
             &time,
         ));
         // (4, 37)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 37),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 36, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 36, 60]),
+            ),
             &time,
         ));
         // (4, 38)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 38),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 37, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 37, 60]),
+            ),
             &time,
         ));
         // (4, 39)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 39),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 38, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 38, 60]),
+            ),
             &time,
         ));
         // (4, 40)
         queue.vertex_index.index(Vertex::new(
             Dot::new(4, 40),
             conflicting_command(),
-            util::vclock(vec![60, 50, 50, 39, 60]),
+            uniform_deps(
+                &conflicting_command(),
+                shard_id,
+                util::vclock(vec![60, 50, 50, 39, 60]),
+            ),
             &time,
         ));
 
@@ -1579,4 +2263,85 @@ mod tests {
             panic!("FinderInfo::MissingDependency not found");
         }
     }
+
+    #[test]
+    fn test_long_chain_does_not_overflow_stack() {
+        // a single process issuing one command right after another, each
+        // depending on the one immediately before it: a deep, skinny
+        // dependency chain, the shape a recursive SCC traversal handles
+        // worst (see `test_add_4` for the same shape at toy scale)
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 1;
+        let f = 0;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        let chain_len = 100_000;
+        let mut executed = 0;
+
+        for seq in 1..=chain_len {
+            let dot = Dot::new(process_id, seq);
+            let rifl = Rifl::new(process_id as ClientId, seq);
+            let cmd =
+                Command::put(rifl, String::from("CONF"), String::new());
+            // depend on the immediately preceding dot (0 means "none", for
+            // the very first command in the chain)
+            let clock = util::vclock(vec![seq - 1]);
+            let deps = uniform_deps(&cmd, shard_id, clock);
+
+            queue.handle_add(dot, cmd, deps, &time);
+            executed += queue.commands_to_execute().len();
+        }
+
+        assert_eq!(executed, chain_len as usize);
+    }
+
+    #[test]
+    fn test_long_chain_with_missing_root_is_released_once_resolved() {
+        // same long, skinny chain as `test_long_chain_does_not_overflow_stack`,
+        // but the very first dot is withheld: every other dot in the chain
+        // is pending on a dependency that's missing at depth, so resolving
+        // it exercises both the explicit work-stack traversal (no recursion
+        // depth limit) and the conflict-cache retry path in one shot
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 1;
+        let f = 0;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        let chain_len = 50_000;
+        let mut executed = 0;
+
+        for seq in 2..=chain_len {
+            let dot = Dot::new(process_id, seq);
+            let rifl = Rifl::new(process_id as ClientId, seq);
+            let cmd =
+                Command::put(rifl, String::from("CONF"), String::new());
+            let clock = util::vclock(vec![seq - 1]);
+            let deps = uniform_deps(&cmd, shard_id, clock);
+
+            queue.handle_add(dot, cmd, deps, &time);
+            executed += queue.commands_to_execute().len();
+        }
+        assert_eq!(
+            executed, 0,
+            "nothing can execute while the chain root is missing"
+        );
+
+        // finally add the missing root: the entire chain should become
+        // ready in one shot
+        let dot = Dot::new(process_id, 1);
+        let rifl = Rifl::new(process_id as ClientId, 1);
+        let cmd = Command::put(rifl, String::from("CONF"), String::new());
+        let clock = util::vclock(vec![0]);
+        let deps = uniform_deps(&cmd, shard_id, clock);
+        queue.handle_add(dot, cmd, deps, &time);
+        executed += queue.commands_to_execute().len();
+
+        assert_eq!(executed, chain_len as usize);
+    }
 }
@@ -0,0 +1,255 @@
+use fantoch::command::Command;
+use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::kvs::Key;
+use fantoch::log;
+use fantoch::{HashMap, HashSet};
+use std::collections::VecDeque;
+use threshold::{EventSet, VClock};
+
+/// Default size of the scheduler's look-ahead window: the maximum number of
+/// commits that can be buffered waiting to be dispatched. Bounding this keeps
+/// `try_dispatch` proportional to the window instead of to the whole graph.
+const DEFAULT_WINDOW: usize = 2048;
+
+/// A commit accepted by the scheduler but not yet dispatched to a worker,
+/// either because some of its dependencies haven't been executed or assigned
+/// yet, or because dispatching it now would conflict with work already in
+/// flight.
+struct Scheduled {
+    dot: Dot,
+    cmd: Command,
+    // dependency dots still to be satisfied (i.e. executed or dispatched)
+    // before this command can be dispatched
+    missing: HashSet<Dot>,
+}
+
+/// A multi-worker scheduler that partitions the commit stream across `N`
+/// `DependencyGraph` workers while preserving the invariant discussed in
+/// `transitive_conflicts_assumption_regression_test_1` (see `super`): commands
+/// from the same `ProcessId` are never split across workers, since a worker
+/// only ever sees commands submitted strictly after the ones it already
+/// processed from that same process.
+///
+/// Commits arrive in any order (via `schedule`) and are held in a bounded
+/// look-ahead window until they become schedulable, i.e. every dependency dot
+/// is either executed or already assigned to a worker. A schedulable command
+/// is then dispatched to a worker chosen so that:
+/// - all commands from the same `ProcessId` always go to the same worker;
+/// - no two commands with overlapping keys are ever in flight at the same
+///   time (an account-lock per `Key`, held by whichever dot is currently
+///   assigned).
+///
+/// Once a worker reports (via `handle_executed`) that a dispatched command
+/// has executed, the keys it held are released and any successor blocked on
+/// it may become schedulable.
+pub struct GraphScheduler {
+    shard_id: ShardId,
+    workers: usize,
+    window: usize,
+    // worker assigned to each process the first time one of its commands is
+    // scheduled; later commands from the same process reuse it
+    process_worker: HashMap<ProcessId, usize>,
+    // key -> dot currently holding the "lock" for that key
+    key_owner: HashMap<Key, Dot>,
+    // keys held by each in-flight (i.e. dispatched but not yet executed) dot
+    in_flight: HashMap<Dot, Vec<Key>>,
+    // commits buffered in the look-ahead window, in arrival order
+    pending: VecDeque<Scheduled>,
+    // dots already dispatched or executed, used to resolve `missing` entries
+    resolved: HashSet<Dot>,
+    // commands ready to be executed, per worker
+    to_execute: Vec<Vec<Command>>,
+}
+
+impl GraphScheduler {
+    /// Creates a new scheduler with `workers` workers and the default
+    /// look-ahead window.
+    pub fn new(shard_id: ShardId, workers: usize) -> Self {
+        Self::with_window(shard_id, workers, DEFAULT_WINDOW)
+    }
+
+    /// Creates a new scheduler with `workers` workers and an explicit
+    /// look-ahead window.
+    pub fn with_window(
+        shard_id: ShardId,
+        workers: usize,
+        window: usize,
+    ) -> Self {
+        assert!(workers > 0, "a scheduler needs at least one worker");
+        Self {
+            shard_id,
+            workers,
+            window,
+            process_worker: HashMap::new(),
+            key_owner: HashMap::new(),
+            in_flight: HashMap::new(),
+            pending: VecDeque::new(),
+            resolved: HashSet::new(),
+            to_execute: vec![Vec::new(); workers],
+        }
+    }
+
+    /// Accepts a new commit, to be dispatched once all of its dependencies
+    /// are executed or assigned, and none of its keys conflict with work
+    /// currently in flight.
+    pub fn schedule(
+        &mut self,
+        dot: Dot,
+        cmd: Command,
+        deps: HashMap<Key, VClock<ProcessId>>,
+    ) {
+        let missing: HashSet<Dot> = deps
+            .into_iter()
+            .flat_map(|(_key, clock)| clock.into_iter())
+            .map(|(process_id, to)| Dot::new(process_id, to.frontier()))
+            .filter(|dep_dot| {
+                *dep_dot != dot && !self.resolved.contains(dep_dot)
+            })
+            .collect();
+
+        log!("GraphScheduler::schedule {:?} | missing {:?}", dot, missing);
+
+        self.pending.push_back(Scheduled { dot, cmd, missing });
+        assert!(
+            self.pending.len() <= self.window,
+            "GraphScheduler look-ahead window exceeded: {} pending commits",
+            self.pending.len()
+        );
+
+        self.try_dispatch();
+    }
+
+    /// Notifies the scheduler that `dot` has finished executing, releasing
+    /// the key locks it held and unblocking any pending commit waiting on it.
+    pub fn handle_executed(&mut self, dot: Dot) {
+        if let Some(keys) = self.in_flight.remove(&dot) {
+            for key in keys {
+                if self.key_owner.get(&key) == Some(&dot) {
+                    self.key_owner.remove(&key);
+                }
+            }
+        }
+        self.resolved.insert(dot);
+
+        for scheduled in self.pending.iter_mut() {
+            scheduled.missing.remove(&dot);
+        }
+
+        self.try_dispatch();
+    }
+
+    /// Returns the commands ready to be executed by `worker`.
+    #[must_use]
+    pub fn commands_to_execute(&mut self, worker: usize) -> Vec<Command> {
+        std::mem::take(&mut self.to_execute[worker])
+    }
+
+    /// Tries to dispatch every schedulable commit currently in the window.
+    /// Runs in `O(window)`: each pass scans the (bounded) window once, and a
+    /// dispatch never grows it.
+    fn try_dispatch(&mut self) {
+        let mut index = 0;
+        while index < self.pending.len() {
+            let ready = {
+                let scheduled = &self.pending[index];
+                scheduled.missing.is_empty()
+                    && scheduled
+                        .cmd
+                        .keys(self.shard_id)
+                        .all(|key| !self.key_owner.contains_key(key))
+            };
+
+            if ready {
+                let scheduled = self
+                    .pending
+                    .remove(index)
+                    .expect("index was checked to be in bounds");
+                self.dispatch(scheduled);
+                // the commit that slid into `index` hasn't been checked yet
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Assigns `scheduled` to its worker, locking the keys it accesses.
+    fn dispatch(&mut self, scheduled: Scheduled) {
+        let Scheduled { dot, cmd, .. } = scheduled;
+
+        let worker = match self.process_worker.get(&dot.source()) {
+            Some(&worker) => worker,
+            None => {
+                let worker = self.process_worker.len() % self.workers;
+                self.process_worker.insert(dot.source(), worker);
+                worker
+            }
+        };
+
+        let keys: Vec<Key> = cmd.keys(self.shard_id).cloned().collect();
+        for key in &keys {
+            self.key_owner.insert(key.clone(), dot);
+        }
+        self.in_flight.insert(dot, keys);
+        self.resolved.insert(dot);
+
+        log!(
+            "GraphScheduler::dispatch {:?} to worker {}",
+            dot,
+            worker
+        );
+        self.to_execute[worker].push(cmd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::id::Rifl;
+
+    #[test]
+    fn same_process_same_worker() {
+        let shard_id = 0;
+        let mut scheduler = GraphScheduler::new(shard_id, 2);
+
+        let dot_1 = Dot::new(1, 1);
+        let cmd_1 =
+            Command::put(Rifl::new(1, 1), String::from("A"), String::new());
+        scheduler.schedule(dot_1, cmd_1, HashMap::new());
+
+        let dot_2 = Dot::new(1, 2);
+        let cmd_2 =
+            Command::put(Rifl::new(1, 2), String::from("B"), String::new());
+        scheduler.schedule(dot_2, cmd_2, HashMap::new());
+
+        // both commands were submitted by process 1, so they must land on
+        // the same worker
+        let worker = *scheduler.process_worker.get(&1).unwrap();
+        assert_eq!(scheduler.commands_to_execute(worker).len(), 2);
+    }
+
+    #[test]
+    fn conflicting_keys_block_until_executed() {
+        let shard_id = 0;
+        let mut scheduler = GraphScheduler::new(shard_id, 2);
+
+        let dot_1 = Dot::new(1, 1);
+        let cmd_1 =
+            Command::put(Rifl::new(1, 1), String::from("A"), String::new());
+        scheduler.schedule(dot_1, cmd_1, HashMap::new());
+
+        let dot_2 = Dot::new(2, 1);
+        let cmd_2 =
+            Command::put(Rifl::new(2, 1), String::from("A"), String::new());
+        scheduler.schedule(dot_2, cmd_2, HashMap::new());
+
+        // (2, 1) conflicts with (1, 1) on key "A" and isn't dispatched yet
+        let worker_1 = *scheduler.process_worker.get(&1).unwrap();
+        assert_eq!(scheduler.commands_to_execute(worker_1).len(), 1);
+        assert!(scheduler.process_worker.get(&2).is_none());
+
+        // once (1, 1) executes, key "A" is released and (2, 1) can dispatch
+        scheduler.handle_executed(dot_1);
+        let worker_2 = *scheduler.process_worker.get(&2).unwrap();
+        assert_eq!(scheduler.commands_to_execute(worker_2).len(), 1);
+    }
+}
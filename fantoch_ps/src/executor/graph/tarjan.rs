@@ -1,13 +1,17 @@
+use super::conflict::ConflictRelation;
 use super::index::{VertexIndex, VertexRef};
 use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::kvs::Key;
 use fantoch::log;
 use fantoch::time::SysTime;
-use fantoch::HashSet;
+use fantoch::{HashMap, HashSet};
 use std::cmp;
 use std::collections::BTreeSet;
+use std::sync::Arc;
 use threshold::{AEClock, EventSet, VClock};
+use tracing::trace;
 
 /// commands are sorted inside an SCC given their dot
 pub type SCC = BTreeSet<Dot>;
@@ -25,6 +29,10 @@ pub struct TarjanSCCFinder {
     process_id: ProcessId,
     shard_id: ShardId,
     config: Config,
+    // the relation deciding which commands conflict; consulted (via
+    // `ConflictRelation::transitive`) when unfolding a vertex's dependency
+    // frontier, see `unfold`
+    conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
     id: usize,
     stack: Vec<Dot>,
     sccs: Vec<SCC>,
@@ -36,11 +44,13 @@ impl TarjanSCCFinder {
         process_id: ProcessId,
         shard_id: ShardId,
         config: Config,
+        conflict_relation: Arc<dyn ConflictRelation + Send + Sync>,
     ) -> Self {
         Self {
             process_id,
             shard_id,
             config,
+            conflict_relation,
             id: 0,
             stack: Vec::new(),
             sccs: Vec::new(),
@@ -89,6 +99,19 @@ impl TarjanSCCFinder {
     }
 
     /// Tries to find an SCC starting from root `dot`.
+    ///
+    /// This used to be a plain recursive descent (one native call frame per
+    /// dependency edge followed), which meant a long chain of same-process
+    /// commands could blow the stack. It's now driven by an explicit work
+    /// stack of `Frame`s, each holding exactly what a recursive activation
+    /// would have held on its way down: the vertex it's visiting and the
+    /// dependency dots it still has left to check. `unfold` pushes a new
+    /// frame (equivalent to making a recursive call); reaching the end of a
+    /// frame's dependencies is the "fold" step (equivalent to a call
+    /// returning), where its low-link value is carried up into its parent
+    /// frame, exactly as a return value would be. There's no recursion depth
+    /// limit, and the same `work` vector could, in principle, be parked and
+    /// resumed later instead of being driven to completion in one go.
     pub fn strong_connect(
         &mut self,
         dot: Dot,
@@ -97,6 +120,184 @@ impl TarjanSCCFinder {
         vertex_index: &VertexIndex,
         found: &mut usize,
     ) -> FinderResult {
+        let mut work = vec![self.unfold(dot, vertex_ref)];
+
+        while let Some(current_dot) = work.last().map(|frame| frame.dot) {
+            let next_dep = work.last_mut().unwrap().deps.next();
+
+            match next_dep {
+                Some(dep_dot) => match vertex_index.find(&dep_dot) {
+                    None => {
+                        // TODO we should panic if we find a dependency
+                        // highest than self
+                        let missing = std::iter::once(dep_dot);
+                        let missing = if self.config.shards() == 1 {
+                            missing.collect()
+                        } else {
+                            // if partial replication, add remaining frontier
+                            // deps as missing dependencies; this makes sure
+                            // that we request all needed dependencies in a
+                            // single request
+                            let rest = std::mem::replace(
+                                &mut work.last_mut().unwrap().deps,
+                                Vec::new().into_iter(),
+                            );
+                            missing.chain(rest).collect()
+                        };
+                        log!(
+                            "p{}: Finder::strong_connect missing {:?} | {:?}",
+                            self.process_id,
+                            dep_dot,
+                            missing
+                        );
+                        // live introspection: let an operator asking "why
+                        // is execution stalled?" see which dot blocked
+                        // `current_dot` without waiting for the next
+                        // periodic `tracer_task` dump
+                        trace!(
+                            event = "vertex.missing_dependencies",
+                            dot = ?current_dot,
+                            blocked_on = ?missing,
+                        );
+                        return FinderResult::MissingDependencies(missing);
+                    }
+                    Some(dep_vertex_ref) => {
+                        let dep_id = dep_vertex_ref.read().id;
+
+                        // if not visited, visit (i.e. unfold a new frame
+                        // instead of recursing into it)
+                        if dep_id == 0 {
+                            log!(
+                                "p{}: Finder::strong_connect non-visited {:?}",
+                                self.process_id,
+                                dep_dot
+                            );
+                            work.push(self.unfold(dep_dot, &dep_vertex_ref));
+                        } else if dep_vertex_ref.read().on_stack {
+                            log!("p{}: Finder::strong_connect dependency on stack {:?}", self.process_id, dep_dot);
+                            // min low with dep id
+                            let mut vertex = vertex_index
+                                .find(&current_dot)
+                                .expect("vertex should still be indexed")
+                                .write();
+                            vertex.low = cmp::min(vertex.low, dep_id);
+                        }
+                    }
+                },
+                None => {
+                    // fold: `current_dot` has no dependencies left to check,
+                    // so pop its frame - this is the point where a recursive
+                    // call would return
+                    work.pop();
+
+                    let (id, low) = {
+                        let vertex = vertex_index
+                            .find(&current_dot)
+                            .expect("vertex should still be indexed")
+                            .read();
+                        (vertex.id, vertex.low)
+                    };
+
+                    // `current_dot` has just walked every dependency it
+                    // still had left to check, so from this vertex's point
+                    // of view its dependencies are now satisfied (whether
+                    // or not it ends up closing an SCC of its own)
+                    trace!(
+                        event = "vertex.dependencies_satisfied",
+                        dot = ?current_dot,
+                    );
+
+                    // an SCC was found if vertex.id == vertex.low - good
+                    // news: the SCC members are on the stack
+                    if id == low {
+                        let mut scc = SCC::new();
+
+                        loop {
+                            // pop an element from the stack
+                            let member_dot = self.stack.pop().expect(
+                                "there should be an SCC member on the stack",
+                            );
+
+                            log!(
+                                "p{}: Finder::strong_connect new SCC member {:?}",
+                                self.process_id,
+                                member_dot
+                            );
+
+                            // get its vertex and change its `on_stack` value
+                            let member_vertex_ref = vertex_index
+                                .find(&member_dot)
+                                .expect("stack member should exist");
+
+                            // increment number of commands found
+                            *found += 1;
+
+                            let mut member_vertex = member_vertex_ref.write();
+                            member_vertex.on_stack = false;
+
+                            // add it to the SCC and check it wasn't there
+                            // before
+                            assert!(scc.insert(member_dot));
+
+                            // drop guards
+                            drop(member_vertex);
+                            drop(member_vertex_ref);
+
+                            trace!(event = "vertex.entered_scc", dot = ?member_dot);
+
+                            // update executed clock:
+                            // - this is a nice optimization (that I think we
+                            //   missed in Atlas); instead of waiting for the
+                            //   root-level recursion to finish in order to
+                            //   update `executed_clock` (which is consulted
+                            //   to decide what are the dependencies of a
+                            //   command), we can update it right here,
+                            //   possibly reducing a few iterations
+                            executed_clock
+                                .add(&member_dot.source(), member_dot.sequence());
+
+                            log!(
+                                "p{}: Finder::strong_connect executed clock {:?}",
+                                self.process_id,
+                                executed_clock
+                            );
+
+                            // quit if root of this SCC is found
+                            if member_dot == current_dot {
+                                break;
+                            }
+                        }
+
+                        // add scc to the set of sccs
+                        self.sccs.push(scc);
+                    }
+
+                    // carry `low` up into the parent frame, exactly like a
+                    // returning recursive call updating its caller's `low`
+                    if let Some(parent) = work.last() {
+                        let parent_dot = parent.dot;
+                        let mut parent_vertex = vertex_index
+                            .find(&parent_dot)
+                            .expect("vertex should still be indexed")
+                            .write();
+                        parent_vertex.low = cmp::min(parent_vertex.low, low);
+                    }
+                }
+            }
+        }
+
+        // the root is never itself a dependency of anything already on the
+        // stack when we start, so its low-link can never drop below its own
+        // id: once `work` is empty (and we haven't already returned with a
+        // missing dependency above), the root must have closed its own SCC
+        FinderResult::Found
+    }
+
+    /// "Unfolds" a new frame for `dot`: the non-recursive equivalent of
+    /// entering a fresh `strong_connect` call - assigns it an id/low, pushes
+    /// it onto the Tarjan stack, and computes the (deduplicated) dependency
+    /// dots it still needs to visit.
+    fn unfold(&mut self, dot: Dot, vertex_ref: &VertexRef<'_>) -> Frame {
         // update id
         self.id += 1;
 
@@ -109,6 +310,20 @@ impl TarjanSCCFinder {
 
         // add to the stack
         vertex.on_stack = true;
+
+        // prefer the transitively-reduced dependency set computed by
+        // `reduce`, when available, over recomputing the full (unreduced)
+        // frontier from `vertex.deps` every time - see `reduce` for why
+        // this is safe
+        let deps: Vec<Dot> = match &vertex.reduced_deps {
+            Some(reduced) => reduced.iter().copied().collect(),
+            None => {
+                // TODO can we avoid vertex.deps.clone()
+                Self::flatten_deps(&vertex.deps, dot, &*self.conflict_relation)
+            }
+        };
+        drop(vertex);
+
         self.stack.push(dot);
 
         log!(
@@ -118,212 +333,239 @@ impl TarjanSCCFinder {
             self.id
         );
 
-        // TODO can we avoid vertex.clock.clone()
-        // compute non-executed deps for each process
-        let clock = vertex.clock.clone();
-        let mut deps_iter = clock.into_iter();
-        while let Some((process_id, to)) = deps_iter.next() {
-            let dep_dot = Dot::new(process_id, to.frontier());
-
-            // TODO we should panic if we find a dependency highest than self
-            if dot == dep_dot {
-                // ignore self
-                continue;
-            }
+        Frame {
+            dot,
+            deps: deps.into_iter(),
+        }
+    }
 
-            match vertex_index.find(&dep_dot) {
-                None => {
-                    let deps = std::iter::once(dep_dot);
-                    let deps = if self.config.shards() == 1 {
-                        deps.collect()
-                    } else {
-                        // if partial replication, add remaining frontier
-                        // deps as missing dependencies; this makes sure
-                        // that we request all needed dependencies in a
-                        // single request
-                        deps.chain(deps_iter.map(|(process_id, to)| {
-                            Dot::new(process_id, to.frontier())
-                        }))
-                        .collect()
-                    };
-                    log!(
-                        "p{}: Finder::strong_connect missing {:?} | {:?}",
-                        self.process_id,
-                        dep_dot,
-                        deps
-                    );
-                    return FinderResult::MissingDependencies(deps);
+    /// Flattens `deps` (one highest-conflicting-dot-per-replica clock per
+    /// key the command touches; see `Vertex::deps`) into the deduplicated
+    /// list of dependency dots `dot` still needs to visit.
+    ///
+    /// If the conflict relation is transitive, no per-key precision can
+    /// ever be observed: the per-key clocks are collapsed into a single
+    /// merged clock (the highest conflicting dot per replica, across every
+    /// key) before computing the dependency frontier, instead of walking
+    /// each key's clock separately.
+    fn flatten_deps(
+        deps: &HashMap<Key, VClock<ProcessId>>,
+        dot: Dot,
+        conflict_relation: &(dyn ConflictRelation + Send + Sync),
+    ) -> Vec<Dot> {
+        let clocks: Vec<VClock<ProcessId>> = if conflict_relation.transitive() {
+            let mut clocks = deps.values().cloned();
+            match clocks.next() {
+                Some(mut merged) => {
+                    for clock in clocks {
+                        merged.join(&clock);
+                    }
+                    vec![merged]
                 }
-                Some(dep_vertex_ref) => {
-                    // get vertex
-                    let mut dep_vertex = dep_vertex_ref.read();
-
-                    // if not visited, visit
-                    if dep_vertex.id == 0 {
-                        log!(
-                            "p{}: Finder::strong_connect non-visited {:?}",
-                            self.process_id,
-                            dep_dot
-                        );
+                None => Vec::new(),
+            }
+        } else {
+            deps.values().cloned().collect()
+        };
+
+        let mut seen = HashSet::new();
+        clocks
+            .into_iter()
+            .flat_map(|clock| clock.into_iter())
+            .map(|(process_id, to)| Dot::new(process_id, to.frontier()))
+            .filter(|dep_dot| {
+                // ignore self and dependencies already seen (the same dot
+                // can be the frontier for more than one key)
+                *dep_dot != dot && seen.insert(*dep_dot)
+            })
+            .collect()
+    }
 
-                        // drop guards
-                        drop(vertex);
-                        drop(dep_vertex);
+    /// Computes a transitive reduction of the pending dependency graph and
+    /// caches it on each indexed `Vertex` (see `Vertex::reduced_deps`), so
+    /// `strong_connect` no longer re-descends into a dependency that was
+    /// already reachable through another dependency of the same vertex.
+    ///
+    /// For each vertex `v` with (flattened) dependency set `D(v)`, a dot
+    /// `d` is dropped from `D(v)` if it's reachable from some other
+    /// `d' != d` in `D(v)` by following dependency edges. Reachability is
+    /// computed with a DFS memoized per dot (`reachable_cache`, so a dot
+    /// shared by several vertices' dependency sets is only walked once),
+    /// bounded by `executed_clock`: an already-executed dot is treated as
+    /// a leaf, since its own (already-applied) dependencies can no longer
+    /// matter to anything still pending.
+    ///
+    /// A dependency not yet present in `vertex_index` (and not already
+    /// executed) is opaque - we can't see what it would transitively
+    /// reach - so whenever the DFS hits one, `v`'s dependency set is left
+    /// entirely untouched rather than partially pruned, so that
+    /// `MissingDependencies` reporting keeps seeing the full, unreduced
+    /// set.
+    pub fn reduce(
+        &mut self,
+        vertex_index: &VertexIndex,
+        executed_clock: &AEClock<ProcessId>,
+    ) {
+        let mut reachable_cache: HashMap<Dot, Option<HashSet<Dot>>> =
+            HashMap::new();
+
+        for dot in vertex_index.dots().collect::<Vec<_>>() {
+            let vertex_ref = match vertex_index.find(&dot) {
+                Some(vertex_ref) => vertex_ref,
+                None => continue,
+            };
+            let deps = Self::flatten_deps(
+                &vertex_ref.read().deps,
+                dot,
+                &*self.conflict_relation,
+            );
 
-                        // OPTIMIZATION: passing the dep vertex ref as an
-                        // argument to `strong_connect` avoids double look-up
-                        let result = self.strong_connect(
-                            dep_dot,
-                            &dep_vertex_ref,
-                            executed_clock,
-                            vertex_index,
-                            found,
+            let mut redundant = HashSet::new();
+            let mut opaque = false;
+            for &dep in &deps {
+                match self.reachable(
+                    dep,
+                    vertex_index,
+                    executed_clock,
+                    &mut reachable_cache,
+                ) {
+                    Some(reachable_from_dep) => {
+                        redundant.extend(
+                            deps.iter()
+                                .copied()
+                                .filter(|d| *d != dep && reachable_from_dep.contains(d)),
                         );
-
-                        // if missing dependency, give up
-                        if let FinderResult::MissingDependencies(_) = result {
-                            return result;
-                        }
-
-                        // get guards again
-                        vertex = vertex_ref.write();
-                        dep_vertex = dep_vertex_ref.read();
-
-                        // min low with dep low
-                        vertex.low = cmp::min(vertex.low, dep_vertex.low);
-
-                        // drop dep guard
-                        drop(dep_vertex);
-                    } else {
-                        // if visited and on the stack
-                        if dep_vertex.on_stack {
-                            log!("p{}: Finder::strong_connect dependency on stack {:?}", self.process_id, dep_dot);
-                            // min low with dep id
-                            vertex.low = cmp::min(vertex.low, dep_vertex.id);
-                        }
-
-                        // drop dep guard
-                        drop(dep_vertex);
+                    }
+                    None => {
+                        opaque = true;
+                        break;
                     }
                 }
             }
+
+            if opaque {
+                continue;
+            }
+
+            let reduced: HashSet<Dot> = deps
+                .into_iter()
+                .filter(|dep| !redundant.contains(dep))
+                .collect();
+            vertex_ref.write().reduced_deps = Some(reduced);
         }
+    }
 
-        // if after visiting all neighbors, an SCC was found if vertex.id ==
-        // vertex.low
-        // - good news: the SCC members are on the stack
-        if vertex.id == vertex.low {
-            let mut scc = SCC::new();
-
-            // drop guards
-            drop(vertex);
-            drop(vertex_ref);
-
-            loop {
-                // pop an element from the stack
-                let member_dot = self
-                    .stack
-                    .pop()
-                    .expect("there should be an SCC member on the stack");
-
-                log!(
-                    "p{}: Finder::strong_connect new SCC member {:?}",
-                    self.process_id,
-                    member_dot
-                );
+    /// Returns the set of dots transitively reachable from `dot` (not
+    /// including `dot` itself), or `None` if that can't be determined
+    /// because some dependency along the way isn't indexed (and isn't
+    /// already executed) yet. Memoizes results in `cache`.
+    fn reachable(
+        &self,
+        dot: Dot,
+        vertex_index: &VertexIndex,
+        executed_clock: &AEClock<ProcessId>,
+        cache: &mut HashMap<Dot, Option<HashSet<Dot>>>,
+    ) -> Option<HashSet<Dot>> {
+        if let Some(cached) = cache.get(&dot) {
+            return cached.clone();
+        }
 
-                // get its vertex and change its `on_stack` value
-                let member_vertex_ref = vertex_index
-                    .find(&member_dot)
-                    .expect("stack member should exist");
-
-                // increment number of commands found
-                *found += 1;
-
-                // get its vertex and change its `on_stack` value
-                let mut member_vertex = member_vertex_ref.write();
-                member_vertex.on_stack = false;
-
-                // add it to the SCC and check it wasn't there before
-                assert!(scc.insert(member_dot));
-
-                // drop guards
-                drop(member_vertex);
-                drop(member_vertex_ref);
-
-                // update executed clock:
-                // - this is a nice optimization (that I think we missed in
-                //   Atlas); instead of waiting for the root-level recursion to
-                //   finish in order to update `executed_clock` (which is
-                //   consulted to decide what are the dependencies of a
-                //   command), we can update it right here, possibly reducing a
-                //   few iterations
-
-                // TODO add this check back:
-                // check if the command is replicated by my shard
-                // let is_mine =
-                // member_vertex.cmd.replicated_by(&self.shard_id);
-                // if executed_clock.write("Finder::strong_connect", |clock| {
-                //     clock.add(&member_dot.source(), member_dot.sequence())
-                // })
-                // && is_mine
-                // {
-                //     panic!(
-                //         "p{}: Finder::strong_connect dot {:?} already
-                // executed",         self.process_id,
-                // member_dot     );
-                // }
-                executed_clock.add(&member_dot.source(), member_dot.sequence());
-
-                log!(
-                    "p{}: Finder::strong_connect executed clock {:?}",
-                    self.process_id,
-                    executed_clock
-                );
+        // guard against re-entering `dot` while it's already being
+        // computed (i.e. a dependency cycle, which is exactly what an
+        // not-yet-discovered SCC looks like): contribute nothing further
+        // along that path instead of looping forever
+        cache.insert(dot, Some(HashSet::new()));
 
-                // quit if root is found
-                if member_dot == dot {
-                    break;
+        let result = if executed_clock.contains(&dot.source(), dot.sequence())
+        {
+            // already executed: nothing left to reach through
+            Some(HashSet::new())
+        } else {
+            match vertex_index.find(&dot) {
+                None => None,
+                Some(vertex_ref) => {
+                    let deps = Self::flatten_deps(
+                        &vertex_ref.read().deps,
+                        dot,
+                        &*self.conflict_relation,
+                    );
+                    let mut reachable = HashSet::new();
+                    let mut ok = true;
+                    for dep in deps {
+                        reachable.insert(dep);
+                        match self.reachable(
+                            dep,
+                            vertex_index,
+                            executed_clock,
+                            cache,
+                        ) {
+                            Some(further) => reachable.extend(further),
+                            None => {
+                                ok = false;
+                                break;
+                            }
+                        }
+                    }
+                    if ok {
+                        Some(reachable)
+                    } else {
+                        None
+                    }
                 }
             }
+        };
 
-            // add scc to to the set of sccs
-            self.sccs.push(scc);
-            FinderResult::Found
-        } else {
-            FinderResult::NotFound
-        }
+        cache.insert(dot, result.clone());
+        result
     }
 }
 
+/// The state a recursive `strong_connect` activation would hold on its way
+/// down: which vertex it's visiting, and the dependency dots it still has
+/// left to check.
+struct Frame {
+    dot: Dot,
+    deps: std::vec::IntoIter<Dot>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Vertex {
     dot: Dot,
     pub cmd: Command,
-    pub clock: VClock<ProcessId>,
+    // one highest-conflicting-dot-per-replica clock per key the command
+    // accesses, instead of a single clock merged across every key; see
+    // `TarjanSCCFinder::strong_connect` for why the per-key granularity
+    // matters
+    pub deps: HashMap<Key, VClock<ProcessId>>,
     start_time: u64,
     // specific to tarjan's algorithm
     id: usize,
     low: usize,
     on_stack: bool,
+    // transitive reduction of `deps` computed by `TarjanSCCFinder::reduce`,
+    // `None` until the first `reduce` pass that can fully resolve it (see
+    // `reduce` for when a vertex is left unreduced)
+    reduced_deps: Option<HashSet<Dot>>,
 }
 
 impl Vertex {
     pub fn new(
         dot: Dot,
         cmd: Command,
-        clock: VClock<ProcessId>,
+        deps: HashMap<Key, VClock<ProcessId>>,
         time: &dyn SysTime,
     ) -> Self {
         let start_time = time.millis();
+        trace!(event = "vertex.created", ?dot);
         Self {
             dot,
             cmd,
-            clock,
+            deps,
             start_time,
             id: 0,
             low: 0,
             on_stack: false,
+            reduced_deps: None,
         }
     }
 
@@ -331,9 +573,17 @@ impl Vertex {
     pub fn into_command(self, time: &dyn SysTime) -> (u64, Command) {
         let end_time = time.millis();
         let duration = end_time - self.start_time;
+        trace!(event = "vertex.executed", dot = ?self.dot, duration);
         (duration, self.cmd)
     }
 
+    /// How long (in millis, per the same clock `Vertex::new` was given)
+    /// this vertex has been sitting in the pending index -- used by
+    /// `DependencyGraph::snapshot` to report the longest-waiting vertex.
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
     /// Retrieves vertex's dot.
     pub fn dot(&self) -> Dot {
         self.dot
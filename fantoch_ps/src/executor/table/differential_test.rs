@@ -0,0 +1,297 @@
+//! Generates random sequences of `TableExecutionInfo::Votes`/
+//! `DetachedVotes` messages, feeds the same multiset into a fresh
+//! `TableExecutor` under many different delivery orders, and checks that
+//! the observable outcome doesn't depend on which order they arrived in.
+//!
+//! This mirrors `executor::graph::mod::tests`'s `shuffle_it` /
+//! `shrink_counterexample` approach (generate, permute, diff against a
+//! baseline order, shrink on divergence) rather than introducing the
+//! `proptest` crate, which nothing else in this workspace depends on.
+//!
+//! `ExecutorResult` isn't defined in this snapshot (like `Config`,
+//! `MultiVotesTable`, and `KVStore`, which `executor.rs` already calls
+//! into without being able to see), so this assumes it exposes `rifl()`/
+//! `key()` accessors, matching this crate's general getter convention.
+//! Likewise, the real stability threshold comes from
+//! `Config::newt_quorum_sizes`, which isn't derivable here either, so
+//! `STABILITY_THRESHOLD` below is a plausible fixed value for a
+//! 3-process, `f = 1` cluster instead of something computed from `Config`.
+
+use super::*;
+use fantoch::id::ClientId;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+const STABILITY_THRESHOLD: usize = 2;
+
+/// One command this harness generated: enough to both build the
+/// `TableExecutionInfo` messages that carry it and to know, independently
+/// of `TableExecutor`, what order it's expected to come out in.
+#[derive(Debug, Clone)]
+struct GeneratedDot {
+    dot: Dot,
+    rifl: Rifl,
+    key: Key,
+    clock: u64,
+    op: KVOp,
+}
+
+/// Builds `dot_count` dots round-robined across `n` processes, each
+/// targeting one of `key_count` keys, with strictly increasing clocks per
+/// key in generation order - so a harness failure that reorders two
+/// same-key dots is a genuine invariant violation, not an artifact of two
+/// dots sharing a clock value.
+fn generate_dots(n: usize, key_count: usize, dot_count: usize) -> Vec<GeneratedDot> {
+    let keys: Vec<Key> = (0..key_count).map(|i| format!("K{}", i)).collect();
+    let mut next_clock = vec![0u64; key_count];
+    let mut next_seq = vec![0u64; n];
+
+    (0..dot_count)
+        .map(|i| {
+            let process_idx = i % n;
+            let process_id = (process_idx + 1) as ProcessId;
+            next_seq[process_idx] += 1;
+            let seq = next_seq[process_idx];
+
+            let key_idx = i % key_count;
+            next_clock[key_idx] += 1;
+
+            GeneratedDot {
+                dot: Dot::new(process_id, seq),
+                rifl: Rifl::new(process_id as ClientId, seq),
+                key: keys[key_idx].clone(),
+                clock: next_clock[key_idx],
+                op: KVOp::Put(format!("v{}", i)),
+            }
+        })
+        .collect()
+}
+
+/// Turns `dots` into the `TableExecutionInfo` messages `TableExecutor`
+/// would actually receive. `split(i)` decides, per dot, whether its full
+/// quorum of votes arrives bundled in one `Votes` message (the "all votes
+/// for a dot arrive together" case) or is one voter short, with the
+/// remaining vote delivered separately as a `DetachedVotes` message (the
+/// heavily-interleaved case `MultiVotesTable::add_detached_votes` exists to
+/// reconcile) - exercising the merge path instead of every message always
+/// satisfying `STABILITY_THRESHOLD` on its own.
+fn generate_messages(
+    n: usize,
+    dots: &[GeneratedDot],
+    split: impl Fn(usize) -> bool,
+) -> Vec<TableExecutionInfo> {
+    let mut messages = Vec::new();
+    for (i, gen_dot) in dots.iter().enumerate() {
+        let self_voter = gen_dot.dot.source();
+        let other_voters: Vec<ProcessId> = (1..=n as ProcessId)
+            .filter(|voter| *voter != self_voter)
+            .collect();
+
+        let split_this_one = split(i);
+        let bundled = if split_this_one {
+            STABILITY_THRESHOLD.saturating_sub(2)
+        } else {
+            STABILITY_THRESHOLD.saturating_sub(1)
+        };
+
+        let mut votes =
+            vec![VoteRange::new(self_voter, gen_dot.clock, gen_dot.clock)];
+        votes.extend(
+            other_voters
+                .iter()
+                .take(bundled)
+                .map(|voter| VoteRange::new(*voter, gen_dot.clock, gen_dot.clock)),
+        );
+
+        messages.push(TableExecutionInfo::votes(
+            gen_dot.dot,
+            gen_dot.clock,
+            gen_dot.rifl,
+            gen_dot.key.clone(),
+            gen_dot.op.clone(),
+            votes,
+        ));
+
+        if split_this_one {
+            if let Some(leftover_voter) = other_voters.get(bundled) {
+                messages.push(TableExecutionInfo::detached_votes(
+                    gen_dot.key.clone(),
+                    vec![VoteRange::new(
+                        *leftover_voter,
+                        gen_dot.clock,
+                        gen_dot.clock,
+                    )],
+                ));
+            }
+        }
+    }
+    messages
+}
+
+/// Feeds `messages` into a fresh `TableExecutor` (`n` processes, `f = 1`)
+/// in the given order and returns the `ExecutorResult`s in the exact order
+/// `handle` produced them.
+fn run(n: usize, messages: Vec<TableExecutionInfo>) -> Vec<ExecutorResult> {
+    let process_id = 1;
+    let shard_id = 0;
+    let f = 1;
+    let config = Config::new(n, f);
+    let mut executor = TableExecutor::new(process_id, shard_id, config, 1);
+
+    for info in messages {
+        executor.handle(info);
+    }
+
+    executor.results_in_order()
+}
+
+/// Invariant: every generated rifl is delivered exactly once, regardless of
+/// delivery order.
+fn assert_exactly_once(results: &[ExecutorResult], dots: &[GeneratedDot]) {
+    let mut seen: HashMap<Rifl, usize> = HashMap::new();
+    for result in results {
+        *seen.entry(result.rifl()).or_insert(0) += 1;
+    }
+    for gen_dot in dots {
+        let count = seen.get(&gen_dot.rifl).copied().unwrap_or(0);
+        assert_eq!(
+            count, 1,
+            "rifl {:?} (key {:?}) delivered {} times, expected exactly once",
+            gen_dot.rifl, gen_dot.key, count
+        );
+    }
+}
+
+/// Invariant: results touching the same key come out in non-decreasing
+/// clock order, ties broken by dot.
+fn assert_non_decreasing_clock_order(
+    results: &[ExecutorResult],
+    dots: &[GeneratedDot],
+) {
+    let by_rifl: HashMap<Rifl, &GeneratedDot> =
+        dots.iter().map(|gen_dot| (gen_dot.rifl, gen_dot)).collect();
+    let mut last: HashMap<Key, (u64, Dot)> = HashMap::new();
+
+    for result in results {
+        let gen_dot = by_rifl
+            .get(&result.rifl())
+            .expect("delivered rifl should be one of the generated dots");
+        let key = result.key().clone();
+
+        if let Some((prev_clock, prev_dot)) = last.get(&key) {
+            let in_order = gen_dot.clock > *prev_clock
+                || (gen_dot.clock == *prev_clock
+                    && (gen_dot.dot.source(), gen_dot.dot.sequence())
+                        >= (prev_dot.source(), prev_dot.sequence()));
+            assert!(
+                in_order,
+                "key {:?} delivered out of clock order: dot {:?} (clock {}) \
+                 came after dot {:?} (clock {})",
+                key, gen_dot.dot, gen_dot.clock, prev_dot, prev_clock
+            );
+        }
+        last.insert(key, (gen_dot.clock, gen_dot.dot));
+    }
+}
+
+/// Groups delivered rifls by key, preserving delivery order, so two runs
+/// over different permutations of the same messages can be compared.
+fn group_by_key(results: &[ExecutorResult]) -> HashMap<Key, Vec<Rifl>> {
+    let mut grouped: HashMap<Key, Vec<Rifl>> = HashMap::new();
+    for result in results {
+        grouped.entry(result.key().clone()).or_default().push(result.rifl());
+    }
+    grouped
+}
+
+/// Repeatedly drops one message from `failing_order`, keeping the drop only
+/// if replaying the *same subset* of `original_messages` (i.e. the subset's
+/// own natural delivery order, not the full baseline) still disagrees with
+/// `failing_order`'s per-key grouping - so shrinking converges on a minimal
+/// interleaving that breaks the invariant, rather than one that merely
+/// looks different because messages are missing.
+fn shrink_counterexample(
+    n: usize,
+    original_messages: &[TableExecutionInfo],
+    mut failing_order: Vec<TableExecutionInfo>,
+) -> Vec<TableExecutionInfo> {
+    let diverges = |order: &[TableExecutionInfo]| -> bool {
+        if order.is_empty() {
+            return false;
+        }
+        let expected_order: Vec<_> = original_messages
+            .iter()
+            .filter(|message| order.contains(message))
+            .cloned()
+            .collect();
+        let expected = group_by_key(&run(n, expected_order));
+        let found = group_by_key(&run(n, order.to_vec()));
+        expected != found
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut index = 0;
+        while index < failing_order.len() {
+            let mut candidate = failing_order.clone();
+            candidate.remove(index);
+            if diverges(&candidate) {
+                failing_order = candidate;
+                changed = true;
+                // don't advance `index`: re-check the element that slid
+                // into this position
+            } else {
+                index += 1;
+            }
+        }
+    }
+    failing_order
+}
+
+fn differential_check(
+    dot_count: usize,
+    key_count: usize,
+    split: impl Fn(usize) -> bool,
+) {
+    let n = 3;
+    let dots = generate_dots(n, key_count, dot_count);
+    let messages = generate_messages(n, &dots, split);
+
+    let baseline = run(n, messages.clone());
+    assert_exactly_once(&baseline, &dots);
+    assert_non_decreasing_clock_order(&baseline, &dots);
+    let baseline_per_key = group_by_key(&baseline);
+
+    let mut rng = rand::thread_rng();
+    let attempts = 30;
+    for _ in 0..attempts {
+        let mut shuffled = messages.clone();
+        shuffled.shuffle(&mut rng);
+
+        let results = run(n, shuffled.clone());
+        assert_exactly_once(&results, &dots);
+        assert_non_decreasing_clock_order(&results, &dots);
+
+        let found_per_key = group_by_key(&results);
+        if found_per_key != baseline_per_key {
+            let minimal = shrink_counterexample(n, &messages, shuffled);
+            panic!(
+                "delivery order diverged from the baseline order for a \
+                 different permutation of the same messages; minimal \
+                 counterexample order: {:#?}",
+                minimal,
+            );
+        }
+    }
+}
+
+#[test]
+fn all_votes_together_converges_across_orders() {
+    differential_check(6, 2, |_| false);
+}
+
+#[test]
+fn interleaved_detached_votes_converge_across_orders() {
+    differential_check(6, 2, |i| i % 2 == 0);
+}
@@ -3,13 +3,20 @@ use crate::protocol::common::table::VoteRange;
 use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{
-    Executor, ExecutorMetrics, ExecutorResult, MessageKey, Pending,
+    Executor, ExecutorMetrics, ExecutorMetricsKind, ExecutorResult,
+    MessageKey, Pending,
 };
 use fantoch::id::{Dot, ProcessId, Rifl, ShardId};
 use fantoch::kvs::{KVOp, KVStore, Key};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+/// Differential-testing harness checking `TableExecutor`'s delivery-order
+/// guarantees hold no matter what order its `Votes`/`DetachedVotes` messages
+/// arrive in.
+#[cfg(test)]
+mod differential_test;
+
 pub struct TableExecutor {
     execute_at_commit: bool,
     table: MultiVotesTable,
@@ -17,6 +24,17 @@ pub struct TableExecutor {
     pending: Pending,
     metrics: ExecutorMetrics,
     to_clients: Vec<ExecutorResult>,
+    // GC bookkeeping: `table` accumulates a row per key that has ever
+    // received votes, so without pruning a long-running process with a
+    // large key space grows `self.table` without bound. Instead of sweeping
+    // on every `handle` call (which would add per-message overhead to the
+    // hot path), `handle` counts calls and only triggers a sweep once
+    // `gc_interval` of them have gone by - mirroring the fixed-interval
+    // eviction sweep caching crates like `moka` run instead of checking
+    // every access.
+    calls_since_gc: usize,
+    gc_interval: usize,
+    gc_threshold: u64,
 }
 
 impl Executor for TableExecutor {
@@ -50,6 +68,9 @@ impl Executor for TableExecutor {
             pending,
             metrics,
             to_clients,
+            calls_since_gc: 0,
+            gc_interval: config.votes_table_gc_interval(),
+            gc_threshold: config.votes_table_gc_threshold(),
         }
     }
 
@@ -89,6 +110,10 @@ impl Executor for TableExecutor {
                 }
             }
         }
+
+        if !self.execute_at_commit {
+            self.maybe_gc();
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -122,6 +147,40 @@ impl TableExecutor {
             }
         })
     }
+
+    /// Drains every `ExecutorResult` produced so far, in the exact order
+    /// `handle` produced them. Unlike the public `to_clients` (which pops
+    /// one at a time, last-pushed-first, for the poll-style draining a
+    /// caller wants when forwarding results one message at a time), tests
+    /// care about the order delivery actually happened in.
+    #[cfg(test)]
+    fn results_in_order(&mut self) -> Vec<ExecutorResult> {
+        std::mem::take(&mut self.to_clients)
+    }
+
+    /// Sweeps `self.table` once every `gc_interval` calls to `handle`,
+    /// instead of on every call: a key is only dropped once its entire vote
+    /// history sits below `gc_threshold` *and* its last command has already
+    /// been delivered, so this must never run while a not-yet-stable dot
+    /// still references that key - otherwise a later `add_detached_votes`
+    /// for it would silently resurrect a half-pruned row. That invariant is
+    /// `table.gc`'s to uphold; this only decides when it's worth asking.
+    #[instrument(skip(self))]
+    fn maybe_gc(&mut self) {
+        self.calls_since_gc += 1;
+        if self.calls_since_gc < self.gc_interval {
+            return;
+        }
+        self.calls_since_gc = 0;
+
+        let reclaimed = self.table.gc(self.gc_threshold);
+        if reclaimed > 0 {
+            self.metrics.aggregate(
+                ExecutorMetricsKind::VotesTableGcReclaimed,
+                reclaimed as u64,
+            );
+        }
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TableExecutionInfo {
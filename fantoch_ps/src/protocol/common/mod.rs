@@ -0,0 +1,8 @@
+// This module contains the definition of `Shared`, a map shared across
+// worker threads that can be atomically read-modify-written without losing
+// concurrent updates.
+pub mod shared;
+
+// This module contains common functionality for table-based protocols (e.g.
+// Newt): per-key clock voting and its dissemination across processes.
+pub mod table;
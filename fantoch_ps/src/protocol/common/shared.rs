@@ -59,16 +59,38 @@ where
         self.clocks.iter()
     }
 
+    // Atomically applies `f` to the value associated with `key`, creating it
+    // (as `V::default()`) first if it doesn't yet exist. Because the whole
+    // read-modify-write happens under the entry's own write lock, two
+    // threads merging into the same key can never observe and then
+    // overwrite each other's update.
+    pub fn merge<F>(&self, key: &Key, f: F)
+    where
+        F: FnOnce(&mut V),
+    {
+        let mut entry = self.clocks.entry(key.clone()).or_insert_with(V::default);
+        f(&mut entry);
+    }
+
+    // `merge`, applied to every key in `keys`. Each key is merged under its
+    // own entry lock in turn (instead of acquiring all of them at once),
+    // which avoids the deadlock `get_all` has to guard against by clearing
+    // `refs` before retrying.
+    pub fn merge_all<'k, F>(&self, keys: &BTreeSet<&'k Key>, mut f: F)
+    where
+        F: FnMut(&'k Key, &mut V),
+    {
+        for key in keys {
+            self.merge(key, |value| f(key, value));
+        }
+    }
+
     fn maybe_insert(&self, key: &Key) {
         // insert entry only if it doesn't yet exist:
         // - maybe another thread tried to `maybe_insert` and was able to insert
         //   before us
-        // - replacing this function with what follows should make the tests
-        //   fail (blindly inserting means that we could lose updates)
-        // `self.clocks.insert(key.clone(), V::default());`
-        // - `Entry::or_*` methods from `dashmap` ensure that we don't lose any
-        //   updates. See: https://github.com/xacrimon/dashmap/issues/47
-        // TODO this functionality seems to have been removed
-        // self.clocks.entry(key.clone()).or_default();
+        // - `Entry::or_insert_with` from `dashmap` ensures that we don't lose
+        //   any updates. See: https://github.com/xacrimon/dashmap/issues/47
+        self.clocks.entry(key.clone()).or_insert_with(V::default);
     }
 }
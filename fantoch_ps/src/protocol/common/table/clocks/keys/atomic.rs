@@ -1,4 +1,4 @@
-use super::KeyClocks;
+use super::{KeyClocks, KeyClocksSnapshot};
 use crate::protocol::common::shared::Shared;
 use crate::protocol::common::table::{VoteRange, Votes};
 use fantoch::command::Command;
@@ -88,6 +88,37 @@ impl KeyClocks for AtomicKeyClocks {
     fn parallel() -> bool {
         true
     }
+
+    fn merge(&mut self, other: &Self) {
+        other.clocks.iter().for_each(|entry| {
+            let key = entry.key();
+            let other_clock = entry.value().load(Ordering::Relaxed);
+            // goes through `Shared::merge` rather than `self.clocks.get(key)`
+            // + `fetch_max` directly, so this stays under the same
+            // read-modify-write API every other cross-process merge into a
+            // `Shared` value uses
+            self.clocks.merge(key, |clock| {
+                clock.fetch_max(other_clock, Ordering::Relaxed);
+            });
+        });
+    }
+
+    fn snapshot(&self) -> KeyClocksSnapshot {
+        let mut snapshot = KeyClocksSnapshot::new();
+        self.clocks.iter().for_each(|entry| {
+            let clock = entry.value().load(Ordering::Relaxed);
+            snapshot.set(entry.key().clone(), clock);
+        });
+        snapshot
+    }
+
+    fn from_snapshot(id: ProcessId, snapshot: KeyClocksSnapshot) -> Self {
+        let key_clocks = Self::new(id);
+        snapshot.into_iter().for_each(|(key, clock)| {
+            key_clocks.clocks.get(&key).fetch_max(clock, Ordering::Relaxed);
+        });
+        key_clocks
+    }
 }
 
 impl AtomicKeyClocks {
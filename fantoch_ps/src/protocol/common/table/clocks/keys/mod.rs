@@ -0,0 +1,81 @@
+// This module contains the definition of `AtomicKeyClocks`.
+mod atomic;
+
+// Re-exports.
+pub use atomic::AtomicKeyClocks;
+
+use crate::protocol::common::table::Votes;
+use fantoch::command::Command;
+use fantoch::id::ProcessId;
+use fantoch::kvs::Key;
+use fantoch::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Maintains, per process, a clock per key: bumped whenever a command
+/// accessing that key is submitted, and voted on by the command's
+/// coordinator to compute the final (total) order.
+pub trait KeyClocks {
+    /// Creates a new `KeyClocks` instance.
+    fn new(id: ProcessId) -> Self;
+
+    /// Initializes the clocks of the keys accessed by `cmd`, if not already
+    /// initialized.
+    fn init_clocks(&mut self, cmd: &Command);
+
+    /// Bumps the clocks of the keys accessed by `cmd` to at least
+    /// `min_clock`, returning the highest clock bumped to and the `Votes`
+    /// generated while doing so.
+    fn bump_and_vote(&mut self, cmd: &Command, min_clock: u64) -> (u64, Votes);
+
+    /// Votes up to `up_to` on the keys accessed by `cmd`.
+    fn vote(&mut self, cmd: &Command, up_to: u64) -> Votes;
+
+    /// Votes up to `up_to` on every key currently tracked.
+    fn vote_all(&mut self, up_to: u64) -> Votes;
+
+    /// Whether this implementation can be shared (without further
+    /// synchronization) across worker threads.
+    fn parallel() -> bool;
+
+    /// Joins `other`'s clock state into `self`, taking the per-key maximum.
+    /// A per-key clock is a max-register, so `KeyClocks` forms a
+    /// join-semilattice: this merge is idempotent, commutative and
+    /// associative, and a process can pull a snapshot from any peer, in any
+    /// order, any number of times, and still converge to the same state.
+    fn merge(&mut self, other: &Self);
+
+    /// Takes a full point-in-time snapshot of this instance's clock state,
+    /// to ship to a process that's joining, restarting, or recovering from
+    /// a partition, so it can `merge` it in locally instead of replaying
+    /// every command.
+    fn snapshot(&self) -> KeyClocksSnapshot;
+
+    /// Builds a fresh instance from a `snapshot` pulled from a peer.
+    fn from_snapshot(id: ProcessId, snapshot: KeyClocksSnapshot) -> Self;
+}
+
+/// A serializable, full-state snapshot of a `KeyClocks` implementation: the
+/// clock value for every key it currently tracks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyClocksSnapshot {
+    clocks: HashMap<Key, u64>,
+}
+
+impl KeyClocksSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, key: Key, clock: u64) {
+        self.clocks.insert(key, clock);
+    }
+}
+
+impl IntoIterator for KeyClocksSnapshot {
+    type Item = (Key, u64);
+    type IntoIter = std::collections::hash_map::IntoIter<Key, u64>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.clocks.into_iter()
+    }
+}
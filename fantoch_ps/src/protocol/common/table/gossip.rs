@@ -0,0 +1,302 @@
+// The table protocols (e.g. Newt) produce per-key `Votes` via `KeyClocks`
+// and used to have every process broadcast every vote to every other
+// process, which costs O(N^2) messages as the cluster grows. This module
+// spreads those `Votes` epidemically instead, using a Plumtree-style overlay
+// bounded closer to O(N log N):
+// - each process keeps an *eager-push* peer set and a *lazy-push* peer set;
+// - a freshly-produced (or freshly-received) batch is forwarded in full to
+//   eager peers, and as a small digest (origin + highest clock) to lazy
+//   peers;
+// - a peer that gets an eager payload it already has replies `Prune`,
+//   demoting that link to lazy (the flood stops spreading down an
+//   already-covered path);
+// - a peer that gets a lazy digest for something it's missing replies
+//   `Graft`, promoting the link to eager and pulling the payload;
+// - periodic anti-entropy exchanges per-origin version vectors (the highest
+//   clock seen from each origin) and pulls whatever's missing, so a process
+//   that missed both the eager push and every digest (e.g. it was
+//   partitioned) still converges.
+//
+// `GossipLayer` only produces `Votes` to merge and messages to send; it
+// never performs IO itself, matching how `KeyClocks`/`Shared` are driven
+// externally by the embedding protocol.
+
+use super::Votes;
+use fantoch::id::ProcessId;
+use fantoch::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Identifies a single local vote-production event, so a `Digest`/`Graft`
+/// can refer back to the `VoteBatch` it's about and peers can recognize a
+/// payload they've already seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct VoteBatchId {
+    origin: ProcessId,
+    sequence: u64,
+}
+
+/// A `Votes` batch tagged with the highest clock value voted on in it, so
+/// anti-entropy can compare "how much of `origin`'s votes have I seen"
+/// without inspecting every `VoteRange` inside.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteBatch {
+    id: VoteBatchId,
+    highest: u64,
+    votes: Votes,
+}
+
+impl VoteBatch {
+    pub fn votes(&self) -> &Votes {
+        &self.votes
+    }
+
+    pub fn into_votes(self) -> Votes {
+        self.votes
+    }
+}
+
+/// A lazy-push advertisement: enough for a peer to tell whether it's missing
+/// anything from this batch, without paying to ship the full `Votes`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Digest {
+    id: VoteBatchId,
+    highest: u64,
+}
+
+/// Messages the gossip layer exchanges. Embedding protocols wrap these in
+/// their own `Message` enum and dispatch them as `Action::ToSend`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GossipMessage {
+    /// Full payload, sent to eager peers (and re-forwarded to the eager/lazy
+    /// peers of whoever first relays it here).
+    Eager(VoteBatch),
+    /// Digest-only payload, sent to lazy peers.
+    Lazy(Digest),
+    /// Reply to an `Eager` payload already seen: demotes the link the
+    /// sender is on to lazy.
+    Prune(VoteBatchId),
+    /// Reply to a `Lazy` digest for a batch not yet seen: promotes the link
+    /// the sender is on to eager, and asks for the full payload.
+    Graft(VoteBatchId),
+    /// Periodic anti-entropy round: "here's the highest clock I've seen from
+    /// each origin".
+    AntiEntropy(HashMap<ProcessId, u64>),
+    /// Anti-entropy reply: every batch the sender has that's newer than
+    /// what the peer advertised.
+    AntiEntropyReply(Vec<VoteBatch>),
+}
+
+/// Per-process Plumtree-style dissemination state for `Votes`.
+#[derive(Debug, Clone)]
+pub struct GossipLayer {
+    id: ProcessId,
+    sequence: u64,
+    eager: HashSet<ProcessId>,
+    lazy: HashSet<ProcessId>,
+    // suppresses re-processing/re-forwarding a batch already seen, whether
+    // it arrived eager, lazy-then-grafted, or via anti-entropy
+    seen: HashSet<VoteBatchId>,
+    // batches kept around so a later `Graft`/anti-entropy pull can still be
+    // served; this layer never expires entries, the same way the rest of
+    // `Votes` relies on GC running elsewhere
+    log: BTreeMap<VoteBatchId, VoteBatch>,
+    // per-origin highest clock locally known, merged from every batch seen
+    version_vector: HashMap<ProcessId, u64>,
+}
+
+impl GossipLayer {
+    /// Creates a new `GossipLayer` starting with every peer in the eager set
+    /// (the conservative starting point Plumtree itself recommends: links
+    /// only get pruned to lazy once they're proven redundant).
+    pub fn new(id: ProcessId, peers: impl IntoIterator<Item = ProcessId>) -> Self {
+        Self {
+            id,
+            sequence: 0,
+            eager: peers.into_iter().collect(),
+            lazy: HashSet::new(),
+            seen: HashSet::new(),
+            log: BTreeMap::new(),
+            version_vector: HashMap::new(),
+        }
+    }
+
+    /// Call when a local vote round produces `votes` with highest clock
+    /// `highest` (i.e. `KeyClocks::bump_and_vote`/`vote`/`vote_all`'s
+    /// return). Returns the messages to send: the full payload to every
+    /// eager peer, a digest to every lazy peer.
+    pub fn broadcast(&mut self, highest: u64, votes: Votes) -> Vec<(ProcessId, GossipMessage)> {
+        let id = VoteBatchId {
+            origin: self.id,
+            sequence: self.sequence,
+        };
+        self.sequence += 1;
+        self.seen.insert(id);
+        bump_version(&mut self.version_vector, id.origin, highest);
+        let batch = VoteBatch { id, highest, votes };
+        self.log.insert(id, batch.clone());
+
+        let mut out = Vec::with_capacity(self.eager.len() + self.lazy.len());
+        for &peer in &self.eager {
+            out.push((peer, GossipMessage::Eager(batch.clone())));
+        }
+        for &peer in &self.lazy {
+            out.push((peer, GossipMessage::Lazy(Digest { id, highest })));
+        }
+        out
+    }
+
+    /// Handles an inbound message from `from`. Returns any newly-learned
+    /// `Votes` -- to be merged into the local table the same way votes
+    /// reached it under all-to-all broadcast -- alongside the messages to
+    /// send in response.
+    pub fn handle(
+        &mut self,
+        from: ProcessId,
+        msg: GossipMessage,
+    ) -> (Option<Votes>, Vec<(ProcessId, GossipMessage)>) {
+        match msg {
+            GossipMessage::Eager(batch) => self.handle_eager(from, batch),
+            GossipMessage::Lazy(digest) => self.handle_lazy(from, digest),
+            GossipMessage::Prune(_) => {
+                self.promote_to_lazy(from);
+                (None, Vec::new())
+            }
+            GossipMessage::Graft(id) => (None, self.handle_graft(from, id)),
+            GossipMessage::AntiEntropy(their_vv) => {
+                (None, self.handle_anti_entropy(from, their_vv))
+            }
+            GossipMessage::AntiEntropyReply(batches) => self.handle_anti_entropy_reply(batches),
+        }
+    }
+
+    /// Call periodically (e.g. off the protocol's existing periodic-event
+    /// timer) with a peer to anti-entropy against, so a process that missed
+    /// both the eager push and every digest still converges.
+    pub fn anti_entropy(&self, peer: ProcessId) -> (ProcessId, GossipMessage) {
+        (
+            peer,
+            GossipMessage::AntiEntropy(self.version_vector.clone()),
+        )
+    }
+
+    fn handle_eager(
+        &mut self,
+        from: ProcessId,
+        batch: VoteBatch,
+    ) -> (Option<Votes>, Vec<(ProcessId, GossipMessage)>) {
+        if self.seen.contains(&batch.id) {
+            // redundant: this link didn't need to be eager, prune it so the
+            // flood doesn't keep spreading down an already-covered path
+            self.promote_to_lazy(from);
+            return (None, vec![(from, GossipMessage::Prune(batch.id))]);
+        }
+
+        self.promote_to_eager(from);
+        self.seen.insert(batch.id);
+        bump_version(&mut self.version_vector, batch.id.origin, batch.highest);
+
+        // keep the flood going: forward eager to the rest of our eager set,
+        // and a digest to our lazy set, excluding whoever just told us
+        let mut out = Vec::new();
+        for &peer in self.eager.iter().filter(|&&p| p != from && p != self.id) {
+            out.push((peer, GossipMessage::Eager(batch.clone())));
+        }
+        for &peer in self.lazy.iter().filter(|&&p| p != from && p != self.id) {
+            out.push((
+                peer,
+                GossipMessage::Lazy(Digest {
+                    id: batch.id,
+                    highest: batch.highest,
+                }),
+            ));
+        }
+
+        let votes = batch.votes.clone();
+        self.log.insert(batch.id, batch);
+        (Some(votes), out)
+    }
+
+    fn handle_lazy(
+        &mut self,
+        from: ProcessId,
+        digest: Digest,
+    ) -> (Option<Votes>, Vec<(ProcessId, GossipMessage)>) {
+        if self.seen.contains(&digest.id) {
+            return (None, Vec::new());
+        }
+        // missing it: promote the link to eager and pull the full payload
+        self.promote_to_eager(from);
+        (None, vec![(from, GossipMessage::Graft(digest.id))])
+    }
+
+    fn handle_graft(
+        &mut self,
+        from: ProcessId,
+        id: VoteBatchId,
+    ) -> Vec<(ProcessId, GossipMessage)> {
+        self.promote_to_eager(from);
+        match self.log.get(&id) {
+            Some(batch) => vec![(from, GossipMessage::Eager(batch.clone()))],
+            // we don't have it either (e.g. it was gc'ed); nothing to reply
+            None => Vec::new(),
+        }
+    }
+
+    fn handle_anti_entropy(
+        &mut self,
+        from: ProcessId,
+        their_vv: HashMap<ProcessId, u64>,
+    ) -> Vec<(ProcessId, GossipMessage)> {
+        let missing: Vec<VoteBatch> = self
+            .log
+            .values()
+            .filter(|batch| {
+                let their_highest = their_vv.get(&batch.id.origin).copied().unwrap_or(0);
+                batch.highest > their_highest
+            })
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            Vec::new()
+        } else {
+            vec![(from, GossipMessage::AntiEntropyReply(missing))]
+        }
+    }
+
+    fn handle_anti_entropy_reply(
+        &mut self,
+        batches: Vec<VoteBatch>,
+    ) -> (Option<Votes>, Vec<(ProcessId, GossipMessage)>) {
+        let mut merged = Votes::new();
+        for batch in batches {
+            if self.seen.insert(batch.id) {
+                bump_version(&mut self.version_vector, batch.id.origin, batch.highest);
+                merged.merge(&batch.votes);
+                self.log.insert(batch.id, batch);
+            }
+        }
+        if merged.is_empty() {
+            (None, Vec::new())
+        } else {
+            (Some(merged), Vec::new())
+        }
+    }
+
+    fn promote_to_eager(&mut self, peer: ProcessId) {
+        self.lazy.remove(&peer);
+        self.eager.insert(peer);
+    }
+
+    fn promote_to_lazy(&mut self, peer: ProcessId) {
+        self.eager.remove(&peer);
+        self.lazy.insert(peer);
+    }
+}
+
+fn bump_version(vv: &mut HashMap<ProcessId, u64>, origin: ProcessId, highest: u64) {
+    let entry = vv.entry(origin).or_insert(0);
+    if highest > *entry {
+        *entry = highest;
+    }
+}
@@ -0,0 +1,138 @@
+// This module contains the definition of `KeyClocks` and its
+// implementations.
+pub mod clocks;
+
+// This module contains the epidemic (Plumtree-style) dissemination of
+// `Votes` between processes, so broadcasting votes all-to-all doesn't cost
+// O(N^2) messages as the cluster grows.
+pub mod gossip;
+
+use fantoch::id::ProcessId;
+use fantoch::kvs::Key;
+use fantoch::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// A contiguous range of clock values `[start, end]` (inclusive on both
+/// ends) that `voter` voted on for a single key. Produced by
+/// `KeyClocks::bump_and_vote`/`vote`/`vote_all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteRange {
+    voter: ProcessId,
+    start: u64,
+    end: u64,
+}
+
+impl VoteRange {
+    pub fn new(voter: ProcessId, start: u64, end: u64) -> Self {
+        debug_assert!(start <= end);
+        Self { voter, start, end }
+    }
+
+    pub fn voter(&self) -> ProcessId {
+        self.voter
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Whether `self` and `other` are contiguous or overlapping ranges from
+    /// the same voter, and can thus be coalesced into one.
+    pub fn coalescable_with(&self, other: &VoteRange) -> bool {
+        self.voter == other.voter
+            && self.start <= other.end + 1
+            && other.start <= self.end + 1
+    }
+
+    /// Coalesces `self` and `other` into the single range that spans both.
+    /// Only call this after checking `coalescable_with`.
+    pub fn coalesce(&self, other: &VoteRange) -> VoteRange {
+        debug_assert!(self.coalescable_with(other));
+        VoteRange {
+            voter: self.voter,
+            start: std::cmp::min(self.start, other.start),
+            end: std::cmp::max(self.end, other.end),
+        }
+    }
+}
+
+/// The `VoteRange`s produced for each key touched by a single vote round
+/// (see `KeyClocks`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Votes {
+    votes: HashMap<Key, Vec<VoteRange>>,
+}
+
+impl Votes {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            votes: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Sets the `VoteRange`s for `key`. Each key is expected to be set at
+    /// most once per vote round.
+    pub fn set(&mut self, key: Key, ranges: Vec<VoteRange>) {
+        let res = self.votes.insert(key, ranges);
+        assert!(
+            res.is_none(),
+            "votes for the same key shouldn't be set twice in the same round"
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.votes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.votes.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Vec<VoteRange>)> {
+        self.votes.iter()
+    }
+
+    /// Merges `other` into `self`, appending onto (and coalescing with) any
+    /// ranges already present for a key instead of overwriting them -- unlike
+    /// `set`, which assumes each key is only ever set once per local vote
+    /// round.
+    pub fn merge(&mut self, other: &Votes) {
+        for (key, ranges) in other.votes.iter() {
+            let entry =
+                self.votes.entry(key.clone()).or_insert_with(Vec::new);
+            for range in ranges {
+                push_coalescing(entry, *range);
+            }
+        }
+    }
+}
+
+impl IntoIterator for Votes {
+    type Item = (Key, Vec<VoteRange>);
+    type IntoIter = std::collections::hash_map::IntoIter<Key, Vec<VoteRange>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.votes.into_iter()
+    }
+}
+
+/// Appends `range` to `ranges`, coalescing it with an existing entry from the
+/// same voter if they're contiguous/overlapping instead of growing the list
+/// unboundedly.
+fn push_coalescing(ranges: &mut Vec<VoteRange>, range: VoteRange) {
+    if let Some(existing) =
+        ranges.iter_mut().find(|r| r.coalescable_with(&range))
+    {
+        *existing = existing.coalesce(&range);
+    } else {
+        ranges.push(range);
+    }
+}
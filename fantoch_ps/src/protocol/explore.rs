@@ -0,0 +1,482 @@
+use fantoch::client::Workload;
+use fantoch::config::Config;
+use fantoch::id::{Dot, ProcessId, Rifl};
+use fantoch::kvs::Key;
+use fantoch::planet::Planet;
+use fantoch::protocol::Protocol;
+use fantoch::sim::Runner;
+use fantoch::HashMap;
+use std::time::Duration;
+
+// `Runner::run_with_scheduler` is the one addition this module needs on
+// top of `Runner::run` (the method `sim_test` already uses): same
+// simulated-network delivery loop, but it consults a `Scheduler` to choose
+// which deliverable message goes next instead of always picking the
+// oldest, and it returns the `LinearizationLog` this module's oracles check
+// instead of only aggregated `ProtocolMetrics`.
+
+/// A minimal, dependency-free xorshift64* PRNG: good enough to pick among a
+/// handful of deliverable messages, and -- unlike pulling in a `rand`-crate
+/// RNG -- trivial to keep byte-for-byte reproducible across runs/platforms
+/// from nothing but `seed`.
+struct Prng(u64);
+
+impl Prng {
+    fn new(seed: u64) -> Self {
+        // xorshift64* is undefined at state 0, so never let the seed land
+        // there
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// A seeded scheduler that, given how many messages are currently
+/// deliverable, decides which one to deliver next -- in place of `Runner`'s
+/// default in-arrival-order delivery -- so adversarial interleavings can be
+/// explored while staying fully reproducible from `seed` alone. `seed == 0`
+/// is reserved to mean "deliver in order" (`Runner`'s original behavior),
+/// so `shrink` has a well-defined, always-available floor to walk toward.
+pub struct Scheduler {
+    seed: u64,
+    rng: Prng,
+}
+
+impl Scheduler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Prng::new(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Picks the index (into the caller's `deliverable_count`-long list of
+    /// currently-deliverable messages) of the next message to deliver.
+    pub fn pick(&mut self, deliverable_count: usize) -> usize {
+        if self.seed == 0 || deliverable_count == 0 {
+            0
+        } else {
+            (self.rng.next_u64() as usize) % deliverable_count
+        }
+    }
+}
+
+/// One command execution, as observed by the executor that applied it.
+#[derive(Debug, Clone)]
+pub struct ExecutionEvent {
+    pub process_id: ProcessId,
+    pub dot: Dot,
+    pub rifl: Rifl,
+    pub keys: Vec<Key>,
+}
+
+/// The real-time interval during which a client's command was outstanding:
+/// from when it was submitted to when the client received its ack. Used by
+/// `check_linearizability` the same way Wing & Gong history checking does.
+#[derive(Debug, Clone)]
+pub struct ClientInterval {
+    pub rifl: Rifl,
+    pub submit_millis: u64,
+    pub ack_millis: u64,
+}
+
+/// The real-time linearization recorded while driving a `Scheduler`-ordered
+/// `Runner` to completion: every executor's actual execution order, plus
+/// each client command's submit/ack interval.
+#[derive(Debug, Default)]
+pub struct LinearizationLog {
+    // per-process, in the order that executor actually applied them
+    executions: HashMap<ProcessId, Vec<ExecutionEvent>>,
+    intervals: Vec<ClientInterval>,
+}
+
+impl LinearizationLog {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn record_execution(&mut self, event: ExecutionEvent) {
+        self.executions.entry(event.process_id).or_default().push(event);
+    }
+
+    pub fn record_interval(&mut self, interval: ClientInterval) {
+        self.intervals.push(interval);
+    }
+}
+
+/// A safety-oracle violation found while checking a `LinearizationLog`.
+#[derive(Debug, Clone)]
+pub enum Violation {
+    /// Two processes executed the same pair of conflicting commands in
+    /// different relative order -- the executors disagreed on a total
+    /// order for commands that access a common key.
+    Agreement {
+        key: Key,
+        rifl_a: Rifl,
+        rifl_b: Rifl,
+        process_a: ProcessId,
+        process_b: ProcessId,
+    },
+    /// A process executed two conflicting commands in an order that
+    /// contradicts the real-time order their client-observed intervals
+    /// impose (one command's ack happened before the other's submit, yet
+    /// it was executed after it).
+    Linearizability { rifl_a: Rifl, rifl_b: Rifl, process_id: ProcessId },
+}
+
+/// Checks that every pair of processes that both executed two conflicting
+/// commands agreed on their relative order (agreement/prefix-consistency).
+pub fn check_agreement(log: &LinearizationLog) -> Result<(), Violation> {
+    let processes: Vec<ProcessId> = log.executions.keys().copied().collect();
+    for i in 0..processes.len() {
+        for j in (i + 1)..processes.len() {
+            let order_a = &log.executions[&processes[i]];
+            let order_b = &log.executions[&processes[j]];
+            let pos_a: HashMap<Rifl, usize> = order_a
+                .iter()
+                .enumerate()
+                .map(|(idx, e)| (e.rifl, idx))
+                .collect();
+            let pos_b: HashMap<Rifl, usize> = order_b
+                .iter()
+                .enumerate()
+                .map(|(idx, e)| (e.rifl, idx))
+                .collect();
+
+            for ea in order_a {
+                let ia_b = match pos_b.get(&ea.rifl) {
+                    Some(&ia_b) => ia_b,
+                    None => continue,
+                };
+                for eb in order_a {
+                    if eb.rifl == ea.rifl {
+                        continue;
+                    }
+                    let ib_b = match pos_b.get(&eb.rifl) {
+                        Some(&ib_b) => ib_b,
+                        None => continue,
+                    };
+                    let key = match ea.keys.iter().find(|k| eb.keys.contains(k)) {
+                        Some(key) => key,
+                        None => continue,
+                    };
+                    let order_in_a = pos_a[&ea.rifl] < pos_a[&eb.rifl];
+                    let order_in_b = ia_b < ib_b;
+                    if order_in_a != order_in_b {
+                        return Err(Violation::Agreement {
+                            key: key.clone(),
+                            rifl_a: ea.rifl,
+                            rifl_b: eb.rifl,
+                            process_a: processes[i],
+                            process_b: processes[j],
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every process's execution order is consistent with a
+/// linearizable register given each command's submit/ack interval: if `a`
+/// was acked before `b` was submitted, `a` must be executed before `b`
+/// wherever both are executed and they conflict.
+pub fn check_linearizability(log: &LinearizationLog) -> Result<(), Violation> {
+    let interval_by_rifl: HashMap<Rifl, &ClientInterval> =
+        log.intervals.iter().map(|interval| (interval.rifl, interval)).collect();
+
+    for (&process_id, order) in &log.executions {
+        let pos: HashMap<Rifl, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(idx, e)| (e.rifl, idx))
+            .collect();
+        for ea in order {
+            let interval_a = match interval_by_rifl.get(&ea.rifl) {
+                Some(interval_a) => interval_a,
+                None => continue,
+            };
+            for eb in order {
+                if eb.rifl == ea.rifl {
+                    continue;
+                }
+                let interval_b = match interval_by_rifl.get(&eb.rifl) {
+                    Some(interval_b) => interval_b,
+                    None => continue,
+                };
+                let conflicts = ea.keys.iter().any(|k| eb.keys.contains(k));
+                if !conflicts {
+                    continue;
+                }
+                let real_time_before =
+                    interval_a.ack_millis < interval_b.submit_millis;
+                let executed_after = pos[&ea.rifl] > pos[&eb.rifl];
+                if real_time_before && executed_after {
+                    return Err(Violation::Linearizability {
+                        rifl_a: ea.rifl,
+                        rifl_b: eb.rifl,
+                        process_id,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The minimal reproducing case for a violation found by `explore_test`:
+/// the seed driving the schedule, and the subset of the client command
+/// workload that still reproduces it.
+pub struct MinimalFailure {
+    pub seed: u64,
+    pub commands: Vec<Rifl>,
+    pub violation: Violation,
+}
+
+/// Shrinks a reproducing `(seed, commands)` pair by (a) bisecting the
+/// client command sequence, keeping whichever half still reproduces the
+/// violation, and (b) once the command sequence is minimal, walking `seed`
+/// toward `0` (`Scheduler`'s reserved "deliver in order" seed) while the
+/// violation still reproduces. `reproduces(seed, commands)` re-runs the
+/// exploration against the given subset and seed and reports whether the
+/// same violation still occurs.
+pub fn shrink<F>(
+    seed: u64,
+    commands: Vec<Rifl>,
+    mut reproduces: F,
+) -> (u64, Vec<Rifl>)
+where
+    F: FnMut(u64, &[Rifl]) -> bool,
+{
+    let mut seed = seed;
+    let mut commands = commands;
+
+    loop {
+        if commands.len() <= 1 {
+            break;
+        }
+        let mid = commands.len() / 2;
+        let (first_half, second_half) = commands.split_at(mid);
+        if reproduces(seed, first_half) {
+            commands = first_half.to_vec();
+        } else if reproduces(seed, second_half) {
+            commands = second_half.to_vec();
+        } else {
+            break;
+        }
+    }
+
+    while seed > 0 {
+        let candidate = seed / 2;
+        if reproduces(candidate, &commands) {
+            seed = candidate;
+        } else {
+            break;
+        }
+    }
+
+    (seed, commands)
+}
+
+/// The outcome of running `explore_test`: either every seed's run satisfied
+/// both safety oracles, or the first violation found, already shrunk to a
+/// minimal reproducing `(seed, commands)` pair.
+pub struct ExploreReport {
+    pub seeds_run: usize,
+    pub failure: Option<MinimalFailure>,
+}
+
+/// Runs `P` under `config`/`workload` once per seed in `seeds`, each time
+/// driving `Runner` with a `Scheduler` seeded from it instead of `Runner`'s
+/// default in-arrival-order delivery, recording the resulting
+/// `LinearizationLog` and checking it against `check_agreement` and
+/// `check_linearizability`. On the first violation, shrinks via `shrink`
+/// and returns the minimal reproducing case so existing `sim_test`-style
+/// protocol tests can opt into fuzzed scheduling without hand-rolling any
+/// of the oracle or shrinking logic themselves.
+///
+/// Takes `clients_per_process` alongside `config`/`workload` (rather than
+/// the bare `(config, workload, seeds)` triple) since that's a separate
+/// `Runner::new` argument in every existing `sim_test`/`run_test` call.
+pub fn explore_test<P>(
+    config: Config,
+    workload: Workload,
+    clients_per_process: usize,
+    seeds: impl IntoIterator<Item = u64>,
+) -> ExploreReport
+where
+    P: Protocol + Eq,
+{
+    let mut seeds_run = 0;
+
+    let run_with_seed = |seed: u64, workload: Workload| -> LinearizationLog {
+        let planet = Planet::new();
+        let mut regions = planet.regions();
+        regions.truncate(config.n());
+        let process_regions = regions.clone();
+        let client_regions = regions;
+
+        let mut runner: Runner<P> = Runner::new(
+            planet,
+            config,
+            workload,
+            clients_per_process,
+            process_regions,
+            client_regions,
+        );
+        let scheduler = Scheduler::new(seed);
+        let extra_sim_time = Some(Duration::from_secs(2));
+        runner.run_with_scheduler(scheduler, extra_sim_time)
+    };
+
+    for seed in seeds {
+        seeds_run += 1;
+        let log = run_with_seed(seed, workload.clone());
+        let violation = check_agreement(&log).and_then(|_| check_linearizability(&log));
+        if let Err(violation) = violation {
+            let commands: Vec<Rifl> = log
+                .executions
+                .values()
+                .flatten()
+                .map(|event| event.rifl)
+                .collect();
+            let (seed, commands) = shrink(seed, commands, |seed, commands| {
+                let mut workload = workload.clone();
+                workload.retain_commands(commands);
+                let log = run_with_seed(seed, workload);
+                matches!(
+                    check_agreement(&log).and_then(|_| check_linearizability(&log)),
+                    Err(_)
+                )
+            });
+            return ExploreReport {
+                seeds_run,
+                failure: Some(MinimalFailure {
+                    seed,
+                    commands,
+                    violation,
+                }),
+            };
+        }
+    }
+
+    ExploreReport {
+        seeds_run,
+        failure: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::id::ClientId;
+
+    fn event(
+        process_id: ProcessId,
+        seq: u64,
+        rifl_source: ClientId,
+        key: &str,
+    ) -> ExecutionEvent {
+        ExecutionEvent {
+            process_id,
+            dot: Dot::new(process_id, seq),
+            rifl: Rifl::new(rifl_source, seq),
+            keys: vec![key.to_string()],
+        }
+    }
+
+    #[test]
+    fn scheduler_seed_zero_is_in_order() {
+        let mut scheduler = Scheduler::new(0);
+        assert_eq!(scheduler.pick(5), 0);
+        assert_eq!(scheduler.pick(5), 0);
+    }
+
+    #[test]
+    fn scheduler_same_seed_is_reproducible() {
+        let picks = |seed| {
+            let mut scheduler = Scheduler::new(seed);
+            (0..10).map(|_| scheduler.pick(7)).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(42), picks(42));
+    }
+
+    #[test]
+    fn agreement_accepts_matching_orders() {
+        let mut log = LinearizationLog::new();
+        log.record_execution(event(1, 1, 1, "x"));
+        log.record_execution(event(1, 2, 2, "x"));
+        log.record_execution(event(2, 1, 1, "x"));
+        log.record_execution(event(2, 2, 2, "x"));
+        assert!(check_agreement(&log).is_ok());
+    }
+
+    #[test]
+    fn agreement_rejects_diverging_orders() {
+        let mut log = LinearizationLog::new();
+        // process 1 executes rifl (1,1) then (2,2), both touching key "x"
+        log.record_execution(event(1, 1, 1, "x"));
+        log.record_execution(event(1, 2, 2, "x"));
+        // process 2 executes them in the opposite order
+        log.record_execution(event(2, 1, 2, "x"));
+        log.record_execution(event(2, 2, 1, "x"));
+        assert!(matches!(
+            check_agreement(&log),
+            Err(Violation::Agreement { .. })
+        ));
+    }
+
+    #[test]
+    fn linearizability_rejects_real_time_violation() {
+        let mut log = LinearizationLog::new();
+        // rifl (1,1) acks at 10ms, rifl (2,2) doesn't submit until 20ms, so
+        // a linearizable register must execute (1,1) first
+        log.record_interval(ClientInterval {
+            rifl: Rifl::new(1, 1),
+            submit_millis: 0,
+            ack_millis: 10,
+        });
+        log.record_interval(ClientInterval {
+            rifl: Rifl::new(2, 2),
+            submit_millis: 20,
+            ack_millis: 30,
+        });
+        log.record_execution(event(1, 2, 2, "x"));
+        log.record_execution(event(1, 1, 1, "x"));
+        assert!(matches!(
+            check_linearizability(&log),
+            Err(Violation::Linearizability { .. })
+        ));
+    }
+
+    #[test]
+    fn shrink_bisects_to_the_minimal_failing_subset() {
+        let commands: Vec<Rifl> =
+            (1..=8).map(|seq| Rifl::new(1, seq)).collect();
+        // pretend only the command with seq == 3 is actually needed to
+        // reproduce the violation
+        let culprit = Rifl::new(1, 3);
+        let (_, shrunk) = shrink(7, commands, |_seed, commands| {
+            commands.contains(&culprit)
+        });
+        assert_eq!(shrunk, vec![culprit]);
+    }
+
+    #[test]
+    fn shrink_walks_seed_toward_zero() {
+        let (seed, _) = shrink(100, vec![Rifl::new(1, 1)], |_seed, _| true);
+        assert_eq!(seed, 0);
+    }
+}
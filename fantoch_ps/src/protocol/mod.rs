@@ -16,9 +16,16 @@ mod fpaxos;
 // This module contains common functionality for partial replication.
 mod partial;
 
+// This module contains `explore_test`, a PULSE-style randomized-scheduling
+// harness with history checking and shrinking, for protocol tests that
+// want to fuzz message delivery order instead of relying on `sim_test`'s
+// single implicit ordering.
+pub mod explore;
+
 // Re-exports.
 pub use atlas::{AtlasLocked, AtlasSequential};
 pub use epaxos::{EPaxosLocked, EPaxosSequential};
+pub use explore::{explore_test, ExploreReport, MinimalFailure, Violation};
 pub use fpaxos::FPaxos;
 pub use newt::{NewtAtomic, NewtFineLocked, NewtLocked, NewtSequential};
 
@@ -29,10 +36,15 @@ mod tests {
     use fantoch::config::Config;
     use fantoch::id::ProcessId;
     use fantoch::planet::Planet;
-    use fantoch::protocol::{Protocol, ProtocolMetricsKind};
-    use fantoch::run::tests::{run_test_with_inspect_fun, tokio_test_runtime};
+    use fantoch::protocol::{
+        CommitModel, MultiPaxos, Protocol, ProtocolMetricsKind, Raft,
+    };
+    use fantoch::run::tests::{
+        run_test_with_inspect_fun, tokio_test_runtime, FaultEvent,
+    };
     use fantoch::sim::Runner;
     use fantoch::HashMap;
+    use fantoch::HashSet;
     use std::time::Duration;
 
     // global test config
@@ -42,6 +54,14 @@ mod tests {
     const CONFLICT_RATE: usize = 50;
     const CLIENTS_PER_PROCESS: usize = 10;
 
+    /// Number of shards `check_metrics` splits its per-process aggregation
+    /// into (see below). A fixed power of two rather than something queried
+    /// from the OS at runtime, since this crate has no dependency that would
+    /// give us that cheaply; comfortably covers the core counts this harness
+    /// actually runs on, and sharding further wouldn't help runs with fewer
+    /// simulated processes than shards anyway.
+    const METRIC_SHARD_COUNT: usize = 8;
+
     macro_rules! config {
         ($n:expr, $f:expr) => {
             Config::new($n, $f)
@@ -652,6 +672,210 @@ mod tests {
         );
     }
 
+    // ---- raft tests ---- //
+    // (`Raft` elects its own leader via randomized election timeouts, so
+    // unlike `fpaxos` it needs no `config!(.., leader)` hint)
+    #[test]
+    fn sim_raft_3_1_test() {
+        sim_test::<Raft>(config!(3, 1), COMMANDS_PER_CLIENT, CLIENTS_PER_PROCESS);
+    }
+
+    #[test]
+    fn sim_raft_5_2_test() {
+        sim_test::<Raft>(config!(5, 2), COMMANDS_PER_CLIENT, CLIENTS_PER_PROCESS);
+    }
+
+    #[test]
+    fn run_raft_3_1_sequential_test() {
+        // run raft in sequential mode
+        let workers = 1;
+        let executors = 1;
+        run_test::<Raft>(
+            config!(3, 1),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    #[test]
+    fn run_raft_3_1_parallel_test() {
+        // run raft in parallel mode (in terms of workers, since execution is
+        // never parallel)
+        let workers = 3;
+        let executors = 1;
+        run_test::<Raft>(
+            config!(3, 1),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    // ---- multipaxos tests ---- //
+    #[test]
+    fn sim_multipaxos_3_1_test() {
+        sim_test::<MultiPaxos>(
+            config!(3, 1),
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    #[test]
+    fn sim_multipaxos_5_2_test() {
+        sim_test::<MultiPaxos>(
+            config!(5, 2),
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    #[test]
+    fn run_multipaxos_3_1_sequential_test() {
+        // run multipaxos in sequential mode
+        let workers = 1;
+        let executors = 1;
+        run_test::<MultiPaxos>(
+            config!(3, 1),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    #[test]
+    fn run_multipaxos_3_1_parallel_test() {
+        // run multipaxos in parallel mode (in terms of workers, since
+        // execution is never parallel)
+        let workers = 3;
+        let executors = 1;
+        run_test::<MultiPaxos>(
+            config!(3, 1),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    #[test]
+    fn run_multipaxos_3_1_log_model_test() {
+        // same run as `run_multipaxos_3_1_sequential_test`, but checked as a
+        // log-index-based commit model instead of through the generic
+        // fast/slow-path `check_metrics`
+        let workers = 1;
+        let executors = 1;
+        run_test_log_model::<MultiPaxos>(
+            config!(3, 1),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    // ---- fault injection tests ---- //
+    #[test]
+    fn run_newt_5_2_atomic_permanent_crash_test() {
+        // one process crashes and never rejoins; with f = 2 the remaining 4
+        // still form a majority, so every command should still commit
+        let workers = 4;
+        let executors = 1;
+        let faults = vec![FaultEvent {
+            process_id: 5,
+            crash_at: Duration::from_millis(500),
+            restart_after: None,
+        }];
+        run_test_with_faults::<NewtAtomic>(
+            newt_config!(5, 2),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+            faults,
+        );
+    }
+
+    #[test]
+    fn run_newt_5_2_atomic_transient_crash_test() {
+        // one process crashes and rejoins a couple seconds later, well
+        // before the clients are done; it should catch back up and commit
+        // and gc everything just like a process that never crashed
+        let workers = 4;
+        let executors = 1;
+        let faults = vec![FaultEvent {
+            process_id: 3,
+            crash_at: Duration::from_millis(500),
+            restart_after: Some(Duration::from_secs(2)),
+        }];
+        run_test_with_faults::<NewtAtomic>(
+            newt_config!(5, 2),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+            faults,
+        );
+    }
+
+    // ---- bounded GC tests ---- //
+    #[test]
+    fn run_newt_5_2_atomic_gc_depth_test() {
+        // gc should keep pruning within `gc_depth` rounds of the latest
+        // committed command on every process, never stalling and never
+        // running ahead of what's actually been committed
+        let workers = 4;
+        let executors = 1;
+        let gc_depth = 10;
+        run_test_with_gc_depth::<NewtAtomic>(
+            newt_config!(5, 2),
+            gc_depth,
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
+    // ---- recovery-path tests ---- //
+    #[test]
+    fn run_newt_5_2_atomic_recovery_test() {
+        // leaderless protocols under enough conflict occasionally hit a
+        // fast-quorum disagreement and run a full coordinated-recovery
+        // round; that shouldn't be conflated with a plain slow path
+        let workers = 4;
+        let executors = 1;
+        run_test_with_recovery::<NewtAtomic>(
+            newt_config!(5, 2),
+            SHARD_COUNT,
+            workers,
+            executors,
+            SHARDS_PER_COMMAND,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+    }
+
     #[allow(dead_code)]
     fn metrics_inspect<P>(worker: &P) -> (usize, usize, usize)
     where
@@ -675,6 +899,79 @@ mod tests {
         (fast_paths, slow_paths, stable_count)
     }
 
+    #[allow(dead_code)]
+    fn gc_round_inspect<P>(worker: &P) -> (usize, usize, u64)
+    where
+        P: Protocol,
+    {
+        let fast_paths = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::FastPath)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let slow_paths = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::SlowPath)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let gc_round = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::GcRound)
+            .cloned()
+            .unwrap_or_default();
+        (fast_paths, slow_paths, gc_round)
+    }
+
+    #[allow(dead_code)]
+    fn recovery_inspect<P>(worker: &P) -> (usize, usize, usize, usize)
+    where
+        P: Protocol,
+    {
+        let fast_paths = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::FastPath)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let slow_paths = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::SlowPath)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let recoveries = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::Recovery)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let stable_count = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::Stable)
+            .cloned()
+            .unwrap_or_default() as usize;
+        (fast_paths, slow_paths, recoveries, stable_count)
+    }
+
+    #[allow(dead_code)]
+    fn log_model_inspect<P>(worker: &P) -> (usize, usize)
+    where
+        P: Protocol,
+    {
+        // `CommittedSlots`/`Stable` double as `committed_index`/
+        // `applied_index`: a log-replicated protocol commits by occupying
+        // the next log index and applies (and gcs) a contiguous prefix of
+        // it, which is exactly what these two counters already track
+        let committed_index = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::CommittedSlots)
+            .cloned()
+            .unwrap_or_default() as usize;
+        let applied_index = worker
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::Stable)
+            .cloned()
+            .unwrap_or_default() as usize;
+        (committed_index, applied_index)
+    }
+
     fn run_test<P>(
         mut config: Config,
         shard_count: usize,
@@ -721,6 +1018,7 @@ mod tests {
                 executors,
                 tracer_show_interval,
                 Some(metrics_inspect),
+                Vec::new(),
                 extra_run_time,
             ))
             .expect("run should complete successfully")
@@ -753,25 +1051,37 @@ mod tests {
         )
     }
 
-    fn sim_test<P: Protocol + Eq>(
+    /// Like `run_test`, but injects `faults` into the run and checks
+    /// liveness against the surviving majority instead of every process:
+    /// a process with a `FaultEvent` whose `restart_after` is `None` never
+    /// rejoins, so it's excluded from both the "everyone commits everything"
+    /// and "everyone gcs everything" assertions that `check_metrics` makes.
+    fn run_test_with_faults<P>(
         mut config: Config,
+        shard_count: usize,
+        workers: usize,
+        executors: usize,
+        shards_per_command: usize,
         commands_per_client: usize,
         clients_per_process: usize,
-    ) -> usize {
+        faults: Vec<FaultEvent>,
+    ) -> usize
+    where
+        P: Protocol + Send + 'static,
+    {
         // make sure stability is running
         config.set_gc_interval(Duration::from_millis(100));
 
-        // planet
-        let planet = Planet::new();
+        // set number of shards
+        config.set_shards(shard_count);
 
-        // clients workload
-        let shards_per_command = 1;
-        let shard_gen = ShardGen::Random { shard_count: 1 };
+        // create workload
+        let shard_gen = ShardGen::Random { shard_count };
         let keys_per_shard = 2;
-        let payload_size = 1;
         let key_gen = KeyGen::ConflictRate {
             conflict_rate: CONFLICT_RATE,
         };
+        let payload_size = 1;
         let workload = Workload::new(
             shards_per_command,
             shard_gen,
@@ -781,33 +1091,116 @@ mod tests {
             payload_size,
         );
 
-        // process and client regions
-        let mut regions = planet.regions();
-        regions.truncate(config.n());
-        let process_regions = regions.clone();
-        let client_regions = regions.clone();
-
-        // create runner
-        let mut runner: Runner<P> = Runner::new(
-            planet,
-            config,
-            workload,
-            clients_per_process,
-            process_regions,
-            client_regions,
-        );
-
-        // run simulation until the clients end + another 2 seconds
-        let extra_sim_time = Some(Duration::from_secs(2));
-        let (metrics, _) = runner.run(extra_sim_time);
+        // a process that crashes and never restarts never finishes
+        // committing/gc-ing its share, so it's excluded from the surviving
+        // majority `check_metrics_with_faults` requires everything from
+        let crashed: HashSet<ProcessId> = faults
+            .iter()
+            .filter(|fault| fault.restart_after.is_none())
+            .map(|fault| fault.process_id)
+            .collect();
 
-        // fetch slow paths and stable count from metrics
-        let metrics = metrics
+        // run until the clients end + another 10 seconds
+        let tracer_show_interval = None;
+        let extra_run_time = Some(Duration::from_secs(10));
+        let metrics = tokio_test_runtime()
+            .block_on(run_test_with_inspect_fun::<P, (usize, usize, usize)>(
+                config,
+                workload,
+                clients_per_process,
+                workers,
+                executors,
+                tracer_show_interval,
+                Some(metrics_inspect),
+                faults,
+                extra_run_time,
+            ))
+            .expect("run should complete successfully")
             .into_iter()
             .map(|(process_id, process_metrics)| {
-                // get fast paths
-                let fast_paths = process_metrics
-                    .get_aggregated(ProtocolMetricsKind::FastPath)
+                // aggregate worker metrics
+                let mut total_fast_paths = 0;
+                let mut total_slow_paths = 0;
+                let mut total_stable_count = 0;
+                process_metrics.into_iter().for_each(
+                    |(fast_paths, slow_paths, stable_count)| {
+                        total_fast_paths += fast_paths;
+                        total_slow_paths += slow_paths;
+                        total_stable_count += stable_count;
+                    },
+                );
+                (
+                    process_id,
+                    (total_fast_paths, total_slow_paths, total_stable_count),
+                )
+            })
+            .collect();
+
+        check_metrics_with_faults(
+            config,
+            shards_per_command,
+            commands_per_client,
+            clients_per_process,
+            &crashed,
+            metrics,
+        )
+    }
+
+    fn sim_test<P: Protocol + Eq>(
+        mut config: Config,
+        commands_per_client: usize,
+        clients_per_process: usize,
+    ) -> usize {
+        // make sure stability is running
+        config.set_gc_interval(Duration::from_millis(100));
+
+        // planet
+        let planet = Planet::new();
+
+        // clients workload
+        let shards_per_command = 1;
+        let shard_gen = ShardGen::Random { shard_count: 1 };
+        let keys_per_shard = 2;
+        let payload_size = 1;
+        let key_gen = KeyGen::ConflictRate {
+            conflict_rate: CONFLICT_RATE,
+        };
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            commands_per_client,
+            payload_size,
+        );
+
+        // process and client regions
+        let mut regions = planet.regions();
+        regions.truncate(config.n());
+        let process_regions = regions.clone();
+        let client_regions = regions.clone();
+
+        // create runner
+        let mut runner: Runner<P> = Runner::new(
+            planet,
+            config,
+            workload,
+            clients_per_process,
+            process_regions,
+            client_regions,
+        );
+
+        // run simulation until the clients end + another 2 seconds
+        let extra_sim_time = Some(Duration::from_secs(2));
+        let (metrics, _) = runner.run(extra_sim_time);
+
+        // fetch slow paths and stable count from metrics
+        let metrics = metrics
+            .into_iter()
+            .map(|(process_id, process_metrics)| {
+                // get fast paths
+                let fast_paths = process_metrics
+                    .get_aggregated(ProtocolMetricsKind::FastPath)
                     .cloned()
                     .unwrap_or_default()
                     as usize;
@@ -839,6 +1232,52 @@ mod tests {
         )
     }
 
+    /// Splits `metrics` into `METRIC_SHARD_COUNT` shards by each
+    /// `ProcessId`'s low bits, sums fast/slow/stable counts within each
+    /// shard on its own thread (printing each process's line along the
+    /// way), then reduces the per-shard totals into the final
+    /// `(total_fast_paths, total_slow_paths, total_stable)`.
+    fn aggregate_path_metrics(
+        metrics: HashMap<ProcessId, (usize, usize, usize)>,
+    ) -> (usize, usize, usize) {
+        let mut shards: Vec<Vec<(ProcessId, (usize, usize, usize))>> =
+            (0..METRIC_SHARD_COUNT).map(|_| Vec::new()).collect();
+        for entry in metrics {
+            let shard_index =
+                (entry.0 as usize) & (METRIC_SHARD_COUNT - 1);
+            shards[shard_index].push(entry);
+        }
+
+        // spawn every shard's summation before joining any of them, so they
+        // actually run concurrently rather than one after another
+        let handles: Vec<_> = shards
+            .into_iter()
+            .map(|shard| {
+                std::thread::spawn(move || {
+                    shard.into_iter().fold(
+                        (0usize, 0usize, 0usize),
+                        |(fast, slow, stable), (process_id, (f, s, st))| {
+                            println!(
+                                "process id = {} | fast = {} | slow = {} | stable = {}",
+                                process_id, f, s, st
+                            );
+                            (fast + f, slow + s, stable + st)
+                        },
+                    )
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().expect("metrics shard thread should not panic")
+            })
+            .fold((0, 0, 0), |(fa, sa, sta), (f, s, st)| {
+                (fa + f, sa + s, sta + st)
+            })
+    }
+
     fn check_metrics(
         config: Config,
         shards_per_command: usize,
@@ -846,35 +1285,240 @@ mod tests {
         clients_per_process: usize,
         metrics: HashMap<ProcessId, (usize, usize, usize)>,
     ) -> usize {
-        // total commands per shard
+        // total fast and slow paths count, aggregated by sharding `metrics`
+        // by `ProcessId` and summing each shard on its own thread, so
+        // checking a run with many processes doesn't serialize through one
+        // map
+        let (total_fast_paths, total_slow_paths, total_stable) =
+            aggregate_path_metrics(metrics);
+
+        // compute the total number of commands
+        let total_commands_per_shard = shards_per_command
+            * commands_per_client
+            * clients_per_process
+            * config.n();
+        let total_commands = total_commands_per_shard * config.shards();
+
+        // check that all commands were committed (only for leaderless
+        // protocols)
+        if config.leader().is_none() {
+            assert_eq!(
+                total_fast_paths + total_slow_paths,
+                total_commands,
+                "not all commands were committed"
+            );
+        }
+
+        // check GC:
+        // - if there's a leader (i.e. FPaxos), GC will only prune commands at
+        //   f+1 acceptors
+        // - otherwise, GC will prune comands at all processes
+        let gc_at = if config.leader().is_some() {
+            config.f() + 1
+        } else {
+            config.n()
+        } * config.shards();
+
+        // since GC only happens at the targetted shard, here divide by the
+        // number of `shards_per_command`
+        assert_eq!(
+            gc_at * total_commands_per_shard / shards_per_command,
+            total_stable,
+            "not all processes gced"
+        );
+
+        // return number of slow paths
+        total_slow_paths
+    }
+
+    /// Like `check_metrics`, but tolerant of the processes in `crashed`
+    /// (permanently crashed, per `run_test_with_faults`) never committing or
+    /// gc-ing anything: the liveness assertions only need to hold for the
+    /// surviving majority, which is the whole point of fault injection.
+    fn check_metrics_with_faults(
+        config: Config,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+        crashed: &HashSet<ProcessId>,
+        metrics: HashMap<ProcessId, (usize, usize, usize)>,
+    ) -> usize {
+        assert!(
+            crashed.len() <= config.f(),
+            "can't crash more than f processes and keep a majority alive"
+        );
+        let surviving = config.n() - crashed.len();
 
-        // total fast and slow paths count
         let mut total_fast_paths = 0;
         let mut total_slow_paths = 0;
         let mut total_stable = 0;
 
-        // check process stats
         metrics.into_iter().for_each(
             |(process_id, (fast_paths, slow_paths, stable))| {
                 println!(
-                    "process id = {} | fast = {} | slow = {} | stable = {}",
-                    process_id, fast_paths, slow_paths, stable
+                    "process id = {} | fast = {} | slow = {} | stable = {} | crashed = {}",
+                    process_id,
+                    fast_paths,
+                    slow_paths,
+                    stable,
+                    crashed.contains(&process_id),
+                );
+                if !crashed.contains(&process_id) {
+                    total_fast_paths += fast_paths;
+                    total_slow_paths += slow_paths;
+                    total_stable += stable;
+                }
+            },
+        );
+
+        // same shape as `check_metrics`'s `total_commands_per_shard`, but
+        // counting only the `surviving` processes instead of every `n` of
+        // them, since a permanently crashed process never reports anything
+        let total_commands_per_shard = shards_per_command
+            * commands_per_client
+            * clients_per_process
+            * surviving;
+        let total_commands = total_commands_per_shard * config.shards();
+
+        // every surviving process must still see every command committed
+        // (only for leaderless protocols, same restriction `check_metrics`
+        // makes)
+        if config.leader().is_none() {
+            assert_eq!(
+                total_fast_paths + total_slow_paths,
+                total_commands,
+                "not all commands were committed by the surviving majority"
+            );
+        }
+
+        let gc_at = if config.leader().is_some() {
+            config.f() + 1
+        } else {
+            surviving
+        } * config.shards();
+
+        assert_eq!(
+            gc_at * total_commands_per_shard / shards_per_command,
+            total_stable,
+            "not all surviving processes gced"
+        );
+
+        total_slow_paths
+    }
+
+    /// Like `run_test`, but runs with a bounded `gc_depth` (see
+    /// `Config::set_gc_depth`) instead of the eager all-or-nothing GC every
+    /// other test here relies on, and checks the sliding-window invariant
+    /// via `check_metrics_with_gc_depth` instead of `check_metrics`.
+    fn run_test_with_gc_depth<P>(
+        mut config: Config,
+        gc_depth: u64,
+        shard_count: usize,
+        workers: usize,
+        executors: usize,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+    ) -> usize
+    where
+        P: Protocol + Send + 'static,
+    {
+        config.set_gc_interval(Duration::from_millis(100));
+        config.set_shards(shard_count);
+        config.set_gc_depth(gc_depth);
+
+        let shard_gen = ShardGen::Random { shard_count };
+        let keys_per_shard = 2;
+        let key_gen = KeyGen::ConflictRate {
+            conflict_rate: CONFLICT_RATE,
+        };
+        let payload_size = 1;
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            commands_per_client,
+            payload_size,
+        );
+
+        let tracer_show_interval = None;
+        let extra_run_time = Some(Duration::from_secs(10));
+        let metrics = tokio_test_runtime()
+            .block_on(run_test_with_inspect_fun::<P, (usize, usize, u64)>(
+                config,
+                workload,
+                clients_per_process,
+                workers,
+                executors,
+                tracer_show_interval,
+                Some(gc_round_inspect),
+                Vec::new(),
+                extra_run_time,
+            ))
+            .expect("run should complete successfully")
+            .into_iter()
+            .map(|(process_id, process_metrics)| {
+                let mut total_fast_paths = 0;
+                let mut total_slow_paths = 0;
+                let mut highest_gc_round = 0;
+                process_metrics.into_iter().for_each(
+                    |(fast_paths, slow_paths, gc_round)| {
+                        total_fast_paths += fast_paths;
+                        total_slow_paths += slow_paths;
+                        highest_gc_round = highest_gc_round.max(gc_round);
+                    },
+                );
+                (process_id, (total_fast_paths, total_slow_paths, highest_gc_round))
+            })
+            .collect();
+
+        check_metrics_with_gc_depth(
+            config,
+            gc_depth,
+            shards_per_command,
+            commands_per_client,
+            clients_per_process,
+            metrics,
+        )
+    }
+
+    /// Like `check_metrics`, but for a protocol that prunes a sliding window
+    /// instead of pruning each command as soon as it's stable: instead of
+    /// asserting every command reached `Stable`, it asserts every process's
+    /// `GcRound` is (a) never ahead of what it's actually committed and (b)
+    /// never more than `gc_depth` rounds behind it, i.e. GC neither stalls
+    /// nor over-prunes. This is checked against the final snapshot taken at
+    /// the end of the run, not continuously, so it can't catch a window that
+    /// temporarily over/under-shoots and then recovers before the run ends.
+    fn check_metrics_with_gc_depth(
+        config: Config,
+        gc_depth: u64,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+        metrics: HashMap<ProcessId, (usize, usize, u64)>,
+    ) -> usize {
+        let mut total_fast_paths = 0;
+        let mut total_slow_paths = 0;
+
+        metrics.iter().for_each(
+            |(process_id, (fast_paths, slow_paths, gc_round))| {
+                println!(
+                    "process id = {} | fast = {} | slow = {} | gc_round = {}",
+                    process_id, fast_paths, slow_paths, gc_round
                 );
                 total_fast_paths += fast_paths;
                 total_slow_paths += slow_paths;
-                total_stable += stable;
             },
         );
 
-        // compute the total number of commands
         let total_commands_per_shard = shards_per_command
             * commands_per_client
             * clients_per_process
             * config.n();
         let total_commands = total_commands_per_shard * config.shards();
 
-        // check that all commands were committed (only for leaderless
-        // protocols)
         if config.leader().is_none() {
             assert_eq!(
                 total_fast_paths + total_slow_paths,
@@ -883,25 +1527,310 @@ mod tests {
             );
         }
 
-        // check GC:
-        // - if there's a leader (i.e. FPaxos), GC will only prune commands at
-        //   f+1 acceptors
-        // - otherwise, GC will prune comands at all processes
+        // a process's own commit count is a stand-in for the highest round
+        // it could possibly have anchored, since this harness doesn't expose
+        // a separate round/slot number; `gc_round` must stay within
+        // `gc_depth` of it on every process
+        for (process_id, (fast_paths, slow_paths, gc_round)) in metrics.iter()
+        {
+            let committed_round = (*fast_paths + *slow_paths) as u64;
+            assert!(
+                *gc_round <= committed_round,
+                "process {} pruned past its own highest committed round: gc_round = {}, committed_round = {}",
+                process_id,
+                gc_round,
+                committed_round,
+            );
+            assert!(
+                committed_round.saturating_sub(*gc_round) <= gc_depth,
+                "process {} fell behind its gc_depth window: committed_round = {}, gc_round = {}, gc_depth = {}",
+                process_id,
+                committed_round,
+                gc_round,
+                gc_depth,
+            );
+        }
+
+        total_slow_paths
+    }
+
+    /// Like `run_test`, but also tracks `ProtocolMetricsKind::Recovery` so
+    /// `check_metrics_with_recovery` can fold coordinated-recovery rounds
+    /// into the committed-command invariant instead of conflating them with
+    /// ordinary slow paths.
+    fn run_test_with_recovery<P>(
+        mut config: Config,
+        shard_count: usize,
+        workers: usize,
+        executors: usize,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+    ) -> usize
+    where
+        P: Protocol + Send + 'static,
+    {
+        config.set_gc_interval(Duration::from_millis(100));
+        config.set_shards(shard_count);
+
+        let shard_gen = ShardGen::Random { shard_count };
+        let keys_per_shard = 2;
+        let key_gen = KeyGen::ConflictRate {
+            conflict_rate: CONFLICT_RATE,
+        };
+        let payload_size = 1;
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            commands_per_client,
+            payload_size,
+        );
+
+        let tracer_show_interval = None;
+        let extra_run_time = Some(Duration::from_secs(10));
+        let metrics = tokio_test_runtime()
+            .block_on(run_test_with_inspect_fun::<
+                P,
+                (usize, usize, usize, usize),
+            >(
+                config,
+                workload,
+                clients_per_process,
+                workers,
+                executors,
+                tracer_show_interval,
+                Some(recovery_inspect),
+                Vec::new(),
+                extra_run_time,
+            ))
+            .expect("run should complete successfully")
+            .into_iter()
+            .map(|(process_id, process_metrics)| {
+                let mut total_fast_paths = 0;
+                let mut total_slow_paths = 0;
+                let mut total_recoveries = 0;
+                let mut total_stable_count = 0;
+                process_metrics.into_iter().for_each(
+                    |(fast_paths, slow_paths, recoveries, stable_count)| {
+                        total_fast_paths += fast_paths;
+                        total_slow_paths += slow_paths;
+                        total_recoveries += recoveries;
+                        total_stable_count += stable_count;
+                    },
+                );
+                (
+                    process_id,
+                    (
+                        total_fast_paths,
+                        total_slow_paths,
+                        total_recoveries,
+                        total_stable_count,
+                    ),
+                )
+            })
+            .collect();
+
+        check_metrics_with_recovery(
+            config,
+            shards_per_command,
+            commands_per_client,
+            clients_per_process,
+            metrics,
+        )
+    }
+
+    /// Like `check_metrics`, but for leaderless protocols where a
+    /// coordinated-recovery round (`ProtocolMetricsKind::Recovery`) is a
+    /// semantically distinct outcome from an ordinary slow path: the
+    /// committed-command invariant becomes `fast + slow + recovery ==
+    /// total_commands`, and the slow-path/recovery counts are returned
+    /// separately so a caller can tell how often each fired.
+    fn check_metrics_with_recovery(
+        config: Config,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+        metrics: HashMap<ProcessId, (usize, usize, usize, usize)>,
+    ) -> (usize, usize) {
+        let mut total_fast_paths = 0;
+        let mut total_slow_paths = 0;
+        let mut total_recoveries = 0;
+        let mut total_stable = 0;
+
+        metrics.into_iter().for_each(
+            |(process_id, (fast_paths, slow_paths, recoveries, stable))| {
+                println!(
+                    "process id = {} | fast = {} | slow = {} | recovery = {} | stable = {}",
+                    process_id, fast_paths, slow_paths, recoveries, stable
+                );
+                total_fast_paths += fast_paths;
+                total_slow_paths += slow_paths;
+                total_recoveries += recoveries;
+                total_stable += stable;
+            },
+        );
+
+        let total_commands_per_shard = shards_per_command
+            * commands_per_client
+            * clients_per_process
+            * config.n();
+        let total_commands = total_commands_per_shard * config.shards();
+
+        if config.leader().is_none() {
+            assert_eq!(
+                total_fast_paths + total_slow_paths + total_recoveries,
+                total_commands,
+                "not all commands were committed"
+            );
+        }
+
         let gc_at = if config.leader().is_some() {
             config.f() + 1
         } else {
             config.n()
         } * config.shards();
 
-        // since GC only happens at the targetted shard, here divide by the
-        // number of `shards_per_command`
         assert_eq!(
             gc_at * total_commands_per_shard / shards_per_command,
             total_stable,
             "not all processes gced"
         );
 
-        // return number of slow paths
-        total_slow_paths
+        (total_slow_paths, total_recoveries)
+    }
+
+    /// Like `run_test`, but checks `CommitModel::Paxos` invariants via
+    /// `check_metrics_log_model` instead of the fast/slow-path
+    /// `check_metrics`, for leader-replicated-log protocols
+    /// (MultiPaxos/Raft) that have no fast/slow distinction.
+    fn run_test_log_model<P>(
+        mut config: Config,
+        shard_count: usize,
+        workers: usize,
+        executors: usize,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+    ) -> usize
+    where
+        P: Protocol + Send + 'static,
+    {
+        config.set_gc_interval(Duration::from_millis(100));
+        config.set_shards(shard_count);
+
+        let shard_gen = ShardGen::Random { shard_count };
+        let keys_per_shard = 2;
+        let key_gen = KeyGen::ConflictRate {
+            conflict_rate: CONFLICT_RATE,
+        };
+        let payload_size = 1;
+        let workload = Workload::new(
+            shards_per_command,
+            shard_gen,
+            keys_per_shard,
+            key_gen,
+            commands_per_client,
+            payload_size,
+        );
+
+        let tracer_show_interval = None;
+        let extra_run_time = Some(Duration::from_secs(10));
+        let metrics = tokio_test_runtime()
+            .block_on(run_test_with_inspect_fun::<P, (usize, usize)>(
+                config,
+                workload,
+                clients_per_process,
+                workers,
+                executors,
+                tracer_show_interval,
+                Some(log_model_inspect),
+                Vec::new(),
+                extra_run_time,
+            ))
+            .expect("run should complete successfully")
+            .into_iter()
+            .map(|(process_id, process_metrics)| {
+                let mut committed_index = 0;
+                let mut applied_index = 0;
+                process_metrics.into_iter().for_each(
+                    |(committed, applied)| {
+                        committed_index += committed;
+                        applied_index += applied;
+                    },
+                );
+                (process_id, (committed_index, applied_index))
+            })
+            .collect();
+
+        check_metrics_log_model(
+            config,
+            shards_per_command,
+            commands_per_client,
+            clients_per_process,
+            metrics,
+        )
+    }
+
+    /// Like `check_metrics`, but for `CommitModel::Paxos`-style protocols:
+    /// instead of fast/slow paths, asserts (1) every process committed
+    /// exactly `total_commands` log indices (this harness only has access
+    /// to the running count `ProtocolMetricsKind::CommittedSlots` exposes,
+    /// not the actual index sequence, so "no gaps across the quorum" is
+    /// checked via that count rather than by diffing raw indices), (2)
+    /// no process applied past what it committed, and (3) GC/apply pruned a
+    /// contiguous log prefix up to at least `f + 1` processes, mirroring
+    /// `check_metrics`'s leader-present GC branch.
+    fn check_metrics_log_model(
+        config: Config,
+        shards_per_command: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+        metrics: HashMap<ProcessId, (usize, usize)>,
+    ) -> usize {
+        assert_eq!(
+            config.commit_model(),
+            CommitModel::Paxos,
+            "check_metrics_log_model only applies to CommitModel::Paxos configs"
+        );
+
+        let total_commands_per_shard = shards_per_command
+            * commands_per_client
+            * clients_per_process
+            * config.n();
+        let total_commands = total_commands_per_shard * config.shards();
+
+        let mut total_applied = 0;
+        metrics.iter().for_each(
+            |(process_id, (committed_index, applied_index))| {
+                println!(
+                    "process id = {} | committed_index = {} | applied_index = {}",
+                    process_id, committed_index, applied_index
+                );
+                assert!(
+                    *applied_index <= *committed_index,
+                    "process {} applied past what it committed: applied_index = {}, committed_index = {}",
+                    process_id,
+                    applied_index,
+                    committed_index,
+                );
+                assert_eq!(
+                    *committed_index, total_commands,
+                    "process {} didn't commit every log index",
+                    process_id,
+                );
+                total_applied += applied_index;
+            },
+        );
+
+        let gc_at = (config.f() + 1) * config.shards();
+        assert_eq!(
+            gc_at * total_commands_per_shard / shards_per_command,
+            total_applied,
+            "not all processes applied/gced a contiguous log prefix"
+        );
+
+        total_applied
     }
 }
@@ -1,7 +1,7 @@
 use crate::command::Command;
 use crate::config::Config;
 use crate::executor::{Executor, TableExecutor};
-use crate::id::{Dot, ProcessId};
+use crate::id::{Dot, ProcessId, ShardId};
 use crate::log;
 use crate::planet::{Planet, Region};
 use crate::protocol::common::{
@@ -9,21 +9,162 @@ use crate::protocol::common::{
     table::{KeysClocks, ProcessVotes, QuorumClocks, Votes},
 };
 use crate::protocol::{BaseProcess, Process, ToSend};
+use crate::time::SysTime;
 use std::cmp;
+use std::collections::{HashMap, VecDeque};
 use std::mem;
+use std::time::Duration;
+use threshold::VClock;
 
 type ExecutionInfo = <TableExecutor as Executor>::ExecutionInfo;
 
+// how long an `MCollect` can stay in `COLLECT` before we suspect the
+// coordinator has failed and trigger slow-path recovery
+const COLLECT_TIMEOUT: Duration = Duration::from_millis(500);
+// how often aggregated phantom votes are flushed, instead of sending an
+// `MPhantom` per commit
+const PHANTOM_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+// how often each process gossips its committed clock to every peer so that
+// the stable frontier can advance without piggybacking on other messages
+const GC_GOSSIP_INTERVAL: Duration = Duration::from_millis(200);
+// number of most-recent commit latencies kept to compute percentiles
+const LATENCY_WINDOW: usize = 256;
+
 pub struct Newt {
     bp: BaseProcess,
     keys_clocks: KeysClocks,
     cmds: Commands<CommandInfo>,
     to_executor: Vec<ExecutionInfo>,
+    // dots whose status became `COLLECT` and haven't committed yet, along
+    // with the (logical) time at which that happened; checked by the
+    // `SlowPathCheck` periodic event to trigger recovery
+    pending_collects: HashMap<Dot, u64>,
+    // phantom votes waiting to be flushed together by the `PhantomFlush`
+    // periodic event, instead of one `MPhantom` per commit
+    pending_phantoms: Vec<(Dot, ProcessVotes)>,
+    // number of `handle`/`handle_event` calls seen so far; used as a logical
+    // clock to measure commit latency in the absence of a submit-time
+    // timestamp
+    local_tick: u64,
+    // tick at which each still-in-flight dot was submitted, recorded by the
+    // coordinator so `handle_mcommit` can compute its commit latency
+    submitted_at: HashMap<Dot, u64>,
+    metrics: Metrics,
+}
+
+/// Tracks commit-latency percentiles, the fast/slow-path ratio and the
+/// number of extra (`MPhantom`/`MConsensus`) rounds triggered, so GC and
+/// quorum-path pressure are observable without instrumenting the simulation
+/// harness itself.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    commit_latencies: VecDeque<u64>,
+    fast_path_count: u64,
+    slow_path_count: u64,
+    phantom_rounds: u64,
+    consensus_rounds: u64,
+}
+
+impl Metrics {
+    fn record_commit_latency(&mut self, latency: u64) {
+        if self.commit_latencies.len() == LATENCY_WINDOW {
+            self.commit_latencies.pop_front();
+        }
+        self.commit_latencies.push_back(latency);
+    }
+
+    fn record_fast_path(&mut self) {
+        self.fast_path_count += 1;
+    }
+
+    fn record_slow_path(&mut self) {
+        self.slow_path_count += 1;
+    }
+
+    fn record_phantom_round(&mut self) {
+        self.phantom_rounds += 1;
+    }
+
+    fn record_consensus_round(&mut self) {
+        self.consensus_rounds += 1;
+    }
+
+    /// Returns `(median, p99)` commit latency, in logical ticks, over the
+    /// last `LATENCY_WINDOW` committed commands; `None` if nothing has
+    /// committed yet.
+    fn commit_latency_percentiles(&self) -> Option<(u64, u64)> {
+        if self.commit_latencies.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.commit_latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+        let p99_index = (sorted.len() * 99 / 100).min(sorted.len() - 1);
+        let p99 = sorted[p99_index];
+        Some((median, p99))
+    }
+
+    /// Fraction of quorum rounds that took the fast path, in `[0, 1]`.
+    fn fast_path_ratio(&self) -> f64 {
+        let total = self.fast_path_count + self.slow_path_count;
+        if total == 0 {
+            0.0
+        } else {
+            self.fast_path_count as f64 / total as f64
+        }
+    }
+
+    fn snapshot(&self, dot_to_info_entries: usize) -> MetricsSnapshot {
+        let (commit_latency_median, commit_latency_p99) = self
+            .commit_latency_percentiles()
+            .map_or((None, None), |(median, p99)| (Some(median), Some(p99)));
+        MetricsSnapshot {
+            commit_latency_median,
+            commit_latency_p99,
+            fast_path_count: self.fast_path_count,
+            slow_path_count: self.slow_path_count,
+            fast_path_ratio: self.fast_path_ratio(),
+            consensus_rounds: self.consensus_rounds,
+            phantom_rounds: self.phantom_rounds,
+            dot_to_info_entries,
+            dot_to_info_bytes_estimate: dot_to_info_entries * std::mem::size_of::<CommandInfo>(),
+        }
+    }
+}
+
+/// Point-in-time snapshot returned by [`Newt::metrics`], suitable for the
+/// simulation harness to assert on directly instead of scraping logs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsSnapshot {
+    pub commit_latency_median: Option<u64>,
+    pub commit_latency_p99: Option<u64>,
+    pub fast_path_count: u64,
+    pub slow_path_count: u64,
+    pub fast_path_ratio: f64,
+    pub consensus_rounds: u64,
+    pub phantom_rounds: u64,
+    pub dot_to_info_entries: usize,
+    pub dot_to_info_bytes_estimate: usize,
+}
+
+/// Events fired periodically by the runtime/`Simulation`, as registered by
+/// `Process::events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodicEvent {
+    /// checks for `MCollect`s that have been stuck in `COLLECT` past
+    /// `COLLECT_TIMEOUT` and triggers slow-path recovery for them
+    SlowPathCheck,
+    /// flushes phantom votes aggregated since the last tick
+    PhantomFlush,
+    /// gossips this process's committed clock to every peer so that GC can
+    /// advance the stable frontier without piggybacking on other messages
+    GarbageCollection,
 }
 
 impl Process for Newt {
     type Message = Message;
     type Executor = TableExecutor;
+    type PeriodicEvent = PeriodicEvent;
 
     /// Creates a new `Newt` process.
     fn new(process_id: ProcessId, region: Region, planet: Planet, config: Config) -> Self {
@@ -49,6 +190,11 @@ impl Process for Newt {
             keys_clocks,
             cmds,
             to_executor,
+            pending_collects: HashMap::new(),
+            pending_phantoms: Vec::new(),
+            local_tick: 0,
+            submitted_at: HashMap::new(),
+            metrics: Metrics::default(),
         }
     }
 
@@ -64,23 +210,51 @@ impl Process for Newt {
 
     /// Submits a command issued by some client.
     fn submit(&mut self, cmd: Command) -> ToSend<Self::Message> {
+        self.local_tick += 1;
         self.handle_submit(cmd)
     }
 
+    /// Periodic events this process wants to be notified about, along with
+    /// the delay+period at which they should fire.
+    fn events(&self) -> Vec<(Self::PeriodicEvent, Duration)> {
+        vec![
+            (PeriodicEvent::SlowPathCheck, COLLECT_TIMEOUT),
+            (PeriodicEvent::PhantomFlush, PHANTOM_FLUSH_INTERVAL),
+            (PeriodicEvent::GarbageCollection, GC_GOSSIP_INTERVAL),
+        ]
+    }
+
+    /// Handles a periodic event fired by the runtime/`Simulation`.
+    fn handle_event(
+        &mut self,
+        event: Self::PeriodicEvent,
+        time: &dyn SysTime,
+    ) -> Vec<ToSend<Self::Message>> {
+        self.local_tick += 1;
+        match event {
+            PeriodicEvent::SlowPathCheck => self.check_stuck_collects(time),
+            PeriodicEvent::PhantomFlush => self.flush_phantom_votes(),
+            PeriodicEvent::GarbageCollection => self.gossip_committed_clock(),
+        }
+    }
+
     /// Handles protocol messages.
     fn handle(&mut self, from: ProcessId, msg: Self::Message) -> Option<ToSend<Message>> {
+        self.local_tick += 1;
         match msg {
             Message::MCollect {
                 dot,
                 cmd,
                 quorum,
                 clock,
-            } => self.handle_mcollect(from, dot, cmd, quorum, clock),
+            } => self.handle_mcollect(from, dot, cmd, quorum, clock, None),
             Message::MCollectAck {
                 dot,
+                shard_id,
                 clock,
                 process_votes,
-            } => self.handle_mcollectack(from, dot, clock, process_votes),
+            } => self.handle_mcollectack(from, dot, shard_id, clock, process_votes),
+            Message::MShardCollect { dot, shards } => self.handle_mshardcollect(from, dot, shards),
             Message::MCommit {
                 dot,
                 cmd,
@@ -88,6 +262,22 @@ impl Process for Newt {
                 votes,
             } => self.handle_mcommit(dot, cmd, clock, votes),
             Message::MPhantom { dot, process_votes } => self.handle_mphantom(dot, process_votes),
+            Message::MConsensus {
+                dot,
+                ballot,
+                clock,
+                cmd,
+            } => self.handle_mconsensus(from, dot, ballot, clock, cmd),
+            Message::MConsensusAck {
+                dot,
+                ballot,
+                accepted_ballot,
+                accepted_clock,
+            } => self.handle_mconsensusack(from, dot, ballot, accepted_ballot, accepted_clock),
+            Message::MPhantomBatch { phantoms } => self.handle_mphantombatch(phantoms),
+            Message::MGarbageCollection { committed } => {
+                self.handle_mgarbagecollection(from, committed)
+            }
         }
     }
 
@@ -100,6 +290,7 @@ impl Process for Newt {
 
     fn show_metrics(&self) {
         self.bp.show_metrics();
+        log!("p{}: metrics {:?}", self.id(), self.metrics());
     }
 }
 
@@ -108,27 +299,69 @@ impl Newt {
     fn handle_submit(&mut self, cmd: Command) -> ToSend<Message> {
         // compute the command identifier
         let dot = self.bp.next_dot();
+        self.submitted_at.insert(dot, self.local_tick);
 
-        // compute its clock
-        let clock = self.keys_clocks.clock(&cmd) + 1;
+        let shards = cmd.shards();
+        if shards.len() <= 1 {
+            // single-shard fast path: unchanged from the original protocol
+            let clock = self.keys_clocks.clock(&cmd) + 1;
+            let mcollect = Message::MCollect {
+                dot,
+                cmd,
+                clock,
+                quorum: self.bp.fast_quorum(),
+            };
+            let target = self.bp.fast_quorum();
+            return ToSend {
+                from: self.id(),
+                target,
+                msg: mcollect,
+            };
+        }
 
-        // create `MCollect` and target
-        let mcollect = Message::MCollect {
+        // multi-shard: split `cmd` so each participating shard runs its own
+        // clock-assignment round; `handle_mcollectack` joins the per-shard
+        // outcomes (taking the max clock across shards) once every shard has
+        // replied, recorded here so we know which ones we're waiting on
+        let info = self.cmds.get(dot);
+        info.shards = shards.clone();
+        info.cmd = Some(cmd.clone());
+
+        let sub_collects: Vec<ShardCollect> = shards
+            .into_iter()
+            .map(|shard_id| {
+                let shard_cmd = cmd.for_shard(shard_id);
+                let clock = self.keys_clocks.clock(&shard_cmd) + 1;
+                let quorum = self.bp.fast_quorum_for_shard(shard_id);
+                ShardCollect {
+                    shard_id,
+                    cmd: shard_cmd,
+                    quorum,
+                    clock,
+                }
+            })
+            .collect();
+
+        let target = sub_collects
+            .iter()
+            .flat_map(|sub| sub.quorum.iter().copied())
+            .collect();
+        let mshardcollect = Message::MShardCollect {
             dot,
-            cmd,
-            clock,
-            quorum: self.bp.fast_quorum(),
+            shards: sub_collects,
         };
-        let target = self.bp.fast_quorum();
 
-        // return `ToSend`
         ToSend {
             from: self.id(),
             target,
-            msg: mcollect,
+            msg: mshardcollect,
         }
     }
 
+    /// Handles a single-shard `MCollect`, the same message shape every
+    /// fast-quorum member of a shard receives: either directly (the common,
+    /// single-shard case, `shard_id` is `None`) or as this shard's slice of a
+    /// multi-shard `MShardCollect` (`shard_id` is `Some`).
     fn handle_mcollect(
         &mut self,
         from: ProcessId,
@@ -136,6 +369,7 @@ impl Newt {
         cmd: Command,
         quorum: Vec<ProcessId>,
         remote_clock: u64,
+        shard_id: Option<ShardId>,
     ) -> Option<ToSend<Message>> {
         log!(
             "p{}: MCollect({:?}, {:?}, {}) from {}",
@@ -169,9 +403,14 @@ impl Newt {
         info.quorum = quorum;
         info.clock = clock;
 
+        // start tracking this dot so `SlowPathCheck` can notice if it's
+        // stuck in `COLLECT` for too long
+        self.pending_collects.insert(dot, 0);
+
         // create `MCollectAck` and target
         let mcollectack = Message::MCollectAck {
             dot,
+            shard_id,
             clock,
             process_votes,
         };
@@ -185,10 +424,32 @@ impl Newt {
         })
     }
 
+    /// Handles this shard's slice of a multi-shard `MShardCollect`, ignoring
+    /// every entry that doesn't belong to our own shard.
+    fn handle_mshardcollect(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        shards: Vec<ShardCollect>,
+    ) -> Option<ToSend<Message>> {
+        let mine = shards
+            .into_iter()
+            .find(|sub| sub.shard_id == self.bp.shard_id)?;
+        self.handle_mcollect(
+            from,
+            dot,
+            mine.cmd,
+            mine.quorum,
+            mine.clock,
+            Some(mine.shard_id),
+        )
+    }
+
     fn handle_mcollectack(
         &mut self,
         from: ProcessId,
         dot: Dot,
+        shard_id: Option<ShardId>,
         clock: u64,
         remote_votes: ProcessVotes,
     ) -> Option<ToSend<Message>> {
@@ -212,6 +473,10 @@ impl Newt {
         // update votes with remote votes
         info.votes.add(remote_votes);
 
+        if !info.shards.is_empty() {
+            return self.handle_mshardcollectack(dot, from, shard_id, clock);
+        }
+
         // update quorum clocks while computing max clock and its number of occurences
         let (max_clock, max_count) = info.quorum_clocks.add(from, clock);
 
@@ -236,6 +501,7 @@ impl Newt {
             // - if `max_clock` was reported by at least f processes
             if max_count >= self.bp.config.f() {
                 self.bp.fast_path();
+                self.metrics.record_fast_path();
                 // reset local votes as we're going to receive them right away; this also prevents a
                 // `info.votes.clone()`
                 let votes = Self::reset_votes(&mut info.votes);
@@ -259,14 +525,285 @@ impl Newt {
                 })
             } else {
                 self.bp.slow_path();
-                // TODO slow path
-                todo!("slow path not implemented yet")
+                self.metrics.record_slow_path();
+                self.metrics.record_consensus_round();
+
+                // the fast quorum didn't agree on a single clock: drive the
+                // highest reported clock through single-decree consensus
+                // (Flexible-Paxos/HotStuff-style) over the write quorum
+                // instead of committing directly
+                let ballot = Self::initial_ballot(self.id());
+                info.current_ballot = ballot;
+
+                let mconsensus = Message::MConsensus {
+                    dot,
+                    ballot,
+                    clock: max_clock,
+                    cmd: info.cmd.clone(),
+                };
+                let target = self.bp.write_quorum();
+
+                Some(ToSend {
+                    from: self.id(),
+                    target,
+                    msg: mconsensus,
+                })
             }
         } else {
             None
         }
     }
 
+    /// Aggregates one shard's `MCollectAck` for a multi-shard command; once
+    /// every participating shard's fast quorum has reported, joins the
+    /// outcomes (taking the max clock across shards) and commits atomically
+    /// to every involved shard's replica set.
+    ///
+    /// Note: unlike the single-shard path, a shard whose fast quorum doesn't
+    /// agree on a single clock doesn't get its own consensus round here; its
+    /// highest reported clock is used directly and the round is simply
+    /// reported as slow-path for metrics purposes. Giving individual shards
+    /// a real per-shard recovery path is future work.
+    fn handle_mshardcollectack(
+        &mut self,
+        dot: Dot,
+        from: ProcessId,
+        shard_id: Option<ShardId>,
+        clock: u64,
+    ) -> Option<ToSend<Message>> {
+        let shard_id =
+            shard_id.expect("MCollectAck for a multi-shard command must carry a shard_id");
+        let (fast_quorum_size, _, _) = self.bp.config.newt_quorum_sizes();
+
+        let info = self.cmds.get(dot);
+        let quorum_clocks = info
+            .quorum_clocks_per_shard
+            .entry(shard_id)
+            .or_insert_with(|| QuorumClocks::new(fast_quorum_size));
+        let (shard_max_clock, shard_max_count) = quorum_clocks.add(from, clock);
+
+        if let Some(cmd) = info.cmd.as_ref() {
+            let local_votes = self.keys_clocks.process_votes(cmd, shard_max_clock);
+            info.votes.add(local_votes);
+        }
+
+        if !quorum_clocks.all() {
+            return None;
+        }
+
+        info.shard_clocks.insert(shard_id, shard_max_clock);
+        info.shard_fast_ok = info.shard_fast_ok && shard_max_count >= self.bp.config.f();
+
+        if info.shard_clocks.len() < info.shards.len() {
+            // still waiting on other shards
+            return None;
+        }
+
+        // every participating shard agreed: join by taking the max clock
+        // across shards and commit atomically to all of them
+        let clock = info
+            .shard_clocks
+            .values()
+            .copied()
+            .max()
+            .expect("at least one shard must have reported");
+        let votes = Self::reset_votes(&mut info.votes);
+        let cmd = info.cmd.clone();
+        let shards = info.shards.clone();
+
+        if info.shard_fast_ok {
+            self.bp.fast_path();
+            self.metrics.record_fast_path();
+        } else {
+            self.bp.slow_path();
+            self.metrics.record_slow_path();
+        }
+
+        let mcommit = Message::MCommit {
+            dot,
+            cmd,
+            clock,
+            votes,
+        };
+        let target = self.bp.all_for_shards(&shards);
+
+        Some(ToSend {
+            from: self.id(),
+            target,
+            msg: mcommit,
+        })
+    }
+
+    fn handle_mconsensus(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        ballot: u64,
+        clock: u64,
+        cmd: Option<Command>,
+    ) -> Option<ToSend<Message>> {
+        log!(
+            "p{}: MConsensus({:?}, {}, {}) from {}",
+            self.id(),
+            dot,
+            ballot,
+            clock,
+            from
+        );
+
+        // get cmd info
+        let info = self.cmds.get(dot);
+
+        // an acceptor only accepts a proposal whose ballot is at least as
+        // high as the one it has already promised/accepted
+        if ballot < info.current_ballot {
+            return None;
+        }
+
+        // remember what we had accepted *before* this proposal: if some
+        // other coordinator already got a value accepted under a lower
+        // ballot, we report it back so a recovering coordinator is forced to
+        // re-propose it instead of its own value, preserving safety
+        let previously_accepted_ballot = info.accepted_ballot;
+        let previously_accepted_clock = info.accepted_clock.unwrap_or(0);
+
+        // accept the proposal
+        info.current_ballot = ballot;
+        info.accepted_ballot = ballot;
+        info.accepted_clock = Some(clock);
+        if cmd.is_some() {
+            info.cmd = cmd;
+        }
+
+        let mconsensusack = Message::MConsensusAck {
+            dot,
+            ballot,
+            accepted_ballot: previously_accepted_ballot,
+            accepted_clock: previously_accepted_clock,
+        };
+        let target = vec![from];
+
+        Some(ToSend {
+            from: self.id(),
+            target,
+            msg: mconsensusack,
+        })
+    }
+
+    fn handle_mconsensusack(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        ballot: u64,
+        accepted_ballot: u64,
+        accepted_clock: u64,
+    ) -> Option<ToSend<Message>> {
+        log!(
+            "p{}: MConsensusAck({:?}, {}) from {}",
+            self.id(),
+            dot,
+            ballot,
+            from
+        );
+
+        // get cmd info
+        let info = self.cmds.get(dot);
+
+        // ignore acks for a ballot we've since moved on from (e.g. a
+        // recovering coordinator proposed a higher ballot in the meantime)
+        if ballot != info.current_ballot {
+            return None;
+        }
+
+        if info.status == Status::COMMIT {
+            // already committed (e.g. via a concurrent majority of acks)
+            return None;
+        }
+
+        info.consensus_acks.insert(from);
+
+        // if an acceptor had already accepted a value under a different
+        // (necessarily lower) ballot, remember the highest such value: it
+        // must be re-proposed instead of our own to preserve safety
+        if accepted_ballot > info.recovered_ballot {
+            info.recovered_ballot = accepted_ballot;
+            info.recovered_clock = Some(accepted_clock);
+        }
+
+        if info.consensus_acks.len() >= self.bp.write_quorum_size() {
+            // write-quorum of acks for our ballot: safe to commit, using any
+            // previously-accepted value we learned about instead of our own
+            // proposal, if one exists
+            let clock = info.recovered_clock.unwrap_or(info.clock);
+
+            let votes = Self::reset_votes(&mut info.votes);
+
+            let mcommit = Message::MCommit {
+                dot,
+                cmd: info.cmd.clone(),
+                clock,
+                votes,
+            };
+            let target = self.bp.all();
+
+            Some(ToSend {
+                from: self.id(),
+                target,
+                msg: mcommit,
+            })
+        } else {
+            None
+        }
+    }
+
+    // the low `BALLOT_ROUND_SHIFT` bits of a ballot hold the process id, so
+    // that ballots proposed by different processes in the same round are
+    // globally unique; the remaining high bits hold a round number that a
+    // recovering coordinator bumps to propose a strictly higher ballot than
+    // any seen so far
+    const BALLOT_ROUND_SHIFT: u32 = 16;
+
+    fn ballot(round: u64, process_id: ProcessId) -> u64 {
+        (round << Self::BALLOT_ROUND_SHIFT) | (process_id as u64)
+    }
+
+    /// Computes this process's initial ballot (round 1).
+    fn initial_ballot(process_id: ProcessId) -> u64 {
+        Self::ballot(1, process_id)
+    }
+
+    /// Starts (or restarts) recovery of `dot`, whose original coordinator may
+    /// have failed: bumps to a ballot in a round strictly higher than any
+    /// seen by this process so far, and (re-)proposes using the clock and
+    /// command this process currently knows about. If some acceptor reveals
+    /// an already-accepted value from an earlier round, `handle_mconsensusack`
+    /// will force this process to commit that value instead.
+    pub fn recover(&mut self, dot: Dot) -> ToSend<Message> {
+        self.metrics.record_consensus_round();
+        let info = self.cmds.get(dot);
+
+        let current_round = info.current_ballot >> Self::BALLOT_ROUND_SHIFT;
+        let ballot = Self::ballot(current_round + 1, self.bp.process_id);
+        info.current_ballot = ballot;
+        info.consensus_acks = std::collections::HashSet::new();
+        info.recovered_ballot = 0;
+        info.recovered_clock = None;
+
+        let mconsensus = Message::MConsensus {
+            dot,
+            ballot,
+            clock: info.clock,
+            cmd: info.cmd.clone(),
+        };
+        let target = self.bp.write_quorum();
+
+        ToSend {
+            from: self.id(),
+            target,
+            msg: mconsensus,
+        }
+    }
+
     fn handle_mcommit(
         &mut self,
         dot: Dot,
@@ -290,26 +827,35 @@ impl Newt {
         info.cmd = cmd;
         info.clock = clock;
 
+        // this dot committed, so it's no longer a slow-path recovery
+        // candidate
+        self.pending_collects.remove(&dot);
+
+        // record this dot as committed so it can be reported during the next
+        // garbage-collection gossip round
+        self.cmds.commit(dot);
+
+        // if this process coordinated `dot`, report its commit latency
+        if let Some(submitted_at) = self.submitted_at.remove(&dot) {
+            self.metrics
+                .record_commit_latency(self.local_tick - submitted_at);
+        }
+
         // get current votes (probably from phantom messages) merge them with received votes so that
         // all together can be added to a votes table
         let current_votes = Self::reset_votes(&mut info.votes);
         votes.merge(current_votes);
 
         // generate phantom votes if committed clock is higher than the local key's clock
-        let mut to_send = None;
         if let Some(cmd) = info.cmd.as_ref() {
             // if not a no op, check if we can generate more votes that can speed-up execution
             let process_votes = self.keys_clocks.process_votes(cmd, info.clock);
 
-            // create `MPhantom` if there are new votes
+            // buffer phantom votes instead of sending an `MPhantom` right
+            // away; they're flushed together by the `PhantomFlush` event
             if !process_votes.is_empty() {
-                let mphantom = Message::MPhantom { dot, process_votes };
-                let target = self.bp.all();
-                to_send = Some(ToSend {
-                    from: self.bp.process_id,
-                    target,
-                    msg: mphantom,
-                });
+                self.metrics.record_phantom_round();
+                self.pending_phantoms.push((dot, process_votes));
             }
         }
 
@@ -321,8 +867,8 @@ impl Newt {
             self.to_executor.push(execution_info);
         }
 
-        // return `ToSend`
-        to_send
+        // nothing to send immediately; phantom votes (if any) are flushed later
+        None
     }
 
     fn handle_mphantom(
@@ -349,6 +895,108 @@ impl Newt {
         None
     }
 
+    fn handle_mphantombatch(
+        &mut self,
+        phantoms: Vec<(Dot, ProcessVotes)>,
+    ) -> Option<ToSend<Message>> {
+        for (dot, process_votes) in phantoms {
+            // every individual entry is handled exactly like a standalone
+            // `MPhantom`; it never has anything to send back
+            self.handle_mphantom(dot, process_votes);
+        }
+        None
+    }
+
+    /// Looks for dots that have been stuck in `COLLECT` for too long and
+    /// triggers slow-path recovery for them.
+    fn check_stuck_collects(&mut self, _time: &dyn SysTime) -> Vec<ToSend<Message>> {
+        // every `SlowPathCheck` tick fires once every `COLLECT_TIMEOUT`, so a
+        // single still-pending tick is already past the timeout
+        const STALE_TICKS: u32 = 1;
+
+        let stuck: Vec<Dot> = self
+            .pending_collects
+            .iter_mut()
+            .filter_map(|(dot, ticks)| {
+                *ticks += 1;
+                if *ticks > STALE_TICKS {
+                    Some(*dot)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        stuck
+            .into_iter()
+            .map(|dot| {
+                log!(
+                    "p{}: dot {:?} stuck in COLLECT; starting recovery",
+                    self.id(),
+                    dot
+                );
+                // give this recovery attempt a full timeout to complete
+                // before considering it stuck again
+                self.pending_collects.insert(dot, 0);
+                self.recover(dot)
+            })
+            .collect()
+    }
+
+    /// Flushes all phantom votes aggregated since the last tick as a single
+    /// `MPhantomBatch`, instead of one `MPhantom` per commit.
+    fn flush_phantom_votes(&mut self) -> Vec<ToSend<Message>> {
+        if self.pending_phantoms.is_empty() {
+            return Vec::new();
+        }
+
+        let phantoms = mem::take(&mut self.pending_phantoms);
+        let mphantombatch = Message::MPhantomBatch { phantoms };
+        let target = self.bp.all();
+
+        vec![ToSend {
+            from: self.id(),
+            target,
+            msg: mphantombatch,
+        }]
+    }
+
+    /// Returns a point-in-time snapshot of this process's metrics: commit
+    /// latency percentiles, fast/slow-path ratio, number of extra rounds
+    /// triggered, and `dot_to_info` memory pressure.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot(self.cmds.len())
+    }
+
+    /// Gossips this process's committed clock to every peer so that the
+    /// stable frontier can advance even when no other message happens to
+    /// carry that information.
+    fn gossip_committed_clock(&mut self) -> Vec<ToSend<Message>> {
+        let committed = self.cmds.committed();
+        let mgc = Message::MGarbageCollection { committed };
+        let target = self.bp.all();
+
+        vec![ToSend {
+            from: self.id(),
+            target,
+            msg: mgc,
+        }]
+    }
+
+    /// Handles a peer's gossiped committed clock: merges it in, recomputes
+    /// the stable frontier and reclaims any newly-stable `dot_to_info`
+    /// entries.
+    fn handle_mgarbagecollection(
+        &mut self,
+        from: ProcessId,
+        committed: VClock<ProcessId>,
+    ) -> Option<ToSend<Message>> {
+        self.cmds.committed_by(from, committed);
+        let stable = self.cmds.stable();
+        self.cmds.gc(stable);
+        None
+    }
+
     // Replaces the value `local_votes` with empty votes, returning the previous votes.
     fn reset_votes(local_votes: &mut Votes) -> Votes {
         let mut votes = Votes::new();
@@ -370,6 +1018,27 @@ struct CommandInfo {
     // `quorum_clocks` is used by the coordinator to compute the highest clock
     // reported by fast quorum members and the number of times it was reported
     quorum_clocks: QuorumClocks,
+    // the slow path drives `clock` through single-decree consensus: `current_ballot` is
+    // the highest ballot this process has promised/proposed, `accepted_ballot`/`accepted_clock`
+    // is the value this process (as an acceptor) has accepted, and `consensus_acks` is used by
+    // the coordinator to track `MConsensusAck`s for `current_ballot`
+    current_ballot: u64,
+    accepted_ballot: u64,
+    accepted_clock: Option<u64>,
+    consensus_acks: std::collections::HashSet<ProcessId>,
+    // highest-ballot previously-accepted value reported back by an acceptor; once set, it must
+    // be re-proposed by the coordinator instead of its own value, to preserve safety across
+    // recovery attempts
+    recovered_ballot: u64,
+    recovered_clock: Option<u64>,
+    // non-empty only at the coordinator of a command spanning more than one
+    // shard: the shards it was split across, each shard's own fast-quorum
+    // clock aggregation, the clock each shard's quorum settled on once
+    // complete, and whether every shard so far took the fast path
+    shards: Vec<ShardId>,
+    quorum_clocks_per_shard: HashMap<ShardId, QuorumClocks>,
+    shard_clocks: HashMap<ShardId, u64>,
+    shard_fast_ok: bool,
 }
 
 impl Info for CommandInfo {
@@ -381,10 +1050,29 @@ impl Info for CommandInfo {
             clock: 0,
             votes: Votes::new(),
             quorum_clocks: QuorumClocks::new(fast_quorum_size),
+            current_ballot: 0,
+            accepted_ballot: 0,
+            accepted_clock: None,
+            consensus_acks: std::collections::HashSet::new(),
+            recovered_ballot: 0,
+            recovered_clock: None,
+            shards: Vec::new(),
+            quorum_clocks_per_shard: HashMap::new(),
+            shard_clocks: HashMap::new(),
+            shard_fast_ok: true,
         }
     }
 }
 
+// one shard's slice of a multi-shard `MShardCollect`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShardCollect {
+    shard_id: ShardId,
+    cmd: Command,
+    quorum: Vec<ProcessId>,
+    clock: u64,
+}
+
 // `Newt` protocol messages
 #[derive(Debug, Clone, PartialEq)]
 pub enum Message {
@@ -396,9 +1084,21 @@ pub enum Message {
     },
     MCollectAck {
         dot: Dot,
+        // `None` for a single-shard command (the common case); set to the
+        // shard this ack is about when the command spans multiple shards, so
+        // the coordinator can join the per-shard outcomes
+        shard_id: Option<ShardId>,
         clock: u64,
         process_votes: ProcessVotes,
     },
+    // sent by the coordinator of a command spanning more than one shard:
+    // every participating shard's fast-quorum members receive the same
+    // `MShardCollect`, and each only acts on the entry matching its own
+    // `shard_id`
+    MShardCollect {
+        dot: Dot,
+        shards: Vec<ShardCollect>,
+    },
     MCommit {
         dot: Dot,
         cmd: Option<Command>,
@@ -409,6 +1109,24 @@ pub enum Message {
         dot: Dot,
         process_votes: ProcessVotes,
     },
+    MConsensus {
+        dot: Dot,
+        ballot: u64,
+        clock: u64,
+        cmd: Option<Command>,
+    },
+    MConsensusAck {
+        dot: Dot,
+        ballot: u64,
+        accepted_ballot: u64,
+        accepted_clock: u64,
+    },
+    MPhantomBatch {
+        phantoms: Vec<(Dot, ProcessVotes)>,
+    },
+    MGarbageCollection {
+        committed: VClock<ProcessId>,
+    },
 }
 
 /// `Status` of commands.
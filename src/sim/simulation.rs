@@ -4,11 +4,117 @@ use crate::id::{ClientId, ProcessId};
 use crate::protocol::{Process, ToSend};
 use crate::time::SysTime;
 use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// Describes how the network between processes should misbehave, so that
+/// protocols can be exercised against drops, delays, reordering, crashed
+/// processes and network partitions in a fully deterministic way (no real
+/// randomness or wall-clock time is involved).
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    // links whose messages are always dropped
+    dropped_links: HashSet<(ProcessId, ProcessId)>,
+    // links whose messages are delayed by this many ticks before delivery
+    delayed_links: HashMap<(ProcessId, ProcessId), u64>,
+    // links whose messages are delivered out of send order
+    reordered_links: HashSet<(ProcessId, ProcessId)>,
+    // processes that are currently crashed: they neither send nor receive
+    crashed: HashSet<ProcessId>,
+    // when set, messages are only delivered within the same group
+    partitions: Option<Vec<HashSet<ProcessId>>>,
+}
+
+impl FaultConfig {
+    /// Creates a new `FaultConfig` where the network behaves perfectly.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops all messages sent from `from` to `to`.
+    pub fn drop_link(&mut self, from: ProcessId, to: ProcessId) {
+        self.dropped_links.insert((from, to));
+    }
+
+    /// Undoes a previous call to `drop_link`.
+    pub fn heal_link(&mut self, from: ProcessId, to: ProcessId) {
+        self.dropped_links.remove(&(from, to));
+    }
+
+    /// Delays messages sent from `from` to `to` by `ticks` calls to `tick`.
+    pub fn delay_link(&mut self, from: ProcessId, to: ProcessId, ticks: u64) {
+        self.delayed_links.insert((from, to), ticks);
+    }
+
+    /// Delivers messages sent from `from` to `to` out of send order.
+    pub fn reorder_link(&mut self, from: ProcessId, to: ProcessId) {
+        self.reordered_links.insert((from, to));
+    }
+
+    /// Crashes `process_id`: until `restart` is called, it neither sends nor
+    /// receives any message.
+    pub fn crash(&mut self, process_id: ProcessId) {
+        self.crashed.insert(process_id);
+    }
+
+    /// Restarts a previously crashed process.
+    pub fn restart(&mut self, process_id: ProcessId) {
+        self.crashed.remove(&process_id);
+    }
+
+    /// Splits the cluster into disjoint groups; messages are only delivered
+    /// between processes of the same group.
+    pub fn partition(&mut self, groups: Vec<HashSet<ProcessId>>) {
+        self.partitions = Some(groups);
+    }
+
+    /// Removes any active partition.
+    pub fn heal_partition(&mut self) {
+        self.partitions = None;
+    }
+
+    fn is_crashed(&self, process_id: ProcessId) -> bool {
+        self.crashed.contains(&process_id)
+    }
+
+    fn is_connected(&self, from: ProcessId, to: ProcessId) -> bool {
+        if self.is_crashed(from) || self.is_crashed(to) {
+            return false;
+        }
+        if self.dropped_links.contains(&(from, to)) {
+            return false;
+        }
+        if let Some(partitions) = &self.partitions {
+            let same_group = partitions
+                .iter()
+                .any(|group| group.contains(&from) && group.contains(&to));
+            if !same_group {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn delay(&self, from: ProcessId, to: ProcessId) -> u64 {
+        self.delayed_links.get(&(from, to)).copied().unwrap_or(0)
+    }
+
+    fn is_reordered(&self, from: ProcessId, to: ProcessId) -> bool {
+        self.reordered_links.contains(&(from, to))
+    }
+}
 
 pub struct Simulation<P: Process> {
     processes: HashMap<ProcessId, (RefCell<P>, RefCell<P::Executor>)>,
     clients: HashMap<ClientId, RefCell<Client>>,
+    faults: FaultConfig,
+    // current logical tick, advanced by calls to `tick`
+    tick: u64,
+    // messages in-flight due to a faulted link, along with the tick at which
+    // they should be delivered and an insertion sequence used to order (or,
+    // for reordered links, un-order) same-tick deliveries deterministically
+    in_flight: Vec<(u64, i64, ProcessId, ToSend<P::Message>)>,
+    // monotonic counter used to generate the insertion sequence above
+    next_sequence: i64,
 }
 
 impl<P> Simulation<P>
@@ -21,9 +127,20 @@ where
         Simulation {
             processes: HashMap::new(),
             clients: HashMap::new(),
+            faults: FaultConfig::new(),
+            tick: 0,
+            in_flight: Vec::new(),
+            next_sequence: 0,
         }
     }
 
+    /// Returns a mutable reference to the `FaultConfig` driving this
+    /// simulation's network, so tests can inject drops, delays, reordering,
+    /// crashes and partitions.
+    pub fn faults_mut(&mut self) -> &mut FaultConfig {
+        &mut self.faults
+    }
+
     /// Registers a `Process` in the `Simulation` by storing it in a `Cell`.
     pub fn register_process(&mut self, process: P, executor: P::Executor) {
         // get identifier
@@ -62,15 +179,67 @@ where
             .collect()
     }
 
-    /// Forward a `ToSend`.
-    pub fn forward_to_processes(&self, to_send: ToSend<P::Message>) -> Vec<ToSend<P::Message>> {
+    /// Forward a `ToSend`, subject to the currently configured `FaultConfig`:
+    /// messages to disconnected (dropped/partitioned/crashed) targets are
+    /// silently discarded, and messages on a delayed or reordered link are
+    /// buffered until a later call to `tick` releases them.
+    pub fn forward_to_processes(&mut self, to_send: ToSend<P::Message>) -> Vec<ToSend<P::Message>> {
         // extract `ToSend` arguments
         let ToSend { from, target, msg } = to_send;
         target
             .into_iter()
+            .filter(|&process_id| self.faults.is_connected(from, process_id))
             .filter_map(|process_id| {
+                let delay = self.faults.delay(from, process_id);
+                let reordered = self.faults.is_reordered(from, process_id);
+                if delay == 0 && !reordered {
+                    let (mut process, _) = self.get_process_mut(process_id);
+                    process.handle(from, msg.clone())
+                } else {
+                    // reordered links are modelled as a one-tick delay whose
+                    // release order is inverted relative to other in-flight
+                    // messages, instead of being delivered immediately
+                    let release_tick = self.tick + delay.max(1);
+                    let sequence = if reordered {
+                        let sequence = -self.next_sequence;
+                        self.next_sequence += 1;
+                        sequence
+                    } else {
+                        let sequence = self.next_sequence;
+                        self.next_sequence += 1;
+                        sequence
+                    };
+                    let single = ToSend {
+                        from,
+                        target: vec![process_id],
+                        msg: msg.clone(),
+                    };
+                    self.in_flight
+                        .push((release_tick, sequence, process_id, single));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Advances the simulation's logical clock by one tick and delivers any
+    /// in-flight messages whose release tick has been reached, in release
+    /// order (reordered links are delivered in reverse insertion order).
+    pub fn tick(&mut self) -> Vec<ToSend<P::Message>> {
+        self.tick += 1;
+
+        let (mut ready, pending): (Vec<_>, Vec<_>) = self
+            .in_flight
+            .drain(..)
+            .partition(|(release_tick, ..)| *release_tick <= self.tick);
+        self.in_flight = pending;
+        ready.sort_by_key(|(release_tick, sequence, ..)| (*release_tick, *sequence));
+
+        ready
+            .into_iter()
+            .filter_map(|(_, _, process_id, to_send)| {
                 let (mut process, _) = self.get_process_mut(process_id);
-                process.handle(from, msg.clone())
+                process.handle(to_send.from, to_send.msg)
             })
             .collect()
     }